@@ -0,0 +1,216 @@
+use clap::{Args, ValueEnum};
+use energiapro::SystemClock;
+use energiapro::analysis::anomaly::{self, Anomaly, AnomalyOptions, Severity};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct AnomaliesArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        required = true,
+        help = "Installation identifiers (num_inst) to scan"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(long, help = FROM_HELP)]
+    from: Option<String>,
+    #[arg(long, help = TO_HELP)]
+    to: Option<String>,
+    #[arg(
+        long,
+        default_value_t = AnomalyOptions::default().threshold,
+        help = "Smallest robust z-score reported as an anomaly"
+    )]
+    threshold: f64,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = MinSeverity::Low,
+        help = "Only list anomalies of at least this severity"
+    )]
+    min_severity: MinSeverity,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+enum MinSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+impl From<MinSeverity> for Severity {
+    fn from(severity: MinSeverity) -> Self {
+        match severity {
+            MinSeverity::Low => Self::Low,
+            MinSeverity::Medium => Self::Medium,
+            MinSeverity::High => Self::High,
+        }
+    }
+}
+
+pub(super) async fn run(args: AnomaliesArgs) -> Result<(), DynError> {
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+    let options = AnomalyOptions::default().with_threshold(args.threshold);
+    let min_severity = Severity::from(args.min_severity);
+
+    let mut anomalies = Vec::new();
+    for installation_id in &args.installation_ids {
+        let measurements = client
+            .measurements
+            .query(&args.client_id, installation_id, args.scope.as_str(), range)
+            .await?;
+        anomalies.extend(
+            anomaly::detect_with(&measurements, &options)?
+                .into_iter()
+                .filter(|anomaly| anomaly.severity >= min_severity),
+        );
+    }
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_anomalies_text(&anomalies).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&anomalies)?,
+        format => export_dataframe(format, &mut anomalies_to_dataframe(&anomalies)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn render_anomalies_text(anomalies: &[Anomaly]) -> String {
+    let rows = anomalies
+        .iter()
+        .map(|anomaly| {
+            vec![
+                anomaly.installation_id.clone(),
+                anomaly.timestamp.clone(),
+                anomaly.kind.to_string(),
+                anomaly.severity.to_string(),
+                format!("{:.3}", anomaly.observed_kwh),
+                format!("{:.3}", anomaly.expected_kwh),
+                format!("{:.2}", anomaly.z_score),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &[
+            "installation_id",
+            "timestamp",
+            "kind",
+            "severity",
+            "observed_kwh",
+            "expected_kwh",
+            "z_score",
+        ],
+        &rows,
+    )
+}
+
+fn anomalies_to_dataframe(anomalies: &[Anomaly]) -> Result<DataFrame, DynError> {
+    let text = |f: fn(&Anomaly) -> String| anomalies.iter().map(f).collect::<Vec<_>>();
+    let number = |f: fn(&Anomaly) -> f64| anomalies.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            text(|anomaly| anomaly.installation_id.clone()),
+        )
+        .into(),
+        Series::new(
+            "timestamp".into(),
+            text(|anomaly| anomaly.timestamp.clone()),
+        )
+        .into(),
+        Series::new("kind".into(), text(|anomaly| anomaly.kind.to_string())).into(),
+        Series::new(
+            "severity".into(),
+            text(|anomaly| anomaly.severity.to_string()),
+        )
+        .into(),
+        Series::new(
+            "observed_kwh".into(),
+            number(|anomaly| anomaly.observed_kwh),
+        )
+        .into(),
+        Series::new(
+            "expected_kwh".into(),
+            number(|anomaly| anomaly.expected_kwh),
+        )
+        .into(),
+        Series::new("z_score".into(), number(|anomaly| anomaly.z_score)).into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::Measurement;
+
+    use super::*;
+
+    fn sample_anomalies() -> Vec<Anomaly> {
+        let measurement = |day: u32, consumption_kwh: f64| Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: format!("2024-01-{day:02} 08:00:00"),
+            index_m3: 0.0,
+            consumption_m3: consumption_kwh / 10.0,
+            consumption_kwh,
+        };
+
+        anomaly::detect(&[
+            measurement(1, 10.0),
+            measurement(8, 11.0),
+            measurement(15, 10.0),
+            measurement(22, 9.0),
+            measurement(29, 0.0),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_anomalies() {
+        let text = render_anomalies_text(&sample_anomalies());
+
+        assert!(text.contains("INSTALLATION_ID_1"));
+        assert!(text.contains("2024-01-29 08:00:00"));
+        assert!(text.contains("drop"));
+        assert!(text.contains("10.000"));
+    }
+
+    #[test]
+    fn exports_anomalies_as_csv() {
+        let mut dataframe = anomalies_to_dataframe(&sample_anomalies()).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert!(csv.starts_with(
+            "installation_id,timestamp,kind,severity,observed_kwh,expected_kwh,z_score\n"
+        ));
+        assert!(csv.contains("INSTALLATION_ID_1,2024-01-29 08:00:00,drop,"));
+    }
+}
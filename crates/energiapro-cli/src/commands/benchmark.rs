@@ -0,0 +1,399 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Datelike;
+use clap::Args;
+use energiapro::analysis::benchmark::{
+    self, Benchmark, BenchmarkEntry, BenchmarkOptions, BuildingRegistry, ClassScale,
+};
+use energiapro::analysis::degree_days::TemperatureSeries;
+use energiapro::{Clock, DateRange, SystemClock};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct BenchmarkArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(
+        value_name = "CLIENT_ID",
+        help = "EnergiaPro client identifier, or a comma-separated list to rank a whole portfolio"
+    )]
+    client_ids: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        help = "Installation identifiers (num_inst) to benchmark; defaults to every installation of the clients"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(
+        long,
+        short = 'r',
+        value_name = "FILE",
+        help = "Building registry in TOML or CSV with the energy reference area, building type and construction year of each installation"
+    )]
+    registry: PathBuf,
+    #[arg(long, help = "Calendar year to benchmark; defaults to last year")]
+    year: Option<i32>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "CSV of daily mean temperatures (e.g. a MeteoSwiss export) to normalize consumption to the reference climate"
+    )]
+    temperatures: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "reference_kwh_per_m2",
+        help = "Target classes in TOML"
+    )]
+    classes: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "KWH_PER_M2",
+        help = "Reference intensity of the A to G classes, e.g. the SIA 380/1 limit, in kWh/m²·a"
+    )]
+    reference_kwh_per_m2: Option<f64>,
+    #[arg(
+        long,
+        default_value_t = BenchmarkOptions::default().min_coverage,
+        help = "Share of the year that must have measurements, between 0 and 1; partial years are not extrapolated"
+    )]
+    min_coverage: f64,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: BenchmarkArgs) -> Result<(), DynError> {
+    let registry = load_registry(&args.registry)?;
+    let year = args.year.unwrap_or_else(|| SystemClock.today().year() - 1);
+
+    let mut options = BenchmarkOptions::default().with_min_coverage(args.min_coverage);
+    if let Some(path) = &args.temperatures {
+        options = options.with_temperatures(TemperatureSeries::load(path)?);
+    }
+    if let Some(path) = &args.classes {
+        options = options.with_classes(load_classes(path)?);
+    }
+    if let Some(reference) = args.reference_kwh_per_m2 {
+        options = options.with_classes(ClassScale::relative_to(reference));
+    }
+
+    let client = args.connection.client()?;
+    // Installations are listed per client, so a portfolio can mix clients
+    // while explicit identifiers only restrict which installations are kept.
+    let range = DateRange::year(year)?;
+    let client_ids = args
+        .client_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|client_id| !client_id.is_empty())
+        .collect::<Vec<_>>();
+    let mut measurements = Vec::new();
+    for client_id in &client_ids {
+        let installation_ids = match client_ids.as_slice() {
+            [_] if !args.installation_ids.is_empty() => args.installation_ids.clone(),
+            _ => client
+                .installations
+                .list(client_id)
+                .await?
+                .into_iter()
+                .map(|installation| installation.id)
+                .filter(|id| args.installation_ids.is_empty() || args.installation_ids.contains(id))
+                .collect(),
+        };
+
+        for installation_id in &installation_ids {
+            measurements.extend(
+                client
+                    .measurements
+                    .query(client_id, installation_id, args.scope.as_str(), range)
+                    .await?,
+            );
+        }
+    }
+
+    let benchmark = benchmark::benchmark_with(&measurements, &registry, year, &options)?;
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_benchmark_text(&benchmark).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&benchmark)?,
+        format => export_dataframe(format, &mut benchmark_to_dataframe(&benchmark)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn load_registry(path: &Path) -> Result<BuildingRegistry, DynError> {
+    let is_toml = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    if !is_toml {
+        return Ok(BuildingRegistry::load_csv(path)?);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read registry {}: {err}", path.display()))?;
+    let registry: BuildingRegistry = toml::from_str(&contents)
+        .map_err(|err| format!("invalid registry {}: {err}", path.display()))?;
+    registry.validate()?;
+    Ok(registry)
+}
+
+fn load_classes(path: &Path) -> Result<ClassScale, DynError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read classes {}: {err}", path.display()))?;
+    let classes: ClassScale = toml::from_str(&contents)
+        .map_err(|err| format!("invalid classes {}: {err}", path.display()))?;
+    classes.validate()?;
+    Ok(classes)
+}
+
+fn render_benchmark_text(benchmark: &Benchmark) -> String {
+    let rows = benchmark
+        .entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.rank.to_string(),
+                entry.installation_id.clone(),
+                entry.building_type.to_string(),
+                entry
+                    .construction_year
+                    .map_or_else(|| "-".to_owned(), |year| year.to_string()),
+                format!("{:.1}", entry.energy_reference_area_m2),
+                format!(
+                    "{:.1}%{}",
+                    entry.coverage * 100.0,
+                    if entry.is_partial() { "*" } else { "" }
+                ),
+                format!("{:.1}", entry.consumption_kwh),
+                entry
+                    .normalized_kwh
+                    .map_or_else(|| "-".to_owned(), |kwh| format!("{kwh:.1}")),
+                format!("{:.1}", entry.intensity_kwh_per_m2),
+                entry.class.clone().unwrap_or_else(|| "-".to_owned()),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut text = render_table(
+        &[
+            "rank",
+            "installation_id",
+            "building_type",
+            "construction_year",
+            "area_m2",
+            "coverage",
+            "consumption_kwh",
+            "normalized_kwh",
+            "kwh_per_m2",
+            "class",
+        ],
+        &rows,
+    );
+    if benchmark.entries.iter().any(BenchmarkEntry::is_partial) {
+        text.push_str("\n* measured on part of the year, consumption not extrapolated\n");
+    }
+    if !benchmark.skipped.is_empty() {
+        let skipped = benchmark
+            .skipped
+            .iter()
+            .map(|skipped| vec![skipped.installation_id.clone(), skipped.reason.clone()])
+            .collect::<Vec<_>>();
+        text.push('\n');
+        text.push_str(&render_table(&["skipped", "reason"], &skipped));
+    }
+    text
+}
+
+/// One row per benchmarked installation, followed by the skipped ones with
+/// only their identifier and the reason they were left out.
+fn benchmark_to_dataframe(benchmark: &Benchmark) -> Result<DataFrame, DynError> {
+    let entries = &benchmark.entries;
+    let skipped = benchmark.skipped.len();
+    let column = |f: fn(&BenchmarkEntry) -> Option<f64>| {
+        entries
+            .iter()
+            .map(f)
+            .chain(std::iter::repeat_n(None, skipped))
+            .collect::<Vec<_>>()
+    };
+    let rank = |f: fn(&BenchmarkEntry) -> usize| {
+        entries
+            .iter()
+            .map(|entry| Some(f(entry) as u32))
+            .chain(std::iter::repeat_n(None, skipped))
+            .collect::<Vec<_>>()
+    };
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new("rank".into(), rank(|entry| entry.rank)).into(),
+        Series::new("type_rank".into(), rank(|entry| entry.type_rank)).into(),
+        Series::new(
+            "installation_id".into(),
+            entries
+                .iter()
+                .map(|entry| entry.installation_id.clone())
+                .chain(
+                    benchmark
+                        .skipped
+                        .iter()
+                        .map(|skipped| skipped.installation_id.clone()),
+                )
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "building_type".into(),
+            entries
+                .iter()
+                .map(|entry| Some(entry.building_type.as_str()))
+                .chain(std::iter::repeat_n(None, skipped))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "construction_year".into(),
+            entries
+                .iter()
+                .map(|entry| entry.construction_year)
+                .chain(std::iter::repeat_n(None, skipped))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "energy_reference_area_m2".into(),
+            column(|entry| Some(entry.energy_reference_area_m2)),
+        )
+        .into(),
+        Series::new("coverage".into(), column(|entry| Some(entry.coverage))).into(),
+        Series::new(
+            "consumption_kwh".into(),
+            column(|entry| Some(entry.consumption_kwh)),
+        )
+        .into(),
+        Series::new(
+            "normalized_kwh".into(),
+            column(|entry| entry.normalized_kwh),
+        )
+        .into(),
+        Series::new(
+            "intensity_kwh_per_m2".into(),
+            column(|entry| Some(entry.intensity_kwh_per_m2)),
+        )
+        .into(),
+        Series::new(
+            "class".into(),
+            entries
+                .iter()
+                .map(|entry| entry.class.clone())
+                .chain(std::iter::repeat_n(None, skipped))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "skipped_reason".into(),
+            std::iter::repeat_n(None, entries.len())
+                .chain(
+                    benchmark
+                        .skipped
+                        .iter()
+                        .map(|skipped| Some(skipped.reason.clone())),
+                )
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::Measurement;
+    use energiapro::analysis::benchmark::{BuildingMetadata, BuildingType};
+
+    use super::*;
+
+    fn sample_benchmark() -> Benchmark {
+        let registry = BuildingRegistry::new()
+            .with_building(
+                "INSTALLATION_ID_1",
+                BuildingMetadata::new(1000.0, BuildingType::MultiFamily)
+                    .with_construction_year(1972),
+            )
+            .with_building(
+                "INSTALLATION_ID_2",
+                BuildingMetadata::new(500.0, BuildingType::School),
+            );
+
+        let mut measurements = Vec::new();
+        for (installation_id, kwh_per_day) in [
+            ("INSTALLATION_ID_1", 400.0),
+            ("INSTALLATION_ID_2", 50.0),
+            ("INSTALLATION_ID_3", 80.0),
+        ] {
+            let mut date = DateRange::year(2023).unwrap().start().unwrap();
+            for _ in 0..365 {
+                measurements.push(Measurement {
+                    client_id: 1,
+                    installation_id: installation_id.to_owned(),
+                    timestamp: format!("{date} 00:00:00"),
+                    index_m3: 0.0,
+                    consumption_m3: kwh_per_day / 10.0,
+                    consumption_kwh: kwh_per_day,
+                });
+                date = date.succ_opt().unwrap();
+            }
+        }
+
+        let options = BenchmarkOptions::default().with_classes(ClassScale::relative_to(100.0));
+        benchmark::benchmark_with(&measurements, &registry, 2023, &options).unwrap()
+    }
+
+    #[test]
+    fn renders_ranked_benchmark() {
+        let text = render_benchmark_text(&sample_benchmark());
+
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("kwh_per_m2"));
+        assert!(text.find("INSTALLATION_ID_2").unwrap() < text.find("INSTALLATION_ID_1").unwrap());
+        assert!(text.contains("146.0"));
+        assert!(text.contains("1972"));
+        assert!(text.contains("100.0%"));
+        assert!(!text.contains("not extrapolated"));
+        assert!(text.contains("INSTALLATION_ID_3"));
+        assert!(text.contains("no building data in the registry"));
+    }
+
+    #[test]
+    fn exports_benchmark_as_csv() {
+        let mut dataframe = benchmark_to_dataframe(&sample_benchmark()).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert!(csv.starts_with(
+            "rank,type_rank,installation_id,building_type,construction_year,energy_reference_area_m2,coverage,consumption_kwh,normalized_kwh,intensity_kwh_per_m2,class,skipped_reason\n"
+        ));
+        assert!(csv.contains("1,1,INSTALLATION_ID_2,school,,500.0,1.0,18250.0,,36.5,A,\n"));
+        assert!(
+            csv.contains("2,1,INSTALLATION_ID_1,multi_family,1972,1000.0,1.0,146000.0,,146.0,C,\n")
+        );
+        assert!(csv.contains(",,INSTALLATION_ID_3,,,,,,,,,no building data in the registry\n"));
+    }
+}
@@ -0,0 +1,420 @@
+use clap::Args;
+use energiapro::analysis::quality::{self, QualityOptions, QualityReport};
+use energiapro::{Measurement, SystemClock};
+use futures_util::TryStreamExt;
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct CheckArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        required = true,
+        help = "Installation identifiers (num_inst) to check"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(long, help = FROM_HELP)]
+    from: Option<String>,
+    #[arg(long, help = TO_HELP)]
+    to: Option<String>,
+    #[arg(
+        long,
+        default_value_t = QualityOptions::default().max_flow_m3_per_hour,
+        help = "Highest plausible flow in m3 per hour"
+    )]
+    max_flow: f64,
+    #[arg(
+        long,
+        default_value_t = QualityOptions::default().delta_tolerance_m3,
+        help = "Accepted difference in m3 between index delta and consumption"
+    )]
+    delta_tolerance: f64,
+    #[arg(
+        long,
+        help = "Exit with an error if any installation scores below this value (0-100)"
+    )]
+    min_score: Option<f64>,
+    #[arg(
+        long,
+        help = "Output one row per issue instead of one row per installation"
+    )]
+    issues: bool,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: CheckArgs) -> Result<(), DynError> {
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+
+    // `query` deduplicates rows shared by its windows, which would hide the
+    // duplicates this command is meant to report, so rows are streamed as-is.
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for installation_id in &args.installation_ids {
+        let rows: Vec<Measurement> = client
+            .measurements
+            .stream_rows(&args.client_id, installation_id, args.scope.as_str(), range)
+            .await?
+            .try_collect()
+            .await?;
+        measurements.extend(rows);
+    }
+
+    let options = QualityOptions::default()
+        .with_max_flow_m3_per_hour(args.max_flow)
+        .with_delta_tolerance_m3(args.delta_tolerance);
+    let reports = analyze(&measurements, &args.installation_ids, &options);
+
+    let bytes = match (args.format, args.issues) {
+        (OutputFormat::Text, false) => render_reports_text(&reports).into_bytes(),
+        (OutputFormat::Text, true) => render_issues_text(&reports).into_bytes(),
+        (OutputFormat::Json, false) => serde_json::to_vec_pretty(&reports)?,
+        (format, true) => export_dataframe(format, &mut issues_to_dataframe(&reports)?)?,
+        (format, false) => export_dataframe(format, &mut reports_to_dataframe(&reports)?)?,
+    };
+
+    write_stdout(&bytes)?;
+
+    if let Some(min_score) = args.min_score
+        && let Some(report) = reports.iter().find(|report| report.score < min_score)
+    {
+        return Err(format!(
+            "installation {} scored {} which is below the minimum of {min_score}",
+            report.installation_id, report.score
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+fn analyze(
+    measurements: &[Measurement],
+    installation_ids: &[String],
+    options: &QualityOptions,
+) -> Vec<QualityReport> {
+    let mut reports = quality::analyze_with(measurements, options);
+
+    // Installations without any measurement still deserve a (failing) report.
+    for installation_id in installation_ids {
+        if !reports
+            .iter()
+            .any(|report| &report.installation_id == installation_id)
+        {
+            reports.push(empty_report(installation_id));
+        }
+    }
+
+    reports
+}
+
+fn empty_report(installation_id: &str) -> QualityReport {
+    QualityReport {
+        installation_id: installation_id.to_owned(),
+        measurement_count: 0,
+        first_timestamp: None,
+        last_timestamp: None,
+        resolution_minutes: None,
+        expected_count: 0,
+        missing_count: 0,
+        duplicate_count: 0,
+        invalid_timestamp_count: 0,
+        negative_count: 0,
+        implausible_count: 0,
+        index_regression_count: 0,
+        delta_mismatch_count: 0,
+        score: 0.0,
+        issues: Vec::new(),
+    }
+}
+
+fn render_reports_text(reports: &[QualityReport]) -> String {
+    let summary = reports
+        .iter()
+        .map(|report| {
+            vec![
+                report.installation_id.clone(),
+                report.measurement_count.to_string(),
+                report
+                    .resolution_minutes
+                    .map_or_else(|| "-".to_owned(), |minutes| format!("{minutes} min")),
+                report.missing_count.to_string(),
+                report.duplicate_count.to_string(),
+                report.negative_count.to_string(),
+                report.implausible_count.to_string(),
+                report.index_regression_count.to_string(),
+                report.delta_mismatch_count.to_string(),
+                format!("{:.1}", report.score),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "{}\n{}",
+        render_table(
+            &[
+                "installation_id",
+                "measurements",
+                "resolution",
+                "missing",
+                "duplicates",
+                "negative",
+                "implausible",
+                "regressions",
+                "mismatches",
+                "score",
+            ],
+            &summary,
+        ),
+        render_issues_text(reports)
+    )
+}
+
+fn render_issues_text(reports: &[QualityReport]) -> String {
+    let issues = reports
+        .iter()
+        .flat_map(|report| {
+            report.issues.iter().map(|issue| {
+                vec![
+                    report.installation_id.clone(),
+                    issue.timestamp().to_owned(),
+                    issue.kind().to_owned(),
+                    issue.to_string(),
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &["installation_id", "timestamp", "issue", "details"],
+        &issues,
+    )
+}
+
+fn reports_to_dataframe(reports: &[QualityReport]) -> Result<DataFrame, DynError> {
+    let column = |f: fn(&QualityReport) -> u64| reports.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            reports
+                .iter()
+                .map(|report| report.installation_id.clone())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "measurement_count".into(),
+            column(|report| report.measurement_count as u64),
+        )
+        .into(),
+        Series::new(
+            "resolution_minutes".into(),
+            reports
+                .iter()
+                .map(|report| report.resolution_minutes)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "missing_count".into(),
+            column(|report| report.missing_count as u64),
+        )
+        .into(),
+        Series::new(
+            "duplicate_count".into(),
+            column(|report| report.duplicate_count as u64),
+        )
+        .into(),
+        Series::new(
+            "negative_count".into(),
+            column(|report| report.negative_count as u64),
+        )
+        .into(),
+        Series::new(
+            "implausible_count".into(),
+            column(|report| report.implausible_count as u64),
+        )
+        .into(),
+        Series::new(
+            "index_regression_count".into(),
+            column(|report| report.index_regression_count as u64),
+        )
+        .into(),
+        Series::new(
+            "delta_mismatch_count".into(),
+            column(|report| report.delta_mismatch_count as u64),
+        )
+        .into(),
+        Series::new(
+            "score".into(),
+            reports
+                .iter()
+                .map(|report| report.score)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+fn issues_to_dataframe(reports: &[QualityReport]) -> Result<DataFrame, DynError> {
+    let issues = reports
+        .iter()
+        .flat_map(|report| {
+            report
+                .issues
+                .iter()
+                .map(move |issue| (report.installation_id.as_str(), issue))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            issues
+                .iter()
+                .map(|(installation_id, _)| installation_id.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "timestamp".into(),
+            issues
+                .iter()
+                .map(|(_, issue)| issue.timestamp().to_owned())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "issue".into(),
+            issues
+                .iter()
+                .map(|(_, issue)| issue.kind().to_owned())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "details".into(),
+            issues
+                .iter()
+                .map(|(_, issue)| issue.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_reports() -> Vec<QualityReport> {
+        let measurement = |timestamp: &str, index_m3: f64, consumption_m3: f64| Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: timestamp.to_owned(),
+            index_m3,
+            consumption_m3,
+            consumption_kwh: consumption_m3 * 10.0,
+        };
+
+        quality::analyze(&[
+            measurement("2024-04-01 00:00:00", 100.0, 0.0),
+            measurement("2024-04-01 01:00:00", 101.0, 1.0),
+            measurement("2024-04-01 03:00:00", 103.0, 1.0),
+        ])
+    }
+
+    #[test]
+    fn renders_summary_and_issues() {
+        let text = render_reports_text(&sample_reports());
+
+        assert!(text.contains("INSTALLATION_ID_1"));
+        assert!(text.contains("60 min"));
+        assert!(text.contains("missing_intervals"));
+        assert!(text.contains("75.0"));
+
+        let issues = render_issues_text(&sample_reports());
+        assert!(issues.contains("missing_intervals"));
+        assert!(!issues.contains("75.0"));
+    }
+
+    #[test]
+    fn exports_summary_and_issue_rows_as_csv() {
+        let reports = sample_reports();
+
+        let mut dataframe = reports_to_dataframe(&reports).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with("installation_id,measurement_count,resolution_minutes,"));
+        assert!(csv.contains("INSTALLATION_ID_1,3,60,1,0,0,0,0,0,75.0"));
+
+        let mut dataframe = issues_to_dataframe(&reports).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with("installation_id,timestamp,issue,details\n"));
+        assert!(csv.contains("INSTALLATION_ID_1,2024-04-01 03:00:00,missing_intervals,"));
+    }
+
+    #[tokio::test]
+    async fn reports_duplicated_rows_but_not_the_repeated_autumn_hour() {
+        let row = |timestamp: &str, index_m3: f64| {
+            Ok(Measurement {
+                client_id: 1,
+                installation_id: "INSTALLATION_ID_1".to_owned(),
+                timestamp: timestamp.to_owned(),
+                index_m3,
+                consumption_m3: 1.0,
+                consumption_kwh: 10.0,
+            })
+        };
+        let rows: energiapro::MeasurementStream = Box::pin(futures_util::stream::iter(vec![
+            row("2024-10-27 01:00:00", 101.0),
+            row("2024-10-27 02:00:00", 102.0),
+            row("2024-10-27 02:00:00", 103.0),
+            row("2024-10-27 03:00:00", 104.0),
+            row("2024-10-27 03:00:00", 104.0),
+        ]));
+
+        let measurements: Vec<Measurement> = rows.try_collect().await.unwrap();
+        let reports = analyze(
+            &measurements,
+            &[
+                "INSTALLATION_ID_1".to_owned(),
+                "INSTALLATION_ID_2".to_owned(),
+            ],
+            &QualityOptions::default(),
+        );
+
+        assert_eq!(reports[0].measurement_count, 5);
+        assert_eq!(reports[0].duplicate_count, 1);
+        assert_eq!(reports[0].missing_count, 0);
+        assert_eq!(reports[0].issues[0].kind(), "duplicate_timestamp");
+        assert_eq!(reports[0].issues[0].timestamp(), "2024-10-27 03:00:00");
+        assert_eq!(reports[1].installation_id, "INSTALLATION_ID_2");
+        assert_eq!(reports[1].score, 0.0);
+    }
+}
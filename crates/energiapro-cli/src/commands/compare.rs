@@ -0,0 +1,301 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use energiapro::analysis::comparison::{
+    self, Change, Comparison, ComparisonOptions, ComparisonRow,
+};
+use energiapro::analysis::degree_days::TemperatureSeries;
+use energiapro::{Measurement, SystemClock};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::commands::cost::load_tariff;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, Period, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct CompareArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        help = "Installation identifier (num_inst)"
+    )]
+    installation_id: String,
+    #[arg(long, help = FROM_HELP)]
+    from: String,
+    #[arg(long, help = TO_HELP)]
+    to: String,
+    #[arg(
+        long,
+        requires = "reference_to",
+        help = "First day of the reference period; defaults to the same period last year"
+    )]
+    reference_from: Option<String>,
+    #[arg(
+        long,
+        requires = "reference_from",
+        help = "Last day of the reference period"
+    )]
+    reference_to: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Period::Month,
+        help = "Period of each comparison row"
+    )]
+    period: Period,
+    #[arg(
+        long,
+        short = 't',
+        value_name = "FILE",
+        help = "Tariff definition in TOML, to compare costs"
+    )]
+    tariff: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "CSV of daily mean temperatures (e.g. a MeteoSwiss export) to adjust the reference period to the weather"
+    )]
+    temperatures: Option<PathBuf>,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: CompareArgs) -> Result<(), DynError> {
+    let current = date_range(Some(&args.from), Some(&args.to), &SystemClock)?;
+    let reference = match (&args.reference_from, &args.reference_to) {
+        (Some(from), Some(to)) => date_range(Some(from), Some(to), &SystemClock)?,
+        _ => comparison::same_period_last_year(current)?,
+    };
+
+    let mut options = ComparisonOptions::default().with_granularity(args.period.into());
+    if let Some(path) = &args.tariff {
+        options = options.with_tariff(load_tariff(path)?);
+    }
+    if let Some(path) = &args.temperatures {
+        options = options.with_temperatures(TemperatureSeries::load(path)?);
+    }
+
+    let client = args.connection.client()?;
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for range in [reference, current] {
+        measurements.extend(
+            client
+                .measurements
+                .query(
+                    &args.client_id,
+                    &args.installation_id,
+                    args.scope.as_str(),
+                    range,
+                )
+                .await?,
+        );
+    }
+
+    let comparison = comparison::compare_with(&measurements, current, reference, &options)?;
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_comparison_text(&comparison).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&comparison)?,
+        format => export_dataframe(format, &mut comparison_to_dataframe(&comparison)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn render_comparison_text(comparison: &Comparison) -> String {
+    let with_cost = comparison.total.cost.is_some();
+    let with_weather = comparison.total.weather_adjusted_kwh.is_some();
+
+    let mut headers = vec![
+        "period",
+        "reference",
+        "m3",
+        "reference_m3",
+        "change_m3",
+        "kwh",
+        "reference_kwh",
+        "change_kwh",
+        "change",
+    ];
+    if with_cost {
+        headers.extend(["cost", "reference_cost", "change_cost"]);
+    }
+    if with_weather {
+        headers.extend(["adjusted_reference_kwh", "adjusted_change"]);
+    }
+
+    let rows = comparison
+        .rows
+        .iter()
+        .map(|row| (format!("{}..{}", row.current.start, row.current.end), row))
+        .chain([("total".to_owned(), &comparison.total)])
+        .map(|(label, row)| {
+            let mut cells = vec![
+                label,
+                format!("{}..{}", row.reference.start, row.reference.end),
+                format!("{:.2}", row.current.consumption_m3),
+                format!("{:.2}", row.reference.consumption_m3),
+                format!("{:+.2}", row.m3.absolute),
+                format!("{:.2}", row.current.consumption_kwh),
+                format!("{:.2}", row.reference.consumption_kwh),
+                format!("{:+.2}", row.kwh.absolute),
+                percent(row.kwh),
+            ];
+            if with_cost {
+                cells.extend([
+                    optional(row.current.cost),
+                    optional(row.reference.cost),
+                    row.cost.map_or_else(
+                        || "-".to_owned(),
+                        |change| format!("{:+.2}", change.absolute),
+                    ),
+                ]);
+            }
+            if with_weather {
+                cells.extend([
+                    optional(row.weather_adjusted_reference_kwh),
+                    row.weather_adjusted_kwh
+                        .map_or_else(|| "-".to_owned(), percent),
+                ]);
+            }
+            cells
+        })
+        .collect::<Vec<_>>();
+
+    render_table(&headers, &rows)
+}
+
+fn percent(change: Change) -> String {
+    change.relative.map_or_else(
+        || "-".to_owned(),
+        |relative| format!("{:+.1}%", relative * 100.0),
+    )
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| format!("{value:.2}"))
+}
+
+fn comparison_to_dataframe(comparison: &Comparison) -> Result<DataFrame, DynError> {
+    let rows = comparison.rows.iter().collect::<Vec<_>>();
+    let text = |f: fn(&ComparisonRow) -> String| rows.iter().map(|row| f(row)).collect::<Vec<_>>();
+    let number = |f: fn(&ComparisonRow) -> f64| rows.iter().map(|row| f(row)).collect::<Vec<_>>();
+    let optional =
+        |f: fn(&ComparisonRow) -> Option<f64>| rows.iter().map(|row| f(row)).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new("start".into(), text(|row| row.current.start.to_string())).into(),
+        Series::new("end".into(), text(|row| row.current.end.to_string())).into(),
+        Series::new(
+            "reference_start".into(),
+            text(|row| row.reference.start.to_string()),
+        )
+        .into(),
+        Series::new(
+            "reference_end".into(),
+            text(|row| row.reference.end.to_string()),
+        )
+        .into(),
+        Series::new("m3".into(), number(|row| row.current.consumption_m3)).into(),
+        Series::new(
+            "reference_m3".into(),
+            number(|row| row.reference.consumption_m3),
+        )
+        .into(),
+        Series::new("kwh".into(), number(|row| row.current.consumption_kwh)).into(),
+        Series::new(
+            "reference_kwh".into(),
+            number(|row| row.reference.consumption_kwh),
+        )
+        .into(),
+        Series::new("change_kwh".into(), number(|row| row.kwh.absolute)).into(),
+        Series::new(
+            "relative_change_kwh".into(),
+            optional(|row| row.kwh.relative),
+        )
+        .into(),
+        Series::new("cost".into(), optional(|row| row.current.cost)).into(),
+        Series::new("reference_cost".into(), optional(|row| row.reference.cost)).into(),
+        Series::new(
+            "adjusted_reference_kwh".into(),
+            optional(|row| row.weather_adjusted_reference_kwh),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::DateRange;
+
+    use super::*;
+
+    fn sample_comparison() -> Comparison {
+        let measurement = |timestamp: String, consumption_kwh: f64| Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp,
+            index_m3: 0.0,
+            consumption_m3: consumption_kwh / 10.0,
+            consumption_kwh,
+        };
+
+        let mut measurements = Vec::new();
+        for day in 1..=28 {
+            measurements.push(measurement(format!("2023-02-{day:02} 00:00:00"), 100.0));
+            measurements.push(measurement(format!("2024-02-{day:02} 00:00:00"), 90.0));
+        }
+
+        let current = DateRange::between("2024-02-01", "2024-02-28").unwrap();
+        comparison::compare(
+            &measurements,
+            current,
+            comparison::same_period_last_year(current).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_comparison_with_total() {
+        let text = render_comparison_text(&sample_comparison());
+
+        assert!(text.contains("2024-02-01..2024-02-28"));
+        assert!(text.contains("2023-02-01..2023-02-28"));
+        assert!(text.contains("-280.00"));
+        assert!(text.contains("-10.0%"));
+        assert!(text.contains("total"));
+        assert!(!text.contains("reference_cost"));
+    }
+
+    #[test]
+    fn exports_comparison_as_csv() {
+        let mut dataframe = comparison_to_dataframe(&sample_comparison()).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert!(csv.starts_with("start,end,reference_start,reference_end,m3,reference_m3,kwh,"));
+        assert!(csv.contains(
+            "2024-02-01,2024-02-28,2023-02-01,2023-02-28,252.0,280.0,2520.0,2800.0,-280.0,-0.1,,,\n"
+        ));
+    }
+}
@@ -0,0 +1,346 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use energiapro::analysis::cost::{self, CostLine, CostReport, Tariff, TariffComparison};
+use energiapro::{Measurement, SystemClock};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, Period, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct CostArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        required = true,
+        help = "Installation identifiers (num_inst) to bill"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(
+        long = "tariff",
+        short = 't',
+        value_name = "FILE",
+        required = true,
+        help = "Tariff definition in TOML; repeat to compare several tariffs"
+    )]
+    tariffs: Vec<PathBuf>,
+    #[arg(long, help = FROM_HELP)]
+    from: Option<String>,
+    #[arg(long, help = TO_HELP)]
+    to: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Period::Month,
+        help = "Period of each cost line"
+    )]
+    period: Period,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: CostArgs) -> Result<(), DynError> {
+    let tariffs = args
+        .tariffs
+        .iter()
+        .map(|path| load_tariff(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for installation_id in &args.installation_ids {
+        measurements.extend(
+            client
+                .measurements
+                .query(&args.client_id, installation_id, args.scope.as_str(), range)
+                .await?,
+        );
+    }
+
+    let bytes = if let [tariff] = tariffs.as_slice() {
+        let report = cost::calculate(&measurements, tariff, args.period.into())?;
+        match args.format {
+            OutputFormat::Text => render_report_text(&report).into_bytes(),
+            OutputFormat::Json => serde_json::to_vec_pretty(&report)?,
+            format => export_dataframe(format, &mut report_to_dataframe(&report)?)?,
+        }
+    } else {
+        let comparisons = cost::compare(&measurements, &tariffs)?;
+        match args.format {
+            OutputFormat::Text => render_comparisons_text(&comparisons).into_bytes(),
+            OutputFormat::Json => serde_json::to_vec_pretty(&comparisons)?,
+            format => export_dataframe(format, &mut comparisons_to_dataframe(&comparisons)?)?,
+        }
+    };
+
+    write_stdout(&bytes)
+}
+
+pub(super) fn load_tariff(path: &Path) -> Result<Tariff, DynError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read tariff {}: {err}", path.display()))?;
+    let tariff: Tariff = toml::from_str(&contents)
+        .map_err(|err| format!("invalid tariff {}: {err}", path.display()))?;
+    tariff.validate()?;
+    Ok(tariff)
+}
+
+fn render_report_text(report: &CostReport) -> String {
+    let mut rows = report
+        .lines
+        .iter()
+        .map(|line| {
+            vec![
+                line.installation_id.clone(),
+                line.start.to_string(),
+                line.end.to_string(),
+                format!("{:.1}", line.consumption_kwh),
+                format!("{:.2}", line.fixed_fee),
+                format!("{:.2}", line.energy),
+                format!("{:.2}", line.network_fee),
+                format!("{:.2}", line.co2_levy),
+                format!("{:.2}", line.net),
+                format!("{:.2}", line.vat),
+                format!("{:.2}", line.total),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    if !rows.is_empty() {
+        let sum = |f: fn(&CostLine) -> f64| report.lines.iter().map(f).sum::<f64>();
+        rows.push(vec![
+            "total".to_owned(),
+            String::new(),
+            String::new(),
+            format!("{:.1}", report.consumption_kwh()),
+            format!("{:.2}", sum(|line| line.fixed_fee)),
+            format!("{:.2}", sum(|line| line.energy)),
+            format!("{:.2}", sum(|line| line.network_fee)),
+            format!("{:.2}", sum(|line| line.co2_levy)),
+            format!("{:.2}", report.net()),
+            format!("{:.2}", report.vat()),
+            format!("{:.2}", report.total()),
+        ]);
+    }
+
+    format!(
+        "Tariff: {}\n\n{}",
+        report.tariff,
+        render_table(
+            &[
+                "installation_id",
+                "start",
+                "end",
+                "kwh",
+                "fixed_fee",
+                "energy",
+                "network_fee",
+                "co2_levy",
+                "net",
+                "vat",
+                "total",
+            ],
+            &rows,
+        )
+    )
+}
+
+fn render_comparisons_text(comparisons: &[TariffComparison]) -> String {
+    let cheapest = comparisons
+        .first()
+        .map_or(0.0, |comparison| comparison.total);
+
+    let rows = comparisons
+        .iter()
+        .map(|comparison| {
+            vec![
+                comparison.tariff.clone(),
+                format!("{:.1}", comparison.consumption_kwh),
+                format!("{:.2}", comparison.net),
+                format!("{:.2}", comparison.vat),
+                format!("{:.2}", comparison.total),
+                format!("{:+.2}", comparison.total - cheapest),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &["tariff", "kwh", "net", "vat", "total", "difference"],
+        &rows,
+    )
+}
+
+fn report_to_dataframe(report: &CostReport) -> Result<DataFrame, DynError> {
+    let amount = |f: fn(&CostLine) -> f64| report.lines.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            report
+                .lines
+                .iter()
+                .map(|line| line.installation_id.clone())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "start".into(),
+            report
+                .lines
+                .iter()
+                .map(|line| line.start.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "end".into(),
+            report
+                .lines
+                .iter()
+                .map(|line| line.end.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new("consumption_m3".into(), amount(|line| line.consumption_m3)).into(),
+        Series::new(
+            "consumption_kwh".into(),
+            amount(|line| line.consumption_kwh),
+        )
+        .into(),
+        Series::new("fixed_fee".into(), amount(|line| line.fixed_fee)).into(),
+        Series::new("energy".into(), amount(|line| line.energy)).into(),
+        Series::new("network_fee".into(), amount(|line| line.network_fee)).into(),
+        Series::new("co2_levy".into(), amount(|line| line.co2_levy)).into(),
+        Series::new("net".into(), amount(|line| line.net)).into(),
+        Series::new("vat".into(), amount(|line| line.vat)).into(),
+        Series::new("total".into(), amount(|line| line.total)).into(),
+    ])?)
+}
+
+fn comparisons_to_dataframe(comparisons: &[TariffComparison]) -> Result<DataFrame, DynError> {
+    let amount = |f: fn(&TariffComparison) -> f64| comparisons.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "tariff".into(),
+            comparisons
+                .iter()
+                .map(|comparison| comparison.tariff.clone())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "consumption_kwh".into(),
+            amount(|comparison| comparison.consumption_kwh),
+        )
+        .into(),
+        Series::new("net".into(), amount(|comparison| comparison.net)).into(),
+        Series::new("vat".into(), amount(|comparison| comparison.vat)).into(),
+        Series::new("total".into(), amount(|comparison| comparison.total)).into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use energiapro::Granularity;
+
+    fn measurements() -> Vec<Measurement> {
+        let measurement = |timestamp: &str, consumption_kwh: f64| Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: timestamp.to_owned(),
+            index_m3: 0.0,
+            consumption_m3: consumption_kwh / 10.0,
+            consumption_kwh,
+        };
+
+        vec![
+            measurement("2024-01-01 00:00:00", 1000.0),
+            measurement("2024-01-31 00:00:00", 1000.0),
+        ]
+    }
+
+    fn tariff(name: &str, monthly_fee: f64, price: f64) -> Tariff {
+        toml::from_str(&format!(
+            r#"
+            name = "{name}"
+
+            [[versions]]
+            valid_from = "2024-01-01"
+            monthly_fee = {monthly_fee}
+            energy_price_per_kwh = {price}
+            vat_rate = 0.1
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_itemized_lines_with_total() {
+        let report = cost::calculate(
+            &measurements(),
+            &tariff("Flat", 31.0, 0.1),
+            Granularity::Month,
+        )
+        .unwrap();
+
+        let text = render_report_text(&report);
+        assert!(text.starts_with("Tariff: Flat\n"));
+        assert!(text.contains("2024-01-01"));
+        assert!(text.contains("254.10"));
+        assert!(text.contains("total"));
+
+        let mut dataframe = report_to_dataframe(&report).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with(
+            "installation_id,start,end,consumption_m3,consumption_kwh,fixed_fee,energy,network_fee,co2_levy,net,vat,total\n"
+        ));
+        assert!(csv.contains("INSTALLATION_ID_1,2024-01-01,2024-01-31,200.0,2000.0,31.0,200.0,"));
+    }
+
+    #[test]
+    fn renders_comparison_from_cheapest() {
+        let comparisons = cost::compare(
+            &measurements(),
+            &[tariff("Flat", 0.0, 0.1), tariff("Subscription", 31.0, 0.08)],
+        )
+        .unwrap();
+
+        let text = render_comparisons_text(&comparisons);
+        let subscription = text.find("Subscription").unwrap();
+        let flat = text.find("Flat").unwrap();
+        assert!(subscription < flat);
+        assert!(text.contains("+9.90"));
+
+        let mut dataframe = comparisons_to_dataframe(&comparisons).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with("tariff,consumption_kwh,net,vat,total\n"));
+        assert!(csv.contains("Subscription,2000.0,191.0,19.1,210.1"));
+    }
+}
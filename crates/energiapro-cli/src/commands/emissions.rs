@@ -0,0 +1,352 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use energiapro::SystemClock;
+use energiapro::analysis::emissions::{
+    self, EmissionFactorVersion, EmissionFactors, EmissionLine, EmissionOptions, EmissionReport,
+    ReportingLevel, ReportingPeriod,
+};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct EmissionsArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        help = "Installation identifiers (num_inst) to report; defaults to every installation of the client"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(long, help = FROM_HELP)]
+    from: Option<String>,
+    #[arg(long, help = TO_HELP)]
+    to: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        required_unless_present = "factor",
+        conflicts_with = "factor",
+        help = "Dated emission factors in TOML"
+    )]
+    factors: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "KG_CO2E_PER_KWH",
+        help = "Emission factor of natural gas applied to the whole period, in kg CO2e/kWh"
+    )]
+    factor: Option<f64>,
+    #[arg(
+        long,
+        requires = "factor",
+        default_value_t = 0.0,
+        help = "Share of biogas in the blend, between 0 and 1, used with --factor"
+    )]
+    biogas_share: f64,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = EmissionPeriod::Quarter,
+        help = "Reporting period of each line"
+    )]
+    period: EmissionPeriod,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = EmissionLevel::Installation,
+        help = "Aggregate per installation or per client"
+    )]
+    by: EmissionLevel,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+enum EmissionPeriod {
+    Quarter,
+    Year,
+}
+
+impl From<EmissionPeriod> for ReportingPeriod {
+    fn from(period: EmissionPeriod) -> Self {
+        match period {
+            EmissionPeriod::Quarter => Self::Quarter,
+            EmissionPeriod::Year => Self::Year,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+enum EmissionLevel {
+    Installation,
+    Client,
+}
+
+impl From<EmissionLevel> for ReportingLevel {
+    fn from(level: EmissionLevel) -> Self {
+        match level {
+            EmissionLevel::Installation => Self::Installation,
+            EmissionLevel::Client => Self::Client,
+        }
+    }
+}
+
+pub(super) async fn run(args: EmissionsArgs) -> Result<(), DynError> {
+    let factors = match (&args.factors, args.factor) {
+        (Some(path), _) => load_factors(path)?,
+        (None, Some(factor)) => {
+            let factors = EmissionFactors::new(
+                "Constant factor",
+                EmissionFactorVersion::new("1900-01-01".parse()?, factor)
+                    .with_biogas_share(args.biogas_share),
+            );
+            factors.validate()?;
+            factors
+        }
+        (None, None) => return Err("either --factors or --factor is required".into()),
+    };
+    let options = EmissionOptions::default()
+        .with_period(args.period.into())
+        .with_level(args.by.into());
+
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+    let installation_ids = if args.installation_ids.is_empty() {
+        client
+            .installations
+            .list(&args.client_id)
+            .await?
+            .into_iter()
+            .map(|installation| installation.id)
+            .collect()
+    } else {
+        args.installation_ids
+    };
+
+    let mut measurements = Vec::new();
+    for installation_id in &installation_ids {
+        measurements.extend(
+            client
+                .measurements
+                .query(&args.client_id, installation_id, args.scope.as_str(), range)
+                .await?,
+        );
+    }
+
+    let report = emissions::calculate_with(&measurements, &factors, &options)?;
+    let bytes = match args.format {
+        OutputFormat::Text => render_report_text(&report).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&report)?,
+        format => export_dataframe(format, &mut report_to_dataframe(&report)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn load_factors(path: &Path) -> Result<EmissionFactors, DynError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read emission factors {}: {err}", path.display()))?;
+    let factors: EmissionFactors = toml::from_str(&contents)
+        .map_err(|err| format!("invalid emission factors {}: {err}", path.display()))?;
+    factors.validate()?;
+    Ok(factors)
+}
+
+fn render_report_text(report: &EmissionReport) -> String {
+    let mut rows = report
+        .lines
+        .iter()
+        .map(|line| {
+            vec![
+                line.client_id.to_string(),
+                line.installation_id
+                    .clone()
+                    .unwrap_or_else(|| "-".to_owned()),
+                line.period_label(),
+                format!("{:.1}", line.consumption_kwh),
+                format!("{:.1}", line.natural_gas_kwh),
+                format!("{:.1}", line.biogas_kwh),
+                format!("{:.3}", line.scope1_t_co2e()),
+                format!("{:.3}", line.biogenic_co2_kg / 1000.0),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    if !rows.is_empty() {
+        let sum = |f: fn(&EmissionLine) -> f64| report.lines.iter().map(f).sum::<f64>();
+        rows.push(vec![
+            "total".to_owned(),
+            String::new(),
+            String::new(),
+            format!("{:.1}", report.consumption_kwh()),
+            format!("{:.1}", sum(|line| line.natural_gas_kwh)),
+            format!("{:.1}", sum(|line| line.biogas_kwh)),
+            format!("{:.3}", report.scope1_t_co2e()),
+            format!("{:.3}", report.biogenic_co2_kg() / 1000.0),
+        ]);
+    }
+
+    format!(
+        "Emission factors: {}\n\n{}",
+        report.factors,
+        render_table(
+            &[
+                "client_id",
+                "installation_id",
+                "period",
+                "kwh",
+                "natural_gas_kwh",
+                "biogas_kwh",
+                "scope1_t_co2e",
+                "biogenic_t_co2",
+            ],
+            &rows,
+        )
+    )
+}
+
+fn report_to_dataframe(report: &EmissionReport) -> Result<DataFrame, DynError> {
+    let lines = &report.lines;
+    let column = |f: fn(&EmissionLine) -> f64| lines.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "client_id".into(),
+            lines.iter().map(|line| line.client_id).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "installation_id".into(),
+            lines
+                .iter()
+                .map(|line| line.installation_id.clone())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "year".into(),
+            lines.iter().map(|line| line.year).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "quarter".into(),
+            lines.iter().map(|line| line.quarter).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "start".into(),
+            lines
+                .iter()
+                .map(|line| line.start.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "end".into(),
+            lines
+                .iter()
+                .map(|line| line.end.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "consumption_kwh".into(),
+            column(|line| line.consumption_kwh),
+        )
+        .into(),
+        Series::new(
+            "natural_gas_kwh".into(),
+            column(|line| line.natural_gas_kwh),
+        )
+        .into(),
+        Series::new("biogas_kwh".into(), column(|line| line.biogas_kwh)).into(),
+        Series::new("scope1_kg_co2e".into(), column(|line| line.scope1_kg_co2e)).into(),
+        Series::new(
+            "biogenic_co2_kg".into(),
+            column(|line| line.biogenic_co2_kg),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::{DateRange, Measurement};
+
+    use super::*;
+
+    fn sample_report(options: &EmissionOptions) -> EmissionReport {
+        let start = DateRange::year(2024).unwrap().start().unwrap();
+        let factors = EmissionFactors::new(
+            "Gas blend",
+            EmissionFactorVersion::new(start, 0.2).with_biogas_share(0.5),
+        );
+
+        let measurements = [
+            ("INSTALLATION_ID_1", "2024-01-15 00:00:00", 1000.0),
+            ("INSTALLATION_ID_1", "2024-07-15 00:00:00", 500.0),
+            ("INSTALLATION_ID_2", "2024-02-15 00:00:00", 2000.0),
+        ]
+        .map(
+            |(installation_id, timestamp, consumption_kwh)| Measurement {
+                client_id: 1,
+                installation_id: installation_id.to_owned(),
+                timestamp: timestamp.to_owned(),
+                index_m3: 0.0,
+                consumption_m3: consumption_kwh / 10.0,
+                consumption_kwh,
+            },
+        );
+
+        emissions::calculate_with(&measurements, &factors, options).unwrap()
+    }
+
+    #[test]
+    fn renders_report_with_total() {
+        let text = render_report_text(&sample_report(&EmissionOptions::default()));
+
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Emission factors: Gas blend");
+        assert!(lines[2].contains("scope1_t_co2e"));
+        assert!(text.contains("2024-Q3"));
+        assert!(lines.last().unwrap().starts_with("total"));
+        assert!(lines.last().unwrap().contains("0.350"));
+    }
+
+    #[test]
+    fn exports_report_as_csv() {
+        let options = EmissionOptions::default()
+            .with_period(ReportingPeriod::Year)
+            .with_level(ReportingLevel::Client);
+        let mut dataframe = report_to_dataframe(&sample_report(&options)).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            csv,
+            "client_id,installation_id,year,quarter,start,end,consumption_kwh,natural_gas_kwh,biogas_kwh,scope1_kg_co2e,biogenic_co2_kg\n\
+             1,,2024,,2024-01-01,2024-12-31,3500.0,1750.0,1750.0,350.0,0.0\n"
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use energiapro::analysis::degree_days::TemperatureSeries;
+use energiapro::analysis::forecast::{self, Forecast, ForecastOptions, YearEndProjection};
+use energiapro::{DateExpression, DateRange, EnergiaProError, SystemClock};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, Period, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct ForecastArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        required = true,
+        help = "Installation identifiers (num_inst) to forecast"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(long, default_value = "-2y", help = FROM_HELP)]
+    from: String,
+    #[arg(long, default_value = "yesterday", help = TO_HELP)]
+    to: String,
+    #[arg(
+        long,
+        default_value = "end-of-year",
+        help = "Last forecast day, in YYYY-MM-DD or relative form; the forecast starts the day after --to"
+    )]
+    until: String,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Period::Month,
+        help = "Period to aggregate the forecast by"
+    )]
+    period: Period,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "CSV of daily mean temperatures (e.g. a MeteoSwiss export) to train a degree day model"
+    )]
+    temperatures: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = ForecastOptions::default().confidence,
+        help = "Confidence level of the prediction intervals (0-1)"
+    )]
+    confidence: f64,
+    #[arg(
+        long,
+        help = "Project the consumption of the calendar year of --to instead of listing periods"
+    )]
+    year_end: bool,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: ForecastArgs) -> Result<(), DynError> {
+    let history = date_range(Some(&args.from), Some(&args.to), &SystemClock)?;
+    let as_of = history
+        .end()
+        .ok_or_else(|| EnergiaProError::InvalidArgument("--to is required".to_owned()))?;
+
+    let mut options = ForecastOptions::default().with_confidence(args.confidence);
+    if let Some(path) = &args.temperatures {
+        options = options.with_temperatures(TemperatureSeries::load(path)?);
+    }
+
+    let client = args.connection.client()?;
+    let mut measurements = Vec::new();
+    for installation_id in &args.installation_ids {
+        measurements.push(
+            client
+                .measurements
+                .query(
+                    &args.client_id,
+                    installation_id,
+                    args.scope.as_str(),
+                    history,
+                )
+                .await?,
+        );
+    }
+
+    if args.year_end {
+        let projections = measurements
+            .iter()
+            .map(|measurements| forecast::project_year_end(measurements, as_of, &options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let bytes = match args.format {
+            OutputFormat::Text => render_projections_text(&projections).into_bytes(),
+            OutputFormat::Json => serde_json::to_vec_pretty(&projections)?,
+            format => export_dataframe(format, &mut projections_to_dataframe(&projections)?)?,
+        };
+        return write_stdout(&bytes);
+    }
+
+    let start = as_of
+        .succ_opt()
+        .ok_or_else(|| EnergiaProError::InvalidArgument("date is out of range".to_owned()))?;
+    let until = DateExpression::parse(&args.until)?.resolve(&SystemClock)?;
+    let horizon = DateRange::between(start, until)?;
+    let forecasts = measurements
+        .iter()
+        .map(|measurements| {
+            forecast::forecast_with(measurements, horizon, args.period.into(), &options)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_forecasts_text(&forecasts).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&forecasts)?,
+        format => export_dataframe(format, &mut forecasts_to_dataframe(&forecasts)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn render_forecasts_text(forecasts: &[Forecast]) -> String {
+    let rows = forecasts
+        .iter()
+        .flat_map(|forecast| {
+            forecast.periods.iter().map(|period| {
+                vec![
+                    forecast.installation_id.clone(),
+                    period.start.to_string(),
+                    period.end.to_string(),
+                    format!("{:.1}", period.predicted_kwh),
+                    format!("{:.1}", period.lower_kwh),
+                    format!("{:.1}", period.upper_kwh),
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &[
+            "installation_id",
+            "start",
+            "end",
+            "predicted_kwh",
+            "lower_kwh",
+            "upper_kwh",
+        ],
+        &rows,
+    )
+}
+
+fn forecasts_to_dataframe(forecasts: &[Forecast]) -> Result<DataFrame, DynError> {
+    let rows = forecasts
+        .iter()
+        .flat_map(|forecast| {
+            forecast
+                .periods
+                .iter()
+                .map(move |period| (forecast.installation_id.as_str(), period))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            rows.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "start".into(),
+            rows.iter()
+                .map(|(_, period)| period.start.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "end".into(),
+            rows.iter()
+                .map(|(_, period)| period.end.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "predicted_kwh".into(),
+            rows.iter()
+                .map(|(_, period)| period.predicted_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "lower_kwh".into(),
+            rows.iter()
+                .map(|(_, period)| period.lower_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "upper_kwh".into(),
+            rows.iter()
+                .map(|(_, period)| period.upper_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+fn render_projections_text(projections: &[YearEndProjection]) -> String {
+    let rows = projections
+        .iter()
+        .map(|projection| {
+            vec![
+                projection.installation_id.clone(),
+                projection.year.to_string(),
+                projection.as_of.to_string(),
+                format!("{:.1}", projection.actual_kwh),
+                format!("{:.1}", projection.forecast_kwh),
+                format!("{:.1}", projection.projected_kwh),
+                format!("{:.1}", projection.lower_kwh),
+                format!("{:.1}", projection.upper_kwh),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &[
+            "installation_id",
+            "year",
+            "as_of",
+            "actual_kwh",
+            "forecast_kwh",
+            "projected_kwh",
+            "lower_kwh",
+            "upper_kwh",
+        ],
+        &rows,
+    )
+}
+
+fn projections_to_dataframe(projections: &[YearEndProjection]) -> Result<DataFrame, DynError> {
+    let number = |f: fn(&YearEndProjection) -> f64| projections.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            projections
+                .iter()
+                .map(|projection| projection.installation_id.clone())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "year".into(),
+            projections
+                .iter()
+                .map(|projection| projection.year)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "as_of".into(),
+            projections
+                .iter()
+                .map(|projection| projection.as_of.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "actual_kwh".into(),
+            number(|projection| projection.actual_kwh),
+        )
+        .into(),
+        Series::new(
+            "forecast_kwh".into(),
+            number(|projection| projection.forecast_kwh),
+        )
+        .into(),
+        Series::new(
+            "projected_kwh".into(),
+            number(|projection| projection.projected_kwh),
+        )
+        .into(),
+        Series::new(
+            "lower_kwh".into(),
+            number(|projection| projection.lower_kwh),
+        )
+        .into(),
+        Series::new(
+            "upper_kwh".into(),
+            number(|projection| projection.upper_kwh),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::{Granularity, Measurement};
+
+    use super::*;
+
+    fn history() -> Vec<Measurement> {
+        (1..=28)
+            .flat_map(|day| {
+                [1, 2].map(|month| Measurement {
+                    client_id: 1,
+                    installation_id: "INSTALLATION_ID_1".to_owned(),
+                    timestamp: format!("2024-{month:02}-{day:02} 00:00:00"),
+                    index_m3: 0.0,
+                    consumption_m3: 10.0,
+                    consumption_kwh: 100.0,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_and_exports_forecasts() {
+        let forecast = forecast::forecast(
+            &history(),
+            DateRange::between("2024-03-01", "2024-04-30").unwrap(),
+            Granularity::Month,
+        )
+        .unwrap();
+
+        let text = render_forecasts_text(std::slice::from_ref(&forecast));
+        assert!(text.contains("2024-04-30"));
+        assert!(text.contains("3000.0"));
+
+        let mut dataframe = forecasts_to_dataframe(&[forecast]).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with("installation_id,start,end,predicted_kwh,lower_kwh,upper_kwh\n"));
+        assert!(csv.contains("INSTALLATION_ID_1,2024-03-01,2024-03-31,3100.0,3100.0,3100.0\n"));
+    }
+
+    #[test]
+    fn renders_and_exports_year_end_projections() {
+        let projection = forecast::project_year_end(
+            &history(),
+            "2024-02-28".parse().unwrap(),
+            &ForecastOptions::default(),
+        )
+        .unwrap();
+
+        let text = render_projections_text(std::slice::from_ref(&projection));
+        assert!(text.contains("5600.0"));
+        assert!(text.contains("36300.0"));
+
+        let mut dataframe = projections_to_dataframe(&[projection]).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+        assert!(csv.starts_with(
+            "installation_id,year,as_of,actual_kwh,forecast_kwh,projected_kwh,lower_kwh,upper_kwh\n"
+        ));
+        assert!(csv.contains("INSTALLATION_ID_1,2024,2024-02-28,5600.0,30700.0,36300.0,"));
+    }
+}
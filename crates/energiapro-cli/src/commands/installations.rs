@@ -1,29 +1,16 @@
-use std::time::Duration;
-
 use clap::Args;
-use energiapro::{ClientOptions, EnergiaPro, Installation};
+use energiapro::Installation;
 use polars::prelude::*;
 
 use crate::DynError;
+use crate::helpers::connection::ConnectionArgs;
 use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
 use crate::helpers::table::render_table;
 
 #[derive(Args, Debug)]
 pub(crate) struct InstallationsArgs {
-    #[arg(
-        long,
-        short = 'u',
-        env = "ENERGIAPRO_USERNAME",
-        help = "EnergiaPro API username"
-    )]
-    username: Option<String>,
-    #[arg(
-        long,
-        short = 'k',
-        env = "ENERGIAPRO_SECRET_KEY",
-        help = "EnergiaPro API secret key"
-    )]
-    secret_key: Option<String>,
+    #[command(flatten)]
+    connection: ConnectionArgs,
     #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
     client_id: String,
     #[arg(
@@ -34,36 +21,10 @@ pub(crate) struct InstallationsArgs {
         help = "Output format"
     )]
     format: OutputFormat,
-    #[arg(long, default_value_t = 30, help = "HTTP timeout in seconds")]
-    timeout_secs: u64,
-    #[arg(
-        long,
-        env = "ENERGIAPRO_BASE_URL",
-        help = "HTTPS base API URL (or ENERGIAPRO_BASE_URL)"
-    )]
-    base_url: Option<String>,
 }
 
 pub(super) async fn run(args: InstallationsArgs) -> Result<(), DynError> {
-    let username = args.username.ok_or_else(|| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "missing username: pass --username or set ENERGIAPRO_USERNAME",
-        )
-    })?;
-    let secret_key = args.secret_key.ok_or_else(|| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "missing secret key: pass --secret-key or set ENERGIAPRO_SECRET_KEY",
-        )
-    })?;
-
-    let mut options = ClientOptions::default().with_timeout(Duration::from_secs(args.timeout_secs));
-    if let Some(base_url) = args.base_url {
-        options = options.with_base_url(base_url);
-    }
-
-    let client = EnergiaPro::with_options(username, secret_key, options)?;
+    let client = args.connection.client()?;
     let installations: Vec<Installation> = client.installations.list(args.client_id).await?;
     let bytes = match args.format {
         OutputFormat::Text => render_installations_text(&installations).into_bytes(),
@@ -0,0 +1,251 @@
+use clap::Args;
+use energiapro::SystemClock;
+use energiapro::analysis::leak::{self, LeakIncident, LeakOptions};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, TO_HELP, date_range};
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct LeaksArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        required = true,
+        help = "Installation identifiers (num_inst) to scan"
+    )]
+    installation_ids: Vec<String>,
+    #[arg(long, help = FROM_HELP)]
+    from: Option<String>,
+    #[arg(long, help = TO_HELP)]
+    to: Option<String>,
+    #[arg(
+        long,
+        default_value_t = LeakOptions::default().threshold_m3_per_hour,
+        help = "Continuous night flow above which a night is suspicious, in m³/h"
+    )]
+    threshold: f64,
+    #[arg(
+        long,
+        default_value_t = LeakOptions::default().min_days,
+        help = "Number of consecutive suspicious nights that make an incident"
+    )]
+    min_days: usize,
+    #[arg(
+        long,
+        default_value_t = LeakOptions::default().night_start_hour,
+        help = "First local hour of the night window"
+    )]
+    night_start: u32,
+    #[arg(
+        long,
+        default_value_t = LeakOptions::default().night_end_hour,
+        help = "Local hour at which the night window ends (exclusive)"
+    )]
+    night_end: u32,
+    #[arg(
+        long,
+        default_value_t = LeakOptions::default().window_hours,
+        help = "Hours within the night over which the flow must stay above the threshold"
+    )]
+    window: u32,
+    #[arg(long, help = "Price of one kWh, to report the wasted cost")]
+    price_per_kwh: Option<f64>,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: LeaksArgs) -> Result<(), DynError> {
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+    let mut options = LeakOptions::default()
+        .with_night(args.night_start, args.night_end)
+        .with_window_hours(args.window)
+        .with_threshold_m3_per_hour(args.threshold)
+        .with_min_days(args.min_days);
+    if let Some(price_per_kwh) = args.price_per_kwh {
+        options = options.with_price_per_kwh(price_per_kwh);
+    }
+
+    let mut incidents = Vec::new();
+    for installation_id in &args.installation_ids {
+        let measurements = client
+            .measurements
+            .query(&args.client_id, installation_id, args.scope.as_str(), range)
+            .await?;
+        incidents.extend(leak::detect_with(&measurements, &options)?);
+    }
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_incidents_text(&incidents).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&incidents)?,
+        format => export_dataframe(format, &mut incidents_to_dataframe(&incidents)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn render_incidents_text(incidents: &[LeakIncident]) -> String {
+    let rows = incidents
+        .iter()
+        .map(|incident| {
+            vec![
+                incident.installation_id.clone(),
+                incident.start.to_string(),
+                if incident.ongoing {
+                    "ongoing".to_owned()
+                } else {
+                    incident.end.to_string()
+                },
+                incident.days.to_string(),
+                format!("{:.3}", incident.min_flow_m3_per_hour),
+                format!("{:.3}", incident.wasted_m3),
+                format!("{:.3}", incident.wasted_kwh),
+                incident
+                    .wasted_cost
+                    .map_or_else(|| "-".to_owned(), |cost| format!("{cost:.2}")),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &[
+            "installation_id",
+            "start",
+            "end",
+            "days",
+            "min_flow_m3_per_hour",
+            "wasted_m3",
+            "wasted_kwh",
+            "wasted_cost",
+        ],
+        &rows,
+    )
+}
+
+fn incidents_to_dataframe(incidents: &[LeakIncident]) -> Result<DataFrame, DynError> {
+    let text = |f: fn(&LeakIncident) -> String| incidents.iter().map(f).collect::<Vec<_>>();
+    let number = |f: fn(&LeakIncident) -> f64| incidents.iter().map(f).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "installation_id".into(),
+            text(|incident| incident.installation_id.clone()),
+        )
+        .into(),
+        Series::new("start".into(), text(|incident| incident.start.to_string())).into(),
+        Series::new("end".into(), text(|incident| incident.end.to_string())).into(),
+        Series::new(
+            "days".into(),
+            incidents
+                .iter()
+                .map(|incident| incident.days as u64)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "min_flow_m3_per_hour".into(),
+            number(|incident| incident.min_flow_m3_per_hour),
+        )
+        .into(),
+        Series::new(
+            "mean_flow_m3_per_hour".into(),
+            number(|incident| incident.mean_flow_m3_per_hour),
+        )
+        .into(),
+        Series::new("wasted_m3".into(), number(|incident| incident.wasted_m3)).into(),
+        Series::new("wasted_kwh".into(), number(|incident| incident.wasted_kwh)).into(),
+        Series::new(
+            "wasted_cost".into(),
+            incidents
+                .iter()
+                .map(|incident| incident.wasted_cost)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "ongoing".into(),
+            incidents
+                .iter()
+                .map(|incident| incident.ongoing)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::Measurement;
+
+    use super::*;
+
+    fn sample_incidents() -> Vec<LeakIncident> {
+        let mut measurements = Vec::new();
+        for day in 1..=10 {
+            for hour in 0..24 {
+                measurements.push(Measurement {
+                    client_id: 1,
+                    installation_id: "INSTALLATION_ID_1".to_owned(),
+                    timestamp: format!("2024-07-{day:02} {hour:02}:00:00"),
+                    index_m3: 0.0,
+                    consumption_m3: if day <= 8 { 0.1 } else { 0.0 },
+                    consumption_kwh: if day <= 8 { 1.0 } else { 0.0 },
+                });
+            }
+        }
+
+        leak::detect_with(
+            &measurements,
+            &LeakOptions::default().with_price_per_kwh(0.1),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_incidents() {
+        let text = render_incidents_text(&sample_incidents());
+
+        assert!(text.contains("INSTALLATION_ID_1"));
+        assert!(text.contains("2024-07-01"));
+        assert!(text.contains("2024-07-08"));
+        assert!(text.contains("19.200"));
+        assert!(text.contains("192.000"));
+    }
+
+    #[test]
+    fn exports_incidents_as_csv() {
+        let mut dataframe = incidents_to_dataframe(&sample_incidents()).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert!(csv.starts_with(
+            "installation_id,start,end,days,min_flow_m3_per_hour,mean_flow_m3_per_hour,wasted_m3,wasted_kwh,wasted_cost,ongoing\n"
+        ));
+        assert!(
+            csv.contains(
+                "INSTALLATION_ID_1,2024-07-01,2024-07-08,8,0.1,0.1,19.2,192.0,19.2,false\n"
+            )
+        );
+    }
+}
@@ -1,29 +1,29 @@
-use std::time::Duration;
-
 use clap::{Args, ValueEnum};
-use energiapro::{ClientOptions, EnergiaPro, Measurement};
+use energiapro::analysis::fill::{self, FillStrategy, FilledMeasurement};
+use energiapro::analysis::meter;
+use energiapro::{DateWindow, EnergiaProError, Measurement, SystemClock};
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
 use polars::prelude::*;
 
 use crate::DynError;
-use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::{FROM_HELP, TO_HELP, date_range};
+use crate::helpers::output::{
+    OutputFormat, export_dataframe, export_dataframe_chunk, write_stdout,
+};
 use crate::helpers::table::render_table;
 
+/// Number of measurements written at once when streaming an open-ended range.
+const STREAM_CHUNK_SIZE: usize = 10_000;
+
+/// Measurements received from the API, one chunk at a time.
+type MeasurementChunks = BoxStream<'static, Result<Vec<Measurement>, EnergiaProError>>;
+
 #[derive(Args, Debug)]
 pub(crate) struct MeasurementsArgs {
-    #[arg(
-        long,
-        short = 'u',
-        env = "ENERGIAPRO_USERNAME",
-        help = "EnergiaPro API username"
-    )]
-    username: Option<String>,
-    #[arg(
-        long,
-        short = 'k',
-        env = "ENERGIAPRO_SECRET_KEY",
-        help = "EnergiaPro API secret key"
-    )]
-    secret_key: Option<String>,
+    #[command(flatten)]
+    connection: ConnectionArgs,
     #[arg(
         long,
         short = 's',
@@ -39,9 +39,9 @@ pub(crate) struct MeasurementsArgs {
         help = "Installation identifier (num_inst)"
     )]
     installation_id: String,
-    #[arg(long, help = "Start date filter in YYYY-MM-DD")]
+    #[arg(long, help = FROM_HELP)]
     from: Option<String>,
-    #[arg(long, help = "End date filter in YYYY-MM-DD")]
+    #[arg(long, help = TO_HELP)]
     to: Option<String>,
     #[arg(
         long,
@@ -51,65 +51,92 @@ pub(crate) struct MeasurementsArgs {
         help = "Output format"
     )]
     format: OutputFormat,
-    #[arg(long, default_value_t = 30, help = "HTTP timeout in seconds")]
-    timeout_secs: u64,
     #[arg(
         long,
-        env = "ENERGIAPRO_BASE_URL",
-        help = "HTTPS base API URL (or ENERGIAPRO_BASE_URL)"
+        help = "Replace index_m3 with a virtual index that stays continuous across meter rollovers and replacements"
     )]
-    base_url: Option<String>,
+    virtual_index: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Insert missing intervals and fill them with the given strategy; adds a `fill` column"
+    )]
+    fill: Option<Fill>,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
-enum Scope {
-    #[value(name = "lpn-json")]
-    LpnJson,
-    #[value(name = "gc-plus-json")]
-    GcPlusJson,
+enum Fill {
+    Null,
+    Zero,
+    Linear,
+    Profile,
 }
 
-impl Scope {
-    fn as_str(self) -> &'static str {
-        match self {
-            Self::LpnJson => "lpn-json",
-            Self::GcPlusJson => "gc-plus-json",
+impl From<Fill> for FillStrategy {
+    fn from(fill: Fill) -> Self {
+        match fill {
+            Fill::Null => Self::Null,
+            Fill::Zero => Self::Zero,
+            Fill::Linear => Self::Linear,
+            Fill::Profile => Self::Profile,
         }
     }
 }
 
 pub(super) async fn run(args: MeasurementsArgs) -> Result<(), DynError> {
     let scope = args.scope.as_str();
-    let username = args.username.ok_or_else(|| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "missing username: pass --username or set ENERGIAPRO_USERNAME",
-        )
-    })?;
-    let secret_key = args.secret_key.ok_or_else(|| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "missing secret key: pass --secret-key or set ENERGIAPRO_SECRET_KEY",
-        )
-    })?;
-
-    let mut options = ClientOptions::default().with_timeout(Duration::from_secs(args.timeout_secs));
-    if let Some(base_url) = args.base_url {
-        options = options.with_base_url(base_url);
+    let client = args.connection.client()?;
+    let range = date_range(args.from.as_deref(), args.to.as_deref(), &SystemClock)?;
+
+    // Ranges with a start are fetched window by window, with retries; the
+    // others can only be fetched with a single request.
+    let chunks: MeasurementChunks = if range.start().is_some() {
+        client
+            .measurements
+            .stream(
+                args.client_id,
+                args.installation_id,
+                scope,
+                range,
+                DateWindow::default(),
+            )?
+            .map_ok(|batch| batch.measurements)
+            .boxed()
+    } else {
+        client
+            .measurements
+            .stream_rows(args.client_id, args.installation_id, scope, range)
+            .await?
+            .try_chunks(STREAM_CHUNK_SIZE)
+            .map_err(|err| err.1)
+            .boxed()
+    };
+
+    // The virtual index and gap filling depend on the whole series, so they cannot be streamed.
+    if args.format.is_streamable() && !args.virtual_index && args.fill.is_none() {
+        return write_measurements_incrementally(args.format, chunks).await;
+    }
+
+    let mut measurements: Vec<Measurement> = chunks.try_concat().await?;
+    measurements.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    if args.virtual_index {
+        measurements = meter::analyze(&measurements)?.apply(&measurements);
+    }
+
+    if let Some(strategy) = args.fill {
+        let rows = fill::fill(&measurements, strategy.into())?;
+        let bytes = match args.format {
+            OutputFormat::Text => render_filled_text(&rows).into_bytes(),
+            format => {
+                let mut dataframe = filled_to_dataframe(&rows)?;
+                export_dataframe(format, &mut dataframe)?
+            }
+        };
+
+        write_stdout(&bytes)?;
+        return Ok(());
     }
 
-    let client = EnergiaPro::with_options(username, secret_key, options)?;
-
-    let measurements: Vec<Measurement> = client
-        .measurements
-        .get(
-            args.client_id,
-            args.installation_id,
-            scope,
-            args.from,
-            args.to,
-        )
-        .await?;
     let bytes = match args.format {
         OutputFormat::Text => render_measurements_text(&measurements).into_bytes(),
         format => {
@@ -122,6 +149,35 @@ pub(super) async fn run(args: MeasurementsArgs) -> Result<(), DynError> {
     Ok(())
 }
 
+/// Write measurements chunk by chunk as they are received from the API.
+async fn write_measurements_incrementally(
+    format: OutputFormat,
+    mut chunks: MeasurementChunks,
+) -> Result<(), DynError> {
+    let mut include_header = true;
+
+    while let Some(chunk) = chunks.try_next().await? {
+        if chunk.is_empty() {
+            continue;
+        }
+
+        let mut dataframe = measurements_to_dataframe(&chunk)?;
+        write_stdout(&export_dataframe_chunk(
+            format,
+            &mut dataframe,
+            include_header,
+        )?)?;
+        include_header = false;
+    }
+
+    if include_header {
+        let mut dataframe = measurements_to_dataframe(&[])?;
+        write_stdout(&export_dataframe(format, &mut dataframe)?)?;
+    }
+
+    Ok(())
+}
+
 fn render_measurements_text(measurements: &[Measurement]) -> String {
     let rows = measurements
         .iter()
@@ -181,6 +237,60 @@ fn measurements_to_dataframe(measurements: &[Measurement]) -> Result<DataFrame,
     ])?)
 }
 
+fn render_filled_text(rows: &[FilledMeasurement]) -> String {
+    let optional = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+    let rows = rows
+        .iter()
+        .map(|row| {
+            vec![
+                row.client_id.to_string(),
+                row.installation_id.clone(),
+                row.timestamp.clone(),
+                optional(row.index_m3),
+                optional(row.consumption_m3),
+                optional(row.consumption_kwh),
+                row.flag.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    render_table(
+        &[
+            "client_id",
+            "installation_id",
+            "timestamp",
+            "index_m3",
+            "consumption_m3",
+            "consumption_kwh",
+            "fill",
+        ],
+        &rows,
+    )
+}
+
+fn filled_to_dataframe(rows: &[FilledMeasurement]) -> Result<DataFrame, DynError> {
+    let client_ids = rows.iter().map(|r| r.client_id).collect::<Vec<_>>();
+    let installation_ids = rows
+        .iter()
+        .map(|r| r.installation_id.clone())
+        .collect::<Vec<_>>();
+    let timestamps = rows.iter().map(|r| r.timestamp.clone()).collect::<Vec<_>>();
+    let index_m3 = rows.iter().map(|r| r.index_m3).collect::<Vec<_>>();
+    let consumption_m3 = rows.iter().map(|r| r.consumption_m3).collect::<Vec<_>>();
+    let consumption_kwh = rows.iter().map(|r| r.consumption_kwh).collect::<Vec<_>>();
+    let fills = rows.iter().map(|r| r.flag.as_str()).collect::<Vec<_>>();
+
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new("client_id".into(), client_ids).into(),
+        Series::new("installation_id".into(), installation_ids).into(),
+        Series::new("timestamp".into(), timestamps).into(),
+        Series::new("index_m3".into(), index_m3).into(),
+        Series::new("consumption_m3".into(), consumption_m3).into(),
+        Series::new("consumption_kwh".into(), consumption_kwh).into(),
+        Series::new("fill".into(), fills).into(),
+    ])?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +367,46 @@ mod tests {
         assert!(!parquet.is_empty());
     }
 
+    #[test]
+    fn exports_csv_chunks_with_a_single_header() {
+        let measurements = sample_measurements();
+        let mut first = measurements_to_dataframe(&measurements[..1]).unwrap();
+        let mut second = measurements_to_dataframe(&measurements[1..]).unwrap();
+
+        let mut csv = export_dataframe_chunk(OutputFormat::Csv, &mut first, true).unwrap();
+        csv.extend(export_dataframe_chunk(OutputFormat::Csv, &mut second, false).unwrap());
+        let csv = String::from_utf8(csv).unwrap();
+
+        assert_eq!(csv.matches("client_id,").count(), 1);
+        assert_eq!(csv.lines().count(), 3);
+    }
+
+    #[test]
+    fn exports_filled_rows_with_flag_column() {
+        let mut measurements = sample_measurements();
+        measurements[1].installation_id = "INSTALLATION_ID_1".to_owned();
+        measurements[1].timestamp = "2024-04-01 18:00:00".to_owned();
+        measurements.insert(
+            1,
+            Measurement {
+                timestamp: "2024-04-01 16:00:00".to_owned(),
+                ..measurements[0].clone()
+            },
+        );
+
+        let rows = fill::fill(&measurements, FillStrategy::Null).unwrap();
+        let mut dataframe = filled_to_dataframe(&rows).unwrap();
+        let csv = export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        assert!(csv.starts_with(
+            "client_id,installation_id,timestamp,index_m3,consumption_m3,consumption_kwh,fill\n"
+        ));
+        assert!(csv.contains("1,INSTALLATION_ID_1,2024-04-01 17:00:00,,,,null"));
+        assert!(csv.contains("2024-04-01 18:00:00,145595.0,89.3,924.8,measured"));
+        assert!(render_filled_text(&rows).contains("null"));
+    }
+
     #[test]
     fn exports_jsonl_from_dataframe() {
         let measurements = sample_measurements();
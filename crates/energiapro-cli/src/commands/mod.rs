@@ -2,8 +2,17 @@ use clap::Subcommand;
 
 use crate::DynError;
 
+mod anomalies;
+mod benchmark;
+mod check;
+mod compare;
+mod cost;
+mod emissions;
+mod forecast;
 mod installations;
+mod leaks;
 mod measurements;
+mod savings;
 
 #[derive(Subcommand, Debug)]
 pub(crate) enum Commands {
@@ -17,6 +26,51 @@ pub(crate) enum Commands {
         long_about = "Fetch measurements for one installation and write formatted output to stdout. The API response must be a top-level JSON array of objects so it can be loaded into a Polars DataFrame."
     )]
     Measurements(measurements::MeasurementsArgs),
+    #[command(
+        about = "Check the quality of measurements for one or more installations",
+        long_about = "Fetch measurements for one or more installations and report their interval resolution, missing intervals, duplicate timestamps, negative or implausible consumption, meter index regressions and index deltas that disagree with the reported consumption, together with a quality score from 0 to 100."
+    )]
+    Check(check::CheckArgs),
+    #[command(
+        about = "Calculate gas costs for one or more installations from tariff definitions",
+        long_about = "Fetch measurements for one or more installations and compute itemized costs per installation and period (fixed fee, energy, network fee, CO2 levy and VAT) from a tariff defined in TOML. When several tariffs are given, compare their totals on the same consumption, cheapest first."
+    )]
+    Cost(cost::CostArgs),
+    #[command(
+        about = "Measure and verify retrofit savings for one installation (IPMVP Option C)",
+        long_about = "Fetch measurements of a pre-retrofit baseline period and a post-retrofit reporting period, fit a baseline model on daily consumption (an energy signature when a temperature file is given, the mean daily consumption otherwise), project it onto the reporting period and report the avoided energy and cost with their uncertainty."
+    )]
+    Savings(savings::SavingsArgs),
+    #[command(
+        about = "List consumption anomalies for one or more installations",
+        long_about = "Fetch measurements for one or more installations and list intervals whose consumption deviates strongly from comparable intervals of the same season, weekday and hour, using robust z-scores around seasonal medians. Each anomaly shows the observed and expected consumption and a severity."
+    )]
+    Anomalies(anomalies::AnomaliesArgs),
+    #[command(
+        about = "Detect continuous gas flows such as leaks or pilot flames",
+        long_about = "Fetch measurements for one or more installations, compute the minimum flow of every night and report incidents where it stays above a threshold for several consecutive nights, as caused by a gas leak or a pilot flame left burning. Each incident shows its start, its duration and the estimated wasted volume, energy and cost."
+    )]
+    Leaks(leaks::LeaksArgs),
+    #[command(
+        about = "Forecast gas consumption for one or more installations",
+        long_about = "Fetch historical measurements for one or more installations, train a seasonal model on daily consumption (a degree day model when a temperature file is given) and forecast the consumption of each period until the given date, with prediction intervals. With --year-end, combine the actual consumption since January 1st with the forecast to project the consumption of the whole year."
+    )]
+    Forecast(forecast::ForecastArgs),
+    #[command(
+        about = "Compare consumption of an installation between two periods",
+        long_about = "Fetch measurements of an installation for a period and a reference period (by default the same period last year), align both by calendar position and report the absolute and relative differences in volume, energy and, with a tariff, cost for each month or other period. With a temperature file, the reference consumption is also adjusted to the weather of the current period."
+    )]
+    Compare(compare::CompareArgs),
+    #[command(
+        about = "Rank installations by energy intensity per m²",
+        long_about = "Fetch one year of measurements for the installations of a client, divide their consumption by the energy reference area from a building registry (TOML or CSV) and rank them by energy intensity in kWh/m²·a. With a temperature file, the consumption is normalized to the reference climate; with target classes, each installation is assigned a class such as the A to G scale derived from --reference-kwh-per-m2."
+    )]
+    Benchmark(benchmark::BenchmarkArgs),
+    #[command(
+        about = "Report scope-1 CO2e emissions per installation or client",
+        long_about = "Fetch measurements for the installations of a client and convert their consumption into scope-1 CO2e emissions with dated emission factors from a TOML file, or a single --factor with an optional --biogas-share. Emissions are reported per installation or client and per calendar quarter or year in a GHG Protocol style table, with biogenic CO2 from biogas reported separately, outside the scopes."
+    )]
+    Emissions(emissions::EmissionsArgs),
 }
 
 impl Commands {
@@ -24,6 +78,15 @@ impl Commands {
         match self {
             Self::Installations(args) => installations::run(args).await,
             Self::Measurements(args) => measurements::run(args).await,
+            Self::Check(args) => check::run(args).await,
+            Self::Cost(args) => cost::run(args).await,
+            Self::Savings(args) => savings::run(args).await,
+            Self::Anomalies(args) => anomalies::run(args).await,
+            Self::Leaks(args) => leaks::run(args).await,
+            Self::Forecast(args) => forecast::run(args).await,
+            Self::Compare(args) => compare::run(args).await,
+            Self::Benchmark(args) => benchmark::run(args).await,
+            Self::Emissions(args) => emissions::run(args).await,
         }
     }
 }
@@ -0,0 +1,283 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use energiapro::analysis::degree_days::TemperatureSeries;
+use energiapro::analysis::savings::{self, BaselineModel, SavingsOptions, SavingsReport};
+use energiapro::{Measurement, SystemClock};
+use polars::prelude::*;
+
+use crate::DynError;
+use crate::helpers::connection::{ConnectionArgs, Scope};
+use crate::helpers::dates::date_range;
+use crate::helpers::output::{OutputFormat, export_dataframe, write_stdout};
+use crate::helpers::table::render_table;
+
+#[derive(Args, Debug)]
+pub(crate) struct SavingsArgs {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[arg(
+        long,
+        short = 's',
+        value_enum,
+        default_value_t = Scope::LpnJson,
+        help = "Scope to query"
+    )]
+    scope: Scope,
+    #[arg(value_name = "CLIENT_ID", help = "EnergiaPro client identifier")]
+    client_id: String,
+    #[arg(
+        value_name = "INSTALLATION_ID",
+        help = "Installation identifier (num_inst)"
+    )]
+    installation_id: String,
+    #[arg(long, help = "First day of the pre-retrofit baseline period")]
+    baseline_from: String,
+    #[arg(long, help = "Last day of the pre-retrofit baseline period")]
+    baseline_to: String,
+    #[arg(long, help = "First day of the post-retrofit reporting period")]
+    reporting_from: String,
+    #[arg(long, help = "Last day of the post-retrofit reporting period")]
+    reporting_to: String,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "CSV of daily mean temperatures (e.g. a MeteoSwiss export) to adjust the baseline to the weather"
+    )]
+    temperatures: Option<PathBuf>,
+    #[arg(long, help = "Price of one avoided kWh, to report the avoided cost")]
+    price_per_kwh: Option<f64>,
+    #[arg(
+        long,
+        default_value_t = SavingsOptions::default().confidence,
+        help = "Confidence level of the uncertainty (0-1)"
+    )]
+    confidence: f64,
+    #[arg(
+        long,
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format; tabular formats export one row per reporting day"
+    )]
+    format: OutputFormat,
+}
+
+pub(super) async fn run(args: SavingsArgs) -> Result<(), DynError> {
+    let baseline = date_range(
+        Some(&args.baseline_from),
+        Some(&args.baseline_to),
+        &SystemClock,
+    )?;
+    let reporting = date_range(
+        Some(&args.reporting_from),
+        Some(&args.reporting_to),
+        &SystemClock,
+    )?;
+
+    let mut options = SavingsOptions::default().with_confidence(args.confidence);
+    if let Some(path) = &args.temperatures {
+        options = options.with_temperatures(TemperatureSeries::load(path)?);
+    }
+    if let Some(price_per_kwh) = args.price_per_kwh {
+        options = options.with_price_per_kwh(price_per_kwh);
+    }
+
+    let client = args.connection.client()?;
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for range in [baseline, reporting] {
+        measurements.extend(
+            client
+                .measurements
+                .query(
+                    &args.client_id,
+                    &args.installation_id,
+                    args.scope.as_str(),
+                    range,
+                )
+                .await?,
+        );
+    }
+
+    let report = savings::estimate_with(&measurements, baseline, reporting, &options)?;
+
+    let bytes = match args.format {
+        OutputFormat::Text => render_report_text(&report).into_bytes(),
+        OutputFormat::Json => serde_json::to_vec_pretty(&report)?,
+        format => export_dataframe(format, &mut days_to_dataframe(&report)?)?,
+    };
+
+    write_stdout(&bytes)
+}
+
+fn render_report_text(report: &SavingsReport) -> String {
+    let model = match report.model {
+        BaselineModel::Mean { kwh_per_day } => format!("mean, {kwh_per_day:.1} kWh/day"),
+        BaselineModel::Signature {
+            base_load_kwh_per_day,
+            slope_kwh_per_degree,
+            balance_celsius,
+        } => format!(
+            "signature, {base_load_kwh_per_day:.1} kWh/day + {slope_kwh_per_degree:.2} kWh/°C below {balance_celsius:.1} °C"
+        ),
+    };
+    let confidence = (report.confidence * 1_000.0).round() / 10.0;
+
+    let mut rows = vec![
+        vec!["installation_id".to_owned(), report.installation_id.clone()],
+        vec!["baseline model".to_owned(), model],
+        vec!["baseline days".to_owned(), report.baseline_days.to_string()],
+        vec![
+            "reporting days".to_owned(),
+            report.reporting_days.to_string(),
+        ],
+        vec![
+            "r_squared".to_owned(),
+            report
+                .r_squared
+                .map_or_else(|| "-".to_owned(), |r_squared| format!("{r_squared:.3}")),
+        ],
+        vec![
+            "cv(rmse)".to_owned(),
+            report.cv_rmse.map_or_else(
+                || "-".to_owned(),
+                |cv_rmse| format!("{:.1}%", cv_rmse * 100.0),
+            ),
+        ],
+        vec![
+            "adjusted baseline".to_owned(),
+            format!("{:.1} kWh", report.baseline_kwh),
+        ],
+        vec!["actual".to_owned(), format!("{:.1} kWh", report.actual_kwh)],
+        vec![
+            "avoided energy".to_owned(),
+            format!(
+                "{:.1} ± {:.1} kWh ({confidence}% confidence)",
+                report.avoided_kwh, report.uncertainty_kwh
+            ),
+        ],
+        vec![
+            "savings".to_owned(),
+            report.savings_fraction.map_or_else(
+                || "-".to_owned(),
+                |fraction| format!("{:.1}%", fraction * 100.0),
+            ),
+        ],
+    ];
+    if let (Some(avoided_cost), Some(cost_uncertainty)) =
+        (report.avoided_cost, report.cost_uncertainty)
+    {
+        rows.push(vec![
+            "avoided cost".to_owned(),
+            format!("{avoided_cost:.2} ± {cost_uncertainty:.2}"),
+        ]);
+    }
+    rows.push(vec![
+        "significant".to_owned(),
+        if report.is_significant() { "yes" } else { "no" }.to_owned(),
+    ]);
+
+    render_table(&["metric", "value"], &rows)
+}
+
+fn days_to_dataframe(report: &SavingsReport) -> Result<DataFrame, DynError> {
+    Ok(DataFrame::new_infer_height(vec![
+        Series::new(
+            "date".into(),
+            report
+                .days
+                .iter()
+                .map(|day| day.date.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "mean_celsius".into(),
+            report
+                .days
+                .iter()
+                .map(|day| day.mean_celsius)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "baseline_kwh".into(),
+            report
+                .days
+                .iter()
+                .map(|day| day.baseline_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "actual_kwh".into(),
+            report
+                .days
+                .iter()
+                .map(|day| day.actual_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "avoided_kwh".into(),
+            report
+                .days
+                .iter()
+                .map(|day| day.avoided_kwh)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use energiapro::DateRange;
+
+    use super::*;
+
+    fn sample_report() -> SavingsReport {
+        let measurement = |timestamp: String, consumption_kwh: f64| Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp,
+            index_m3: 0.0,
+            consumption_m3: consumption_kwh / 10.0,
+            consumption_kwh,
+        };
+
+        let mut measurements = Vec::new();
+        for day in 1..=28 {
+            measurements.push(measurement(format!("2023-02-{day:02} 00:00:00"), 100.0));
+            measurements.push(measurement(format!("2024-02-{day:02} 00:00:00"), 80.0));
+        }
+
+        savings::estimate_with(
+            &measurements,
+            DateRange::between("2023-02-01", "2023-02-28").unwrap(),
+            DateRange::between("2024-02-01", "2024-02-28").unwrap(),
+            &SavingsOptions::default().with_price_per_kwh(0.15),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_summary() {
+        let text = render_report_text(&sample_report());
+
+        assert!(text.contains("mean, 100.0 kWh/day"));
+        assert!(text.contains("560.0 ± 0.0 kWh (90% confidence)"));
+        assert!(text.contains("20.0%"));
+        assert!(text.contains("84.00 ± 0.00"));
+    }
+
+    #[test]
+    fn exports_reporting_days_as_csv() {
+        let mut dataframe = days_to_dataframe(&sample_report()).unwrap();
+        let csv = String::from_utf8(export_dataframe(OutputFormat::Csv, &mut dataframe).unwrap())
+            .unwrap();
+
+        assert!(csv.starts_with("date,mean_celsius,baseline_kwh,actual_kwh,avoided_kwh\n"));
+        assert!(csv.contains("2024-02-01,,100.0,80.0,20.0\n"));
+    }
+}
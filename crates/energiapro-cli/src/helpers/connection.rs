@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use energiapro::{ClientOptions, EnergiaPro};
+
+use crate::DynError;
+
+/// Credentials and networking flags shared by every command talking to the API.
+#[derive(Args, Debug)]
+pub(crate) struct ConnectionArgs {
+    #[arg(
+        long,
+        short = 'u',
+        env = "ENERGIAPRO_USERNAME",
+        help = "EnergiaPro API username"
+    )]
+    username: Option<String>,
+    #[arg(
+        long,
+        short = 'k',
+        env = "ENERGIAPRO_SECRET_KEY",
+        help = "EnergiaPro API secret key"
+    )]
+    secret_key: Option<String>,
+    #[arg(long, default_value_t = 30, help = "HTTP timeout in seconds")]
+    timeout_secs: u64,
+    #[arg(
+        long,
+        env = "ENERGIAPRO_BASE_URL",
+        help = "HTTPS base API URL (or ENERGIAPRO_BASE_URL)"
+    )]
+    base_url: Option<String>,
+}
+
+impl ConnectionArgs {
+    /// Build an API client from the connection flags.
+    pub(crate) fn client(self) -> Result<EnergiaPro, DynError> {
+        let username = self.username.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "missing username: pass --username or set ENERGIAPRO_USERNAME",
+            )
+        })?;
+        let secret_key = self.secret_key.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "missing secret key: pass --secret-key or set ENERGIAPRO_SECRET_KEY",
+            )
+        })?;
+
+        let mut options =
+            ClientOptions::default().with_timeout(Duration::from_secs(self.timeout_secs));
+        if let Some(base_url) = self.base_url {
+            options = options.with_base_url(base_url);
+        }
+
+        Ok(EnergiaPro::with_options(username, secret_key, options)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub(crate) enum Scope {
+    #[value(name = "lpn-json")]
+    LpnJson,
+    #[value(name = "gc-plus-json")]
+    GcPlusJson,
+}
+
+impl Scope {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::LpnJson => "lpn-json",
+            Self::GcPlusJson => "gc-plus-json",
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use energiapro::{Clock, DateExpression, DateRange, EnergiaProError, Granularity};
+
+/// Help text shared by `--from` flags.
+pub(crate) const FROM_HELP: &str = "Start date in YYYY-MM-DD or relative form (today, yesterday, -30d, start-of-month, last-year, ...)";
+
+/// Help text shared by `--to` flags.
+pub(crate) const TO_HELP: &str =
+    "End date in YYYY-MM-DD or relative form (today, yesterday, -30d, end-of-month, ...)";
+
+/// Build a [`DateRange`] from optional `--from` and `--to` flags.
+///
+/// Each flag accepts an absolute date or a relative [`DateExpression`],
+/// resolved against `clock`.
+pub(crate) fn date_range(
+    from: Option<&str>,
+    to: Option<&str>,
+    clock: &impl Clock,
+) -> Result<DateRange, EnergiaProError> {
+    let from = from
+        .map(|from| DateExpression::parse(from)?.resolve(clock))
+        .transpose()?;
+    let to = to
+        .map(|to| DateExpression::parse(to)?.resolve(clock))
+        .transpose()?;
+
+    match (from, to) {
+        (Some(from), Some(to)) => DateRange::between(from, to),
+        (Some(from), None) => DateRange::since(from),
+        (None, Some(to)) => DateRange::until(to),
+        (None, None) => Ok(DateRange::all()),
+    }
+}
+
+/// Calendar period accepted by `--period` flags.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub(crate) enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+    HeatingSeason,
+}
+
+impl From<Period> for Granularity {
+    fn from(period: Period) -> Self {
+        match period {
+            Period::Day => Self::Day,
+            Period::Week => Self::Week,
+            Period::Month => Self::Month,
+            Period::Year => Self::Year,
+            Period::HeatingSeason => Self::HeatingSeason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use energiapro::FixedClock;
+
+    fn clock() -> FixedClock {
+        FixedClock::new("2024-03-15".parse().unwrap())
+    }
+
+    #[test]
+    fn builds_range_from_optional_flags() {
+        let range = date_range(Some("2024-01-01"), None, &clock()).unwrap();
+        assert_eq!(range.to_string(), "2024-01-01..");
+
+        let range = date_range(None, None, &clock()).unwrap();
+        assert_eq!(range, DateRange::all());
+
+        assert!(date_range(Some("2024-02-01"), Some("2024-01-01"), &clock()).is_err());
+    }
+
+    #[test]
+    fn resolves_relative_flags() {
+        let range = date_range(Some("last-month"), Some("yesterday"), &clock()).unwrap();
+        assert_eq!(range.to_string(), "2024-02-01..=2024-03-14");
+
+        let range = date_range(Some("-7d"), None, &clock()).unwrap();
+        assert_eq!(range.to_string(), "2024-03-08..");
+    }
+
+    #[test]
+    fn rejects_unknown_expressions() {
+        assert!(date_range(Some("next-week"), None, &clock()).is_err());
+    }
+}
@@ -1,2 +1,4 @@
+pub(crate) mod connection;
+pub(crate) mod dates;
 pub(crate) mod output;
 pub(crate) mod table;
@@ -17,6 +17,18 @@ pub(crate) enum OutputFormat {
 pub(crate) fn export_dataframe(
     format: OutputFormat,
     dataframe: &mut DataFrame,
+) -> Result<Vec<u8>, DynError> {
+    export_dataframe_chunk(format, dataframe, true)
+}
+
+/// Export one chunk of a larger output that is written incrementally.
+///
+/// Only `csv` and `jsonl` can be concatenated chunk by chunk. For `csv`, the
+/// header is only written when `include_header` is set.
+pub(crate) fn export_dataframe_chunk(
+    format: OutputFormat,
+    dataframe: &mut DataFrame,
+    include_header: bool,
 ) -> Result<Vec<u8>, DynError> {
     let mut bytes = Vec::new();
 
@@ -34,7 +46,7 @@ pub(crate) fn export_dataframe(
         }
         OutputFormat::Csv => {
             CsvWriter::new(&mut bytes)
-                .include_header(true)
+                .include_header(include_header)
                 .finish(dataframe)?;
         }
         OutputFormat::Parquet => {
@@ -45,6 +57,13 @@ pub(crate) fn export_dataframe(
     Ok(bytes)
 }
 
+impl OutputFormat {
+    /// Return `true` if the format can be written incrementally in chunks.
+    pub(crate) fn is_streamable(self) -> bool {
+        matches!(self, Self::Csv | Self::Jsonl)
+    }
+}
+
 pub(crate) fn write_stdout(bytes: &[u8]) -> Result<(), DynError> {
     let mut stdout = std::io::stdout().lock();
     stdout.write_all(bytes)?;
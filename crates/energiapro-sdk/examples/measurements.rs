@@ -1,10 +1,14 @@
-use energiapro::{EnergiaPro, MeasurementScope};
+use energiapro::{DateRange, DateWindow, EnergiaPro, MeasurementScope};
+use futures_util::TryStreamExt;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     fetch_all_measurements().await?;
     fetch_measurements_for_date().await?;
     fetch_measurements_with_date_range().await?;
+    stream_all_measurements().await?;
+    backfill_measurements_by_month().await?;
+    query_measurements_for_heating_season().await?;
     Ok(())
 }
 
@@ -75,3 +79,81 @@ async fn fetch_measurements_with_date_range() -> Result<(), Box<dyn std::error::
 
     Ok(())
 }
+
+/// Example function demonstrating how to process measurements as they are received.
+async fn stream_all_measurements() -> Result<(), Box<dyn std::error::Error>> {
+    // Create a new EnergiaPro client
+    let energiapro = EnergiaPro::new("<USERNAME>", "<SECRET_KEY>")?;
+
+    // Open a stream over all measurements of the installation
+    let mut measurements = energiapro
+        .measurements
+        .stream_rows(
+            "client-id",
+            "installation-id",
+            MeasurementScope::LpnJson,
+            DateRange::all(),
+        )
+        .await?;
+
+    // Process each measurement without keeping the whole dataset in memory
+    let mut total_kwh = 0.0;
+    while let Some(measurement) = measurements.try_next().await? {
+        total_kwh += measurement.consumption_kwh;
+    }
+
+    println!("consumed {total_kwh} kWh overall");
+
+    Ok(())
+}
+
+/// Example function demonstrating how to backfill a long date range one month at a time.
+async fn backfill_measurements_by_month() -> Result<(), Box<dyn std::error::Error>> {
+    // Create a new EnergiaPro client
+    let energiapro = EnergiaPro::new("<USERNAME>", "<SECRET_KEY>")?;
+
+    // Stream one batch of measurements per month
+    let mut batches = energiapro.measurements.stream(
+        "client-id",
+        "installation-id",
+        MeasurementScope::LpnJson,
+        DateRange::between("2021-01-01", "2024-12-31")?,
+        DateWindow::Month,
+    )?;
+
+    // Store each batch and checkpoint its window before moving to the next one
+    while let Some(batch) = batches.try_next().await? {
+        println!(
+            "stored {} measurements, resume after {}",
+            batch.measurements.len(),
+            batch.to
+        );
+    }
+
+    Ok(())
+}
+
+/// Example function demonstrating how to query measurements with a calendar range.
+async fn query_measurements_for_heating_season() -> Result<(), Box<dyn std::error::Error>> {
+    // Create a new EnergiaPro client
+    let energiapro = EnergiaPro::new("<USERNAME>", "<SECRET_KEY>")?;
+
+    // Fetch measurements for the 2023/2024 heating season
+    let measurements = energiapro
+        .measurements
+        .query(
+            "client-id",
+            "installation-id",
+            MeasurementScope::LpnJson,
+            DateRange::heating_season(2023)?,
+        )
+        .await?;
+
+    // Print out some details about the retrieved measurements
+    println!(
+        "retrieved {} measurements for the 2023/2024 heating season",
+        measurements.len()
+    );
+
+    Ok(())
+}
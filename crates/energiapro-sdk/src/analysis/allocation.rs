@@ -0,0 +1,837 @@
+//! Heating cost allocation between the tenants of a building.
+//!
+//! Buildings with central heating split the heating costs of a billing period
+//! between their units, as required by consumption-based heating cost
+//! billing (VHKA). The costs are divided into:
+//!
+//! - a fixed part, typically 30 to 50%, allocated by a fixed key such as the
+//!   heated area or volume of each unit;
+//! - a variable part, allocated by the sub-meter readings or heat cost
+//!   allocator units of each unit.
+//!
+//! [`allocate`] produces one [`TenantStatement`] per unit. Amounts are split
+//! to the cent with the largest remainder method, so the statements always
+//! add up to the total cost. Allocations export to CSV with
+//! [`Allocation::to_csv`] and to one HTML statement per tenant with
+//! [`Allocation::statement_html`].
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::analysis::allocation::{self, AllocationOptions, TenantUnit};
+//! use energiapro::{DateRange, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurements = [Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: "2024-01-15 00:00:00".to_owned(),
+//!     index_m3: 0.0,
+//!     consumption_m3: 1000.0,
+//!     consumption_kwh: 10_000.0,
+//! }];
+//! let units = [
+//!     TenantUnit::new("1.01", "Tenant A").with_area_m2(60.0).with_meter_units(300.0),
+//!     TenantUnit::new("1.02", "Tenant B").with_area_m2(40.0).with_meter_units(100.0),
+//! ];
+//!
+//! let allocation = allocation::allocate(
+//!     &measurements,
+//!     DateRange::between("2024-01-01", "2024-01-31")?,
+//!     1_000.0,
+//!     &units,
+//!     &AllocationOptions::default(),
+//! )?;
+//!
+//! // 30% by area, 70% by meter units.
+//! assert_eq!(allocation.statements[0].fixed_cost, 180.0);
+//! assert_eq!(allocation.statements[0].variable_cost, 525.0);
+//! assert_eq!(allocation.statements[1].total_cost, 295.0);
+//! assert!(allocation.statement_html(&allocation.statements[1]).contains("Tenant B"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{csv_column, csv_reader, round};
+use crate::errors::EnergiaProError;
+use crate::models::Measurement;
+use crate::types::DateRange;
+
+/// Header names recognized as the unit column, compared case-insensitively.
+const UNIT_COLUMNS: &[&str] = &["unit_id", "unit", "apartment"];
+
+/// Header names recognized as the tenant column.
+const TENANT_COLUMNS: &[&str] = &["tenant", "name"];
+
+/// Header names recognized as the area column.
+const AREA_COLUMNS: &[&str] = &["area_m2", "area"];
+
+/// Header names recognized as the volume column.
+const VOLUME_COLUMNS: &[&str] = &["volume_m3", "volume"];
+
+/// Header names recognized as the meter units column.
+const METER_COLUMNS: &[&str] = &["meter_units", "meter", "units", "reading"];
+
+/// Header names recognized as the columns of meter readings at the start and
+/// end of the period, used when no meter units column is present.
+const METER_START_COLUMNS: &[&str] = &["meter_start", "reading_start", "start"];
+const METER_END_COLUMNS: &[&str] = &["meter_end", "reading_end", "end"];
+
+/// Quantity by which a part of the costs is allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationKey {
+    /// Heated area of the unit in m².
+    Area,
+    /// Heated volume of the unit in m³.
+    Volume,
+    /// Sub-meter or heat cost allocator units of the unit for the period.
+    Meter,
+}
+
+impl AllocationKey {
+    /// Return the snake_case name of the key.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Area => "area",
+            Self::Volume => "volume",
+            Self::Meter => "meter",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            Self::Area => "m²",
+            Self::Volume => "m³",
+            Self::Meter => "units",
+        }
+    }
+
+    fn value(self, unit: &TenantUnit) -> f64 {
+        match self {
+            Self::Area => unit.area_m2,
+            Self::Volume => unit.volume_m3,
+            Self::Meter => unit.meter_units,
+        }
+    }
+}
+
+impl fmt::Display for AllocationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A rented unit and its allocation keys.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TenantUnit {
+    /// Unit identifier, e.g. the apartment number.
+    pub unit_id: String,
+    /// Name of the tenant.
+    pub tenant: String,
+    /// Heated area in m².
+    pub area_m2: f64,
+    /// Heated volume in m³.
+    pub volume_m3: f64,
+    /// Sub-meter or heat cost allocator units for the billing period.
+    pub meter_units: f64,
+}
+
+impl TenantUnit {
+    /// Create a unit with all keys set to zero.
+    pub fn new(unit_id: impl Into<String>, tenant: impl Into<String>) -> Self {
+        Self {
+            unit_id: unit_id.into(),
+            tenant: tenant.into(),
+            area_m2: 0.0,
+            volume_m3: 0.0,
+            meter_units: 0.0,
+        }
+    }
+
+    /// Set the heated area in m².
+    pub fn with_area_m2(mut self, area_m2: f64) -> Self {
+        self.area_m2 = area_m2;
+        self
+    }
+
+    /// Set the heated volume in m³.
+    pub fn with_volume_m3(mut self, volume_m3: f64) -> Self {
+        self.volume_m3 = volume_m3;
+        self
+    }
+
+    /// Set the sub-meter units for the billing period.
+    pub fn with_meter_units(mut self, meter_units: f64) -> Self {
+        self.meter_units = meter_units;
+        self
+    }
+}
+
+/// Read units from a CSV file.
+///
+/// See [`units_from_csv_str`] for the accepted layout.
+pub fn load_units(path: impl AsRef<Path>) -> Result<Vec<TenantUnit>, EnergiaProError> {
+    units_from_csv_str(&std::fs::read_to_string(path)?)
+}
+
+/// Parse units from CSV text.
+///
+/// The delimiter (`;` or `,`) is detected from the header row. Columns are
+/// found by name, case-insensitively: `unit_id`, `tenant`, and the optional
+/// `area_m2`, `volume_m3` and `meter_units`. Without a `meter_units` column,
+/// meter units are the difference between the `meter_end` and `meter_start`
+/// readings. Missing or empty keys are zero.
+pub fn units_from_csv_str(text: &str) -> Result<Vec<TenantUnit>, EnergiaProError> {
+    let invalid = |message: String| EnergiaProError::InvalidArgument(message);
+
+    let (mut reader, headers) = csv_reader(text, "unit file")?;
+    let find = |names: &[&str]| csv_column(&headers, names);
+    let unit_index =
+        find(UNIT_COLUMNS).ok_or_else(|| invalid("unit file has no unit column".to_owned()))?;
+    let tenant_index =
+        find(TENANT_COLUMNS).ok_or_else(|| invalid("unit file has no tenant column".to_owned()))?;
+    let area_index = find(AREA_COLUMNS);
+    let volume_index = find(VOLUME_COLUMNS);
+    let meter_index = find(METER_COLUMNS);
+    let readings = find(METER_START_COLUMNS).zip(find(METER_END_COLUMNS));
+
+    let mut units = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| invalid(format!("invalid unit file: {err}")))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let number = |index: Option<usize>, name: &str| -> Result<f64, EnergiaProError> {
+            match index
+                .and_then(|index| record.get(index))
+                .unwrap_or_default()
+            {
+                "" | "-" => Ok(0.0),
+                value => value
+                    .parse::<f64>()
+                    .map_err(|_| invalid(format!("line {line}: invalid {name} `{value}`"))),
+            }
+        };
+
+        let unit_id = record.get(unit_index).unwrap_or_default();
+        if unit_id.is_empty() {
+            continue;
+        }
+        let meter_units = match (meter_index, readings) {
+            (None, Some((start, end))) => {
+                number(Some(end), "meter reading")? - number(Some(start), "meter reading")?
+            }
+            _ => number(meter_index, "meter units")?,
+        };
+
+        units.push(TenantUnit {
+            unit_id: unit_id.to_owned(),
+            tenant: record.get(tenant_index).unwrap_or_default().to_owned(),
+            area_m2: number(area_index, "area")?,
+            volume_m3: number(volume_index, "volume")?,
+            meter_units,
+        });
+    }
+
+    Ok(units)
+}
+
+/// Parameters of the cost allocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationOptions {
+    /// Share of the costs allocated by the fixed key, between 0 and 1.
+    pub fixed_share: f64,
+
+    /// Key of the fixed part.
+    pub fixed_key: AllocationKey,
+
+    /// Key of the variable part.
+    pub variable_key: AllocationKey,
+
+    /// Currency printed on the statements.
+    pub currency: String,
+}
+
+impl Default for AllocationOptions {
+    fn default() -> Self {
+        Self {
+            fixed_share: 0.3,
+            fixed_key: AllocationKey::Area,
+            variable_key: AllocationKey::Meter,
+            currency: "CHF".to_owned(),
+        }
+    }
+}
+
+impl AllocationOptions {
+    /// Set the share of the costs allocated by the fixed key.
+    pub fn with_fixed_share(mut self, fixed_share: f64) -> Self {
+        self.fixed_share = fixed_share;
+        self
+    }
+
+    /// Set the key of the fixed part.
+    pub fn with_fixed_key(mut self, fixed_key: AllocationKey) -> Self {
+        self.fixed_key = fixed_key;
+        self
+    }
+
+    /// Set the key of the variable part.
+    pub fn with_variable_key(mut self, variable_key: AllocationKey) -> Self {
+        self.variable_key = variable_key;
+        self
+    }
+
+    /// Set the currency printed on the statements.
+    pub fn with_currency(mut self, currency: impl Into<String>) -> Self {
+        self.currency = currency.into();
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if !(0.0..=1.0).contains(&self.fixed_share) {
+            return Err(EnergiaProError::InvalidArgument(
+                "fixed_share must be between 0 and 1".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Costs allocated to one unit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TenantStatement {
+    /// Unit identifier.
+    pub unit_id: String,
+    /// Name of the tenant.
+    pub tenant: String,
+    /// Value of the fixed key for the unit.
+    pub fixed_key_value: f64,
+    /// Fixed part of the costs allocated to the unit.
+    pub fixed_cost: f64,
+    /// Value of the variable key for the unit.
+    pub variable_key_value: f64,
+    /// Variable part of the costs allocated to the unit.
+    pub variable_cost: f64,
+    /// Total costs allocated to the unit.
+    pub total_cost: f64,
+    /// Share of the building's energy attributed to the unit, in
+    /// kilowatt-hours.
+    pub allocated_kwh: f64,
+}
+
+/// Heating costs of a billing period split between units.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Allocation {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// First day of the billing period.
+    pub start: NaiveDate,
+    /// Last day of the billing period.
+    pub end: NaiveDate,
+    /// Measured energy of the period in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Measured volume of the period in cubic meters.
+    pub consumption_m3: f64,
+    /// Heating costs of the period.
+    pub total_cost: f64,
+    /// Currency of the amounts.
+    pub currency: String,
+    /// Key of the fixed part.
+    pub fixed_key: AllocationKey,
+    /// Sum of the fixed key over all units.
+    pub fixed_key_total: f64,
+    /// Fixed part of the costs.
+    pub fixed_cost: f64,
+    /// Key of the variable part.
+    pub variable_key: AllocationKey,
+    /// Sum of the variable key over all units.
+    pub variable_key_total: f64,
+    /// Variable part of the costs.
+    pub variable_cost: f64,
+    /// One statement per unit, in the order of the units.
+    pub statements: Vec<TenantStatement>,
+}
+
+impl Allocation {
+    /// Write the statements as CSV with a header row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a statement cannot be serialized.
+    pub fn to_csv(&self) -> Result<String, EnergiaProError> {
+        let invalid = |err: csv::Error| {
+            EnergiaProError::InvalidArgument(format!("cannot write allocation as CSV: {err}"))
+        };
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        if self.statements.is_empty() {
+            writer
+                .write_record([
+                    "unit_id",
+                    "tenant",
+                    "fixed_key_value",
+                    "fixed_cost",
+                    "variable_key_value",
+                    "variable_cost",
+                    "total_cost",
+                    "allocated_kwh",
+                ])
+                .map_err(invalid)?;
+        }
+        for statement in &self.statements {
+            writer.serialize(statement).map_err(invalid)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|err| invalid(err.into_error().into()))?;
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Render the statement of one unit as a standalone HTML document.
+    pub fn statement_html(&self, statement: &TenantStatement) -> String {
+        let currency = escape_html(&self.currency);
+        let share = |value: f64, total: f64| {
+            if total > 0.0 {
+                value / total * 100.0
+            } else {
+                0.0
+            }
+        };
+        let row = |label: &str,
+                   key: AllocationKey,
+                   value: f64,
+                   total: f64,
+                   cost: f64,
+                   part: f64| {
+            format!(
+                "<tr><td>{label}</td><td>{}</td><td class=\"number\">{value:.2} / {total:.2} {} ({:.2}%)</td><td class=\"number\">{part:.2}</td><td class=\"number\">{cost:.2}</td></tr>\n",
+                key.as_str(),
+                key.unit(),
+                share(value, total),
+            )
+        };
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(
+            html,
+            "<title>Heating cost statement {} {}</title>",
+            escape_html(&statement.unit_id),
+            self.end
+        );
+        html.push_str(
+            "<style>body{font-family:sans-serif}table{border-collapse:collapse}\
+             td,th{border:1px solid #ccc;padding:4px 8px}.number{text-align:right}</style>\n",
+        );
+        html.push_str("</head>\n<body>\n<h1>Heating cost statement</h1>\n<dl>\n");
+        for (term, value) in [
+            ("Tenant", escape_html(&statement.tenant)),
+            ("Unit", escape_html(&statement.unit_id)),
+            ("Installation", escape_html(&self.installation_id)),
+            ("Billing period", format!("{} to {}", self.start, self.end)),
+            (
+                "Building consumption",
+                format!(
+                    "{:.2} kWh ({:.2} m³)",
+                    self.consumption_kwh, self.consumption_m3
+                ),
+            ),
+            (
+                "Building heating costs",
+                format!("{:.2} {currency}", self.total_cost),
+            ),
+        ] {
+            let _ = writeln!(html, "<dt>{term}</dt><dd>{value}</dd>");
+        }
+        html.push_str("</dl>\n<table>\n");
+        let _ = writeln!(
+            html,
+            "<tr><th>Part</th><th>Key</th><th>Your share</th><th>Building costs ({currency})</th><th>Your costs ({currency})</th></tr>"
+        );
+        html.push_str(&row(
+            "Fixed costs",
+            self.fixed_key,
+            statement.fixed_key_value,
+            self.fixed_key_total,
+            statement.fixed_cost,
+            self.fixed_cost,
+        ));
+        html.push_str(&row(
+            "Consumption costs",
+            self.variable_key,
+            statement.variable_key_value,
+            self.variable_key_total,
+            statement.variable_cost,
+            self.variable_cost,
+        ));
+        let _ = writeln!(
+            html,
+            "<tr><th colspan=\"4\">Total</th><th class=\"number\">{:.2}</th></tr>",
+            statement.total_cost
+        );
+        html.push_str("</table>\n");
+        let _ = writeln!(
+            html,
+            "<p>Energy attributed to your unit: {:.2} kWh.</p>",
+            statement.allocated_kwh
+        );
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// Allocate the heating costs of a billing period between units.
+///
+/// Measurements outside `period` are ignored. `total_cost` is the heating
+/// cost of the period, for example the total of a
+/// [`cost::calculate`](super::cost::calculate) report plus maintenance costs.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if `period` is not bounded on
+/// both ends, if the measurements belong to more than one installation or
+/// none falls in the period, if a timestamp cannot be parsed, if the cost or
+/// a key is negative or not finite, if unit identifiers are not unique, or if
+/// a key used for a non-zero part of the costs sums to zero.
+pub fn allocate(
+    measurements: &[Measurement],
+    period: DateRange,
+    total_cost: f64,
+    units: &[TenantUnit],
+    options: &AllocationOptions,
+) -> Result<Allocation, EnergiaProError> {
+    options.validate()?;
+    let invalid = |message: String| Err(EnergiaProError::InvalidArgument(message));
+
+    let (Some(start), Some(end)) = (period.start(), period.end()) else {
+        return invalid("billing period must have a start and an end".to_owned());
+    };
+    if !(total_cost.is_finite() && total_cost >= 0.0) {
+        return invalid("total cost must be a non-negative number".to_owned());
+    }
+    if units.is_empty() {
+        return invalid("at least one unit is required".to_owned());
+    }
+    let mut unit_ids = HashSet::new();
+    for unit in units {
+        if !unit_ids.insert(unit.unit_id.as_str()) {
+            return invalid(format!("duplicate unit `{}`", unit.unit_id));
+        }
+        if [unit.area_m2, unit.volume_m3, unit.meter_units]
+            .iter()
+            .any(|value| !(value.is_finite() && *value >= 0.0))
+        {
+            return invalid(format!(
+                "unit `{}`: keys must be non-negative numbers",
+                unit.unit_id
+            ));
+        }
+    }
+
+    let mut installation_id: Option<&str> = None;
+    let mut consumption_kwh = 0.0;
+    let mut consumption_m3 = 0.0;
+    for measurement in measurements {
+        if !period.contains(measurement.local_timestamp()?.date()) {
+            continue;
+        }
+        match installation_id {
+            Some(id) if id != measurement.installation_id => {
+                return invalid(format!(
+                    "measurements belong to several installations: `{id}` and `{}`",
+                    measurement.installation_id
+                ));
+            }
+            _ => installation_id = Some(measurement.installation_id.as_str()),
+        }
+        consumption_kwh += measurement.consumption_kwh;
+        consumption_m3 += measurement.consumption_m3;
+    }
+    let Some(installation_id) = installation_id else {
+        return invalid(format!("no measurement between {start} and {end}"));
+    };
+
+    let total_cents = (total_cost * 100.0).round() as i64;
+    let fixed_cents = (total_cost * options.fixed_share * 100.0).round() as i64;
+    let variable_cents = total_cents - fixed_cents;
+
+    let weights = |key: AllocationKey, cents: i64| {
+        let weights = units.iter().map(|unit| key.value(unit)).collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 && cents > 0 {
+            return Err(EnergiaProError::InvalidArgument(format!(
+                "the {key} key of the units sums to zero"
+            )));
+        }
+        Ok((weights, total))
+    };
+    let (fixed_weights, fixed_key_total) = weights(options.fixed_key, fixed_cents)?;
+    let (variable_weights, variable_key_total) = weights(options.variable_key, variable_cents)?;
+    let fixed_split = distribute_cents(fixed_cents, &fixed_weights);
+    let variable_split = distribute_cents(variable_cents, &variable_weights);
+
+    let share = |weight: f64, total: f64| if total > 0.0 { weight / total } else { 0.0 };
+    let statements = units
+        .iter()
+        .enumerate()
+        .map(|(index, unit)| {
+            let energy_share = options.fixed_share * share(fixed_weights[index], fixed_key_total)
+                + (1.0 - options.fixed_share) * share(variable_weights[index], variable_key_total);
+            TenantStatement {
+                unit_id: unit.unit_id.clone(),
+                tenant: unit.tenant.clone(),
+                fixed_key_value: fixed_weights[index],
+                fixed_cost: fixed_split[index] as f64 / 100.0,
+                variable_key_value: variable_weights[index],
+                variable_cost: variable_split[index] as f64 / 100.0,
+                total_cost: (fixed_split[index] + variable_split[index]) as f64 / 100.0,
+                allocated_kwh: round(consumption_kwh * energy_share, 2),
+            }
+        })
+        .collect();
+
+    Ok(Allocation {
+        installation_id: installation_id.to_owned(),
+        start,
+        end,
+        consumption_kwh,
+        consumption_m3,
+        total_cost: total_cents as f64 / 100.0,
+        currency: options.currency.clone(),
+        fixed_key: options.fixed_key,
+        fixed_key_total,
+        fixed_cost: fixed_cents as f64 / 100.0,
+        variable_key: options.variable_key,
+        variable_key_total,
+        variable_cost: variable_cents as f64 / 100.0,
+        statements,
+    })
+}
+
+/// Split `cents` proportionally to `weights` with the largest remainder
+/// method, so that the parts add up exactly.
+fn distribute_cents(cents: i64, weights: &[f64]) -> Vec<i64> {
+    let total = weights.iter().sum::<f64>();
+    if total <= 0.0 {
+        return vec![0; weights.len()];
+    }
+
+    let exact = weights
+        .iter()
+        .map(|weight| cents as f64 * weight / total)
+        .collect::<Vec<_>>();
+    let mut parts = exact
+        .iter()
+        .map(|value| value.floor() as i64)
+        .collect::<Vec<_>>();
+
+    let mut order = (0..weights.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let fraction = |index: usize| exact[index] - parts[index] as f64;
+        fraction(b).total_cmp(&fraction(a)).then(a.cmp(&b))
+    });
+    let remainder = cents - parts.iter().sum::<i64>();
+    for &index in order.iter().take(usize::try_from(remainder).unwrap_or(0)) {
+        parts[index] += 1;
+    }
+    parts
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn season() -> DateRange {
+        DateRange::between("2023-07-01", "2024-06-30").unwrap()
+    }
+
+    fn units() -> Vec<TenantUnit> {
+        vec![
+            TenantUnit::new("1.01", "Tenant A")
+                .with_area_m2(50.0)
+                .with_volume_m3(125.0)
+                .with_meter_units(10.0),
+            TenantUnit::new("1.02", "Tenant B")
+                .with_area_m2(50.0)
+                .with_volume_m3(125.0)
+                .with_meter_units(10.0),
+            TenantUnit::new("2.01", "Tenant C")
+                .with_area_m2(50.0)
+                .with_volume_m3(150.0)
+                .with_meter_units(10.0),
+        ]
+    }
+
+    #[test]
+    fn splits_costs_to_the_cent() {
+        let measurements = [
+            measurement("2023-06-30 00:00:00", 5_000.0),
+            measurement("2023-12-01 00:00:00", 40_000.0),
+            measurement("2024-03-01 00:00:00", 20_000.0),
+        ];
+
+        let allocation = allocate(
+            &measurements,
+            season(),
+            1_000.0,
+            &units(),
+            &AllocationOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(allocation.consumption_kwh, 60_000.0);
+        assert_eq!(allocation.fixed_cost, 300.0);
+        assert_eq!(allocation.variable_cost, 700.0);
+        let totals = allocation
+            .statements
+            .iter()
+            .map(|statement| statement.total_cost)
+            .collect::<Vec<_>>();
+        // 1000 / 3 leaves one cent for each part, given to the first units.
+        assert_eq!(totals, [333.34, 333.33, 333.33]);
+        let fixed = allocation
+            .statements
+            .iter()
+            .map(|statement| statement.fixed_cost)
+            .sum::<f64>();
+        assert!((fixed - 300.0).abs() < 1e-9);
+        assert_eq!(allocation.statements[2].allocated_kwh, 20_000.0);
+    }
+
+    #[test]
+    fn uses_the_configured_keys() {
+        let measurements = [measurement("2024-01-01 00:00:00", 10_000.0)];
+        let options = AllocationOptions::default()
+            .with_fixed_share(0.5)
+            .with_fixed_key(AllocationKey::Volume)
+            .with_variable_key(AllocationKey::Area);
+
+        let allocation = allocate(&measurements, season(), 800.0, &units(), &options).unwrap();
+
+        assert_eq!(allocation.fixed_key_total, 400.0);
+        assert_eq!(allocation.statements[2].fixed_cost, 150.0);
+        assert_eq!(allocation.statements[2].variable_cost, 133.33);
+        assert_eq!(allocation.statements[0].fixed_cost, 125.0);
+    }
+
+    #[test]
+    fn parses_units_from_csv() {
+        let units = units_from_csv_str(
+            "unit_id;tenant;area_m2;meter_start;meter_end\n\
+             1.01;Tenant A;72.5;1200;1450\n\
+             1.02;Tenant B;;300;300\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            units,
+            [
+                TenantUnit::new("1.01", "Tenant A")
+                    .with_area_m2(72.5)
+                    .with_meter_units(250.0),
+                TenantUnit::new("1.02", "Tenant B"),
+            ]
+        );
+        assert!(matches!(
+            units_from_csv_str("unit_id,tenant,area_m2\n1.01,A,big\n"),
+            Err(EnergiaProError::InvalidArgument(message)) if message.contains("invalid area `big`")
+        ));
+    }
+
+    #[test]
+    fn exports_csv_and_escaped_html() {
+        let units = [
+            TenantUnit::new("1.01", "Müller & <Fils>")
+                .with_area_m2(60.0)
+                .with_meter_units(3.0),
+            TenantUnit::new("1.02", "Tenant B")
+                .with_area_m2(40.0)
+                .with_meter_units(1.0),
+        ];
+        let allocation = allocate(
+            &[measurement("2024-01-01 00:00:00", 10_000.0)],
+            season(),
+            1_000.0,
+            &units,
+            &AllocationOptions::default(),
+        )
+        .unwrap();
+
+        let csv = allocation.to_csv().unwrap();
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            "unit_id,tenant,fixed_key_value,fixed_cost,variable_key_value,variable_cost,total_cost,allocated_kwh"
+        );
+        assert!(csv.contains("1.01,Müller & <Fils>,60.0,180.0,3.0,525.0,705.0,7050.0\n"));
+
+        let html = allocation.statement_html(&allocation.statements[0]);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<dd>Müller &amp; &lt;Fils&gt;</dd>"));
+        assert!(!html.contains("<Fils>"));
+        assert!(html.contains("60.00 / 100.00 m² (60.00%)"));
+        assert!(html.contains("<th class=\"number\">705.00</th>"));
+    }
+
+    #[test]
+    fn rejects_invalid_inputs() {
+        let measurements = [measurement("2024-01-01 00:00:00", 10_000.0)];
+        let options = AllocationOptions::default();
+        let no_meters = [TenantUnit::new("1.01", "A").with_area_m2(50.0)];
+        let duplicates = [
+            TenantUnit::new("1.01", "A").with_area_m2(50.0),
+            TenantUnit::new("1.01", "B").with_area_m2(50.0),
+        ];
+
+        for result in [
+            allocate(&measurements, season(), 100.0, &no_meters, &options),
+            allocate(&measurements, season(), 100.0, &duplicates, &options),
+            allocate(&measurements, season(), -1.0, &units(), &options),
+            allocate(&measurements, DateRange::all(), 100.0, &units(), &options),
+            allocate(
+                &measurements,
+                DateRange::between("2022-01-01", "2022-12-31").unwrap(),
+                100.0,
+                &units(),
+                &options,
+            ),
+            allocate(
+                &measurements,
+                season(),
+                100.0,
+                &units(),
+                &options.clone().with_fixed_share(1.5),
+            ),
+        ] {
+            assert!(matches!(result, Err(EnergiaProError::InvalidArgument(_))));
+        }
+
+        // Without a variable part, meter units are not needed.
+        let fixed_only = options.with_fixed_share(1.0);
+        assert!(allocate(&measurements, season(), 100.0, &no_meters, &fixed_only).is_ok());
+    }
+}
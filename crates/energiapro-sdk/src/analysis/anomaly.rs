@@ -0,0 +1,367 @@
+//! Statistical anomaly detection.
+//!
+//! Consumption follows weekly and seasonal patterns: a Monday 07:00 in
+//! January looks like other winter Monday mornings, not like a Sunday night in
+//! July. [`detect`] groups intervals by meteorological season, weekday and
+//! hour, and compares each interval with the median of its group using a
+//! robust z-score:
+//!
+//! ```text
+//! z = 0.6745 × (observed − median) / MAD
+//! ```
+//!
+//! where MAD is the median absolute deviation of the group. Medians are not
+//! pulled by the anomalies themselves, so a stuck valve or a heating failure
+//! stands out even when it lasts several intervals.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::anomaly::{self, AnomalyKind};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |day: u32, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: format!("2024-01-{day:02} 08:00:00"),
+//!     index_m3: 0.0,
+//!     consumption_m3: consumption_kwh / 10.0,
+//!     consumption_kwh,
+//! };
+//!
+//! // Five Monday mornings, the last one with a heating failure.
+//! let anomalies = anomaly::detect(&[
+//!     measurement(1, 10.0),
+//!     measurement(8, 11.0),
+//!     measurement(15, 10.0),
+//!     measurement(22, 9.0),
+//!     measurement(29, 0.0),
+//! ])?;
+//!
+//! assert_eq!(anomalies.len(), 1);
+//! assert_eq!(anomalies[0].kind, AnomalyKind::Drop);
+//! assert_eq!(anomalies[0].expected_kwh, 10.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{Datelike, Timelike};
+use serde::Serialize;
+
+use super::{median, robust_scale, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+/// Thresholds used by [`detect_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyOptions {
+    /// Smallest absolute robust z-score reported as an anomaly.
+    ///
+    /// Scores of at least twice and three times this value are reported with
+    /// a medium and high severity.
+    pub threshold: f64,
+
+    /// Smallest number of intervals in a season, weekday and hour group for
+    /// its members to be scored.
+    pub min_samples: usize,
+
+    /// Smallest absolute difference between observed and expected values, in
+    /// kWh. Prevents flagging tiny deviations in very regular groups.
+    pub min_deviation_kwh: f64,
+}
+
+impl Default for AnomalyOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 3.5,
+            min_samples: 4,
+            min_deviation_kwh: 0.1,
+        }
+    }
+}
+
+impl AnomalyOptions {
+    /// Set the smallest absolute robust z-score reported.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set the smallest number of intervals per group.
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Set the smallest absolute deviation reported, in kWh.
+    pub fn with_min_deviation_kwh(mut self, min_deviation_kwh: f64) -> Self {
+        self.min_deviation_kwh = min_deviation_kwh;
+        self
+    }
+}
+
+/// Direction of an anomaly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// Consumption above the expected value.
+    Spike,
+    /// Consumption below the expected value.
+    Drop,
+}
+
+impl AnomalyKind {
+    /// Return the snake_case name of the kind.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Spike => "spike",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How far an anomaly lies from the expected value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// At least the threshold.
+    Low,
+    /// At least twice the threshold.
+    Medium,
+    /// At least three times the threshold.
+    High,
+}
+
+impl Severity {
+    /// Return the snake_case name of the severity.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An interval whose consumption deviates from its season, weekday and hour.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Timestamp of the interval.
+    pub timestamp: String,
+    /// Measured energy in kilowatt-hours.
+    pub observed_kwh: f64,
+    /// Median energy of comparable intervals in kilowatt-hours.
+    pub expected_kwh: f64,
+    /// Robust z-score of the interval.
+    pub z_score: f64,
+    /// Direction of the deviation.
+    pub kind: AnomalyKind,
+    /// Severity derived from the z-score.
+    pub severity: Severity,
+}
+
+/// Detect anomalies with the default [`AnomalyOptions`].
+///
+/// # Errors
+///
+/// See [`detect_with`].
+pub fn detect(measurements: &[Measurement]) -> Result<Vec<Anomaly>, EnergiaProError> {
+    detect_with(measurements, &AnomalyOptions::default())
+}
+
+/// Detect anomalies with custom thresholds.
+///
+/// Intervals are grouped by meteorological season (December to February,
+/// March to May, June to August, September to November), local weekday and
+/// local hour. Groups smaller than `min_samples` are not scored. Anomalies are
+/// returned in chronological order.
+///
+/// # Errors
+///
+/// Returns an error if the measurements belong to more than one installation
+/// or a timestamp cannot be parsed.
+pub fn detect_with(
+    measurements: &[Measurement],
+    options: &AnomalyOptions,
+) -> Result<Vec<Anomaly>, EnergiaProError> {
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+
+    let mut groups: HashMap<(u32, u32, u32), Vec<usize>> = HashMap::new();
+    for (index, timestamp) in series.timestamps().iter().enumerate() {
+        let season = timestamp.month() % 12 / 3;
+        groups
+            .entry((
+                season,
+                timestamp.weekday().num_days_from_monday(),
+                timestamp.hour(),
+            ))
+            .or_default()
+            .push(index);
+    }
+
+    let consumption = series.consumption_kwh();
+    let mut anomalies = Vec::new();
+    for members in groups.values() {
+        if members.len() < options.min_samples.max(2) {
+            continue;
+        }
+
+        let values = members
+            .iter()
+            .map(|&index| consumption[index])
+            .collect::<Vec<_>>();
+        let expected = median(values.clone());
+        let deviations = values
+            .iter()
+            .map(|value| (value - expected).abs())
+            .collect::<Vec<_>>();
+        let scale = robust_scale(deviations);
+        if scale <= 0.0 {
+            continue;
+        }
+
+        for &index in members {
+            let observed = consumption[index];
+            let z_score = (observed - expected) / scale;
+            if z_score.abs() < options.threshold
+                || (observed - expected).abs() < options.min_deviation_kwh
+            {
+                continue;
+            }
+
+            let severity = if z_score.abs() >= 3.0 * options.threshold {
+                Severity::High
+            } else if z_score.abs() >= 2.0 * options.threshold {
+                Severity::Medium
+            } else {
+                Severity::Low
+            };
+
+            anomalies.push((
+                index,
+                Anomaly {
+                    installation_id: series.installation_id().to_owned(),
+                    timestamp: series.timestamps()[index]
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                    observed_kwh: observed,
+                    expected_kwh: expected,
+                    z_score: round(z_score, 2),
+                    kind: if z_score > 0.0 {
+                        AnomalyKind::Spike
+                    } else {
+                        AnomalyKind::Drop
+                    },
+                    severity,
+                },
+            ));
+        }
+    }
+
+    anomalies.sort_by_key(|(index, _)| *index);
+    Ok(anomalies.into_iter().map(|(_, anomaly)| anomaly).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, NaiveDate};
+
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    /// Eight weeks of hourly data with a daytime and a night-time level and a
+    /// small weekly variation.
+    fn weeks(start: &str, count: u64, day_kwh: f64) -> Vec<Measurement> {
+        let start = NaiveDate::parse_from_str(start, "%Y-%m-%d").unwrap();
+        let mut measurements = Vec::new();
+        for day in 0..count * 7 {
+            let date = start + Days::new(day);
+            let wobble = (day / 7 % 3) as f64 * 0.1;
+            for hour in 0..24 {
+                let level = if (6..22).contains(&hour) {
+                    day_kwh
+                } else {
+                    2.0
+                };
+                measurements.push(measurement(
+                    format!("{date} {hour:02}:00:00"),
+                    level + wobble,
+                ));
+            }
+        }
+        measurements
+    }
+
+    #[test]
+    fn flags_spikes_and_drops_against_the_same_hour_of_the_week() {
+        let mut measurements = weeks("2024-01-01", 8, 10.0);
+        // A stuck valve at night and a heating failure during the day.
+        measurements[3].consumption_kwh = 10.0;
+        measurements[24 * 7 + 12].consumption_kwh = 0.0;
+
+        let anomalies = detect(&measurements).unwrap();
+
+        assert_eq!(anomalies.len(), 2);
+        assert_eq!(anomalies[0].timestamp, "2024-01-01 03:00:00");
+        assert_eq!(anomalies[0].kind, AnomalyKind::Spike);
+        assert_eq!(anomalies[0].observed_kwh, 10.0);
+        assert_eq!(anomalies[0].expected_kwh, 2.1);
+        assert_eq!(anomalies[0].severity, Severity::High);
+        assert_eq!(anomalies[1].timestamp, "2024-01-08 12:00:00");
+        assert_eq!(anomalies[1].kind, AnomalyKind::Drop);
+        assert!(anomalies[1].z_score < 0.0);
+    }
+
+    #[test]
+    fn compares_intervals_within_their_season() {
+        // Summer consumption is much lower, but normal for the season.
+        let mut measurements = weeks("2024-01-01", 8, 10.0);
+        measurements.extend(weeks("2024-07-01", 8, 3.0));
+
+        assert!(detect(&measurements).unwrap().is_empty());
+    }
+
+    #[test]
+    fn grades_severity_with_the_threshold() {
+        let mut measurements = weeks("2024-01-01", 8, 10.0);
+        measurements[12].consumption_kwh = 10.35;
+
+        let strict = AnomalyOptions::default().with_threshold(1.0);
+        let anomalies = detect_with(&measurements, &strict).unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].severity, Severity::Low);
+
+        assert!(detect(&measurements).unwrap().is_empty());
+    }
+
+    #[test]
+    fn skips_small_groups_and_rejects_mixed_installations() {
+        let measurements = weeks("2024-01-01", 2, 10.0);
+        assert!(detect(&measurements).unwrap().is_empty());
+
+        let mut mixed = weeks("2024-01-01", 1, 10.0);
+        mixed[0].installation_id = "INSTALLATION_ID_2".to_owned();
+        assert!(matches!(
+            detect(&mixed),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,968 @@
+//! Energy benchmarking per square metre of energy reference area.
+//!
+//! The API describes installations by their address only. A
+//! [`BuildingRegistry`] adds the building data needed to compare them: the
+//! energy reference area (SIA 416/1), the building category (SIA 380/1) and
+//! the construction year. Registries implement [`serde::Deserialize`]; in
+//! TOML, a registry looks like this:
+//!
+//! ```toml
+//! [buildings.INSTALLATION_ID_1]
+//! energy_reference_area_m2 = 850.0
+//! building_type = "multi_family"
+//! construction_year = 1972
+//! ```
+//!
+//! and [`BuildingRegistry::from_csv_str`] reads the same data from CSV.
+//!
+//! [`benchmark`] computes the annual energy intensity in kWh/m²·a of every
+//! installation, weather-normalized when temperatures are given, ranks the
+//! installations and assigns each one a class of a [`ClassScale`], such as
+//! the CECB-style A to G scale of [`ClassScale::relative_to`].
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use energiapro::Measurement;
+//! use energiapro::analysis::benchmark::{self, BenchmarkOptions, BuildingRegistry, ClassScale};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let registry = BuildingRegistry::from_csv_str(
+//!     "installation_id;energy_reference_area_m2;building_type;construction_year\n\
+//!      INSTALLATION_ID_1;1000;multi_family;1972\n\
+//!      INSTALLATION_ID_2;500;multi_family;2015\n",
+//! )?;
+//!
+//! let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+//! let mut measurements = Vec::new();
+//! for (installation_id, kwh_per_day) in [("INSTALLATION_ID_1", 400.0), ("INSTALLATION_ID_2", 50.0)] {
+//!     for date in start.iter_days().take(365) {
+//!         measurements.push(Measurement {
+//!             client_id: 1,
+//!             installation_id: installation_id.to_owned(),
+//!             timestamp: format!("{date} 00:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: kwh_per_day / 10.0,
+//!             consumption_kwh: kwh_per_day,
+//!         });
+//!     }
+//! }
+//!
+//! let options = BenchmarkOptions::default().with_classes(ClassScale::relative_to(100.0));
+//! let benchmark = benchmark::benchmark_with(&measurements, &registry, 2023, &options)?;
+//!
+//! assert_eq!(benchmark.entries[0].installation_id, "INSTALLATION_ID_2");
+//! assert_eq!(benchmark.entries[0].intensity_kwh_per_m2, 36.5);
+//! assert_eq!(benchmark.entries[0].class.as_deref(), Some("A"));
+//! assert_eq!(benchmark.entries[1].intensity_kwh_per_m2, 146.0);
+//! assert_eq!(benchmark.entries[1].class.as_deref(), Some("C"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::degree_days::{DegreeDayOptions, TemperatureSeries, normalize_with};
+use super::{csv_column, csv_reader, median, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Installation, Measurement};
+use crate::types::Granularity;
+
+/// Header names recognized as the installation column, compared
+/// case-insensitively.
+const INSTALLATION_COLUMNS: &[&str] = &["installation_id", "installation", "num_inst", "id"];
+
+/// Header names recognized as the energy reference area column.
+const AREA_COLUMNS: &[&str] = &["energy_reference_area_m2", "area_m2", "sre", "ebf"];
+
+/// Header names recognized as the building type column.
+const TYPE_COLUMNS: &[&str] = &["building_type", "type", "category"];
+
+/// Header names recognized as the construction year column.
+const YEAR_COLUMNS: &[&str] = &["construction_year", "year"];
+
+/// Building category of SIA 380/1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildingType {
+    /// Category I, multi-family housing.
+    MultiFamily,
+    /// Category II, single-family housing.
+    SingleFamily,
+    /// Category III, offices and administration.
+    Administration,
+    /// Category IV, schools.
+    School,
+    /// Category V, retail.
+    Retail,
+    /// Category VI, restaurants.
+    Restaurant,
+    /// Category VII, assembly halls.
+    Assembly,
+    /// Category VIII, hospitals.
+    Hospital,
+    /// Category IX, industry.
+    Industry,
+    /// Category X, storage.
+    Storage,
+    /// Category XI, sports buildings.
+    Sports,
+    /// Category XII, indoor swimming pools.
+    IndoorPool,
+}
+
+impl BuildingType {
+    /// Every building type, in SIA 380/1 order.
+    pub const ALL: [Self; 12] = [
+        Self::MultiFamily,
+        Self::SingleFamily,
+        Self::Administration,
+        Self::School,
+        Self::Retail,
+        Self::Restaurant,
+        Self::Assembly,
+        Self::Hospital,
+        Self::Industry,
+        Self::Storage,
+        Self::Sports,
+        Self::IndoorPool,
+    ];
+
+    /// Return the snake_case name of the building type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MultiFamily => "multi_family",
+            Self::SingleFamily => "single_family",
+            Self::Administration => "administration",
+            Self::School => "school",
+            Self::Retail => "retail",
+            Self::Restaurant => "restaurant",
+            Self::Assembly => "assembly",
+            Self::Hospital => "hospital",
+            Self::Industry => "industry",
+            Self::Storage => "storage",
+            Self::Sports => "sports",
+            Self::IndoorPool => "indoor_pool",
+        }
+    }
+}
+
+impl fmt::Display for BuildingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BuildingType {
+    type Err = EnergiaProError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|building_type| building_type.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| {
+                EnergiaProError::InvalidArgument(format!("unknown building type `{value}`"))
+            })
+    }
+}
+
+/// Building data of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildingMetadata {
+    /// Heated floor area in m², as defined by SIA 416/1.
+    pub energy_reference_area_m2: f64,
+    /// SIA 380/1 building category.
+    pub building_type: BuildingType,
+    /// Year of construction, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub construction_year: Option<i32>,
+}
+
+impl BuildingMetadata {
+    /// Create building data without a construction year.
+    pub fn new(energy_reference_area_m2: f64, building_type: BuildingType) -> Self {
+        Self {
+            energy_reference_area_m2,
+            building_type,
+            construction_year: None,
+        }
+    }
+
+    /// Set the year of construction.
+    pub fn with_construction_year(mut self, construction_year: i32) -> Self {
+        self.construction_year = Some(construction_year);
+        self
+    }
+}
+
+/// Building data keyed by installation identifier.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildingRegistry {
+    /// Building data per installation identifier.
+    #[serde(default)]
+    pub buildings: BTreeMap<String, BuildingMetadata>,
+}
+
+impl BuildingRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the building data of an installation.
+    pub fn with_building(
+        mut self,
+        installation_id: impl Into<String>,
+        metadata: BuildingMetadata,
+    ) -> Self {
+        self.buildings.insert(installation_id.into(), metadata);
+        self
+    }
+
+    /// Return the building data of an installation identifier, if registered.
+    pub fn get(&self, installation_id: &str) -> Option<&BuildingMetadata> {
+        self.buildings.get(installation_id)
+    }
+
+    /// Return the building data of an installation, if registered.
+    pub fn metadata(&self, installation: &Installation) -> Option<&BuildingMetadata> {
+        self.get(&installation.id)
+    }
+
+    /// Return the number of registered installations.
+    pub fn len(&self) -> usize {
+        self.buildings.len()
+    }
+
+    /// Return `true` if no installation is registered.
+    pub fn is_empty(&self) -> bool {
+        self.buildings.is_empty()
+    }
+
+    /// Read a CSV registry.
+    ///
+    /// See [`BuildingRegistry::from_csv_str`] for the accepted layout.
+    pub fn load_csv(path: impl AsRef<Path>) -> Result<Self, EnergiaProError> {
+        Self::from_csv_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a registry from CSV text.
+    ///
+    /// The delimiter (`;` or `,`) is detected from the header row. Columns are
+    /// found by name, case-insensitively: `installation_id` (or `num_inst`),
+    /// `energy_reference_area_m2` (or `area_m2`, `sre`, `ebf`),
+    /// `building_type` and the optional `construction_year`. Building types
+    /// use the snake_case names of [`BuildingType`].
+    pub fn from_csv_str(text: &str) -> Result<Self, EnergiaProError> {
+        let invalid = |message: String| EnergiaProError::InvalidArgument(message);
+
+        let (mut reader, headers) = csv_reader(text, "building registry")?;
+        let find = |names: &[&str]| csv_column(&headers, names);
+        let missing = |column: &str| invalid(format!("building registry has no {column} column"));
+        let installation_index =
+            find(INSTALLATION_COLUMNS).ok_or_else(|| missing("installation"))?;
+        let area_index = find(AREA_COLUMNS).ok_or_else(|| missing("energy reference area"))?;
+        let type_index = find(TYPE_COLUMNS).ok_or_else(|| missing("building type"))?;
+        let year_index = find(YEAR_COLUMNS);
+
+        let mut registry = Self::new();
+        for record in reader.records() {
+            let record =
+                record.map_err(|err| invalid(format!("invalid building registry: {err}")))?;
+            let line = record.position().map_or(0, |position| position.line());
+            let field = |index: usize| record.get(index).unwrap_or_default();
+
+            let installation_id = field(installation_index);
+            if installation_id.is_empty() {
+                continue;
+            }
+            let area = field(area_index);
+            let energy_reference_area_m2 = area
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("line {line}: invalid area `{area}`")))?;
+            let building_type = field(type_index)
+                .parse::<BuildingType>()
+                .map_err(|err| invalid(format!("line {line}: {err}")))?;
+            let construction_year = match year_index.map(field).unwrap_or_default() {
+                "" | "-" => None,
+                year => Some(year.parse::<i32>().map_err(|_| {
+                    invalid(format!("line {line}: invalid construction year `{year}`"))
+                })?),
+            };
+
+            let metadata = BuildingMetadata {
+                energy_reference_area_m2,
+                building_type,
+                construction_year,
+            };
+            if registry
+                .buildings
+                .insert(installation_id.to_owned(), metadata)
+                .is_some()
+            {
+                return Err(invalid(format!(
+                    "line {line}: duplicate installation `{installation_id}`"
+                )));
+            }
+        }
+
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    /// Check that every energy reference area is a positive number.
+    pub fn validate(&self) -> Result<(), EnergiaProError> {
+        match self.buildings.iter().find(|(_, metadata)| {
+            !(metadata.energy_reference_area_m2.is_finite()
+                && metadata.energy_reference_area_m2 > 0.0)
+        }) {
+            Some((installation_id, _)) => Err(EnergiaProError::InvalidArgument(format!(
+                "installation `{installation_id}`: energy reference area must be a positive number"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// One class of a [`ClassScale`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetClass {
+    /// Label of the class, e.g. `A`.
+    pub label: String,
+    /// Highest energy intensity of the class in kWh/m²·a, or `None` for the
+    /// last class.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_kwh_per_m2: Option<f64>,
+}
+
+/// Ordered energy intensity classes, from the most efficient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassScale {
+    /// Classes with increasing limits; only the last one may be unbounded.
+    pub classes: Vec<TargetClass>,
+}
+
+impl ClassScale {
+    /// Create a scale from `(label, limit)` pairs, a `None` limit marking an
+    /// unbounded last class.
+    pub fn new<'a>(classes: impl IntoIterator<Item = (&'a str, Option<f64>)>) -> Self {
+        Self {
+            classes: classes
+                .into_iter()
+                .map(|(label, max_kwh_per_m2)| TargetClass {
+                    label: label.to_owned(),
+                    max_kwh_per_m2,
+                })
+                .collect(),
+        }
+    }
+
+    /// Create a CECB-style scale relative to a reference intensity, such as
+    /// the SIA 380/1 limit of the building: A up to 50% of the reference, B up
+    /// to 100%, then one class per additional 50% up to F, and G above 300%.
+    pub fn relative_to(reference_kwh_per_m2: f64) -> Self {
+        Self::new(
+            [
+                ("A", Some(0.5)),
+                ("B", Some(1.0)),
+                ("C", Some(1.5)),
+                ("D", Some(2.0)),
+                ("E", Some(2.5)),
+                ("F", Some(3.0)),
+                ("G", None),
+            ]
+            .map(|(label, ratio)| (label, ratio.map(|ratio| ratio * reference_kwh_per_m2))),
+        )
+    }
+
+    /// Return the label of the first class whose limit is not exceeded, or
+    /// `None` if the intensity exceeds every limit.
+    pub fn classify(&self, intensity_kwh_per_m2: f64) -> Option<&str> {
+        self.classes
+            .iter()
+            .find(|class| {
+                class
+                    .max_kwh_per_m2
+                    .is_none_or(|max| intensity_kwh_per_m2 <= max)
+            })
+            .map(|class| class.label.as_str())
+    }
+
+    /// Check that the scale has classes with finite increasing limits and
+    /// that only the last one is unbounded.
+    pub fn validate(&self) -> Result<(), EnergiaProError> {
+        let invalid = |message: &str| Err(EnergiaProError::InvalidArgument(message.to_owned()));
+
+        if self.classes.is_empty() {
+            return invalid("class scale needs at least one class");
+        }
+        let mut previous = f64::NEG_INFINITY;
+        for (position, class) in self.classes.iter().enumerate() {
+            match class.max_kwh_per_m2 {
+                Some(max) if !max.is_finite() || max <= previous => {
+                    return invalid("class limits must be finite and increasing");
+                }
+                Some(max) => previous = max,
+                None if position + 1 < self.classes.len() => {
+                    return invalid("only the last class may be unbounded");
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parameters of the benchmark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkOptions {
+    /// Daily mean temperatures used to normalize the consumption to the
+    /// reference climate, or `None` to benchmark the measured consumption.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Degree day method of the weather normalization.
+    pub degree_days: DegreeDayOptions,
+
+    /// Classes assigned to every building type without its own scale, or
+    /// `None` to assign no class.
+    pub classes: Option<ClassScale>,
+
+    /// Classes of specific building types.
+    pub classes_by_type: BTreeMap<BuildingType, ClassScale>,
+
+    /// Share of the days of the year that must have measurements for an
+    /// installation to be benchmarked, between 0 and 1.
+    ///
+    /// Consumption is not extrapolated, so an installation measured on part of
+    /// the year looks more efficient than its peers. The default of 1 only
+    /// benchmarks complete years; lower values admit partial entries, see
+    /// [`BenchmarkEntry::is_partial`].
+    pub min_coverage: f64,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            temperatures: None,
+            degree_days: DegreeDayOptions::default(),
+            classes: None,
+            classes_by_type: BTreeMap::new(),
+            min_coverage: 1.0,
+        }
+    }
+}
+
+impl BenchmarkOptions {
+    /// Normalize the consumption to the weather with daily temperatures.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the degree day method of the weather normalization.
+    pub fn with_degree_days(mut self, degree_days: DegreeDayOptions) -> Self {
+        self.degree_days = degree_days;
+        self
+    }
+
+    /// Set the classes of building types without their own scale.
+    pub fn with_classes(mut self, classes: ClassScale) -> Self {
+        self.classes = Some(classes);
+        self
+    }
+
+    /// Set the classes of one building type.
+    pub fn with_type_classes(mut self, building_type: BuildingType, classes: ClassScale) -> Self {
+        self.classes_by_type.insert(building_type, classes);
+        self
+    }
+
+    /// Set the share of the year that must have measurements.
+    pub fn with_min_coverage(mut self, min_coverage: f64) -> Self {
+        self.min_coverage = min_coverage;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if !(self.min_coverage > 0.0 && self.min_coverage <= 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "min_coverage must be greater than 0 and at most 1".to_owned(),
+            ));
+        }
+        for classes in self.classes.iter().chain(self.classes_by_type.values()) {
+            classes.validate()?;
+        }
+        self.degree_days.validate()
+    }
+}
+
+/// Annual energy intensity of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchmarkEntry {
+    /// Rank in the portfolio, 1 being the lowest intensity.
+    pub rank: usize,
+    /// Rank among the installations of the same building type.
+    pub type_rank: usize,
+    /// Installation identifier.
+    pub installation_id: String,
+    /// SIA 380/1 building category.
+    pub building_type: BuildingType,
+    /// Energy reference area in m².
+    pub energy_reference_area_m2: f64,
+    /// Year of construction, if known.
+    pub construction_year: Option<i32>,
+    /// Share of the days of the year with measurements.
+    pub coverage: f64,
+    /// Measured energy of the year in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Consumption scaled to the reference climate, or `None` without
+    /// temperatures or when no heating was required.
+    pub normalized_kwh: Option<f64>,
+    /// Energy intensity in kWh/m²·a, from the normalized consumption when
+    /// available and the measured consumption otherwise.
+    pub intensity_kwh_per_m2: f64,
+    /// Label of the class of the intensity, if a scale applies.
+    pub class: Option<String>,
+}
+
+impl BenchmarkEntry {
+    /// Return `true` if the intensity is weather-normalized.
+    pub fn is_normalized(&self) -> bool {
+        self.normalized_kwh.is_some()
+    }
+
+    /// Return `true` if some days of the year have no measurements, so that
+    /// the intensity understates the consumption of a full year.
+    pub fn is_partial(&self) -> bool {
+        self.coverage < 1.0
+    }
+}
+
+/// An installation left out of the benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedInstallation {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Why the installation was left out.
+    pub reason: String,
+}
+
+/// Ranked energy intensities of a portfolio over one year.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Benchmark {
+    /// Calendar year of the benchmark.
+    pub year: i32,
+    /// Installations from the lowest to the highest intensity.
+    pub entries: Vec<BenchmarkEntry>,
+    /// Installations without building data or with too few measurements.
+    pub skipped: Vec<SkippedInstallation>,
+}
+
+impl Benchmark {
+    /// Return the median intensity of the portfolio in kWh/m²·a, or `None`
+    /// if no installation was benchmarked.
+    pub fn median_kwh_per_m2(&self) -> Option<f64> {
+        (!self.entries.is_empty()).then(|| {
+            median(
+                self.entries
+                    .iter()
+                    .map(|entry| entry.intensity_kwh_per_m2)
+                    .collect(),
+            )
+        })
+    }
+}
+
+/// Benchmark a portfolio with the default [`BenchmarkOptions`].
+///
+/// # Errors
+///
+/// See [`benchmark_with`].
+pub fn benchmark(
+    measurements: &[Measurement],
+    registry: &BuildingRegistry,
+    year: i32,
+) -> Result<Benchmark, EnergiaProError> {
+    benchmark_with(measurements, registry, year, &BenchmarkOptions::default())
+}
+
+/// Benchmark a portfolio with custom options.
+///
+/// Measurements of every installation are restricted to the calendar `year`
+/// by their local date. Installations without building data or measured on
+/// less than `min_coverage` of the year are listed in
+/// [`Benchmark::skipped`]. Consumption is never extrapolated: entries measured
+/// on part of the year are kept as-is and flagged by
+/// [`BenchmarkEntry::is_partial`].
+///
+/// # Errors
+///
+/// Returns an error if the options or the registry are invalid, if `year` is
+/// out of range, or if a timestamp cannot be parsed.
+pub fn benchmark_with(
+    measurements: &[Measurement],
+    registry: &BuildingRegistry,
+    year: i32,
+    options: &BenchmarkOptions,
+) -> Result<Benchmark, EnergiaProError> {
+    options.validate()?;
+    registry.validate()?;
+
+    let start = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| EnergiaProError::InvalidArgument(format!("invalid year {year}")))?;
+    let (_, end) = Granularity::Year.period(start);
+    let days_in_year = (end - start).num_days() + 1;
+
+    let mut installations: BTreeMap<&str, (Vec<Measurement>, BTreeSet<NaiveDate>)> =
+        BTreeMap::new();
+    for measurement in measurements {
+        let date = measurement.local_timestamp()?.date();
+        if !(start..=end).contains(&date) {
+            continue;
+        }
+        let installation = installations
+            .entry(measurement.installation_id.as_str())
+            .or_default();
+        installation.0.push(measurement.clone());
+        installation.1.insert(date);
+    }
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for (installation_id, (measurements, dates)) in installations {
+        let skip = |reason: String| SkippedInstallation {
+            installation_id: installation_id.to_owned(),
+            reason,
+        };
+        let Some(metadata) = registry.get(installation_id) else {
+            skipped.push(skip("no building data in the registry".to_owned()));
+            continue;
+        };
+        let coverage = dates.len() as f64 / days_in_year as f64;
+        if coverage < options.min_coverage {
+            skipped.push(skip(format!(
+                "measurements cover {:.0}% of {year}",
+                coverage * 100.0
+            )));
+            continue;
+        }
+
+        let consumption_kwh = measurements
+            .iter()
+            .map(|measurement| measurement.consumption_kwh)
+            .sum::<f64>();
+        let normalized_kwh = match &options.temperatures {
+            Some(temperatures) => normalize_with(
+                &measurements,
+                temperatures,
+                Granularity::Year,
+                &options.degree_days,
+            )?
+            .first()
+            .and_then(|period| period.normalized_kwh),
+            None => None,
+        };
+        let intensity =
+            normalized_kwh.unwrap_or(consumption_kwh) / metadata.energy_reference_area_m2;
+        let class = options
+            .classes_by_type
+            .get(&metadata.building_type)
+            .or(options.classes.as_ref())
+            .and_then(|classes| classes.classify(intensity))
+            .map(str::to_owned);
+
+        entries.push(BenchmarkEntry {
+            rank: 0,
+            type_rank: 0,
+            installation_id: installation_id.to_owned(),
+            building_type: metadata.building_type,
+            energy_reference_area_m2: metadata.energy_reference_area_m2,
+            construction_year: metadata.construction_year,
+            coverage: round(coverage, 4),
+            consumption_kwh: round(consumption_kwh, 2),
+            normalized_kwh: normalized_kwh.map(|kwh| round(kwh, 2)),
+            intensity_kwh_per_m2: round(intensity, 2),
+            class,
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        a.intensity_kwh_per_m2
+            .total_cmp(&b.intensity_kwh_per_m2)
+            .then_with(|| a.installation_id.cmp(&b.installation_id))
+    });
+    let mut type_ranks: HashMap<BuildingType, usize> = HashMap::new();
+    for (position, entry) in entries.iter_mut().enumerate() {
+        entry.rank = position + 1;
+        let type_rank = type_ranks.entry(entry.building_type).or_default();
+        *type_rank += 1;
+        entry.type_rank = *type_rank;
+    }
+
+    Ok(Benchmark {
+        year,
+        entries,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Days};
+
+    use super::*;
+    use crate::analysis::test_support::daily;
+
+    fn measurements(
+        installation_id: &str,
+        days: u64,
+        kwh: impl Fn(NaiveDate) -> f64,
+    ) -> Vec<Measurement> {
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        (0..days)
+            .map(|day| {
+                let date = start + Days::new(day);
+                daily(date, kwh(date)).with_installation(installation_id)
+            })
+            .collect()
+    }
+
+    fn registry() -> BuildingRegistry {
+        BuildingRegistry::new()
+            .with_building(
+                "INSTALLATION_ID_1",
+                BuildingMetadata::new(1000.0, BuildingType::MultiFamily)
+                    .with_construction_year(1972),
+            )
+            .with_building(
+                "INSTALLATION_ID_2",
+                BuildingMetadata::new(500.0, BuildingType::MultiFamily),
+            )
+            .with_building(
+                "INSTALLATION_ID_3",
+                BuildingMetadata::new(2000.0, BuildingType::School),
+            )
+            .with_building(
+                "INSTALLATION_ID_4",
+                BuildingMetadata::new(100.0, BuildingType::SingleFamily),
+            )
+    }
+
+    #[test]
+    fn parses_csv_registries() {
+        let registry = BuildingRegistry::from_csv_str(
+            "\u{feff}num_inst,SRE,Building_Type,construction_year\n\
+             INSTALLATION_ID_1,850.5,multi_family,1972\n\
+             INSTALLATION_ID_2,120,single_family,\n",
+        )
+        .unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get("INSTALLATION_ID_1"),
+            Some(
+                &BuildingMetadata::new(850.5, BuildingType::MultiFamily)
+                    .with_construction_year(1972)
+            )
+        );
+        assert_eq!(
+            registry.get("INSTALLATION_ID_2").unwrap().construction_year,
+            None
+        );
+
+        for (text, message) in [
+            (
+                "installation_id;building_type\nA;school\n",
+                "no energy reference area column",
+            ),
+            (
+                "installation_id;area_m2;building_type\nA;100;castle\n",
+                "unknown building type `castle`",
+            ),
+            (
+                "installation_id;area_m2;building_type\nA;-5;school\n",
+                "positive number",
+            ),
+            (
+                "installation_id;area_m2;building_type\nA;100;school\nA;200;school\n",
+                "duplicate installation",
+            ),
+        ] {
+            let Err(EnergiaProError::InvalidArgument(error)) = BuildingRegistry::from_csv_str(text)
+            else {
+                panic!("`{text}` should be rejected");
+            };
+            assert!(error.contains(message), "{error}");
+        }
+    }
+
+    #[test]
+    fn deserializes_registries_and_scales_from_toml() {
+        let registry: BuildingRegistry = toml::from_str(
+            r#"
+            [buildings.INSTALLATION_ID_1]
+            energy_reference_area_m2 = 850.0
+            building_type = "multi_family"
+            construction_year = 1972
+
+            [buildings.INSTALLATION_ID_2]
+            energy_reference_area_m2 = 2000.0
+            building_type = "school"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get("INSTALLATION_ID_2").unwrap().building_type,
+            BuildingType::School
+        );
+
+        let scale: ClassScale = toml::from_str(
+            r#"
+            [[classes]]
+            label = "target"
+            max_kwh_per_m2 = 60.0
+
+            [[classes]]
+            label = "above target"
+            "#,
+        )
+        .unwrap();
+        scale.validate().unwrap();
+        assert_eq!(scale.classify(60.0), Some("target"));
+        assert_eq!(scale.classify(60.1), Some("above target"));
+    }
+
+    #[test]
+    fn classifies_and_validates_scales() {
+        let scale = ClassScale::relative_to(100.0);
+        assert_eq!(scale.classify(0.0), Some("A"));
+        assert_eq!(scale.classify(50.0), Some("A"));
+        assert_eq!(scale.classify(100.1), Some("C"));
+        assert_eq!(scale.classify(1000.0), Some("G"));
+
+        let bounded = ClassScale::new([("good", Some(50.0)), ("fair", Some(100.0))]);
+        assert_eq!(bounded.classify(150.0), None);
+
+        for scale in [
+            ClassScale::new([]),
+            ClassScale::new([("A", Some(100.0)), ("B", Some(50.0))]),
+            ClassScale::new([("A", None), ("B", Some(50.0))]),
+            ClassScale::new([("A", Some(f64::NAN))]),
+        ] {
+            assert!(matches!(
+                scale.validate(),
+                Err(EnergiaProError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn ranks_installations_and_skips_incomplete_ones() {
+        let mut all = Vec::new();
+        all.extend(measurements("INSTALLATION_ID_1", 365, |_| 300.0));
+        all.extend(measurements("INSTALLATION_ID_2", 365, |_| 100.0));
+        all.extend(measurements("INSTALLATION_ID_3", 365, |_| 360.0));
+        all.extend(measurements("INSTALLATION_ID_4", 200, |_| 50.0));
+        all.extend(measurements("INSTALLATION_ID_5", 365, |_| 10.0));
+
+        let options = BenchmarkOptions::default()
+            .with_classes(ClassScale::relative_to(100.0))
+            .with_type_classes(
+                BuildingType::School,
+                ClassScale::new([("ok", Some(50.0)), ("poor", None)]),
+            );
+        let benchmark = benchmark_with(&all, &registry(), 2023, &options).unwrap();
+
+        let ranked = benchmark
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.installation_id.as_str(),
+                    entry.rank,
+                    entry.type_rank,
+                    entry.intensity_kwh_per_m2,
+                    entry.class.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranked,
+            [
+                ("INSTALLATION_ID_3", 1, 1, 65.7, Some("poor")),
+                ("INSTALLATION_ID_2", 2, 1, 73.0, Some("B")),
+                ("INSTALLATION_ID_1", 3, 2, 109.5, Some("C")),
+            ]
+        );
+        assert_eq!(benchmark.median_kwh_per_m2(), Some(73.0));
+        assert_eq!(benchmark.entries[2].construction_year, Some(1972));
+        assert!(!benchmark.entries[0].is_normalized());
+
+        let skipped = benchmark
+            .skipped
+            .iter()
+            .map(|skipped| (skipped.installation_id.as_str(), skipped.reason.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                ("INSTALLATION_ID_4", "measurements cover 55% of 2023"),
+                ("INSTALLATION_ID_5", "no building data in the registry"),
+            ]
+        );
+    }
+
+    #[test]
+    fn benchmarks_partial_years_only_when_allowed() {
+        let mut all = measurements("INSTALLATION_ID_1", 365, |_| 300.0);
+        // Measurements of January are missing.
+        all.extend(measurements("INSTALLATION_ID_2", 365, |_| 100.0).split_off(31));
+
+        let complete = benchmark(&all, &registry(), 2023).unwrap();
+        assert_eq!(complete.entries.len(), 1);
+        assert!(!complete.entries[0].is_partial());
+        assert_eq!(complete.skipped[0].reason, "measurements cover 92% of 2023");
+
+        let options = BenchmarkOptions::default().with_min_coverage(0.9);
+        let partial = benchmark_with(&all, &registry(), 2023, &options).unwrap();
+        let entry = partial
+            .entries
+            .iter()
+            .find(|entry| entry.installation_id == "INSTALLATION_ID_2")
+            .unwrap();
+        assert!(entry.is_partial());
+        assert_eq!(entry.coverage, 0.9151);
+    }
+
+    #[test]
+    fn normalizes_intensity_to_the_reference_climate() {
+        // A warm 2023 against a colder reference year 2022.
+        let celsius = |date: NaiveDate| if date.year() == 2023 { 5.0 } else { 0.0 };
+        let temperatures = TemperatureSeries::from_days(
+            NaiveDate::from_ymd_opt(2022, 1, 1)
+                .unwrap()
+                .iter_days()
+                .take(730)
+                .map(|date| (date, celsius(date))),
+        );
+        let all = measurements("INSTALLATION_ID_1", 365, |_| 150.0);
+
+        let options = BenchmarkOptions::default().with_temperatures(temperatures);
+        let benchmark = benchmark_with(&all, &registry(), 2023, &options).unwrap();
+
+        let entry = &benchmark.entries[0];
+        assert!(entry.is_normalized());
+        assert_eq!(entry.consumption_kwh, 54_750.0);
+        // 15 measured against 17.5 reference degree days per day.
+        assert_eq!(entry.normalized_kwh, Some(63_875.0));
+        assert_eq!(entry.intensity_kwh_per_m2, 63.88);
+    }
+}
@@ -0,0 +1,405 @@
+//! Calorific value audit.
+//!
+//! Every measurement reports both a volume and an energy, so the conversion
+//! factor applied by the utility (in kWh/m³) can be derived for each interval.
+//! [`audit`] computes that factor over time for each installation, detects
+//! changes of the billing calorific value, flags intervals outside a plausible
+//! band and summarizes the effective factor per billing month, so that
+//! EnergiaPro data can be reconciled with invoices.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::calorific;
+//!
+//! let measurement = |timestamp: &str, consumption_m3: f64, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3: 0.0,
+//!     consumption_m3,
+//!     consumption_kwh,
+//! };
+//!
+//! let audits = calorific::audit(&[
+//!     measurement("2024-01-31 23:00:00", 10.0, 112.0),
+//!     measurement("2024-02-01 00:00:00", 10.0, 114.0),
+//!     measurement("2024-02-01 01:00:00", 10.0, 114.0),
+//! ]);
+//!
+//! assert_eq!(audits.len(), 1);
+//! assert_eq!(audits[0].changes.len(), 1);
+//! assert_eq!(audits[0].months[1].kwh_per_m3, Some(11.4));
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{group_by_installation, round};
+use crate::models::Measurement;
+use crate::types::Granularity;
+
+/// Thresholds used by [`audit_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalorificOptions {
+    /// Lowest plausible factor, in kWh/m³.
+    pub min_kwh_per_m3: f64,
+
+    /// Highest plausible factor, in kWh/m³.
+    pub max_kwh_per_m3: f64,
+
+    /// Smallest volume, in cubic meters, for which a factor is computed.
+    ///
+    /// Factors of tiny volumes are dominated by the rounding of the API values.
+    pub min_consumption_m3: f64,
+
+    /// Smallest difference between two factors reported as a change, in kWh/m³.
+    pub change_threshold_kwh_per_m3: f64,
+
+    /// Number of consecutive intervals that must agree on a new factor before
+    /// a change is reported. Shorter deviations are treated as noise.
+    pub confirm_intervals: usize,
+}
+
+impl Default for CalorificOptions {
+    fn default() -> Self {
+        Self {
+            min_kwh_per_m3: 10.0,
+            max_kwh_per_m3: 12.0,
+            min_consumption_m3: 0.1,
+            change_threshold_kwh_per_m3: 0.05,
+            confirm_intervals: 2,
+        }
+    }
+}
+
+impl CalorificOptions {
+    /// Set the plausible band of factors, in kWh/m³.
+    pub fn with_band(mut self, min_kwh_per_m3: f64, max_kwh_per_m3: f64) -> Self {
+        self.min_kwh_per_m3 = min_kwh_per_m3;
+        self.max_kwh_per_m3 = max_kwh_per_m3;
+        self
+    }
+
+    /// Set the smallest volume for which a factor is computed.
+    pub fn with_min_consumption_m3(mut self, min_consumption_m3: f64) -> Self {
+        self.min_consumption_m3 = min_consumption_m3;
+        self
+    }
+
+    /// Set the smallest difference between two factors reported as a change.
+    pub fn with_change_threshold_kwh_per_m3(mut self, change_threshold_kwh_per_m3: f64) -> Self {
+        self.change_threshold_kwh_per_m3 = change_threshold_kwh_per_m3;
+        self
+    }
+
+    /// Set the number of consecutive intervals confirming a change.
+    pub fn with_confirm_intervals(mut self, confirm_intervals: usize) -> Self {
+        self.confirm_intervals = confirm_intervals;
+        self
+    }
+}
+
+/// Conversion factor implied by one measurement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntervalFactor {
+    /// Timestamp of the measurement.
+    pub timestamp: String,
+    /// Consumed volume in cubic meters.
+    pub consumption_m3: f64,
+    /// Consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Implied factor in kWh/m³, or `None` if the volume is too small.
+    pub kwh_per_m3: Option<f64>,
+    /// Whether the factor falls outside the plausible band.
+    pub out_of_band: bool,
+}
+
+/// A lasting change of the billing calorific value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalorificChange {
+    /// Timestamp of the first interval billed with the new factor.
+    pub timestamp: String,
+    /// Factor before the change, in kWh/m³.
+    pub previous_kwh_per_m3: f64,
+    /// Factor after the change, in kWh/m³.
+    pub kwh_per_m3: f64,
+}
+
+/// Effective factor of one billing month.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonthlyFactor {
+    /// First day of the month.
+    pub start: NaiveDate,
+    /// Last day of the month.
+    pub end: NaiveDate,
+    /// Total consumed volume in cubic meters.
+    pub consumption_m3: f64,
+    /// Total consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Effective factor (total energy over total volume), in kWh/m³.
+    pub kwh_per_m3: Option<f64>,
+    /// Lowest interval factor of the month, in kWh/m³.
+    pub min_kwh_per_m3: Option<f64>,
+    /// Highest interval factor of the month, in kWh/m³.
+    pub max_kwh_per_m3: Option<f64>,
+    /// Number of intervals outside the plausible band.
+    pub out_of_band_count: usize,
+}
+
+/// Result of the calorific value audit of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalorificAudit {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Factor of every measurement, in input order.
+    pub intervals: Vec<IntervalFactor>,
+    /// Changes of the billing calorific value, in chronological order.
+    pub changes: Vec<CalorificChange>,
+    /// Effective factor per calendar month, in chronological order.
+    pub months: Vec<MonthlyFactor>,
+}
+
+impl CalorificAudit {
+    /// Iterate over the intervals whose factor falls outside the plausible band.
+    pub fn out_of_band(&self) -> impl Iterator<Item = &IntervalFactor> {
+        self.intervals
+            .iter()
+            .filter(|interval| interval.out_of_band)
+    }
+}
+
+/// Audit measurements with the default [`CalorificOptions`].
+pub fn audit(measurements: &[Measurement]) -> Vec<CalorificAudit> {
+    audit_with(measurements, &CalorificOptions::default())
+}
+
+/// Audit measurements with custom thresholds.
+///
+/// Returns one audit per installation, in order of first appearance.
+/// Measurements are expected in chronological order. Rows whose timestamp
+/// cannot be parsed are audited but left out of the monthly summary.
+pub fn audit_with(measurements: &[Measurement], options: &CalorificOptions) -> Vec<CalorificAudit> {
+    group_by_installation(measurements)
+        .into_iter()
+        .map(|(installation_id, group)| audit_installation(installation_id, &group, options))
+        .collect()
+}
+
+fn audit_installation(
+    installation_id: &str,
+    measurements: &[&Measurement],
+    options: &CalorificOptions,
+) -> CalorificAudit {
+    let intervals = measurements
+        .iter()
+        .map(|measurement| {
+            let kwh_per_m3 = (measurement.consumption_m3 >= options.min_consumption_m3)
+                .then(|| round(measurement.consumption_kwh / measurement.consumption_m3, 4));
+
+            IntervalFactor {
+                timestamp: measurement.timestamp.clone(),
+                consumption_m3: measurement.consumption_m3,
+                consumption_kwh: measurement.consumption_kwh,
+                kwh_per_m3,
+                out_of_band: kwh_per_m3.is_some_and(|factor| {
+                    factor < options.min_kwh_per_m3 || factor > options.max_kwh_per_m3
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    CalorificAudit {
+        installation_id: installation_id.to_owned(),
+        changes: detect_changes(&intervals, options),
+        months: summarize_months(measurements, &intervals),
+        intervals,
+    }
+}
+
+fn detect_changes(
+    intervals: &[IntervalFactor],
+    options: &CalorificOptions,
+) -> Vec<CalorificChange> {
+    let threshold = options.change_threshold_kwh_per_m3;
+    let confirm_intervals = options.confirm_intervals.max(1);
+
+    let mut changes = Vec::new();
+    let mut level: Option<f64> = None;
+    let mut pending: Vec<(&str, f64)> = Vec::new();
+
+    for interval in intervals {
+        let Some(factor) = interval.kwh_per_m3 else {
+            continue;
+        };
+
+        let Some(current) = level else {
+            level = Some(factor);
+            continue;
+        };
+
+        if (factor - current).abs() <= threshold {
+            pending.clear();
+            continue;
+        }
+
+        if pending
+            .first()
+            .is_some_and(|(_, candidate)| (factor - candidate).abs() > threshold)
+        {
+            pending.clear();
+        }
+        pending.push((&interval.timestamp, factor));
+
+        if pending.len() >= confirm_intervals {
+            let kwh_per_m3 = round(
+                pending.iter().map(|(_, factor)| factor).sum::<f64>() / pending.len() as f64,
+                4,
+            );
+            changes.push(CalorificChange {
+                timestamp: pending[0].0.to_owned(),
+                previous_kwh_per_m3: current,
+                kwh_per_m3,
+            });
+            level = Some(kwh_per_m3);
+            pending.clear();
+        }
+    }
+
+    changes
+}
+
+fn summarize_months(
+    measurements: &[&Measurement],
+    intervals: &[IntervalFactor],
+) -> Vec<MonthlyFactor> {
+    let mut months: BTreeMap<NaiveDate, MonthlyFactor> = BTreeMap::new();
+
+    for (measurement, interval) in measurements.iter().zip(intervals) {
+        let Ok(timestamp) = measurement.local_timestamp() else {
+            continue;
+        };
+        let (start, end) = Granularity::Month.period(timestamp.date());
+
+        let month = months.entry(start).or_insert_with(|| MonthlyFactor {
+            start,
+            end,
+            consumption_m3: 0.0,
+            consumption_kwh: 0.0,
+            kwh_per_m3: None,
+            min_kwh_per_m3: None,
+            max_kwh_per_m3: None,
+            out_of_band_count: 0,
+        });
+
+        month.consumption_m3 += measurement.consumption_m3;
+        month.consumption_kwh += measurement.consumption_kwh;
+        if let Some(factor) = interval.kwh_per_m3 {
+            month.min_kwh_per_m3 = Some(month.min_kwh_per_m3.map_or(factor, |min| min.min(factor)));
+            month.max_kwh_per_m3 = Some(month.max_kwh_per_m3.map_or(factor, |max| max.max(factor)));
+        }
+        if interval.out_of_band {
+            month.out_of_band_count += 1;
+        }
+    }
+
+    months
+        .into_values()
+        .map(|mut month| {
+            month.kwh_per_m3 = (month.consumption_m3 > 0.0)
+                .then(|| round(month.consumption_kwh / month.consumption_m3, 4));
+            month
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    #[test]
+    fn computes_interval_factors_and_band() {
+        let audit = audit(&[
+            measurement("2024-01-01 00:00:00", 22.5).with_volume(2.0),
+            measurement("2024-01-01 01:00:00", 26.0).with_volume(2.0),
+            measurement("2024-01-01 02:00:00", 0.2).with_volume(0.01),
+        ])
+        .remove(0);
+
+        assert_eq!(audit.intervals[0].kwh_per_m3, Some(11.25));
+        assert!(!audit.intervals[0].out_of_band);
+        assert_eq!(audit.intervals[1].kwh_per_m3, Some(13.0));
+        assert!(audit.intervals[1].out_of_band);
+        assert_eq!(audit.intervals[2].kwh_per_m3, None);
+        assert!(!audit.intervals[2].out_of_band);
+        assert_eq!(audit.out_of_band().count(), 1);
+    }
+
+    #[test]
+    fn detects_lasting_changes_and_ignores_outliers() {
+        let audit = audit(&[
+            measurement("2024-01-31 22:00:00", 112.0).with_volume(10.0),
+            measurement("2024-01-31 23:00:00", 112.0).with_volume(10.0),
+            measurement("2024-02-01 00:00:00", 113.5).with_volume(10.0),
+            measurement("2024-02-01 01:00:00", 113.5).with_volume(10.0),
+            measurement("2024-02-01 02:00:00", 120.0).with_volume(10.0),
+            measurement("2024-02-01 03:00:00", 113.5).with_volume(10.0),
+        ])
+        .remove(0);
+
+        assert_eq!(
+            audit.changes,
+            vec![CalorificChange {
+                timestamp: "2024-02-01 00:00:00".to_owned(),
+                previous_kwh_per_m3: 11.2,
+                kwh_per_m3: 11.35,
+            }]
+        );
+    }
+
+    #[test]
+    fn summarizes_factor_per_month() {
+        let audit = audit(&[
+            measurement("2024-01-31 23:00:00", 112.0).with_volume(10.0),
+            measurement("2024-02-01 00:00:00", 114.0).with_volume(10.0),
+            measurement("2024-02-15 00:00:00", 330.0).with_volume(30.0),
+            measurement("2024-02-16 00:00:00", 125.0).with_volume(10.0),
+        ])
+        .remove(0);
+
+        assert_eq!(audit.months.len(), 2);
+        let february = &audit.months[1];
+        assert_eq!(february.start.to_string(), "2024-02-01");
+        assert_eq!(february.end.to_string(), "2024-02-29");
+        assert_eq!(february.consumption_m3, 50.0);
+        assert_eq!(february.kwh_per_m3, Some(11.38));
+        assert_eq!(february.min_kwh_per_m3, Some(11.0));
+        assert_eq!(february.max_kwh_per_m3, Some(12.5));
+        assert_eq!(february.out_of_band_count, 1);
+    }
+
+    #[test]
+    fn audits_each_installation_separately() {
+        let audits = audit(&[
+            measurement("2024-01-31 23:00:00", 112.0).with_volume(10.0),
+            measurement("2024-01-31 23:00:00", 105.0)
+                .with_volume(10.0)
+                .with_installation("INSTALLATION_ID_2"),
+            measurement("2024-02-01 00:00:00", 112.0).with_volume(10.0),
+            measurement("2024-02-01 00:00:00", 105.0)
+                .with_volume(10.0)
+                .with_installation("INSTALLATION_ID_2"),
+        ]);
+
+        assert_eq!(audits.len(), 2);
+        assert_eq!(audits[0].installation_id, "INSTALLATION_ID_1");
+        assert_eq!(audits[1].installation_id, "INSTALLATION_ID_2");
+        assert!(audits.iter().all(|audit| audit.changes.is_empty()));
+        assert_eq!(audits[0].months[1].kwh_per_m3, Some(11.2));
+        assert_eq!(audits[1].months[1].kwh_per_m3, Some(10.5));
+    }
+}
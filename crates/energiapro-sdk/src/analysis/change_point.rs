@@ -0,0 +1,511 @@
+//! Change point detection on daily consumption.
+//!
+//! A thermostat change, a new occupant or a broken valve moves the baseline of
+//! a building for good, unlike the short deviations reported by
+//! [`anomaly`](super::anomaly). [`detect`] sums the measurements per day and
+//! splits the daily consumption into segments of constant mean with PELT
+//! (Pruned Exact Linear Time, Killick et al. 2012), which minimizes
+//!
+//! ```text
+//! Σ segments Σ days (kWh − segment mean)² + β × number of changes
+//! ```
+//!
+//! The penalty `β = penalty × σ² × ln(n)` grows with the noise `σ` of the
+//! daily consumption, estimated from the median absolute difference between
+//! consecutive days, so that the level shifts themselves do not inflate it.
+//!
+//! Heating consumption also follows the weather: without temperatures, the
+//! start and end of the heating season are reported as change points. With a
+//! [`TemperatureSeries`], the detection runs on the consumption adjusted to the
+//! mean degree days of the analysed days. The adjustment uses a linear
+//! regression on the degree days with one intercept per segment, so that the
+//! level shifts do not bias the slope; the regression and the segmentation
+//! are alternated until the segments no longer change.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use energiapro::Measurement;
+//! use energiapro::analysis::change_point;
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+//! let measurements = start
+//!     .iter_days()
+//!     .take(60)
+//!     .enumerate()
+//!     .map(|(day, date)| {
+//!         // The thermostat is lowered on January 31st.
+//!         let consumption_kwh = if day < 30 { 100.0 } else { 70.0 };
+//!         Measurement {
+//!             client_id: 1,
+//!             installation_id: "INSTALLATION_ID_1".to_owned(),
+//!             timestamp: format!("{date} 00:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: consumption_kwh / 10.0,
+//!             consumption_kwh,
+//!         }
+//!     })
+//!     .collect::<Vec<_>>();
+//!
+//! let changes = change_point::detect(&measurements)?;
+//!
+//! assert_eq!(changes.len(), 1);
+//! assert_eq!(changes[0].date, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+//! assert_eq!(changes[0].level_before_kwh_per_day, 100.0);
+//! assert_eq!(changes[0].level_after_kwh_per_day, 70.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::degree_days::{DegreeDayOptions, TemperatureSeries};
+use super::{daily_consumption, robust_scale, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+/// Maximum number of alternations between the weather adjustment and the
+/// segmentation.
+const MAX_ITERATIONS: usize = 10;
+
+/// Parameters of the change point detection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangePointOptions {
+    /// Daily mean temperatures used to adjust the consumption to the weather,
+    /// or `None` to analyse the raw daily consumption.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Degree day method of the weather adjustment.
+    pub degree_days: DegreeDayOptions,
+
+    /// Share of a day's intervals required for the day to be analysed.
+    pub min_day_coverage: f64,
+
+    /// Minimum number of days between two change points, and before the first
+    /// and after the last one.
+    pub min_segment_days: usize,
+
+    /// Multiplier of `σ² × ln(n)` in the cost of each change point. `2`
+    /// corresponds to the Bayesian information criterion; the default of `3`
+    /// allows for the correlation between consecutive days.
+    pub penalty: f64,
+}
+
+impl Default for ChangePointOptions {
+    fn default() -> Self {
+        Self {
+            temperatures: None,
+            degree_days: DegreeDayOptions::default(),
+            min_day_coverage: 0.9,
+            min_segment_days: 14,
+            penalty: 3.0,
+        }
+    }
+}
+
+impl ChangePointOptions {
+    /// Adjust the daily consumption to the weather with daily temperatures.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the degree day method of the weather adjustment.
+    pub fn with_degree_days(mut self, degree_days: DegreeDayOptions) -> Self {
+        self.degree_days = degree_days;
+        self
+    }
+
+    /// Set the share of a day's intervals required for the day to be analysed.
+    pub fn with_min_day_coverage(mut self, min_day_coverage: f64) -> Self {
+        self.min_day_coverage = min_day_coverage;
+        self
+    }
+
+    /// Set the minimum number of days between two change points.
+    pub fn with_min_segment_days(mut self, min_segment_days: usize) -> Self {
+        self.min_segment_days = min_segment_days;
+        self
+    }
+
+    /// Set the penalty multiplier; higher values report fewer change points.
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if !(self.min_day_coverage > 0.0 && self.min_day_coverage <= 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "min_day_coverage must be greater than 0 and at most 1".to_owned(),
+            ));
+        }
+        if self.min_segment_days < 2 {
+            return Err(EnergiaProError::InvalidArgument(
+                "min_segment_days must be at least 2".to_owned(),
+            ));
+        }
+        if !(self.penalty.is_finite() && self.penalty > 0.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "penalty must be a positive number".to_owned(),
+            ));
+        }
+        self.degree_days.validate()
+    }
+}
+
+/// A permanent shift of the daily consumption.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangePoint {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// First day at the new level.
+    pub date: NaiveDate,
+    /// Mean daily consumption of the segment before the change, in kWh.
+    pub level_before_kwh_per_day: f64,
+    /// Mean daily consumption of the segment after the change, in kWh.
+    pub level_after_kwh_per_day: f64,
+    /// Difference between the levels after and before the change, in kWh.
+    pub change_kwh_per_day: f64,
+    /// Change relative to the level before, as a fraction, or `None` if that
+    /// level is zero.
+    pub relative_change: Option<f64>,
+    /// Number of analysed days in the segment before the change.
+    pub days_before: usize,
+    /// Number of analysed days in the segment after the change.
+    pub days_after: usize,
+    /// Probability, between 0 and 1, that the levels of both segments differ,
+    /// from a two-sided z-test assuming independent daily noise.
+    pub confidence: f64,
+}
+
+/// Detect change points with the default [`ChangePointOptions`].
+///
+/// # Errors
+///
+/// See [`detect_with`].
+pub fn detect(measurements: &[Measurement]) -> Result<Vec<ChangePoint>, EnergiaProError> {
+    detect_with(measurements, &ChangePointOptions::default())
+}
+
+/// Detect change points with custom options.
+///
+/// Incomplete days, and days without a temperature when temperatures are
+/// given, are skipped: segments are sequences of analysed days. Levels are in
+/// kWh per day, adjusted to the mean degree days of the analysed days when
+/// temperatures are given. Change points are returned in chronological order;
+/// series shorter than two segments have none.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the measurements belong to
+/// more than one installation, if a timestamp cannot be parsed, or if the
+/// degree days of the analysed days do not vary.
+pub fn detect_with(
+    measurements: &[Measurement],
+    options: &ChangePointOptions,
+) -> Result<Vec<ChangePoint>, EnergiaProError> {
+    options.validate()?;
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let mut dates = Vec::new();
+    let mut consumption = Vec::new();
+    let mut degree_days = Vec::new();
+    for (date, kwh) in daily_consumption(&series, options.min_day_coverage) {
+        if let Some(temperatures) = &options.temperatures {
+            let Some(celsius) = temperatures.get(date) else {
+                continue;
+            };
+            degree_days.push(options.degree_days.degree_days(celsius));
+        }
+        dates.push(date);
+        consumption.push(kwh);
+    }
+
+    let min_segment = options.min_segment_days;
+    if dates.len() < 2 * min_segment {
+        return Ok(Vec::new());
+    }
+
+    let mut values = consumption.clone();
+    let mut boundaries = vec![0, values.len()];
+    let mut sigma = 0.0;
+    for _ in 0..MAX_ITERATIONS {
+        if options.temperatures.is_some() {
+            let slope =
+                degree_day_slope(&degree_days, &consumption, &boundaries).ok_or_else(|| {
+                    EnergiaProError::InvalidArgument(format!(
+                        "degree days of installation `{}` do not vary over the analysed days",
+                        series.installation_id()
+                    ))
+                })?;
+            let mean_degree_days = degree_days.iter().sum::<f64>() / degree_days.len() as f64;
+            values = consumption
+                .iter()
+                .zip(&degree_days)
+                .map(|(kwh, dd)| kwh - slope * (dd - mean_degree_days))
+                .collect();
+        }
+
+        let Some(noise) = noise(&values) else {
+            return Ok(Vec::new());
+        };
+        sigma = noise;
+        let penalty = options.penalty * sigma * sigma * (values.len() as f64).ln();
+        let next = pelt(&values, min_segment, penalty);
+        let converged = next == boundaries;
+        boundaries = next;
+        if converged || options.temperatures.is_none() {
+            break;
+        }
+    }
+
+    let segments = boundaries
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect::<Vec<_>>();
+    let mean = |(start, end): (usize, usize)| {
+        values[start..end].iter().sum::<f64>() / (end - start) as f64
+    };
+
+    Ok(segments
+        .windows(2)
+        .map(|pair| {
+            let (before, after) = (pair[0], pair[1]);
+            let days_before = before.1 - before.0;
+            let days_after = after.1 - after.0;
+            let level_before = mean(before);
+            let level_after = mean(after);
+            let change = level_after - level_before;
+            let z = change.abs()
+                / (sigma * (1.0 / days_before as f64 + 1.0 / days_after as f64).sqrt());
+
+            ChangePoint {
+                installation_id: series.installation_id().to_owned(),
+                date: dates[after.0],
+                level_before_kwh_per_day: round(level_before, 2),
+                level_after_kwh_per_day: round(level_after, 2),
+                change_kwh_per_day: round(change, 2),
+                relative_change: (level_before != 0.0).then(|| round(change / level_before, 4)),
+                days_before,
+                days_after,
+                confidence: round(erf(z / std::f64::consts::SQRT_2), 4),
+            }
+        })
+        .collect())
+}
+
+/// Return the segment boundaries minimizing the penalized cost, from `0` to
+/// `values.len()`.
+fn pelt(values: &[f64], min_segment: usize, penalty: f64) -> Vec<usize> {
+    let n = values.len();
+    let mut sums = vec![0.0; n + 1];
+    let mut squares = vec![0.0; n + 1];
+    for (index, value) in values.iter().enumerate() {
+        sums[index + 1] = sums[index] + value;
+        squares[index + 1] = squares[index] + value * value;
+    }
+    let cost = |start: usize, end: usize| {
+        let sum = sums[end] - sums[start];
+        (squares[end] - squares[start]) - sum * sum / (end - start) as f64
+    };
+
+    // `best[t]` is the minimal cost of `values[..t]`, `last[t]` the start of
+    // its last segment. Only ends that leave room for whole segments are
+    // candidates, so every segment has at least `min_segment` days.
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut last = vec![0; n + 1];
+    best[0] = -penalty;
+    let mut candidates = vec![0];
+
+    for end in min_segment..=n {
+        if end >= 2 * min_segment {
+            candidates.push(end - min_segment);
+        }
+
+        let (start, total) = candidates
+            .iter()
+            .map(|&start| (start, best[start] + cost(start, end) + penalty))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("candidates always contain a start");
+        best[end] = total;
+        last[end] = start;
+
+        candidates.retain(|&start| best[start] + cost(start, end) <= total);
+    }
+
+    let mut boundaries = vec![n];
+    let mut end = n;
+    while end > 0 {
+        end = last[end];
+        boundaries.push(end);
+    }
+    boundaries.reverse();
+    boundaries
+}
+
+/// Estimate the standard deviation of the daily noise from the differences
+/// between consecutive days, or `None` if the consumption never changes.
+fn noise(values: &[f64]) -> Option<f64> {
+    let differences = values
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).abs())
+        .collect::<Vec<_>>();
+    if differences.is_empty() {
+        return None;
+    }
+    // The difference of two independent days has a standard deviation of
+    // σ × √2.
+    let sigma = robust_scale(differences) / std::f64::consts::SQRT_2;
+
+    (sigma > 0.0).then_some(sigma)
+}
+
+/// Return the slope of the least squares fit of consumption on degree days
+/// with one intercept per segment, or `None` if the degree days do not vary
+/// within any segment.
+fn degree_day_slope(degree_days: &[f64], consumption: &[f64], boundaries: &[usize]) -> Option<f64> {
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for pair in boundaries.windows(2) {
+        let x = &degree_days[pair[0]..pair[1]];
+        let y = &consumption[pair[0]..pair[1]];
+        let mean_x = x.iter().sum::<f64>() / x.len() as f64;
+        let mean_y = y.iter().sum::<f64>() / y.len() as f64;
+        for (x, y) in x.iter().zip(y) {
+            covariance += (x - mean_x) * (y - mean_y);
+            variance += (x - mean_x) * (x - mean_x);
+        }
+    }
+
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+/// Approximate the error function with the Abramowitz and Stegun formula
+/// 7.1.26, accurate to 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    (1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+    use crate::analysis::test_support::daily;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// One measurement per day from `start`, with a small deterministic noise.
+    fn steps(start: &str, levels: &[(usize, f64)]) -> Vec<Measurement> {
+        let start = date(start);
+        levels
+            .iter()
+            .flat_map(|&(days, level)| std::iter::repeat_n(level, days))
+            .enumerate()
+            .map(|(day, level)| {
+                let noise = [0.0, 2.0, -1.0, 3.0, -2.0, 1.0, -3.0][day % 7];
+                daily(start + Days::new(day as u64), level + noise)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ignores_noise_without_level_shift() {
+        let changes = detect(&steps("2024-01-01", &[(120, 100.0)])).unwrap();
+
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn detects_successive_level_shifts() {
+        let measurements = steps("2024-01-01", &[(40, 100.0), (30, 60.0), (50, 90.0)]);
+
+        let changes = detect(&measurements).unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].date, date("2024-02-10"));
+        assert_eq!(changes[0].days_before, 40);
+        assert_eq!(changes[0].days_after, 30);
+        assert!((changes[0].level_before_kwh_per_day - 100.0).abs() < 1.0);
+        assert!((changes[0].level_after_kwh_per_day - 60.0).abs() < 1.0);
+        assert!((changes[0].relative_change.unwrap() + 0.4).abs() < 0.01);
+        assert!(changes[0].confidence > 0.99);
+        assert_eq!(changes[1].date, date("2024-03-11"));
+        assert_eq!(changes[1].days_after, 50);
+        assert!(changes[1].change_kwh_per_day > 25.0);
+    }
+
+    #[test]
+    fn respects_minimum_segment_length() {
+        let measurements = steps("2024-01-01", &[(40, 100.0), (5, 20.0), (40, 100.0)]);
+
+        let options = ChangePointOptions::default().with_min_segment_days(14);
+        let changes = detect_with(&measurements, &options).unwrap();
+        assert!(
+            changes
+                .iter()
+                .all(|change| change.days_before >= 14 && change.days_after >= 14)
+        );
+
+        let options = ChangePointOptions::default().with_min_segment_days(5);
+        let changes = detect_with(&measurements, &options).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].date, date("2024-02-10"));
+        assert_eq!(changes[1].date, date("2024-02-15"));
+    }
+
+    #[test]
+    fn adjusts_to_the_weather() {
+        let start = date("2024-03-01");
+        let celsius = |day: u64| -2.0 + day as f64 * 0.15;
+        let degree_days = |day: u64| DegreeDayOptions::default().degree_days(celsius(day));
+        let temperatures =
+            TemperatureSeries::from_days((0..90).map(|day| (start + Days::new(day), celsius(day))));
+        let measurements = |drop_from: u64| {
+            (0..90)
+                .map(|day| {
+                    let base = if day >= drop_from { 15.0 } else { 30.0 };
+                    let consumption_kwh =
+                        base + 8.0 * degree_days(day) + [0.0, 1.0, -1.0][day as usize % 3];
+                    daily(start + Days::new(day), consumption_kwh)
+                })
+                .collect::<Vec<_>>()
+        };
+        let options = ChangePointOptions::default().with_temperatures(temperatures);
+
+        assert!(!detect(&measurements(90)).unwrap().is_empty());
+        assert!(detect_with(&measurements(90), &options).unwrap().is_empty());
+
+        let changes = detect_with(&measurements(45), &options).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].date, start + Days::new(45));
+        assert!((changes[0].change_kwh_per_day + 15.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let measurements = steps("2024-01-01", &[(30, 100.0)]);
+
+        for options in [
+            ChangePointOptions::default().with_min_segment_days(1),
+            ChangePointOptions::default().with_penalty(0.0),
+            ChangePointOptions::default().with_min_day_coverage(0.0),
+        ] {
+            assert!(matches!(
+                detect_with(&measurements, &options),
+                Err(EnergiaProError::InvalidArgument(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! Year-over-year and period-over-period comparison.
+//!
+//! A current period is compared with a reference period, by default the same
+//! period one year earlier. Days are aligned by calendar date: when both
+//! periods start on the same day of the month, each current day is compared
+//! with the same day of the month, whole months earlier or later, so that
+//! February 29th has no aligned day in a common year. Otherwise, the n-th day
+//! of the current period is compared with the n-th day of the reference
+//! period. Both are broken down by the periods of a [`Granularity`] applied
+//! to the current period.
+//!
+//! Each row reports volumes, energy and, with a [`Tariff`], costs of both
+//! periods with their absolute and relative differences. With a
+//! [`TemperatureSeries`], the reference energy is also adjusted to the
+//! weather of the current period:
+//!
+//! ```text
+//! weather_adjusted_reference_kwh = reference_kwh × current_degree_days / reference_degree_days
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::analysis::comparison;
+//! use energiapro::{DateRange, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: String, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp,
+//!     index_m3: 0.0,
+//!     consumption_m3: consumption_kwh / 10.0,
+//!     consumption_kwh,
+//! };
+//!
+//! let mut measurements = Vec::new();
+//! for day in 1..=31 {
+//!     measurements.push(measurement(format!("2024-01-{day:02} 00:00:00"), 100.0));
+//!     measurements.push(measurement(format!("2025-01-{day:02} 00:00:00"), 90.0));
+//! }
+//!
+//! let january = DateRange::month(2025, 1)?;
+//! let result = comparison::compare(
+//!     &measurements,
+//!     january,
+//!     comparison::same_period_last_year(january)?,
+//! )?;
+//!
+//! assert_eq!(result.total.kwh.absolute, -310.0);
+//! assert_eq!(result.total.kwh.relative, Some(-0.1));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::Serialize;
+
+use super::cost::{self, Tariff};
+use super::degree_days::{DegreeDayOptions, TemperatureSeries};
+use super::round;
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+use crate::types::{DateRange, Granularity};
+
+/// Parameters of the comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonOptions {
+    /// Periods the current period is broken down by.
+    pub granularity: Granularity,
+
+    /// Tariff used to compare costs, or `None` to skip costs.
+    pub tariff: Option<Tariff>,
+
+    /// Daily mean temperatures used to adjust the reference period to the
+    /// weather of the current period, or `None` to skip the adjustment.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Degree day method of the weather adjustment.
+    pub degree_days: DegreeDayOptions,
+}
+
+impl Default for ComparisonOptions {
+    fn default() -> Self {
+        Self {
+            granularity: Granularity::Month,
+            tariff: None,
+            temperatures: None,
+            degree_days: DegreeDayOptions::default(),
+        }
+    }
+}
+
+impl ComparisonOptions {
+    /// Set the periods the current period is broken down by.
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Compare costs with a tariff.
+    pub fn with_tariff(mut self, tariff: Tariff) -> Self {
+        self.tariff = Some(tariff);
+        self
+    }
+
+    /// Adjust the reference period to the weather with daily temperatures.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the degree day method of the weather adjustment.
+    pub fn with_degree_days(mut self, degree_days: DegreeDayOptions) -> Self {
+        self.degree_days = degree_days;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if let Some(tariff) = &self.tariff {
+            tariff.validate()?;
+        }
+        self.degree_days.validate()
+    }
+}
+
+/// Totals of one side of a comparison row.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodTotals {
+    /// First day of the period.
+    pub start: NaiveDate,
+    /// Last day of the period.
+    pub end: NaiveDate,
+    /// Number of days with measurements.
+    pub days: usize,
+    /// Consumption in cubic meters.
+    pub consumption_m3: f64,
+    /// Consumption in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Cost including VAT, when a tariff is given.
+    pub cost: Option<f64>,
+    /// Heating degree days, when temperatures cover every day of the period.
+    pub degree_days: Option<f64>,
+}
+
+/// Difference between the current and the reference value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Change {
+    /// Current minus reference value.
+    pub absolute: f64,
+    /// Absolute difference as a fraction of the reference value, e.g. `-0.1`
+    /// for 10% less. `None` when the reference value is zero.
+    pub relative: Option<f64>,
+}
+
+impl Change {
+    fn between(current: f64, reference: f64) -> Self {
+        let absolute = current - reference;
+        Self {
+            absolute: round(absolute, 2),
+            relative: (reference != 0.0).then(|| round(absolute / reference, 4)),
+        }
+    }
+}
+
+/// Comparison of one period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComparisonRow {
+    /// Totals of the current period.
+    pub current: PeriodTotals,
+    /// Totals of the aligned days of the reference period.
+    pub reference: PeriodTotals,
+    /// Difference in cubic meters.
+    pub m3: Change,
+    /// Difference in kilowatt-hours.
+    pub kwh: Change,
+    /// Difference in cost, when a tariff is given.
+    pub cost: Option<Change>,
+    /// Reference energy adjusted to the weather of the current period.
+    pub weather_adjusted_reference_kwh: Option<f64>,
+    /// Difference with the weather-adjusted reference energy.
+    pub weather_adjusted_kwh: Option<Change>,
+}
+
+/// Comparison of two periods of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Comparison per period, in chronological order.
+    pub rows: Vec<ComparisonRow>,
+    /// Comparison of the whole periods.
+    pub total: ComparisonRow,
+}
+
+/// Return the same period one year earlier.
+///
+/// February 29th maps to February 28th.
+///
+/// # Errors
+///
+/// Returns an error if the range is unbounded or out of range.
+pub fn same_period_last_year(range: DateRange) -> Result<DateRange, EnergiaProError> {
+    let (Some(start), Some(end)) = (range.start(), range.end()) else {
+        return Err(EnergiaProError::InvalidArgument(
+            "period must have a start and an end".to_owned(),
+        ));
+    };
+    let previous = |date: NaiveDate| {
+        date.checked_sub_months(Months::new(12))
+            .ok_or_else(|| EnergiaProError::InvalidArgument("date is out of range".to_owned()))
+    };
+    DateRange::between(previous(start)?, previous(end)?)
+}
+
+/// Compare two periods with the default [`ComparisonOptions`].
+///
+/// # Errors
+///
+/// See [`compare_with`].
+pub fn compare(
+    measurements: &[Measurement],
+    current: DateRange,
+    reference: DateRange,
+) -> Result<Comparison, EnergiaProError> {
+    compare_with(
+        measurements,
+        current,
+        reference,
+        &ComparisonOptions::default(),
+    )
+}
+
+/// Compare a current period with a reference period.
+///
+/// Current days without an aligned reference day, because the reference
+/// period is shorter or the day does not exist in the reference year, are
+/// compared with nothing.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if either period is
+/// unbounded, if the measurements belong to more than one installation or a
+/// timestamp cannot be parsed, or if the tariff has no version for a
+/// measured day.
+pub fn compare_with(
+    measurements: &[Measurement],
+    current: DateRange,
+    reference: DateRange,
+    options: &ComparisonOptions,
+) -> Result<Comparison, EnergiaProError> {
+    options.validate()?;
+
+    let (Some(current_start), Some(current_end), Some(reference_start), Some(reference_end)) = (
+        current.start(),
+        current.end(),
+        reference.start(),
+        reference.end(),
+    ) else {
+        return Err(EnergiaProError::InvalidArgument(
+            "current and reference periods must both have a start and an end".to_owned(),
+        ));
+    };
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let mut days: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+    for point in series.iter() {
+        let day = days.entry(point.timestamp.date_naive()).or_default();
+        day.consumption_m3 += point.consumption_m3;
+        day.consumption_kwh += point.consumption_kwh;
+    }
+    if let Some(tariff) = &options.tariff {
+        let in_periods = measurements
+            .iter()
+            .filter(|measurement| {
+                measurement.local_timestamp().is_ok_and(|timestamp| {
+                    current.contains(timestamp.date()) || reference.contains(timestamp.date())
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for line in cost::calculate(&in_periods, tariff, Granularity::Day)?.lines {
+            days.entry(line.start).or_default().cost = line.total;
+        }
+    }
+
+    let side = |dates: &[NaiveDate]| totals(dates, &days, options);
+    let row = |current_dates: &[NaiveDate]| {
+        let reference_dates = current_dates
+            .iter()
+            .filter_map(|date| {
+                align(*date, current_start, reference_start)
+                    .filter(|date| *date >= reference_start && *date <= reference_end)
+            })
+            .collect::<Vec<_>>();
+        compare_totals(side(current_dates), side(&reference_dates))
+    };
+
+    let mut periods: BTreeMap<NaiveDate, Vec<NaiveDate>> = BTreeMap::new();
+    for date in current_start
+        .iter_days()
+        .take_while(|date| *date <= current_end)
+    {
+        periods
+            .entry(options.granularity.period(date).0.max(current_start))
+            .or_default()
+            .push(date);
+    }
+    let all_dates = periods.values().flatten().copied().collect::<Vec<_>>();
+
+    Ok(Comparison {
+        installation_id: series.installation_id().to_owned(),
+        rows: periods.values().map(|dates| row(dates)).collect(),
+        total: row(&all_dates),
+    })
+}
+
+/// Return the reference day aligned with a current `date`, or `None` if the
+/// day does not exist in the reference period.
+fn align(
+    date: NaiveDate,
+    current_start: NaiveDate,
+    reference_start: NaiveDate,
+) -> Option<NaiveDate> {
+    if current_start.day() != reference_start.day() {
+        let offset = u64::try_from((date - current_start).num_days()).ok()?;
+        return reference_start.checked_add_days(Days::new(offset));
+    }
+
+    let month = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+    let shift = month(current_start) - month(reference_start);
+    let months = Months::new(shift.unsigned_abs());
+    let aligned = if shift >= 0 {
+        date.checked_sub_months(months)
+    } else {
+        date.checked_add_months(months)
+    }?;
+    // Month arithmetic clamps to the end of the month: February 29th would
+    // fall on February 28th, which is already aligned with its own day.
+    (aligned.day() == date.day()).then_some(aligned)
+}
+
+#[derive(Default)]
+struct Day {
+    consumption_m3: f64,
+    consumption_kwh: f64,
+    cost: f64,
+}
+
+fn totals(
+    dates: &[NaiveDate],
+    days: &BTreeMap<NaiveDate, Day>,
+    options: &ComparisonOptions,
+) -> PeriodTotals {
+    let measured = dates
+        .iter()
+        .filter_map(|date| days.get(date))
+        .collect::<Vec<_>>();
+    let degree_days = options.temperatures.as_ref().and_then(|temperatures| {
+        dates
+            .iter()
+            .map(|date| {
+                temperatures
+                    .get(*date)
+                    .map(|celsius| options.degree_days.degree_days(celsius))
+            })
+            .sum::<Option<f64>>()
+    });
+
+    PeriodTotals {
+        start: dates.first().copied().unwrap_or_default(),
+        end: dates.last().copied().unwrap_or_default(),
+        days: measured.len(),
+        consumption_m3: round(measured.iter().map(|day| day.consumption_m3).sum(), 2),
+        consumption_kwh: round(measured.iter().map(|day| day.consumption_kwh).sum(), 2),
+        cost: options
+            .tariff
+            .as_ref()
+            .map(|_| round(measured.iter().map(|day| day.cost).sum(), 2)),
+        degree_days: degree_days
+            .filter(|_| !dates.is_empty())
+            .map(|value| round(value, 2)),
+    }
+}
+
+fn compare_totals(current: PeriodTotals, reference: PeriodTotals) -> ComparisonRow {
+    let weather_adjusted_reference_kwh = current
+        .degree_days
+        .zip(reference.degree_days)
+        .filter(|(_, reference_degree_days)| *reference_degree_days > 0.0)
+        .map(|(current_degree_days, reference_degree_days)| {
+            round(
+                reference.consumption_kwh * current_degree_days / reference_degree_days,
+                2,
+            )
+        });
+
+    ComparisonRow {
+        m3: Change::between(current.consumption_m3, reference.consumption_m3),
+        kwh: Change::between(current.consumption_kwh, reference.consumption_kwh),
+        cost: current
+            .cost
+            .zip(reference.cost)
+            .map(|(current, reference)| Change::between(current, reference)),
+        weather_adjusted_kwh: weather_adjusted_reference_kwh
+            .map(|reference| Change::between(current.consumption_kwh, reference)),
+        weather_adjusted_reference_kwh,
+        current,
+        reference,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::cost::TariffVersion;
+    use crate::analysis::test_support::history;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn maps_periods_to_last_year() {
+        let range = DateRange::between("2024-02-01", "2024-02-29").unwrap();
+
+        assert_eq!(
+            same_period_last_year(range).unwrap(),
+            DateRange::between("2023-02-01", "2023-02-28").unwrap()
+        );
+        assert!(same_period_last_year(DateRange::since("2024-01-01").unwrap()).is_err());
+    }
+
+    #[test]
+    fn compares_aligned_periods_per_month() {
+        let mut measurements = history("2023-01-01", "2023-02-28", |_| 100.0);
+        measurements.extend(history("2024-01-01", "2024-02-29", |_| 80.0));
+        let current = DateRange::between("2024-01-01", "2024-02-29").unwrap();
+
+        let comparison = compare(
+            &measurements,
+            current,
+            same_period_last_year(current).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(comparison.rows.len(), 2);
+        let january = &comparison.rows[0];
+        assert_eq!(january.reference.start, date("2023-01-01"));
+        assert_eq!(january.reference.end, date("2023-01-31"));
+        assert_eq!(january.kwh.absolute, -620.0);
+        assert_eq!(january.kwh.relative, Some(-0.2));
+        assert_eq!(january.m3.absolute, -62.0);
+        assert_eq!(january.cost, None);
+
+        // The 29th of February has no aligned day in 2023: the reference
+        // period is one day shorter.
+        let february = &comparison.rows[1];
+        assert_eq!(february.current.days, 29);
+        assert_eq!(february.reference.days, 28);
+        assert_eq!(february.reference.end, date("2023-02-28"));
+        assert_eq!(comparison.total.current.consumption_kwh, 60.0 * 80.0);
+        assert_eq!(comparison.total.reference.consumption_kwh, 59.0 * 100.0);
+    }
+
+    #[test]
+    fn aligns_days_by_calendar_date_after_a_leap_day() {
+        let mut measurements = history("2023-01-01", "2023-03-31", |day| f64::from(day.day()));
+        measurements.extend(history("2024-01-01", "2024-03-31", |day| {
+            f64::from(day.day())
+        }));
+        let current = DateRange::between("2024-01-01", "2024-03-31").unwrap();
+
+        let comparison = compare(
+            &measurements,
+            current,
+            same_period_last_year(current).unwrap(),
+        )
+        .unwrap();
+
+        let march = &comparison.rows[2];
+        assert_eq!(march.reference.start, date("2023-03-01"));
+        assert_eq!(march.reference.end, date("2023-03-31"));
+        assert_eq!(march.current.days, 31);
+        assert_eq!(march.reference.days, 31);
+        assert_eq!(march.kwh.absolute, 0.0);
+        assert_eq!(comparison.total.current.days, 91);
+        assert_eq!(comparison.total.reference.days, 90);
+    }
+
+    #[test]
+    fn compares_costs_and_weather_adjusted_energy() {
+        let mut measurements = history("2023-01-01", "2023-01-31", |_| 100.0);
+        measurements.extend(history("2024-01-01", "2024-01-31", |_| 90.0));
+        // 2024 was milder: 15 instead of 20 degree days per day.
+        let temperatures = TemperatureSeries::from_days(
+            date("2023-01-01")
+                .iter_days()
+                .take_while(|day| *day <= date("2024-01-31"))
+                .map(|day| (day, if day.year() == 2024 { 5.0 } else { 0.0 })),
+        );
+        let tariff = Tariff::new(
+            "flat",
+            TariffVersion::new(date("2020-01-01")).with_energy_price_per_kwh(0.1),
+        );
+        let options = ComparisonOptions::default()
+            .with_tariff(tariff)
+            .with_temperatures(temperatures);
+
+        let comparison = compare_with(
+            &measurements,
+            DateRange::month(2024, 1).unwrap(),
+            DateRange::month(2023, 1).unwrap(),
+            &options,
+        )
+        .unwrap();
+
+        let total = &comparison.total;
+        assert_eq!(total.current.cost, Some(279.0));
+        assert_eq!(total.reference.cost, Some(310.0));
+        assert_eq!(
+            total.cost,
+            Some(Change {
+                absolute: -31.0,
+                relative: Some(-0.1),
+            })
+        );
+        assert_eq!(total.current.degree_days, Some(465.0));
+        assert_eq!(total.reference.degree_days, Some(620.0));
+        assert_eq!(total.weather_adjusted_reference_kwh, Some(2325.0));
+        assert_eq!(total.weather_adjusted_kwh.unwrap().relative, Some(0.2));
+    }
+
+    #[test]
+    fn rejects_unbounded_periods() {
+        let measurements = history("2024-01-01", "2024-01-31", |_| 10.0);
+
+        assert!(matches!(
+            compare(
+                &measurements,
+                DateRange::since("2024-01-01").unwrap(),
+                DateRange::month(2023, 1).unwrap()
+            ),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
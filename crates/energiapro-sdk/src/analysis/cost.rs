@@ -0,0 +1,788 @@
+//! Tariff and cost calculation.
+//!
+//! A [`Tariff`] describes how gas is billed: a fixed monthly fee, an energy
+//! price per kWh (flat or tiered), network fees, the CO2 levy and VAT. Prices
+//! change over time, so a tariff is a list of [`TariffVersion`]s, each valid
+//! from a given date until the next one.
+//!
+//! Tariffs implement [`serde::Deserialize`] and can be loaded from any serde
+//! format. In TOML, a tariff looks like this:
+//!
+//! ```toml
+//! name = "Gaz naturel"
+//!
+//! [[versions]]
+//! valid_from = "2024-01-01"
+//! monthly_fee = 12.5
+//! network_fee_per_kwh = 0.045
+//! co2_levy_per_kwh = 0.0242
+//! vat_rate = 0.081
+//!
+//! [[versions.tiers]]
+//! up_to_kwh = 2000.0
+//! price_per_kwh = 0.12
+//!
+//! [[versions.tiers]]
+//! price_per_kwh = 0.10
+//! ```
+//!
+//! [`calculate`] computes itemized costs per installation and period, and
+//! [`compare`] ranks several tariffs on the same consumption.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use energiapro::analysis::cost::{self, Tariff, TariffVersion};
+//! use energiapro::{Granularity, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: &str, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3: 0.0,
+//!     consumption_m3: consumption_kwh / 10.0,
+//!     consumption_kwh,
+//! };
+//!
+//! let tariff = Tariff::new(
+//!     "Flat",
+//!     TariffVersion::new(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+//!         .with_energy_price_per_kwh(0.1)
+//!         .with_vat_rate(0.1),
+//! );
+//!
+//! let report = cost::calculate(
+//!     &[
+//!         measurement("2024-01-01 00:00:00", 100.0),
+//!         measurement("2024-01-01 01:00:00", 50.0),
+//!     ],
+//!     &tariff,
+//!     Granularity::Month,
+//! )?;
+//!
+//! assert_eq!(report.lines[0].energy, 15.0);
+//! assert_eq!(report.total(), 16.5);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::round;
+use crate::errors::EnergiaProError;
+use crate::models::Measurement;
+use crate::types::Granularity;
+
+/// A gas tariff made of one or more dated versions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tariff {
+    /// Human readable name, used to label reports and comparisons.
+    pub name: String,
+    /// Prices of the tariff, each valid from its `valid_from` date until the
+    /// next version.
+    pub versions: Vec<TariffVersion>,
+}
+
+/// Prices of a tariff from a given date.
+///
+/// Amounts are expressed excluding VAT, in the currency of the tariff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffVersion {
+    /// First day on which these prices apply.
+    pub valid_from: NaiveDate,
+    /// Fixed fee per calendar month, prorated per day.
+    #[serde(default)]
+    pub monthly_fee: f64,
+    /// Energy price per kWh, used when no tiers are defined.
+    #[serde(default)]
+    pub energy_price_per_kwh: f64,
+    /// Tiered energy prices, based on the consumption of the calendar month.
+    ///
+    /// When present, tiers replace `energy_price_per_kwh`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<PriceTier>,
+    /// Network usage fee per kWh.
+    #[serde(default)]
+    pub network_fee_per_kwh: f64,
+    /// CO2 levy per kWh.
+    #[serde(default)]
+    pub co2_levy_per_kwh: f64,
+    /// VAT rate applied to all other items, e.g. `0.081` for 8.1%.
+    #[serde(default)]
+    pub vat_rate: f64,
+}
+
+/// One block of a tiered energy price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceTier {
+    /// Monthly consumption in kWh up to which this tier applies, or `None` for
+    /// the last tier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_to_kwh: Option<f64>,
+    /// Energy price per kWh within this tier.
+    pub price_per_kwh: f64,
+}
+
+impl Tariff {
+    /// Create a tariff with a single version.
+    pub fn new(name: impl Into<String>, version: TariffVersion) -> Self {
+        Self {
+            name: name.into(),
+            versions: vec![version],
+        }
+    }
+
+    /// Add a version to the tariff.
+    pub fn with_version(mut self, version: TariffVersion) -> Self {
+        self.versions.push(version);
+        self
+    }
+
+    /// Return the version in force on `date`.
+    pub fn version_on(&self, date: NaiveDate) -> Option<&TariffVersion> {
+        self.versions
+            .iter()
+            .filter(|version| version.valid_from <= date)
+            .max_by_key(|version| version.valid_from)
+    }
+
+    /// Check that the tariff is consistent.
+    ///
+    /// A tariff needs at least one version, distinct `valid_from` dates,
+    /// finite non-negative prices, a VAT rate between 0 and 1, and tiers with
+    /// increasing thresholds where only the last tier is unbounded.
+    pub fn validate(&self) -> Result<(), EnergiaProError> {
+        let invalid = |message: String| {
+            Err(EnergiaProError::InvalidArgument(format!(
+                "tariff `{}`: {message}",
+                self.name
+            )))
+        };
+
+        if self.versions.is_empty() {
+            return invalid("at least one version is required".to_owned());
+        }
+
+        for (position, version) in self.versions.iter().enumerate() {
+            let valid_from = version.valid_from;
+
+            if self.versions[..position]
+                .iter()
+                .any(|other| other.valid_from == valid_from)
+            {
+                return invalid(format!("several versions are valid from {valid_from}"));
+            }
+
+            let amounts = [
+                ("monthly_fee", version.monthly_fee),
+                ("energy_price_per_kwh", version.energy_price_per_kwh),
+                ("network_fee_per_kwh", version.network_fee_per_kwh),
+                ("co2_levy_per_kwh", version.co2_levy_per_kwh),
+            ]
+            .into_iter()
+            .chain(
+                version
+                    .tiers
+                    .iter()
+                    .map(|tier| ("price_per_kwh", tier.price_per_kwh)),
+            );
+            for (field, amount) in amounts {
+                if !amount.is_finite() || amount < 0.0 {
+                    return invalid(format!(
+                        "{field} of the version valid from {valid_from} must be a non-negative number"
+                    ));
+                }
+            }
+
+            if !(0.0..=1.0).contains(&version.vat_rate) {
+                return invalid(format!(
+                    "vat_rate of the version valid from {valid_from} must be between 0 and 1"
+                ));
+            }
+
+            let mut previous = 0.0;
+            for (index, tier) in version.tiers.iter().enumerate() {
+                let last = index + 1 == version.tiers.len();
+                match tier.up_to_kwh {
+                    None if last => {}
+                    None => {
+                        return invalid(format!(
+                            "only the last tier of the version valid from {valid_from} may omit up_to_kwh"
+                        ));
+                    }
+                    Some(_) if last => {
+                        return invalid(format!(
+                            "the last tier of the version valid from {valid_from} must omit up_to_kwh"
+                        ));
+                    }
+                    Some(up_to_kwh) if !up_to_kwh.is_finite() || up_to_kwh <= previous => {
+                        return invalid(format!(
+                            "tier thresholds of the version valid from {valid_from} must be increasing"
+                        ));
+                    }
+                    Some(up_to_kwh) => previous = up_to_kwh,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TariffVersion {
+    /// Create a version valid from `valid_from` with all prices set to zero.
+    pub fn new(valid_from: NaiveDate) -> Self {
+        Self {
+            valid_from,
+            monthly_fee: 0.0,
+            energy_price_per_kwh: 0.0,
+            tiers: Vec::new(),
+            network_fee_per_kwh: 0.0,
+            co2_levy_per_kwh: 0.0,
+            vat_rate: 0.0,
+        }
+    }
+
+    /// Set the fixed fee per calendar month.
+    pub fn with_monthly_fee(mut self, monthly_fee: f64) -> Self {
+        self.monthly_fee = monthly_fee;
+        self
+    }
+
+    /// Set the flat energy price per kWh.
+    pub fn with_energy_price_per_kwh(mut self, energy_price_per_kwh: f64) -> Self {
+        self.energy_price_per_kwh = energy_price_per_kwh;
+        self
+    }
+
+    /// Add a tier to the energy price. Pass `None` for the last tier.
+    pub fn with_tier(mut self, up_to_kwh: Option<f64>, price_per_kwh: f64) -> Self {
+        self.tiers.push(PriceTier {
+            up_to_kwh,
+            price_per_kwh,
+        });
+        self
+    }
+
+    /// Set the network usage fee per kWh.
+    pub fn with_network_fee_per_kwh(mut self, network_fee_per_kwh: f64) -> Self {
+        self.network_fee_per_kwh = network_fee_per_kwh;
+        self
+    }
+
+    /// Set the CO2 levy per kWh.
+    pub fn with_co2_levy_per_kwh(mut self, co2_levy_per_kwh: f64) -> Self {
+        self.co2_levy_per_kwh = co2_levy_per_kwh;
+        self
+    }
+
+    /// Set the VAT rate, e.g. `0.081` for 8.1%.
+    pub fn with_vat_rate(mut self, vat_rate: f64) -> Self {
+        self.vat_rate = vat_rate;
+        self
+    }
+
+    /// Return the energy cost of `kwh` consumed after `month_kwh` were already
+    /// consumed in the same calendar month.
+    fn energy_cost(&self, month_kwh: f64, kwh: f64) -> f64 {
+        if self.tiers.is_empty() {
+            return kwh * self.energy_price_per_kwh;
+        }
+
+        // Corrections reduce the monthly consumption at the marginal price.
+        if kwh < 0.0 {
+            return kwh * self.tier_price((month_kwh + kwh).max(0.0));
+        }
+
+        let mut cost = 0.0;
+        let mut lower = 0.0;
+        for tier in &self.tiers {
+            let upper = tier.up_to_kwh.unwrap_or(f64::INFINITY);
+            let overlap = (month_kwh + kwh).min(upper) - month_kwh.max(lower);
+            if overlap > 0.0 {
+                cost += overlap * tier.price_per_kwh;
+            }
+            lower = upper;
+        }
+        cost
+    }
+
+    fn tier_price(&self, month_kwh: f64) -> f64 {
+        self.tiers
+            .iter()
+            .find(|tier| tier.up_to_kwh.is_none_or(|up_to_kwh| month_kwh < up_to_kwh))
+            .map_or(self.energy_price_per_kwh, |tier| tier.price_per_kwh)
+    }
+}
+
+/// Itemized cost of one installation over one period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostLine {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// First day of the period.
+    pub start: NaiveDate,
+    /// Last day of the period.
+    pub end: NaiveDate,
+    /// Consumed volume in cubic meters.
+    pub consumption_m3: f64,
+    /// Consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Prorated fixed monthly fee.
+    pub fixed_fee: f64,
+    /// Energy price.
+    pub energy: f64,
+    /// Network usage fee.
+    pub network_fee: f64,
+    /// CO2 levy.
+    pub co2_levy: f64,
+    /// Sum of all items, excluding VAT.
+    pub net: f64,
+    /// VAT on the net amount.
+    pub vat: f64,
+    /// Amount including VAT.
+    pub total: f64,
+}
+
+/// Costs of a set of measurements under one tariff.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostReport {
+    /// Name of the tariff.
+    pub tariff: String,
+    /// One line per installation and period, sorted by installation then
+    /// period.
+    pub lines: Vec<CostLine>,
+}
+
+impl CostReport {
+    /// Total consumed energy in kilowatt-hours.
+    pub fn consumption_kwh(&self) -> f64 {
+        self.lines.iter().map(|line| line.consumption_kwh).sum()
+    }
+
+    /// Total amount excluding VAT.
+    pub fn net(&self) -> f64 {
+        round(self.lines.iter().map(|line| line.net).sum(), 2)
+    }
+
+    /// Total VAT.
+    pub fn vat(&self) -> f64 {
+        round(self.lines.iter().map(|line| line.vat).sum(), 2)
+    }
+
+    /// Total amount including VAT.
+    pub fn total(&self) -> f64 {
+        round(self.lines.iter().map(|line| line.total).sum(), 2)
+    }
+}
+
+/// Cost of the same consumption under one of several tariffs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TariffComparison {
+    /// Name of the tariff.
+    pub tariff: String,
+    /// Total consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Total amount excluding VAT.
+    pub net: f64,
+    /// Total VAT.
+    pub vat: f64,
+    /// Total amount including VAT.
+    pub total: f64,
+}
+
+/// Compute itemized costs per installation and period.
+///
+/// Energy-based items are charged at the prices in force on the local date of
+/// each measurement, and tiers are applied to the cumulative consumption of
+/// each installation within a calendar month. The monthly fee accrues for every
+/// day between the first and last measurement of an installation, prorated by
+/// the length of the month. Amounts are rounded to cents per line.
+///
+/// Returns an error if the tariff is invalid, if a timestamp cannot be parsed
+/// or if no tariff version is in force on a day that must be billed.
+pub fn calculate(
+    measurements: &[Measurement],
+    tariff: &Tariff,
+    granularity: Granularity,
+) -> Result<CostReport, EnergiaProError> {
+    tariff.validate()?;
+
+    let mut installations: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+    for measurement in measurements {
+        installations
+            .entry(measurement.installation_id.as_str())
+            .or_default()
+            .push((measurement.local_timestamp()?, measurement));
+    }
+
+    let mut lines = Vec::new();
+    for (installation_id, mut rows) in installations {
+        rows.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let mut periods: BTreeMap<NaiveDate, Accumulator> = BTreeMap::new();
+        let mut month_kwh: HashMap<NaiveDate, f64> = HashMap::new();
+
+        for (timestamp, measurement) in &rows {
+            let date = timestamp.date();
+            let version = version_on(tariff, date)?;
+            let kwh = measurement.consumption_kwh;
+
+            let consumed = month_kwh
+                .entry(Granularity::Month.period(date).0)
+                .or_default();
+            let energy = version.energy_cost(*consumed, kwh);
+            *consumed += kwh;
+
+            let network_fee = kwh * version.network_fee_per_kwh;
+            let co2_levy = kwh * version.co2_levy_per_kwh;
+
+            let period = periods.entry(granularity.period(date).0).or_default();
+            period.consumption_m3 += measurement.consumption_m3;
+            period.consumption_kwh += kwh;
+            period.energy += energy;
+            period.network_fee += network_fee;
+            period.co2_levy += co2_levy;
+            period.vat += (energy + network_fee + co2_levy) * version.vat_rate;
+        }
+
+        if let (Some((first, _)), Some((last, _))) = (rows.first(), rows.last()) {
+            let mut date = first.date();
+            while date <= last.date() {
+                let version = version_on(tariff, date)?;
+                let fee = version.monthly_fee / f64::from(days_in_month(date));
+
+                let period = periods.entry(granularity.period(date).0).or_default();
+                period.fixed_fee += fee;
+                period.vat += fee * version.vat_rate;
+
+                date = date + Days::new(1);
+            }
+        }
+
+        lines.extend(periods.into_iter().map(|(start, period)| {
+            period.into_line(installation_id, start, granularity.period(start).1)
+        }));
+    }
+
+    Ok(CostReport {
+        tariff: tariff.name.clone(),
+        lines,
+    })
+}
+
+/// Compute the cost of the same measurements under several tariffs.
+///
+/// Comparisons are sorted from the cheapest to the most expensive total.
+pub fn compare(
+    measurements: &[Measurement],
+    tariffs: &[Tariff],
+) -> Result<Vec<TariffComparison>, EnergiaProError> {
+    let mut comparisons = tariffs
+        .iter()
+        .map(|tariff| {
+            let report = calculate(measurements, tariff, Granularity::Year)?;
+            Ok(TariffComparison {
+                tariff: report.tariff.clone(),
+                consumption_kwh: report.consumption_kwh(),
+                net: report.net(),
+                vat: report.vat(),
+                total: report.total(),
+            })
+        })
+        .collect::<Result<Vec<_>, EnergiaProError>>()?;
+
+    comparisons.sort_by(|a, b| a.total.total_cmp(&b.total));
+    Ok(comparisons)
+}
+
+#[derive(Default)]
+struct Accumulator {
+    consumption_m3: f64,
+    consumption_kwh: f64,
+    fixed_fee: f64,
+    energy: f64,
+    network_fee: f64,
+    co2_levy: f64,
+    vat: f64,
+}
+
+impl Accumulator {
+    fn into_line(self, installation_id: &str, start: NaiveDate, end: NaiveDate) -> CostLine {
+        let fixed_fee = round(self.fixed_fee, 2);
+        let energy = round(self.energy, 2);
+        let network_fee = round(self.network_fee, 2);
+        let co2_levy = round(self.co2_levy, 2);
+        let net = round(fixed_fee + energy + network_fee + co2_levy, 2);
+        let vat = round(self.vat, 2);
+
+        CostLine {
+            installation_id: installation_id.to_owned(),
+            start,
+            end,
+            consumption_m3: self.consumption_m3,
+            consumption_kwh: self.consumption_kwh,
+            fixed_fee,
+            energy,
+            network_fee,
+            co2_levy,
+            net,
+            vat,
+            total: round(net + vat, 2),
+        }
+    }
+}
+
+fn version_on(tariff: &Tariff, date: NaiveDate) -> Result<&TariffVersion, EnergiaProError> {
+    tariff.version_on(date).ok_or_else(|| {
+        EnergiaProError::InvalidArgument(format!(
+            "tariff `{}` has no version valid on {date}",
+            tariff.name
+        ))
+    })
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    Granularity::Month.period(date).1.day()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn deserializes_tariffs_from_toml() {
+        let tariff: Tariff = toml::from_str(
+            r#"
+            name = "Gaz naturel"
+
+            [[versions]]
+            valid_from = "2024-01-01"
+            monthly_fee = 12.5
+            network_fee_per_kwh = 0.045
+            vat_rate = 0.081
+
+            [[versions.tiers]]
+            up_to_kwh = 2000.0
+            price_per_kwh = 0.12
+
+            [[versions.tiers]]
+            price_per_kwh = 0.10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tariff,
+            Tariff::new(
+                "Gaz naturel",
+                TariffVersion::new(date("2024-01-01"))
+                    .with_monthly_fee(12.5)
+                    .with_network_fee_per_kwh(0.045)
+                    .with_vat_rate(0.081)
+                    .with_tier(Some(2000.0), 0.12)
+                    .with_tier(None, 0.10),
+            )
+        );
+        assert!(tariff.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_inconsistent_tariffs() {
+        let version = || TariffVersion::new(date("2024-01-01"));
+
+        let invalid = [
+            Tariff {
+                name: "empty".to_owned(),
+                versions: Vec::new(),
+            },
+            Tariff::new("duplicate", version()).with_version(version()),
+            Tariff::new("negative", version().with_monthly_fee(-1.0)),
+            Tariff::new("vat", version().with_vat_rate(8.1)),
+            Tariff::new(
+                "unbounded",
+                version().with_tier(None, 0.1).with_tier(None, 0.1),
+            ),
+            Tariff::new("bounded", version().with_tier(Some(100.0), 0.1)),
+            Tariff::new(
+                "decreasing",
+                version()
+                    .with_tier(Some(100.0), 0.1)
+                    .with_tier(Some(50.0), 0.1)
+                    .with_tier(None, 0.1),
+            ),
+        ];
+
+        for tariff in invalid {
+            assert!(
+                matches!(tariff.validate(), Err(EnergiaProError::InvalidArgument(_))),
+                "{} should be invalid",
+                tariff.name
+            );
+        }
+    }
+
+    #[test]
+    fn itemizes_costs_per_period() {
+        let tariff = Tariff::new(
+            "Gaz naturel",
+            TariffVersion::new(date("2024-01-01"))
+                .with_monthly_fee(31.0)
+                .with_energy_price_per_kwh(0.1)
+                .with_network_fee_per_kwh(0.05)
+                .with_co2_levy_per_kwh(0.02)
+                .with_vat_rate(0.1),
+        );
+
+        let report = calculate(
+            &[
+                measurement("2024-01-01 00:00:00", 100.0),
+                measurement("2024-01-10 00:00:00", 100.0),
+            ],
+            &tariff,
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.lines,
+            vec![CostLine {
+                installation_id: "INSTALLATION_ID_1".to_owned(),
+                start: date("2024-01-01"),
+                end: date("2024-01-31"),
+                consumption_m3: 20.0,
+                consumption_kwh: 200.0,
+                fixed_fee: 10.0,
+                energy: 20.0,
+                network_fee: 10.0,
+                co2_levy: 4.0,
+                net: 44.0,
+                vat: 4.4,
+                total: 48.4,
+            }]
+        );
+    }
+
+    #[test]
+    fn applies_tiers_per_calendar_month() {
+        let tariff = Tariff::new(
+            "Tiered",
+            TariffVersion::new(date("2024-01-01"))
+                .with_tier(Some(150.0), 0.2)
+                .with_tier(None, 0.1),
+        );
+
+        let report = calculate(
+            &[
+                measurement("2024-01-30 00:00:00", 100.0),
+                measurement("2024-01-31 00:00:00", 100.0),
+                measurement("2024-02-01 00:00:00", 100.0),
+            ],
+            &tariff,
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(report.lines[0].energy, 35.0);
+        assert_eq!(report.lines[1].energy, 20.0);
+    }
+
+    #[test]
+    fn switches_prices_on_version_dates() {
+        let tariff = Tariff::new(
+            "Indexed",
+            TariffVersion::new(date("2024-01-01"))
+                .with_monthly_fee(31.0)
+                .with_energy_price_per_kwh(0.1),
+        )
+        .with_version(
+            TariffVersion::new(date("2024-01-16"))
+                .with_monthly_fee(62.0)
+                .with_energy_price_per_kwh(0.2),
+        );
+
+        let report = calculate(
+            &[
+                measurement("2024-01-01 00:00:00", 100.0),
+                measurement("2024-01-31 00:00:00", 100.0),
+            ],
+            &tariff,
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(report.lines[0].fixed_fee, 47.0);
+        assert_eq!(report.lines[0].energy, 30.0);
+    }
+
+    #[test]
+    fn rejects_measurements_before_the_first_version() {
+        let tariff = Tariff::new("Late", TariffVersion::new(date("2024-06-01")));
+
+        let result = calculate(
+            &[measurement("2024-01-01 00:00:00", 1.0)],
+            &tariff,
+            Granularity::Month,
+        );
+
+        assert!(matches!(result, Err(EnergiaProError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn splits_lines_per_installation() {
+        let tariff = Tariff::new(
+            "Flat",
+            TariffVersion::new(date("2024-01-01")).with_energy_price_per_kwh(0.1),
+        );
+
+        let report = calculate(
+            &[
+                measurement("2024-01-01 00:00:00", 10.0).with_installation("INSTALLATION_ID_2"),
+                measurement("2024-01-01 00:00:00", 20.0),
+            ],
+            &tariff,
+            Granularity::Year,
+        )
+        .unwrap();
+
+        assert_eq!(report.lines.len(), 2);
+        assert_eq!(report.lines[0].installation_id, "INSTALLATION_ID_1");
+        assert_eq!(report.lines[0].end, date("2024-12-31"));
+        assert_eq!(report.total(), 3.0);
+    }
+
+    #[test]
+    fn ranks_tariffs_from_cheapest() {
+        let measurements = [
+            measurement("2024-01-01 00:00:00", 1000.0),
+            measurement("2024-01-31 00:00:00", 1000.0),
+        ];
+        let flat = Tariff::new(
+            "Flat",
+            TariffVersion::new(date("2024-01-01")).with_energy_price_per_kwh(0.1),
+        );
+        let subscription = Tariff::new(
+            "Subscription",
+            TariffVersion::new(date("2024-01-01"))
+                .with_monthly_fee(31.0)
+                .with_energy_price_per_kwh(0.08),
+        );
+
+        let comparisons = compare(&measurements, &[flat, subscription]).unwrap();
+
+        assert_eq!(comparisons[0].tariff, "Subscription");
+        assert_eq!(comparisons[0].total, 191.0);
+        assert_eq!(comparisons[1].tariff, "Flat");
+        assert_eq!(comparisons[1].total, 200.0);
+    }
+}
@@ -0,0 +1,588 @@
+//! Heating degree day normalization.
+//!
+//! Gas consumption follows the outdoor temperature, so comparing two winters
+//! is only meaningful once consumption is corrected for the weather. This
+//! module loads daily mean temperatures from a local CSV file, computes
+//! heating degree days and scales consumption to a reference climate:
+//!
+//! ```text
+//! normalized_kwh = consumption_kwh × reference_degree_days / degree_days
+//! ```
+//!
+//! Degree days follow the SIA definition: a day whose mean temperature is below
+//! the heating limit contributes the difference between the indoor temperature
+//! and the outdoor mean. The default SIA 20/12 uses 20 °C indoors and a 12 °C
+//! heating limit. The reference is the average of each calendar day over the
+//! years of the temperature file, or over a chosen reference period.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::analysis::degree_days::{self, TemperatureSeries};
+//! use energiapro::{Granularity, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let temperatures = TemperatureSeries::from_csv_str(
+//!     "station_abbr;reference_timestamp;tre200d0\n\
+//!      PUY;01.01.2023 00:00;2.0\n\
+//!      PUY;01.01.2024 00:00;6.0\n",
+//! )?;
+//!
+//! let measurement = Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: "2024-01-01 00:00:00".to_owned(),
+//!     index_m3: 0.0,
+//!     consumption_m3: 14.0,
+//!     consumption_kwh: 140.0,
+//! };
+//!
+//! let periods = degree_days::normalize(&[measurement], &temperatures, Granularity::Month)?;
+//!
+//! assert_eq!(periods[0].degree_days, 14.0);
+//! assert_eq!(periods[0].reference_degree_days, Some(16.0));
+//! assert_eq!(periods[0].normalized_kwh, Some(160.0));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::Serialize;
+
+use super::{csv_column, csv_reader, round};
+use crate::errors::EnergiaProError;
+use crate::models::Measurement;
+use crate::types::Granularity;
+
+/// Header names recognized as the date column, compared case-insensitively.
+const DATE_COLUMNS: &[&str] = &["reference_timestamp", "time", "date", "datum"];
+
+/// Header names recognized as the temperature column, compared case-insensitively.
+///
+/// `tre200d0` is the MeteoSwiss parameter for the daily mean air temperature
+/// 2 m above ground.
+const TEMPERATURE_COLUMNS: &[&str] = &["tre200d0", "temperature", "mean_temperature", "tmean"];
+
+/// Parameters of the degree day computation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeDayOptions {
+    /// Indoor temperature in °C.
+    pub indoor_celsius: f64,
+
+    /// Outdoor daily mean temperature in °C from which the building is heated.
+    pub heating_limit_celsius: f64,
+
+    /// Inclusive range of years averaged into the reference climate, or `None`
+    /// to use every year of the temperature series.
+    pub reference_years: Option<(i32, i32)>,
+}
+
+impl Default for DegreeDayOptions {
+    fn default() -> Self {
+        Self {
+            indoor_celsius: 20.0,
+            heating_limit_celsius: 12.0,
+            reference_years: None,
+        }
+    }
+}
+
+impl DegreeDayOptions {
+    /// Set the indoor temperature.
+    pub fn with_indoor_celsius(mut self, indoor_celsius: f64) -> Self {
+        self.indoor_celsius = indoor_celsius;
+        self
+    }
+
+    /// Set the heating limit temperature.
+    pub fn with_heating_limit_celsius(mut self, heating_limit_celsius: f64) -> Self {
+        self.heating_limit_celsius = heating_limit_celsius;
+        self
+    }
+
+    /// Use a single base temperature, as in the classic `HDD 18` definition.
+    pub fn with_base_celsius(self, base_celsius: f64) -> Self {
+        self.with_indoor_celsius(base_celsius)
+            .with_heating_limit_celsius(base_celsius)
+    }
+
+    /// Restrict the reference climate to the years `from..=to`.
+    pub fn with_reference_years(mut self, from: i32, to: i32) -> Self {
+        self.reference_years = Some((from, to));
+        self
+    }
+
+    /// Return the degree days of one day with the given mean temperature.
+    pub fn degree_days(&self, mean_celsius: f64) -> f64 {
+        if mean_celsius < self.heating_limit_celsius {
+            (self.indoor_celsius - mean_celsius).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), EnergiaProError> {
+        if !self.indoor_celsius.is_finite() || !self.heating_limit_celsius.is_finite() {
+            return Err(EnergiaProError::InvalidArgument(
+                "degree day temperatures must be finite".to_owned(),
+            ));
+        }
+        if let Some((from, to)) = self.reference_years
+            && from > to
+        {
+            return Err(EnergiaProError::InvalidArgument(format!(
+                "reference years {from}-{to} must be in chronological order"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Daily mean outdoor temperatures, one value per date.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemperatureSeries {
+    days: BTreeMap<NaiveDate, f64>,
+}
+
+impl TemperatureSeries {
+    /// Build a series from `(date, mean temperature in °C)` pairs.
+    ///
+    /// When a date appears several times, the last value wins.
+    pub fn from_days(days: impl IntoIterator<Item = (NaiveDate, f64)>) -> Self {
+        Self {
+            days: days.into_iter().collect(),
+        }
+    }
+
+    /// Read a CSV file of daily temperatures.
+    ///
+    /// See [`TemperatureSeries::from_csv_str`] for the accepted layout.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EnergiaProError> {
+        Self::from_csv_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse daily temperatures from CSV text.
+    ///
+    /// The delimiter (`;` or `,`) is detected from the header row. The date
+    /// column is the first one named `reference_timestamp`, `time`, `date` or
+    /// `datum`, and the temperature column the first one named `tre200d0`,
+    /// `temperature`, `mean_temperature` or `tmean`, which covers MeteoSwiss
+    /// open data and IDAweb exports. Dates may be written `YYYY-MM-DD`,
+    /// `DD.MM.YYYY` or `YYYYMMDD`, optionally followed by a time. Rows without
+    /// a value (empty or `-`) are skipped.
+    pub fn from_csv_str(text: &str) -> Result<Self, EnergiaProError> {
+        Self::parse_csv(text, None)
+    }
+
+    /// Parse daily temperatures from CSV text with explicit column names.
+    pub fn from_csv_str_with_columns(
+        text: &str,
+        date_column: &str,
+        temperature_column: &str,
+    ) -> Result<Self, EnergiaProError> {
+        Self::parse_csv(text, Some((date_column, temperature_column)))
+    }
+
+    /// Return the mean temperature of `date`, if known.
+    pub fn get(&self, date: NaiveDate) -> Option<f64> {
+        self.days.get(&date).copied()
+    }
+
+    /// Return the number of days in the series.
+    pub fn len(&self) -> usize {
+        self.days.len()
+    }
+
+    /// Return `true` if the series has no day.
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    /// Iterate over `(date, mean temperature)` pairs in chronological order.
+    pub fn iter(&self) -> impl Iterator<Item = (NaiveDate, f64)> + '_ {
+        self.days.iter().map(|(date, celsius)| (*date, *celsius))
+    }
+
+    fn parse_csv(text: &str, columns: Option<(&str, &str)>) -> Result<Self, EnergiaProError> {
+        let invalid = |message: String| EnergiaProError::InvalidArgument(message);
+
+        let (mut reader, headers) = csv_reader(text, "temperature file")?;
+        let find = |names: &[&str]| csv_column(&headers, names);
+        let (date_index, temperature_index) = match columns {
+            Some((date, temperature)) => (find(&[date]), find(&[temperature])),
+            None => (find(DATE_COLUMNS), find(TEMPERATURE_COLUMNS)),
+        };
+        let date_index =
+            date_index.ok_or_else(|| invalid("temperature file has no date column".to_owned()))?;
+        let temperature_index = temperature_index
+            .ok_or_else(|| invalid("temperature file has no temperature column".to_owned()))?;
+
+        let mut days = BTreeMap::new();
+        for record in reader.records() {
+            let record =
+                record.map_err(|err| invalid(format!("invalid temperature file: {err}")))?;
+            let line = record.position().map_or(0, |position| position.line());
+
+            let value = record.get(temperature_index).unwrap_or_default();
+            if value.is_empty() || value == "-" {
+                continue;
+            }
+            let celsius = value
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("line {line}: invalid temperature `{value}`")))?;
+
+            let raw_date = record.get(date_index).unwrap_or_default();
+            let date = parse_date(raw_date)
+                .ok_or_else(|| invalid(format!("line {line}: invalid date `{raw_date}`")))?;
+
+            if days.insert(date, celsius).is_some() {
+                return Err(invalid(format!("line {line}: duplicate date {date}")));
+            }
+        }
+
+        Ok(Self { days })
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.split_whitespace().next()?;
+    ["%Y-%m-%d", "%d.%m.%Y", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// Weather-normalized consumption of one installation over one period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NormalizedPeriod {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// First day of the period.
+    pub start: NaiveDate,
+    /// Last day of the period.
+    pub end: NaiveDate,
+    /// Measured energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Degree days of the measured days of the period.
+    pub degree_days: f64,
+    /// Average degree days of the same calendar days in the reference climate,
+    /// or `None` if the reference does not cover every day.
+    pub reference_degree_days: Option<f64>,
+    /// Consumption scaled to the reference climate, or `None` if it cannot be
+    /// computed because temperatures are missing or no heating was required.
+    pub normalized_kwh: Option<f64>,
+    /// Number of measured days without a temperature.
+    pub missing_temperature_days: usize,
+}
+
+/// Normalize consumption with the default SIA 20/12 [`DegreeDayOptions`].
+pub fn normalize(
+    measurements: &[Measurement],
+    temperatures: &TemperatureSeries,
+    granularity: Granularity,
+) -> Result<Vec<NormalizedPeriod>, EnergiaProError> {
+    normalize_with(
+        measurements,
+        temperatures,
+        granularity,
+        &DegreeDayOptions::default(),
+    )
+}
+
+/// Normalize consumption per installation and period with custom options.
+///
+/// Consumption is attributed to the local date of each measurement. Degree
+/// days are summed over the days between the first and last measurement of
+/// each installation, so partial periods at both ends are compared with the
+/// same days of the reference climate. Periods are sorted by installation then
+/// date.
+///
+/// Returns an error if the options are invalid or a timestamp cannot be parsed.
+pub fn normalize_with(
+    measurements: &[Measurement],
+    temperatures: &TemperatureSeries,
+    granularity: Granularity,
+    options: &DegreeDayOptions,
+) -> Result<Vec<NormalizedPeriod>, EnergiaProError> {
+    options.validate()?;
+    let reference = reference_climate(temperatures, options);
+
+    let mut installations: BTreeMap<&str, BTreeMap<NaiveDate, f64>> = BTreeMap::new();
+    for measurement in measurements {
+        let date = measurement.local_timestamp()?.date();
+        *installations
+            .entry(measurement.installation_id.as_str())
+            .or_default()
+            .entry(date)
+            .or_default() += measurement.consumption_kwh;
+    }
+
+    let mut periods = Vec::new();
+    for (installation_id, days) in installations {
+        let (Some(first), Some(last)) = (days.keys().next(), days.keys().next_back()) else {
+            continue;
+        };
+
+        let mut current: Option<Accumulator> = None;
+        let mut date = *first;
+        while date <= *last {
+            let start = granularity.period(date).0;
+            if current.as_ref().is_none_or(|period| period.start != start) {
+                periods.extend(
+                    current
+                        .take()
+                        .map(|period| period.finish(installation_id, granularity)),
+                );
+                current = Some(Accumulator::new(start));
+            }
+
+            if let Some(period) = current.as_mut() {
+                period.consumption_kwh += days.get(&date).copied().unwrap_or_default();
+                match temperatures.get(date) {
+                    Some(celsius) => period.degree_days += options.degree_days(celsius),
+                    None => period.missing_temperature_days += 1,
+                }
+                period.reference_degree_days = period
+                    .reference_degree_days
+                    .zip(reference_degree_days(&reference, date))
+                    .map(|(total, day)| total + day);
+            }
+
+            date = date + Days::new(1);
+        }
+
+        periods.extend(current.map(|period| period.finish(installation_id, granularity)));
+    }
+
+    Ok(periods)
+}
+
+/// Average degree days per calendar day `(month, day)`.
+pub(crate) type ReferenceClimate = HashMap<(u32, u32), f64>;
+
+pub(crate) fn reference_climate(
+    temperatures: &TemperatureSeries,
+    options: &DegreeDayOptions,
+) -> ReferenceClimate {
+    let mut totals: HashMap<(u32, u32), (f64, usize)> = HashMap::new();
+
+    for (date, celsius) in temperatures.iter() {
+        if options
+            .reference_years
+            .is_some_and(|(from, to)| !(from..=to).contains(&date.year()))
+        {
+            continue;
+        }
+        let total = totals.entry((date.month(), date.day())).or_default();
+        total.0 += options.degree_days(celsius);
+        total.1 += 1;
+    }
+
+    totals
+        .into_iter()
+        .map(|(day, (sum, count))| (day, sum / count as f64))
+        .collect()
+}
+
+pub(crate) fn reference_degree_days(reference: &ReferenceClimate, date: NaiveDate) -> Option<f64> {
+    reference
+        .get(&(date.month(), date.day()))
+        // Reference periods without a leap year have no February 29th.
+        .or_else(|| {
+            if date.month() == 2 && date.day() == 29 {
+                reference.get(&(2, 28))
+            } else {
+                None
+            }
+        })
+        .copied()
+}
+
+struct Accumulator {
+    start: NaiveDate,
+    consumption_kwh: f64,
+    degree_days: f64,
+    reference_degree_days: Option<f64>,
+    missing_temperature_days: usize,
+}
+
+impl Accumulator {
+    fn new(start: NaiveDate) -> Self {
+        Self {
+            start,
+            consumption_kwh: 0.0,
+            degree_days: 0.0,
+            reference_degree_days: Some(0.0),
+            missing_temperature_days: 0,
+        }
+    }
+
+    fn finish(self, installation_id: &str, granularity: Granularity) -> NormalizedPeriod {
+        let reference_degree_days = self.reference_degree_days.map(|value| round(value, 2));
+        let normalized_kwh = reference_degree_days
+            .filter(|_| self.missing_temperature_days == 0 && self.degree_days > 0.0)
+            .map(|reference| round(self.consumption_kwh * reference / self.degree_days, 2));
+
+        NormalizedPeriod {
+            installation_id: installation_id.to_owned(),
+            start: self.start,
+            end: granularity.period(self.start).1,
+            consumption_kwh: self.consumption_kwh,
+            degree_days: round(self.degree_days, 2),
+            reference_degree_days,
+            normalized_kwh,
+            missing_temperature_days: self.missing_temperature_days,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn computes_sia_and_base_degree_days() {
+        let sia = DegreeDayOptions::default();
+        assert_eq!(sia.degree_days(5.0), 15.0);
+        assert_eq!(sia.degree_days(12.0), 0.0);
+        assert_eq!(sia.degree_days(11.9), 8.1);
+
+        let base = DegreeDayOptions::default().with_base_celsius(18.0);
+        assert_eq!(base.degree_days(15.0), 3.0);
+        assert_eq!(base.degree_days(19.0), 0.0);
+    }
+
+    #[test]
+    fn parses_meteoswiss_and_plain_csv_files() {
+        let open_data = TemperatureSeries::from_csv_str(
+            "station_abbr;reference_timestamp;tre200d0;tre200dn\n\
+             PUY;01.01.2024 00:00;1.5;-2.0\n\
+             PUY;02.01.2024 00:00;-;-3.0\n\
+             PUY;03.01.2024 00:00;-0.5;-4.0\n",
+        )
+        .unwrap();
+        assert_eq!(open_data.len(), 2);
+        assert_eq!(open_data.get(date("2024-01-01")), Some(1.5));
+        assert_eq!(open_data.get(date("2024-01-03")), Some(-0.5));
+
+        let idaweb =
+            TemperatureSeries::from_csv_str("stn;time;tre200d0\nPUY;20240101;1.5\n").unwrap();
+        assert_eq!(idaweb.get(date("2024-01-01")), Some(1.5));
+
+        let plain = TemperatureSeries::from_csv_str_with_columns(
+            "day,mean\n2024-01-01,1.5\n",
+            "day",
+            "mean",
+        )
+        .unwrap();
+        assert_eq!(plain.get(date("2024-01-01")), Some(1.5));
+    }
+
+    #[test]
+    fn rejects_invalid_csv_files() {
+        for text in [
+            "station;value\nPUY;1.0\n",
+            "date;temperature\n2024-01-01;warm\n",
+            "date;temperature\n2024-13-01;1.0\n",
+            "date;temperature\n2024-01-01;1.0\n2024-01-01;2.0\n",
+        ] {
+            assert!(
+                matches!(
+                    TemperatureSeries::from_csv_str(text),
+                    Err(EnergiaProError::InvalidArgument(_))
+                ),
+                "{text:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn normalizes_consumption_to_the_reference_climate() {
+        // 2023 was colder than 2024 on the same days.
+        let temperatures = TemperatureSeries::from_days([
+            (date("2023-01-01"), 0.0),
+            (date("2023-01-02"), 0.0),
+            (date("2024-01-01"), 10.0),
+            (date("2024-01-02"), 10.0),
+        ]);
+
+        let periods = normalize(
+            &[
+                measurement("2024-01-01 00:00:00", 100.0),
+                measurement("2024-01-02 00:00:00", 100.0),
+            ],
+            &temperatures,
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(
+            periods,
+            vec![NormalizedPeriod {
+                installation_id: "INSTALLATION_ID_1".to_owned(),
+                start: date("2024-01-01"),
+                end: date("2024-01-31"),
+                consumption_kwh: 200.0,
+                degree_days: 20.0,
+                reference_degree_days: Some(30.0),
+                normalized_kwh: Some(300.0),
+                missing_temperature_days: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn restricts_the_reference_to_chosen_years() {
+        let temperatures = TemperatureSeries::from_days([
+            (date("2022-01-01"), 0.0),
+            (date("2023-01-01"), 10.0),
+            (date("2024-01-01"), 10.0),
+        ]);
+        let options = DegreeDayOptions::default().with_reference_years(2022, 2022);
+
+        let periods = normalize_with(
+            &[measurement("2024-01-01 00:00:00", 100.0)],
+            &temperatures,
+            Granularity::Year,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(periods[0].end, date("2024-12-31"));
+        assert_eq!(periods[0].reference_degree_days, Some(20.0));
+        assert_eq!(periods[0].normalized_kwh, Some(200.0));
+    }
+
+    #[test]
+    fn leaves_periods_without_heating_or_temperatures_unnormalized() {
+        let temperatures =
+            TemperatureSeries::from_days([(date("2024-01-01"), 5.0), (date("2024-07-01"), 25.0)]);
+
+        let periods = normalize(
+            &[
+                measurement("2024-01-01 00:00:00", 100.0),
+                measurement("2024-01-02 00:00:00", 100.0),
+                measurement("2024-07-01 00:00:00", 10.0),
+            ],
+            &temperatures,
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(periods.len(), 7);
+        assert_eq!(periods[0].missing_temperature_days, 30);
+        assert_eq!(periods[0].reference_degree_days, None);
+        assert_eq!(periods[0].normalized_kwh, None);
+        let july = &periods[6];
+        assert_eq!(july.consumption_kwh, 10.0);
+        assert_eq!(july.degree_days, 0.0);
+        assert_eq!(july.normalized_kwh, None);
+    }
+}
@@ -0,0 +1,659 @@
+//! Scope-1 greenhouse gas emissions.
+//!
+//! Burning gas on site is a direct (scope 1) emission in the sense of the GHG
+//! Protocol. [`calculate`] converts the consumed energy into CO2 equivalents
+//! with [`EmissionFactors`] and reports them per installation or client, and
+//! per calendar quarter or year.
+//!
+//! Emission factors change over time, for example when the supplier raises
+//! the share of biogas in the blend or when the national inventory publishes
+//! new values, so they are a list of [`EmissionFactorVersion`]s, each valid
+//! from a given date until the next one. No factor is built in: use the values
+//! required by your reporting framework, on the same calorific basis (gross or
+//! net) as the measured kWh.
+//!
+//! The biogas share of the blend is split from the fossil natural gas. Its
+//! combustion still emits some CH4 and N2O, counted in scope 1 with
+//! `biogas_kg_co2e_per_kwh`, while its biogenic CO2 is reported separately,
+//! outside the scopes, as the GHG Protocol requires.
+//!
+//! In TOML, emission factors look like this:
+//!
+//! ```toml
+//! name = "Gas blend"
+//!
+//! [[versions]]
+//! valid_from = "2023-01-01"
+//! natural_gas_kg_co2e_per_kwh = 0.2
+//!
+//! [[versions]]
+//! valid_from = "2024-01-01"
+//! natural_gas_kg_co2e_per_kwh = 0.2
+//! biogas_share = 0.1
+//! biogas_kg_co2e_per_kwh = 0.002
+//! biogenic_co2_kg_per_kwh = 0.2
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use energiapro::Measurement;
+//! use energiapro::analysis::emissions::{self, EmissionFactorVersion, EmissionFactors};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: &str, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3: 0.0,
+//!     consumption_m3: consumption_kwh / 10.0,
+//!     consumption_kwh,
+//! };
+//!
+//! let factors = EmissionFactors::new(
+//!     "Gas blend",
+//!     EmissionFactorVersion::new(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 0.2)
+//!         .with_biogas_share(0.25),
+//! );
+//!
+//! let report = emissions::calculate(
+//!     &[
+//!         measurement("2024-01-15 00:00:00", 1000.0),
+//!         measurement("2024-04-15 00:00:00", 1000.0),
+//!     ],
+//!     &factors,
+//! )?;
+//!
+//! assert_eq!(report.lines.len(), 2);
+//! assert_eq!(report.lines[0].period_label(), "2024-Q1");
+//! assert_eq!(report.lines[0].scope1_kg_co2e, 150.0);
+//! assert_eq!(report.scope1_t_co2e(), 0.3);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::round;
+use crate::errors::EnergiaProError;
+use crate::models::Measurement;
+use crate::types::DateRange;
+
+/// Emission factors made of one or more dated versions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmissionFactors {
+    /// Human readable name, used to label reports.
+    pub name: String,
+    /// Factors, each valid from its `valid_from` date until the next version.
+    pub versions: Vec<EmissionFactorVersion>,
+}
+
+/// Emission factors from a given date.
+///
+/// Factors are expressed per kWh of consumed gas, on the calorific basis of
+/// the measurements.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmissionFactorVersion {
+    /// First day on which these factors apply.
+    pub valid_from: NaiveDate,
+    /// Scope-1 emissions of fossil natural gas, in kg CO2e per kWh.
+    pub natural_gas_kg_co2e_per_kwh: f64,
+    /// Share of biogas in the supplied blend, between 0 and 1.
+    #[serde(default)]
+    pub biogas_share: f64,
+    /// Scope-1 emissions of biogas (CH4 and N2O), in kg CO2e per kWh.
+    #[serde(default)]
+    pub biogas_kg_co2e_per_kwh: f64,
+    /// Biogenic CO2 emitted by burning biogas, in kg per kWh, reported
+    /// outside the scopes.
+    #[serde(default)]
+    pub biogenic_co2_kg_per_kwh: f64,
+}
+
+impl EmissionFactors {
+    /// Create emission factors with a single version.
+    pub fn new(name: impl Into<String>, version: EmissionFactorVersion) -> Self {
+        Self {
+            name: name.into(),
+            versions: vec![version],
+        }
+    }
+
+    /// Add a version to the emission factors.
+    pub fn with_version(mut self, version: EmissionFactorVersion) -> Self {
+        self.versions.push(version);
+        self
+    }
+
+    /// Return the version in force on `date`.
+    pub fn version_on(&self, date: NaiveDate) -> Option<&EmissionFactorVersion> {
+        self.versions
+            .iter()
+            .filter(|version| version.valid_from <= date)
+            .max_by_key(|version| version.valid_from)
+    }
+
+    /// Check that the emission factors are consistent.
+    ///
+    /// At least one version is required, with distinct `valid_from` dates,
+    /// finite non-negative factors and a biogas share between 0 and 1.
+    pub fn validate(&self) -> Result<(), EnergiaProError> {
+        let invalid = |message: String| {
+            Err(EnergiaProError::InvalidArgument(format!(
+                "emission factors `{}`: {message}",
+                self.name
+            )))
+        };
+
+        if self.versions.is_empty() {
+            return invalid("at least one version is required".to_owned());
+        }
+
+        for (position, version) in self.versions.iter().enumerate() {
+            let valid_from = version.valid_from;
+
+            if self.versions[..position]
+                .iter()
+                .any(|other| other.valid_from == valid_from)
+            {
+                return invalid(format!("several versions are valid from {valid_from}"));
+            }
+
+            let factors = [
+                (
+                    "natural_gas_kg_co2e_per_kwh",
+                    version.natural_gas_kg_co2e_per_kwh,
+                ),
+                ("biogas_kg_co2e_per_kwh", version.biogas_kg_co2e_per_kwh),
+                ("biogenic_co2_kg_per_kwh", version.biogenic_co2_kg_per_kwh),
+            ];
+            for (field, factor) in factors {
+                if !factor.is_finite() || factor < 0.0 {
+                    return invalid(format!(
+                        "{field} of the version valid from {valid_from} must be a non-negative number"
+                    ));
+                }
+            }
+
+            if !(0.0..=1.0).contains(&version.biogas_share) {
+                return invalid(format!(
+                    "biogas_share of the version valid from {valid_from} must be between 0 and 1"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl EmissionFactorVersion {
+    /// Create a version valid from `valid_from` for pure natural gas.
+    pub fn new(valid_from: NaiveDate, natural_gas_kg_co2e_per_kwh: f64) -> Self {
+        Self {
+            valid_from,
+            natural_gas_kg_co2e_per_kwh,
+            biogas_share: 0.0,
+            biogas_kg_co2e_per_kwh: 0.0,
+            biogenic_co2_kg_per_kwh: 0.0,
+        }
+    }
+
+    /// Set the share of biogas in the blend, e.g. `0.1` for 10%.
+    pub fn with_biogas_share(mut self, biogas_share: f64) -> Self {
+        self.biogas_share = biogas_share;
+        self
+    }
+
+    /// Set the scope-1 emissions of biogas in kg CO2e per kWh.
+    pub fn with_biogas_kg_co2e_per_kwh(mut self, biogas_kg_co2e_per_kwh: f64) -> Self {
+        self.biogas_kg_co2e_per_kwh = biogas_kg_co2e_per_kwh;
+        self
+    }
+
+    /// Set the biogenic CO2 of biogas in kg per kWh.
+    pub fn with_biogenic_co2_kg_per_kwh(mut self, biogenic_co2_kg_per_kwh: f64) -> Self {
+        self.biogenic_co2_kg_per_kwh = biogenic_co2_kg_per_kwh;
+        self
+    }
+}
+
+/// Length of the reporting periods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportingPeriod {
+    /// One period per calendar quarter.
+    #[default]
+    Quarter,
+    /// One period per calendar year.
+    Year,
+}
+
+/// Level at which emissions are aggregated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportingLevel {
+    /// One line per installation.
+    #[default]
+    Installation,
+    /// One line per client, summing all of its installations.
+    Client,
+}
+
+/// Options of [`calculate_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmissionOptions {
+    /// Length of the reporting periods.
+    pub period: ReportingPeriod,
+    /// Level at which emissions are aggregated.
+    pub level: ReportingLevel,
+}
+
+impl EmissionOptions {
+    /// Set the length of the reporting periods.
+    pub fn with_period(mut self, period: ReportingPeriod) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Set the level at which emissions are aggregated.
+    pub fn with_level(mut self, level: ReportingLevel) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+/// Emissions of one installation or client over one reporting period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmissionLine {
+    /// Client identifier.
+    pub client_id: u64,
+    /// Installation identifier, or `None` for a client-level line.
+    pub installation_id: Option<String>,
+    /// Calendar year.
+    pub year: i32,
+    /// Calendar quarter from 1 to 4, or `None` for a yearly line.
+    pub quarter: Option<u32>,
+    /// First day of the period.
+    pub start: NaiveDate,
+    /// Last day of the period.
+    pub end: NaiveDate,
+    /// Consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Part of the consumed energy supplied as fossil natural gas.
+    pub natural_gas_kwh: f64,
+    /// Part of the consumed energy supplied as biogas.
+    pub biogas_kwh: f64,
+    /// Direct (scope 1) emissions in kg CO2e.
+    pub scope1_kg_co2e: f64,
+    /// Biogenic CO2 in kg, reported outside the scopes.
+    pub biogenic_co2_kg: f64,
+}
+
+impl EmissionLine {
+    /// Label of the reporting period, such as `2024-Q1` or `2024`.
+    pub fn period_label(&self) -> String {
+        match self.quarter {
+            Some(quarter) => format!("{}-Q{quarter}", self.year),
+            None => self.year.to_string(),
+        }
+    }
+
+    /// Direct (scope 1) emissions in tonnes CO2e.
+    pub fn scope1_t_co2e(&self) -> f64 {
+        self.scope1_kg_co2e / 1000.0
+    }
+
+    /// Scope-1 emissions per consumed kWh, in kg CO2e, or `None` without
+    /// consumption.
+    pub fn kg_co2e_per_kwh(&self) -> Option<f64> {
+        (self.consumption_kwh > 0.0).then(|| self.scope1_kg_co2e / self.consumption_kwh)
+    }
+}
+
+/// Scope-1 emissions of a set of measurements.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmissionReport {
+    /// Name of the emission factors.
+    pub factors: String,
+    /// Length of the reporting periods.
+    pub period: ReportingPeriod,
+    /// Level at which emissions are aggregated.
+    pub level: ReportingLevel,
+    /// One line per installation or client and period, sorted by client,
+    /// installation then period.
+    pub lines: Vec<EmissionLine>,
+}
+
+impl EmissionReport {
+    /// Total consumed energy in kilowatt-hours.
+    pub fn consumption_kwh(&self) -> f64 {
+        self.lines.iter().map(|line| line.consumption_kwh).sum()
+    }
+
+    /// Total direct (scope 1) emissions in kg CO2e.
+    pub fn scope1_kg_co2e(&self) -> f64 {
+        self.lines.iter().map(|line| line.scope1_kg_co2e).sum()
+    }
+
+    /// Total direct (scope 1) emissions in tonnes CO2e.
+    pub fn scope1_t_co2e(&self) -> f64 {
+        self.scope1_kg_co2e() / 1000.0
+    }
+
+    /// Total biogenic CO2 in kg, reported outside the scopes.
+    pub fn biogenic_co2_kg(&self) -> f64 {
+        self.lines.iter().map(|line| line.biogenic_co2_kg).sum()
+    }
+}
+
+/// Compute scope-1 emissions per installation and calendar quarter.
+///
+/// See [`calculate_with`].
+pub fn calculate(
+    measurements: &[Measurement],
+    factors: &EmissionFactors,
+) -> Result<EmissionReport, EnergiaProError> {
+    calculate_with(measurements, factors, &EmissionOptions::default())
+}
+
+/// Compute scope-1 emissions per installation or client and reporting period.
+///
+/// Each measurement is converted with the factors in force on its local date,
+/// so a change of factors or of the biogas share within a period is reflected
+/// pro rata. Negative corrections reduce the emissions of their period.
+/// Each line is rounded once its measurements are summed, energies to 2
+/// decimals of a kWh and emissions to the gram.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - the emission factors are invalid.
+/// - a timestamp cannot be parsed.
+/// - no version is in force on the date of a measurement.
+pub fn calculate_with(
+    measurements: &[Measurement],
+    factors: &EmissionFactors,
+    options: &EmissionOptions,
+) -> Result<EmissionReport, EnergiaProError> {
+    factors.validate()?;
+
+    let mut periods: BTreeMap<(u64, Option<&str>, NaiveDate), Accumulator> = BTreeMap::new();
+    for measurement in measurements {
+        let date = measurement.local_timestamp()?.date();
+        let version = factors.version_on(date).ok_or_else(|| {
+            EnergiaProError::InvalidArgument(format!(
+                "emission factors `{}` have no version valid on {date}",
+                factors.name
+            ))
+        })?;
+
+        let installation_id = match options.level {
+            ReportingLevel::Installation => Some(measurement.installation_id.as_str()),
+            ReportingLevel::Client => None,
+        };
+        let (start, _) = reporting_period(options.period, date)?;
+
+        let kwh = measurement.consumption_kwh;
+        let biogas_kwh = kwh * version.biogas_share;
+        let natural_gas_kwh = kwh - biogas_kwh;
+
+        let period = periods
+            .entry((measurement.client_id, installation_id, start))
+            .or_default();
+        period.consumption_kwh += kwh;
+        period.natural_gas_kwh += natural_gas_kwh;
+        period.biogas_kwh += biogas_kwh;
+        period.scope1_kg_co2e += natural_gas_kwh * version.natural_gas_kg_co2e_per_kwh
+            + biogas_kwh * version.biogas_kg_co2e_per_kwh;
+        period.biogenic_co2_kg += biogas_kwh * version.biogenic_co2_kg_per_kwh;
+    }
+
+    let lines = periods
+        .into_iter()
+        .map(|((client_id, installation_id, start), period)| {
+            let (_, end) = reporting_period(options.period, start)?;
+            Ok(EmissionLine {
+                client_id,
+                installation_id: installation_id.map(str::to_owned),
+                year: start.year(),
+                quarter: match options.period {
+                    ReportingPeriod::Quarter => Some(start.month0() / 3 + 1),
+                    ReportingPeriod::Year => None,
+                },
+                start,
+                end,
+                consumption_kwh: round(period.consumption_kwh, 2),
+                natural_gas_kwh: round(period.natural_gas_kwh, 2),
+                biogas_kwh: round(period.biogas_kwh, 2),
+                scope1_kg_co2e: round(period.scope1_kg_co2e, 3),
+                biogenic_co2_kg: round(period.biogenic_co2_kg, 3),
+            })
+        })
+        .collect::<Result<_, EnergiaProError>>()?;
+
+    Ok(EmissionReport {
+        factors: factors.name.clone(),
+        period: options.period,
+        level: options.level,
+        lines,
+    })
+}
+
+#[derive(Default)]
+struct Accumulator {
+    consumption_kwh: f64,
+    natural_gas_kwh: f64,
+    biogas_kwh: f64,
+    scope1_kg_co2e: f64,
+    biogenic_co2_kg: f64,
+}
+
+/// Return the first and last day of the reporting period containing `date`.
+fn reporting_period(
+    period: ReportingPeriod,
+    date: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), EnergiaProError> {
+    let range = match period {
+        ReportingPeriod::Quarter => DateRange::quarter(date.year(), date.month0() / 3 + 1)?,
+        ReportingPeriod::Year => DateRange::year(date.year())?,
+    };
+
+    Ok((range.start().unwrap_or(date), range.end().unwrap_or(date)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn sample_measurements() -> Vec<Measurement> {
+        vec![
+            measurement("2023-02-10 00:00:00", 1000.0),
+            measurement("2023-12-31 12:00:00", 500.0),
+            measurement("2024-01-01 12:00:00", 1000.0),
+            measurement("2024-05-01 00:00:00", 2000.0).with_installation("INSTALLATION_ID_2"),
+            measurement("2024-03-31 12:00:00", 400.0)
+                .with_installation("INSTALLATION_ID_3")
+                .with_client(2),
+        ]
+    }
+
+    fn sample_factors() -> EmissionFactors {
+        EmissionFactors::new(
+            "Gas blend",
+            EmissionFactorVersion::new(date("2023-01-01"), 0.2),
+        )
+        .with_version(
+            EmissionFactorVersion::new(date("2024-01-01"), 0.2)
+                .with_biogas_share(0.1)
+                .with_biogas_kg_co2e_per_kwh(0.01)
+                .with_biogenic_co2_kg_per_kwh(0.2),
+        )
+    }
+
+    #[test]
+    fn deserializes_emission_factors_from_toml() {
+        let factors: EmissionFactors = toml::from_str(
+            r#"
+            name = "Gas blend"
+
+            [[versions]]
+            valid_from = "2023-01-01"
+            natural_gas_kg_co2e_per_kwh = 0.2
+
+            [[versions]]
+            valid_from = "2024-01-01"
+            natural_gas_kg_co2e_per_kwh = 0.2
+            biogas_share = 0.1
+            biogas_kg_co2e_per_kwh = 0.01
+            biogenic_co2_kg_per_kwh = 0.2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(factors, sample_factors());
+    }
+
+    #[test]
+    fn rejects_invalid_emission_factors() {
+        let cases = [
+            EmissionFactors {
+                name: "Empty".to_owned(),
+                versions: Vec::new(),
+            },
+            EmissionFactors::new(
+                "Duplicate",
+                EmissionFactorVersion::new(date("2024-01-01"), 0.2),
+            )
+            .with_version(EmissionFactorVersion::new(date("2024-01-01"), 0.1)),
+            EmissionFactors::new(
+                "Negative",
+                EmissionFactorVersion::new(date("2024-01-01"), -0.2),
+            ),
+            EmissionFactors::new(
+                "Share",
+                EmissionFactorVersion::new(date("2024-01-01"), 0.2).with_biogas_share(1.5),
+            ),
+        ];
+
+        for factors in cases {
+            assert!(
+                matches!(factors.validate(), Err(EnergiaProError::InvalidArgument(_))),
+                "{} should be rejected",
+                factors.name
+            );
+        }
+        assert!(sample_factors().validate().is_ok());
+    }
+
+    #[test]
+    fn applies_the_factors_in_force_per_quarter() {
+        let report = calculate(&sample_measurements(), &sample_factors()).unwrap();
+
+        let labels = report
+            .lines
+            .iter()
+            .map(|line| {
+                format!(
+                    "{}/{}/{}",
+                    line.client_id,
+                    line.installation_id.as_deref().unwrap(),
+                    line.period_label()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                "1/INSTALLATION_ID_1/2023-Q1",
+                "1/INSTALLATION_ID_1/2023-Q4",
+                "1/INSTALLATION_ID_1/2024-Q1",
+                "1/INSTALLATION_ID_2/2024-Q2",
+                "2/INSTALLATION_ID_3/2024-Q1",
+            ]
+        );
+
+        let q4 = &report.lines[1];
+        assert_eq!((q4.start, q4.end), (date("2023-10-01"), date("2023-12-31")));
+        assert_close(q4.scope1_kg_co2e, 100.0);
+        assert_close(q4.biogas_kwh, 0.0);
+
+        let q1 = &report.lines[2];
+        assert_eq!((q1.start, q1.end), (date("2024-01-01"), date("2024-03-31")));
+        assert_close(q1.natural_gas_kwh, 900.0);
+        assert_close(q1.biogas_kwh, 100.0);
+        assert_close(q1.scope1_kg_co2e, 900.0 * 0.2 + 100.0 * 0.01);
+        assert_close(q1.biogenic_co2_kg, 20.0);
+        assert_close(q1.kg_co2e_per_kwh().unwrap(), 0.181);
+
+        assert_close(report.consumption_kwh(), 4900.0);
+        assert_close(
+            report.scope1_t_co2e(),
+            (1500.0 * 0.2 + 3400.0 * 0.181) / 1000.0,
+        );
+        assert_close(report.biogenic_co2_kg(), 3400.0 * 0.1 * 0.2);
+    }
+
+    #[test]
+    fn rounds_each_line() {
+        let factors = EmissionFactors::new(
+            "Gas blend",
+            EmissionFactorVersion::new(date("2024-01-01"), 0.198_765_4),
+        );
+        let measurements = [
+            measurement("2024-01-01 00:00:00", 1.234_567),
+            measurement("2024-01-01 01:00:00", 2.345_678),
+        ];
+
+        let report = calculate(&measurements, &factors).unwrap();
+
+        assert_eq!(report.lines[0].consumption_kwh, 3.58);
+        assert_eq!(report.lines[0].scope1_kg_co2e, 0.712);
+    }
+
+    #[test]
+    fn aggregates_per_client_and_year() {
+        let options = EmissionOptions::default()
+            .with_period(ReportingPeriod::Year)
+            .with_level(ReportingLevel::Client);
+        let report = calculate_with(&sample_measurements(), &sample_factors(), &options).unwrap();
+
+        assert_eq!(report.lines.len(), 3);
+        let line = &report.lines[1];
+        assert_eq!((line.client_id, line.installation_id.as_deref()), (1, None));
+        assert_eq!((line.year, line.quarter), (2024, None));
+        assert_eq!(line.period_label(), "2024");
+        assert_eq!(
+            (line.start, line.end),
+            (date("2024-01-01"), date("2024-12-31"))
+        );
+        assert_close(line.consumption_kwh, 3000.0);
+        assert_close(line.scope1_kg_co2e, 3000.0 * 0.181);
+        assert_eq!(report.lines[2].client_id, 2);
+    }
+
+    #[test]
+    fn rejects_measurements_before_the_first_version() {
+        let measurements = [measurement("2022-12-31 12:00:00", 10.0)];
+
+        assert!(matches!(
+            calculate(&measurements, &sample_factors()),
+            Err(EnergiaProError::InvalidArgument(message)) if message.contains("2022-12-31")
+        ));
+    }
+}
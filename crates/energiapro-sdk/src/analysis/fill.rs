@@ -0,0 +1,446 @@
+//! Gap filling for measurement series.
+//!
+//! Hourly series sometimes miss intervals, while downstream models usually
+//! need complete series. [`fill`] detects the resolution of a series, inserts
+//! a row for every missing interval and fills it with the selected
+//! [`FillStrategy`]. Each returned row carries a [`FillFlag`] recording
+//! whether it was measured or how it was produced.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::fill::{self, FillFlag, FillStrategy};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: &str, index_m3: f64, consumption_m3: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3,
+//!     consumption_m3,
+//!     consumption_kwh: consumption_m3 * 10.0,
+//! };
+//!
+//! let rows = fill::fill(
+//!     &[
+//!         measurement("2024-04-01 00:00:00", 100.0, 1.0),
+//!         measurement("2024-04-01 01:00:00", 102.0, 2.0),
+//!         measurement("2024-04-01 04:00:00", 110.0, 2.0),
+//!     ],
+//!     FillStrategy::Linear,
+//! )?;
+//!
+//! assert_eq!(rows.len(), 5);
+//! assert_eq!(rows[2].flag, FillFlag::Linear);
+//! assert_eq!(rows[2].consumption_m3, Some(3.0));
+//! assert_eq!(rows[3].index_m3, Some(108.0));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Days, TimeDelta, Timelike};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use super::{detect_resolution, missing_intervals};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementPoint, MeasurementSeries, resolve_local};
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// How missing intervals are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FillStrategy {
+    /// Insert rows for missing intervals but leave their values empty.
+    Null,
+    /// Assume nothing was consumed during missing intervals.
+    Zero,
+    /// Interpolate the index linearly across the gap and spread the
+    /// consumption it implies evenly over the missing intervals.
+    Linear,
+    /// Distribute the consumption of the gap following the average
+    /// consumption of the same weekday and hour in the rest of the series.
+    ///
+    /// When the index delta across the gap is known, the profile is scaled so
+    /// that the filled rows add up to it and the index stays continuous.
+    Profile,
+}
+
+/// Origin of a row returned by [`fill`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillFlag {
+    /// The row was returned by the API.
+    Measured,
+    /// The row was inserted without values.
+    Null,
+    /// The row was filled with zero consumption.
+    Zero,
+    /// The row was filled by linear interpolation.
+    Linear,
+    /// The row was filled from the weekday and hour profile.
+    Profile,
+}
+
+impl FillFlag {
+    /// Short machine-readable name of the flag.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Measured => "measured",
+            Self::Null => "null",
+            Self::Zero => "zero",
+            Self::Linear => "linear",
+            Self::Profile => "profile",
+        }
+    }
+}
+
+impl fmt::Display for FillFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<FillStrategy> for FillFlag {
+    fn from(strategy: FillStrategy) -> Self {
+        match strategy {
+            FillStrategy::Null => Self::Null,
+            FillStrategy::Zero => Self::Zero,
+            FillStrategy::Linear => Self::Linear,
+            FillStrategy::Profile => Self::Profile,
+        }
+    }
+}
+
+/// A measured or filled row of a complete series.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FilledMeasurement {
+    /// Numeric client identifier.
+    pub client_id: u64,
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Measurement timestamp in Europe/Zurich, formatted as `YYYY-MM-DD HH:MM:SS`.
+    pub timestamp: String,
+    /// Meter index in cubic meters, if known.
+    pub index_m3: Option<f64>,
+    /// Consumed volume in cubic meters for the interval, if known.
+    pub consumption_m3: Option<f64>,
+    /// Consumed energy in kilowatt-hours for the interval, if known.
+    pub consumption_kwh: Option<f64>,
+    /// Whether the row was measured or how it was filled.
+    pub flag: FillFlag,
+}
+
+/// Return a complete series where every missing interval is filled with `strategy`.
+///
+/// Measurements must belong to a single installation. They are sorted and
+/// deduplicated first. Only gaps between two measurements are filled; if the
+/// resolution cannot be detected, the measurements are returned unchanged.
+///
+/// # Errors
+///
+/// Returns an error if a timestamp cannot be parsed or if the measurements
+/// belong to more than one installation.
+pub fn fill(
+    measurements: &[Measurement],
+    strategy: FillStrategy,
+) -> Result<Vec<FilledMeasurement>, EnergiaProError> {
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let resolution = detect_resolution(series.timestamps());
+    let context = FillContext::new(&series, strategy);
+
+    let mut rows = Vec::with_capacity(series.len());
+    let mut previous: Option<MeasurementPoint> = None;
+
+    for point in series.iter() {
+        if let (Some(previous), Some(resolution)) = (previous, resolution) {
+            let elapsed = (point.timestamp - previous.timestamp).num_minutes();
+            let missing = missing_intervals(elapsed, resolution);
+            if missing > 0 {
+                rows.extend(context.fill_gap(previous, point, missing, resolution));
+            }
+        }
+
+        rows.push(context.row(point.timestamp, FillFlag::Measured, Some(point)));
+        previous = Some(point);
+    }
+
+    Ok(rows)
+}
+
+struct FillContext<'a> {
+    series: &'a MeasurementSeries,
+    strategy: FillStrategy,
+    /// Average consumption by weekday and hour, for [`FillStrategy::Profile`].
+    profile: HashMap<(u32, u32), f64>,
+    /// Average consumption of the whole series.
+    mean_m3: f64,
+    /// Energy per volume of the whole series, in kWh/m³.
+    kwh_per_m3: f64,
+}
+
+impl<'a> FillContext<'a> {
+    fn new(series: &'a MeasurementSeries, strategy: FillStrategy) -> Self {
+        let mut sums: HashMap<(u32, u32), (f64, usize)> = HashMap::new();
+        if strategy == FillStrategy::Profile {
+            for point in series.iter() {
+                let sum = sums.entry(profile_key(point.timestamp)).or_default();
+                sum.0 += point.consumption_m3;
+                sum.1 += 1;
+            }
+        }
+
+        let total_m3: f64 = series.consumption_m3().iter().sum();
+        let total_kwh: f64 = series.consumption_kwh().iter().sum();
+
+        Self {
+            series,
+            strategy,
+            profile: sums
+                .into_iter()
+                .map(|(key, (sum, count))| (key, sum / count as f64))
+                .collect(),
+            mean_m3: if series.is_empty() {
+                0.0
+            } else {
+                total_m3 / series.len() as f64
+            },
+            kwh_per_m3: ratio(total_kwh, total_m3).unwrap_or(0.0),
+        }
+    }
+
+    fn fill_gap(
+        &self,
+        previous: MeasurementPoint,
+        next: MeasurementPoint,
+        missing: usize,
+        resolution: i64,
+    ) -> Vec<FilledMeasurement> {
+        let timestamps = (1..=missing)
+            .map(|step| step_timestamp(previous.timestamp, resolution, step))
+            .collect::<Vec<_>>();
+        let flag = FillFlag::from(self.strategy);
+
+        // Volume consumed during the missing intervals, as implied by the index.
+        let gap_m3 = next.index_m3 - next.consumption_m3 - previous.index_m3;
+        let kwh_per_m3 = ratio(
+            previous.consumption_kwh + next.consumption_kwh,
+            previous.consumption_m3 + next.consumption_m3,
+        )
+        .unwrap_or(self.kwh_per_m3);
+
+        let volumes = match self.strategy {
+            FillStrategy::Null => {
+                return timestamps
+                    .into_iter()
+                    .map(|timestamp| self.row(timestamp, flag, None))
+                    .collect();
+            }
+            FillStrategy::Zero => vec![0.0; missing],
+            FillStrategy::Linear => vec![gap_m3.max(0.0) / missing as f64; missing],
+            FillStrategy::Profile => {
+                let weights = timestamps
+                    .iter()
+                    .map(|timestamp| {
+                        self.profile
+                            .get(&profile_key(*timestamp))
+                            .copied()
+                            .unwrap_or(self.mean_m3)
+                            .max(0.0)
+                    })
+                    .collect::<Vec<_>>();
+                let total: f64 = weights.iter().sum();
+
+                if gap_m3 >= 0.0 && total > 0.0 {
+                    weights
+                        .iter()
+                        .map(|weight| weight * gap_m3 / total)
+                        .collect()
+                } else if gap_m3 >= 0.0 {
+                    vec![gap_m3 / missing as f64; missing]
+                } else {
+                    weights
+                }
+            }
+        };
+
+        let mut index_m3 = previous.index_m3;
+        timestamps
+            .into_iter()
+            .zip(volumes)
+            .map(|(timestamp, consumption_m3)| {
+                index_m3 += consumption_m3;
+                self.row(
+                    timestamp,
+                    flag,
+                    Some(MeasurementPoint {
+                        timestamp,
+                        index_m3,
+                        consumption_m3,
+                        consumption_kwh: consumption_m3 * kwh_per_m3,
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    fn row(
+        &self,
+        timestamp: DateTime<Tz>,
+        flag: FillFlag,
+        point: Option<MeasurementPoint>,
+    ) -> FilledMeasurement {
+        FilledMeasurement {
+            client_id: self.series.client_id(),
+            installation_id: self.series.installation_id().to_owned(),
+            timestamp: timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            index_m3: point.map(|point| point.index_m3),
+            consumption_m3: point.map(|point| point.consumption_m3),
+            consumption_kwh: point.map(|point| point.consumption_kwh),
+            flag,
+        }
+    }
+}
+
+/// Return the timestamp `step` intervals after `start`.
+///
+/// Daily and longer resolutions keep the local wall-clock time, so filled
+/// days stay aligned on midnight across daylight saving changes.
+fn step_timestamp(start: DateTime<Tz>, resolution: i64, step: usize) -> DateTime<Tz> {
+    let step = i64::try_from(step).unwrap_or(i64::MAX);
+
+    if resolution % MINUTES_PER_DAY == 0 {
+        let days = u64::try_from(resolution / MINUTES_PER_DAY * step).unwrap_or(0);
+        if let Some(local) = start.naive_local().checked_add_days(Days::new(days)) {
+            return resolve_local(local, 0);
+        }
+    }
+
+    start + TimeDelta::minutes(resolution.saturating_mul(step))
+}
+
+fn profile_key(timestamp: DateTime<Tz>) -> (u32, u32) {
+    (timestamp.weekday().num_days_from_monday(), timestamp.hour())
+}
+
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    (denominator > 0.0).then(|| numerator / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::metered;
+
+    fn gap() -> Vec<Measurement> {
+        vec![
+            metered("2024-04-01 00:00:00", 100.0, 1.0),
+            metered("2024-04-01 01:00:00", 101.0, 1.0),
+            metered("2024-04-01 04:00:00", 108.0, 1.0),
+        ]
+    }
+
+    fn filled(rows: &[FilledMeasurement]) -> Vec<&FilledMeasurement> {
+        rows.iter()
+            .filter(|row| row.flag != FillFlag::Measured)
+            .collect()
+    }
+
+    #[test]
+    fn inserts_empty_rows() {
+        let rows = fill(&gap(), FillStrategy::Null).unwrap();
+        let filled = filled(&rows);
+
+        assert_eq!(rows.len(), 5);
+        assert_eq!(filled.len(), 2);
+        assert_eq!(filled[0].timestamp, "2024-04-01 02:00:00");
+        assert_eq!(filled[0].flag, FillFlag::Null);
+        assert_eq!(filled[0].index_m3, None);
+        assert_eq!(filled[1].consumption_kwh, None);
+        assert_eq!(rows[4].flag, FillFlag::Measured);
+    }
+
+    #[test]
+    fn fills_with_zero_consumption() {
+        let rows = fill(&gap(), FillStrategy::Zero).unwrap();
+        let filled = filled(&rows);
+
+        assert_eq!(filled[0].consumption_m3, Some(0.0));
+        assert_eq!(filled[1].index_m3, Some(101.0));
+    }
+
+    #[test]
+    fn interpolates_index_linearly() {
+        let rows = fill(&gap(), FillStrategy::Linear).unwrap();
+        let filled = filled(&rows);
+
+        assert_eq!(filled[0].consumption_m3, Some(3.0));
+        assert_eq!(filled[0].consumption_kwh, Some(30.0));
+        assert_eq!(filled[0].index_m3, Some(104.0));
+        assert_eq!(filled[1].index_m3, Some(107.0));
+    }
+
+    #[test]
+    fn fills_from_weekday_and_hour_profile() {
+        let mut measurements = Vec::new();
+        let mut index_m3 = 0.0;
+        // Two complete Mondays, where 02:00 consumes three times more than 03:00.
+        for day in ["2024-04-01", "2024-04-08"] {
+            for (hour, consumption_m3) in [(0, 1.0), (1, 1.0), (2, 3.0), (3, 1.0), (4, 1.0)] {
+                index_m3 += consumption_m3;
+                measurements.push(metered(
+                    &format!("{day} {hour:02}:00:00"),
+                    index_m3,
+                    consumption_m3,
+                ));
+            }
+        }
+        // A third Monday missing 02:00 and 03:00, with 8 m³ consumed during the gap.
+        measurements.push(metered("2024-04-15 01:00:00", 100.0, 1.0));
+        measurements.push(metered("2024-04-15 04:00:00", 109.0, 1.0));
+
+        let rows = fill(&measurements, FillStrategy::Profile).unwrap();
+        let filled = filled(&rows)
+            .into_iter()
+            .filter(|row| row.timestamp.as_str() > "2024-04-15 01:00:00")
+            .collect::<Vec<_>>();
+
+        assert_eq!(filled.len(), 2);
+        assert_eq!(filled[0].flag, FillFlag::Profile);
+        assert_eq!(filled[0].consumption_m3, Some(6.0));
+        assert_eq!(filled[1].consumption_m3, Some(2.0));
+        assert_eq!(filled[1].index_m3, Some(108.0));
+    }
+
+    #[test]
+    fn keeps_daily_rows_on_local_midnight_across_daylight_saving() {
+        let rows = fill(
+            &[
+                metered("2024-03-29 00:00:00", 1.0, 1.0),
+                metered("2024-03-30 00:00:00", 2.0, 1.0),
+                metered("2024-04-01 00:00:00", 4.0, 1.0),
+            ],
+            FillStrategy::Linear,
+        )
+        .unwrap();
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[2].timestamp, "2024-03-31 00:00:00");
+    }
+
+    #[test]
+    fn returns_complete_series_unchanged() {
+        let measurements = [
+            metered("2024-04-01 00:00:00", 1.0, 1.0),
+            metered("2024-04-01 01:00:00", 2.0, 1.0),
+        ];
+
+        let rows = fill(&measurements, FillStrategy::Profile).unwrap();
+        assert!(rows.iter().all(|row| row.flag == FillFlag::Measured));
+        assert_eq!(rows[1].index_m3, Some(2.0));
+    }
+}
@@ -0,0 +1,711 @@
+//! Consumption forecasting and year-end projections.
+//!
+//! A model is trained on the daily consumption of the historical
+//! measurements:
+//!
+//! - without temperatures, the seasonal model is the mean daily consumption of
+//!   each calendar month. Months missing from the history are interpolated
+//!   between their nearest neighbours;
+//! - with a [`TemperatureSeries`], the degree day model is a linear regression
+//!   of the daily consumption on the heating degree days of the day. Future
+//!   days use the measured temperature when the file has one, and the average
+//!   degree days of the same calendar day over the file otherwise.
+//!
+//! Prediction intervals assume independent daily errors: a period of `d` days
+//! has a half-width of `t × RMSE × √d`, where `t` is the Student quantile of
+//! the chosen confidence.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::analysis::forecast;
+//! use energiapro::{DateRange, Granularity, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let mut measurements = Vec::new();
+//! for month in 1..=12 {
+//!     for day in 1..=28 {
+//!         measurements.push(Measurement {
+//!             client_id: 1,
+//!             installation_id: "INSTALLATION_ID_1".to_owned(),
+//!             timestamp: format!("2023-{month:02}-{day:02} 00:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: 10.0,
+//!             consumption_kwh: 100.0,
+//!         });
+//!     }
+//! }
+//!
+//! let forecast = forecast::forecast(
+//!     &measurements,
+//!     DateRange::between("2024-01-01", "2024-01-31")?,
+//!     Granularity::Month,
+//! )?;
+//!
+//! assert_eq!(forecast.periods.len(), 1);
+//! assert_eq!(forecast.periods[0].predicted_kwh, 3100.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::Serialize;
+
+use super::degree_days::{
+    DegreeDayOptions, ReferenceClimate, TemperatureSeries, reference_climate, reference_degree_days,
+};
+use super::signature::least_squares;
+use super::{daily_consumption, round, student_t_quantile};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+use crate::types::{DateRange, Granularity};
+
+/// Parameters of the forecast.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForecastOptions {
+    /// Daily mean temperatures used to train a degree day model, or `None` to
+    /// use the seasonal model.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Degree day method of the degree day model. `reference_years` restricts
+    /// the years averaged for future days without a measured temperature.
+    pub degree_days: DegreeDayOptions,
+
+    /// Share of a day's intervals required for the day to be used in training.
+    pub min_day_coverage: f64,
+
+    /// Minimum number of complete days required to train the model.
+    pub min_days: usize,
+
+    /// Two-sided confidence level of the prediction intervals, between 0 and 1.
+    pub confidence: f64,
+}
+
+impl Default for ForecastOptions {
+    fn default() -> Self {
+        Self {
+            temperatures: None,
+            degree_days: DegreeDayOptions::default(),
+            min_day_coverage: 0.9,
+            min_days: 28,
+            confidence: 0.9,
+        }
+    }
+}
+
+impl ForecastOptions {
+    /// Train a degree day model with daily temperatures.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the degree day method of the degree day model.
+    pub fn with_degree_days(mut self, degree_days: DegreeDayOptions) -> Self {
+        self.degree_days = degree_days;
+        self
+    }
+
+    /// Set the minimum number of complete days required to train the model.
+    pub fn with_min_days(mut self, min_days: usize) -> Self {
+        self.min_days = min_days;
+        self
+    }
+
+    /// Set the confidence level of the prediction intervals, e.g. `0.9` for 90%.
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "confidence must be between 0 and 1".to_owned(),
+            ));
+        }
+        self.degree_days.validate()
+    }
+}
+
+/// Model projecting the daily consumption onto future days.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ForecastModel {
+    /// Mean daily consumption per calendar month.
+    Seasonal {
+        /// Consumption in kWh per day, from January to December.
+        kwh_per_day_by_month: [f64; 12],
+    },
+    /// Linear regression on heating degree days.
+    DegreeDays {
+        /// Consumption independent of the weather, in kWh per day.
+        base_load_kwh_per_day: f64,
+        /// Additional consumption per heating degree day, in kWh.
+        kwh_per_degree_day: f64,
+    },
+}
+
+impl ForecastModel {
+    /// Return the predicted consumption of a day, in kWh. The degree days are
+    /// ignored by the seasonal model.
+    pub fn predict(&self, date: NaiveDate, degree_days: f64) -> f64 {
+        let kwh = match *self {
+            Self::Seasonal {
+                kwh_per_day_by_month,
+            } => kwh_per_day_by_month[date.month0() as usize],
+            Self::DegreeDays {
+                base_load_kwh_per_day,
+                kwh_per_degree_day,
+            } => base_load_kwh_per_day + kwh_per_degree_day * degree_days,
+        };
+        kwh.max(0.0)
+    }
+}
+
+/// Forecast consumption of one period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForecastPeriod {
+    /// First day of the period, clipped to the forecast horizon.
+    pub start: NaiveDate,
+    /// Last day of the period, clipped to the forecast horizon.
+    pub end: NaiveDate,
+    /// Number of forecast days in the period.
+    pub days: usize,
+    /// Predicted consumption in kWh.
+    pub predicted_kwh: f64,
+    /// Lower bound of the prediction interval, in kWh.
+    pub lower_kwh: f64,
+    /// Upper bound of the prediction interval, in kWh.
+    pub upper_kwh: f64,
+}
+
+/// Trained model and forecast periods of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Forecast {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Trained model.
+    pub model: ForecastModel,
+    /// Number of days used to train the model.
+    pub training_days: usize,
+    /// Root mean squared error of the model on the training days, in kWh.
+    pub rmse_kwh: f64,
+    /// Confidence level of the prediction intervals.
+    pub confidence: f64,
+    /// Forecast per period, in chronological order.
+    pub periods: Vec<ForecastPeriod>,
+}
+
+/// Year-end consumption combining actuals to date with a forecast.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct YearEndProjection {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Calendar year of the projection.
+    pub year: i32,
+    /// Last day of actual consumption: the last complete measured day up to
+    /// the requested date. It falls in the previous year when the year has no
+    /// complete day yet.
+    pub as_of: NaiveDate,
+    /// Measured consumption from January 1st to `as_of`, in kWh.
+    pub actual_kwh: f64,
+    /// Forecast consumption from the day after `as_of` to December 31st, in kWh.
+    pub forecast_kwh: f64,
+    /// Projected consumption of the whole year, in kWh.
+    pub projected_kwh: f64,
+    /// Lower bound of the projection, in kWh.
+    pub lower_kwh: f64,
+    /// Upper bound of the projection, in kWh.
+    pub upper_kwh: f64,
+    /// Confidence level of the bounds.
+    pub confidence: f64,
+}
+
+/// Forecast consumption with the default [`ForecastOptions`].
+///
+/// # Errors
+///
+/// See [`forecast_with`].
+pub fn forecast(
+    measurements: &[Measurement],
+    horizon: DateRange,
+    granularity: Granularity,
+) -> Result<Forecast, EnergiaProError> {
+    forecast_with(
+        measurements,
+        horizon,
+        granularity,
+        &ForecastOptions::default(),
+    )
+}
+
+/// Train a model on `measurements` and forecast the days of `horizon`,
+/// aggregated per `granularity` period.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the horizon is unbounded,
+/// if the measurements belong to more than one installation or a timestamp
+/// cannot be parsed, if the history has fewer complete days than
+/// `min_days`, or if a forecast day has neither a temperature nor a
+/// reference climate.
+pub fn forecast_with(
+    measurements: &[Measurement],
+    horizon: DateRange,
+    granularity: Granularity,
+    options: &ForecastOptions,
+) -> Result<Forecast, EnergiaProError> {
+    options.validate()?;
+    let (Some(start), Some(end)) = (horizon.start(), horizon.end()) else {
+        return Err(EnergiaProError::InvalidArgument(
+            "forecast horizon must have a start and an end".to_owned(),
+        ));
+    };
+
+    let trained = train(measurements, options)?;
+    let periods = predict_periods(&trained, start, end, granularity, options)?;
+
+    Ok(Forecast {
+        installation_id: trained.installation_id,
+        model: trained.model,
+        training_days: trained.days,
+        rmse_kwh: round(trained.rmse, 2),
+        confidence: options.confidence,
+        periods,
+    })
+}
+
+/// Project the consumption of the calendar year of `as_of`.
+///
+/// Measurements up to `as_of` train the model. The actual consumption runs
+/// from January 1st to the last complete measured day up to `as_of`, and the
+/// rest of the year is forecast from the day after, so days missing at the
+/// end of the history are forecast rather than lost. Later measurements are
+/// ignored.
+///
+/// # Errors
+///
+/// See [`forecast_with`].
+pub fn project_year_end(
+    measurements: &[Measurement],
+    as_of: NaiveDate,
+    options: &ForecastOptions,
+) -> Result<YearEndProjection, EnergiaProError> {
+    options.validate()?;
+
+    let mut history = Vec::new();
+    for measurement in measurements {
+        if measurement.local_timestamp()?.date() <= as_of {
+            history.push(measurement.clone());
+        }
+    }
+
+    let trained = train(&history, options)?;
+    // Training succeeded, so the history has at least one complete day.
+    let series = MeasurementSeries::from_measurements(history.iter().cloned())?;
+    let last_day = daily_consumption(&series, options.min_day_coverage)
+        .into_keys()
+        .next_back()
+        .unwrap_or(as_of);
+
+    let year_start = as_of.with_ordinal(1).unwrap_or(as_of);
+    let mut actual_kwh = 0.0;
+    for measurement in &history {
+        let date = measurement.local_timestamp()?.date();
+        if date >= year_start && date <= last_day {
+            actual_kwh += measurement.consumption_kwh;
+        }
+    }
+
+    let (_, year_end) = Granularity::Year.period(as_of);
+    let remaining = match last_day.checked_add_days(Days::new(1)) {
+        Some(next) if next <= year_end => predict_periods(
+            &trained,
+            next.max(year_start),
+            year_end,
+            Granularity::Year,
+            options,
+        )?
+        .into_iter()
+        .next(),
+        _ => None,
+    };
+
+    let actual_kwh = round(actual_kwh, 2);
+    let (forecast_kwh, lower_kwh, upper_kwh) = remaining.map_or((0.0, 0.0, 0.0), |period| {
+        (period.predicted_kwh, period.lower_kwh, period.upper_kwh)
+    });
+
+    Ok(YearEndProjection {
+        installation_id: trained.installation_id,
+        year: as_of.year(),
+        as_of: last_day,
+        actual_kwh,
+        forecast_kwh,
+        projected_kwh: round(actual_kwh + forecast_kwh, 2),
+        lower_kwh: round(actual_kwh + lower_kwh, 2),
+        upper_kwh: round(actual_kwh + upper_kwh, 2),
+        confidence: options.confidence,
+    })
+}
+
+struct Trained {
+    installation_id: String,
+    model: ForecastModel,
+    days: usize,
+    rmse: f64,
+    degrees_of_freedom: usize,
+    climate: Option<ReferenceClimate>,
+}
+
+fn train(
+    measurements: &[Measurement],
+    options: &ForecastOptions,
+) -> Result<Trained, EnergiaProError> {
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let mut days = daily_consumption(&series, options.min_day_coverage);
+    if let Some(temperatures) = &options.temperatures {
+        days.retain(|date, _| temperatures.get(*date).is_some());
+    }
+    let min_days = options.min_days.max(3);
+    if days.len() < min_days {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "history of installation `{}` has {} complete days, at least {min_days} are required",
+            series.installation_id(),
+            days.len()
+        )));
+    }
+
+    let (model, parameters, climate) = match &options.temperatures {
+        Some(temperatures) => {
+            let points = days
+                .iter()
+                .filter_map(|(date, kwh)| {
+                    let celsius = temperatures.get(*date)?;
+                    Some((options.degree_days.degree_days(celsius), *kwh))
+                })
+                .collect::<Vec<_>>();
+            let fit = least_squares(&points).ok_or_else(|| {
+                EnergiaProError::InvalidArgument(format!(
+                    "degree days of installation `{}` do not vary over the history",
+                    series.installation_id()
+                ))
+            })?;
+            let model = ForecastModel::DegreeDays {
+                base_load_kwh_per_day: round(fit.intercept, 2),
+                kwh_per_degree_day: round(fit.slope, 2),
+            };
+            (
+                model,
+                2,
+                Some(reference_climate(temperatures, &options.degree_days)),
+            )
+        }
+        None => {
+            let (model, months) = fit_seasonal(&days);
+            (model, months, None)
+        }
+    };
+
+    let residuals = days
+        .iter()
+        .map(|(date, kwh)| {
+            let degree_days = options
+                .temperatures
+                .as_ref()
+                .and_then(|temperatures| temperatures.get(*date))
+                .map_or(0.0, |celsius| options.degree_days.degree_days(celsius));
+            kwh - model.predict(*date, degree_days)
+        })
+        .collect::<Vec<_>>();
+    let degrees_of_freedom = days.len().saturating_sub(parameters).max(1);
+    let rmse = (residuals
+        .iter()
+        .map(|residual| residual * residual)
+        .sum::<f64>()
+        / degrees_of_freedom as f64)
+        .sqrt();
+
+    Ok(Trained {
+        installation_id: series.installation_id().to_owned(),
+        model,
+        days: days.len(),
+        rmse,
+        degrees_of_freedom,
+        climate,
+    })
+}
+
+/// Fit the seasonal model and return it with the number of months covered
+/// by the history.
+fn fit_seasonal(days: &BTreeMap<NaiveDate, f64>) -> (ForecastModel, usize) {
+    let mut totals = [(0.0, 0usize); 12];
+    for (date, kwh) in days {
+        let total = &mut totals[date.month0() as usize];
+        total.0 += kwh;
+        total.1 += 1;
+    }
+    let means = totals.map(|(sum, count)| (count > 0).then(|| sum / count as f64));
+    let covered = means.iter().flatten().count();
+
+    let mut kwh_per_day_by_month = [0.0; 12];
+    for (month, value) in kwh_per_day_by_month.iter_mut().enumerate() {
+        *value = round(
+            means[month].unwrap_or_else(|| {
+                // Interpolate linearly, around the year, between the nearest
+                // covered months before and after.
+                let (before, before_distance) = (1..12)
+                    .find_map(|distance| means[(month + 12 - distance) % 12].map(|v| (v, distance)))
+                    .unwrap_or((0.0, 1));
+                let (after, after_distance) = (1..12)
+                    .find_map(|distance| means[(month + distance) % 12].map(|v| (v, distance)))
+                    .unwrap_or((0.0, 1));
+                before
+                    + (after - before) * before_distance as f64
+                        / (before_distance + after_distance) as f64
+            }),
+            2,
+        );
+    }
+
+    (
+        ForecastModel::Seasonal {
+            kwh_per_day_by_month,
+        },
+        covered,
+    )
+}
+
+fn predict_periods(
+    trained: &Trained,
+    start: NaiveDate,
+    end: NaiveDate,
+    granularity: Granularity,
+    options: &ForecastOptions,
+) -> Result<Vec<ForecastPeriod>, EnergiaProError> {
+    let mut periods: BTreeMap<NaiveDate, (NaiveDate, usize, f64)> = BTreeMap::new();
+    for date in start.iter_days().take_while(|date| *date <= end) {
+        let degree_days = match &options.temperatures {
+            Some(temperatures) => temperatures
+                .get(date)
+                .map(|celsius| options.degree_days.degree_days(celsius))
+                .or_else(|| {
+                    trained
+                        .climate
+                        .as_ref()
+                        .and_then(|climate| reference_degree_days(climate, date))
+                })
+                .ok_or_else(|| {
+                    EnergiaProError::InvalidArgument(format!(
+                        "no temperature or reference climate for {date}"
+                    ))
+                })?,
+            None => 0.0,
+        };
+
+        let (period_start, period_end) = granularity.period(date);
+        let period =
+            periods
+                .entry(period_start.max(start))
+                .or_insert((period_end.min(end), 0, 0.0));
+        period.1 += 1;
+        period.2 += trained.model.predict(date, degree_days);
+    }
+
+    let t = student_t_quantile(
+        (1.0 + options.confidence) / 2.0,
+        trained.degrees_of_freedom as f64,
+    );
+    Ok(periods
+        .into_iter()
+        .map(|(start, (end, days, kwh))| {
+            let half_width = t * trained.rmse * (days as f64).sqrt();
+            ForecastPeriod {
+                start,
+                end,
+                days,
+                predicted_kwh: round(kwh, 2),
+                lower_kwh: round((kwh - half_width).max(0.0), 2),
+                upper_kwh: round(kwh + half_width, 2),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::history;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn forecasts_monthly_seasonal_consumption() {
+        let measurements = history("2022-01-01", "2023-12-31", |day| {
+            10.0 * f64::from(day.month())
+        });
+
+        let forecast = forecast(
+            &measurements,
+            DateRange::between("2024-01-15", "2024-03-31").unwrap(),
+            Granularity::Month,
+        )
+        .unwrap();
+
+        assert_eq!(forecast.training_days, 730);
+        assert_eq!(forecast.rmse_kwh, 0.0);
+        assert_eq!(
+            forecast
+                .periods
+                .iter()
+                .map(|period| (period.start, period.days, period.predicted_kwh))
+                .collect::<Vec<_>>(),
+            vec![
+                (date("2024-01-15"), 17, 170.0),
+                (date("2024-02-01"), 29, 580.0),
+                (date("2024-03-01"), 31, 930.0),
+            ]
+        );
+        assert_eq!(forecast.periods[1].lower_kwh, 580.0);
+    }
+
+    #[test]
+    fn interpolates_months_missing_from_the_history() {
+        let mut measurements = history("2023-01-01", "2023-01-31", |_| 100.0);
+        measurements.extend(history("2023-04-01", "2023-04-30", |_| 40.0));
+
+        let forecast = forecast(
+            &measurements,
+            DateRange::between("2023-05-01", "2024-03-31").unwrap(),
+            Granularity::Month,
+        )
+        .unwrap();
+
+        let ForecastModel::Seasonal {
+            kwh_per_day_by_month,
+        } = forecast.model
+        else {
+            panic!("expected a seasonal model");
+        };
+        assert_eq!(kwh_per_day_by_month[1], 80.0);
+        assert_eq!(kwh_per_day_by_month[2], 60.0);
+        assert_eq!(kwh_per_day_by_month[6], 60.0);
+        assert_eq!(kwh_per_day_by_month[7], 66.67);
+    }
+
+    #[test]
+    fn forecasts_with_degree_days_and_reference_climate() {
+        // 10 kWh base load and 5 kWh per degree day below 12 °C.
+        let celsius = |day: NaiveDate| if day.month() <= 2 { 0.0 } else { 15.0 };
+        let measurements = history("2023-01-01", "2023-06-30", |day| {
+            10.0 + 5.0 * DegreeDayOptions::default().degree_days(celsius(day))
+        });
+        let temperatures = TemperatureSeries::from_days(
+            date("2023-01-01")
+                .iter_days()
+                .take_while(|day| *day <= date("2023-06-30"))
+                .map(|day| (day, celsius(day))),
+        );
+        let options = ForecastOptions::default().with_temperatures(temperatures);
+
+        let forecast = forecast_with(
+            &measurements,
+            DateRange::between("2024-01-01", "2024-01-31").unwrap(),
+            Granularity::Month,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            forecast.model,
+            ForecastModel::DegreeDays {
+                base_load_kwh_per_day: 10.0,
+                kwh_per_degree_day: 5.0,
+            }
+        );
+        assert_eq!(forecast.periods[0].predicted_kwh, 31.0 * 110.0);
+
+        let beyond_climate = forecast_with(
+            &measurements,
+            DateRange::between("2024-07-01", "2024-07-31").unwrap(),
+            Granularity::Month,
+            &options,
+        );
+        assert!(matches!(
+            beyond_climate,
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn projects_year_end_from_actuals_and_forecast() {
+        let measurements = history("2023-01-01", "2024-12-31", |day| {
+            if day.year() == 2023 {
+                50.0 + f64::from(day.day() % 3)
+            } else {
+                40.0
+            }
+        });
+
+        let projection = project_year_end(
+            &measurements,
+            date("2024-06-30"),
+            &ForecastOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(projection.year, 2024);
+        assert_eq!(projection.actual_kwh, 182.0 * 40.0);
+        assert!(projection.forecast_kwh > 184.0 * 40.0);
+        assert_eq!(
+            projection.projected_kwh,
+            round(projection.actual_kwh + projection.forecast_kwh, 2)
+        );
+        assert!(projection.lower_kwh < projection.projected_kwh);
+        assert!(projection.upper_kwh > projection.projected_kwh);
+    }
+
+    #[test]
+    fn forecasts_the_days_missing_before_the_projection_date() {
+        let measurements = history("2023-01-01", "2024-06-20", |_| 40.0);
+
+        let projection = project_year_end(
+            &measurements,
+            date("2024-06-30"),
+            &ForecastOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(projection.as_of, date("2024-06-20"));
+        assert_eq!(projection.actual_kwh, 172.0 * 40.0);
+        assert_eq!(projection.forecast_kwh, 194.0 * 40.0);
+        assert_eq!(projection.projected_kwh, 366.0 * 40.0);
+    }
+
+    #[test]
+    fn rejects_short_histories_and_unbounded_horizons() {
+        let measurements = history("2024-01-01", "2024-01-10", |_| 10.0);
+
+        assert!(matches!(
+            forecast(
+                &measurements,
+                DateRange::between("2024-02-01", "2024-02-29").unwrap(),
+                Granularity::Day
+            ),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            forecast(
+                &history("2023-01-01", "2023-12-31", |_| 10.0),
+                DateRange::since("2024-01-01").unwrap(),
+                Granularity::Day
+            ),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+//! Swiss public holidays.
+//!
+//! EnergiaPro distributes gas in the canton of Vaud, so the calendar covers
+//! the federal holiday and the public holidays of Vaud. Movable holidays are
+//! derived from Easter Sunday, computed with the anonymous Gregorian
+//! algorithm.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use energiapro::analysis::holidays;
+//!
+//! let easter = holidays::easter_sunday(2024);
+//! assert_eq!(easter, NaiveDate::from_ymd_opt(2024, 3, 31));
+//!
+//! let ascension = NaiveDate::from_ymd_opt(2024, 5, 9).unwrap();
+//! assert!(holidays::is_holiday(ascension));
+//! ```
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Serialize;
+
+/// A public holiday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Holiday {
+    /// Date of the holiday.
+    pub date: NaiveDate,
+    /// English name of the holiday.
+    pub name: &'static str,
+}
+
+/// Return the date of Easter Sunday in the Gregorian calendar.
+///
+/// Returns `None` if the date is outside the range supported by `chrono`.
+pub fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year.rem_euclid(19);
+    let b = year.div_euclid(100);
+    let c = year.rem_euclid(100);
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Return the public holidays of the canton of Vaud in `year`, in
+/// chronological order.
+pub fn holidays(year: i32) -> Vec<Holiday> {
+    let fixed = |month: u32, day: u32, name: &'static str| {
+        NaiveDate::from_ymd_opt(year, month, day).map(|date| Holiday { date, name })
+    };
+    let easter = easter_sunday(year);
+    let from_easter = |days: i64, name: &'static str| {
+        let date = if days < 0 {
+            easter?.checked_sub_days(Days::new(days.unsigned_abs()))
+        } else {
+            easter?.checked_add_days(Days::new(days.unsigned_abs()))
+        };
+        date.map(|date| Holiday { date, name })
+    };
+    // Monday after the third Sunday of September.
+    let federal_fast_monday = NaiveDate::from_weekday_of_month_opt(year, 9, Weekday::Sun, 3)
+        .and_then(|sunday| sunday.succ_opt())
+        .map(|date| Holiday {
+            date,
+            name: "Federal Fast Monday",
+        });
+
+    let mut holidays = [
+        fixed(1, 1, "New Year's Day"),
+        fixed(1, 2, "Berchtold's Day"),
+        from_easter(-2, "Good Friday"),
+        from_easter(1, "Easter Monday"),
+        from_easter(39, "Ascension Day"),
+        from_easter(50, "Whit Monday"),
+        fixed(8, 1, "Swiss National Day"),
+        federal_fast_monday,
+        fixed(12, 25, "Christmas Day"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    holidays.sort_by_key(|holiday| holiday.date);
+    holidays
+}
+
+/// Return `true` if `date` is a public holiday in the canton of Vaud.
+pub fn is_holiday(date: NaiveDate) -> bool {
+    holidays(date.year())
+        .iter()
+        .any(|holiday| holiday.date == date)
+}
+
+/// Return `true` if `date` is a Saturday, a Sunday or a public holiday.
+pub fn is_non_working_day(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun) || is_holiday(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn computes_easter_sunday() {
+        assert_eq!(easter_sunday(2019), Some(date("2019-04-21")));
+        assert_eq!(easter_sunday(2024), Some(date("2024-03-31")));
+        assert_eq!(easter_sunday(2025), Some(date("2025-04-20")));
+        assert_eq!(easter_sunday(2038), Some(date("2038-04-25")));
+    }
+
+    #[test]
+    fn lists_holidays_of_vaud() {
+        let dates = holidays(2024)
+            .iter()
+            .map(|holiday| holiday.date.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            dates,
+            [
+                "2024-01-01",
+                "2024-01-02",
+                "2024-03-29",
+                "2024-04-01",
+                "2024-05-09",
+                "2024-05-20",
+                "2024-08-01",
+                "2024-09-16",
+                "2024-12-25",
+            ]
+        );
+    }
+
+    #[test]
+    fn treats_weekends_and_holidays_as_non_working_days() {
+        assert!(is_non_working_day(date("2024-03-30")));
+        assert!(is_non_working_day(date("2024-04-01")));
+        assert!(!is_non_working_day(date("2024-04-02")));
+        assert!(!is_holiday(date("2024-12-26")));
+    }
+}
@@ -0,0 +1,496 @@
+//! Split of consumption between space heating and domestic hot water.
+//!
+//! Many installations heat both the building and its hot water with gas. On
+//! days without heating, all the gas goes to hot water, so the mean daily
+//! consumption of those days is a hot water baseline:
+//!
+//! - with a [`TemperatureSeries`], baseline days are the days whose mean
+//!   temperature is at or above the heating limit of the
+//!   [`DegreeDayOptions`];
+//! - without temperatures, baseline days are the days of the summer months,
+//!   June to August by default.
+//!
+//! Each month's consumption is then split into `baseline × days` of hot water
+//! and the remainder of heating. The hot water demand is usually somewhat
+//! higher in winter, when the mains water is colder, so the heating part is
+//! an upper estimate.
+//!
+//! The uncertainty combines the day-to-day variation of the baseline days and
+//! the standard error of their mean: a month of `d` days has a half-width of
+//! `t × √(d × s² + d² × s² / n)`, where `s` is the standard deviation of the
+//! `n` baseline days and `t` the Student quantile of the chosen confidence.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::{Datelike, NaiveDate};
+//! use energiapro::Measurement;
+//! use energiapro::analysis::hot_water;
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let start = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+//! let measurements = start
+//!     .iter_days()
+//!     .take_while(|date| *date < NaiveDate::from_ymd_opt(2024, 2, 1).unwrap())
+//!     .map(|date| {
+//!         // 20 kWh of hot water per day, and 80 kWh of heating in January.
+//!         let consumption_kwh = if date.year() == 2024 { 100.0 } else { 20.0 };
+//!         Measurement {
+//!             client_id: 1,
+//!             installation_id: "INSTALLATION_ID_1".to_owned(),
+//!             timestamp: format!("{date} 00:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: consumption_kwh / 10.0,
+//!             consumption_kwh,
+//!         }
+//!     })
+//!     .collect::<Vec<_>>();
+//!
+//! let split = hot_water::split(&measurements)?;
+//! let january = split.months.last().unwrap();
+//!
+//! assert_eq!(split.baseline_kwh_per_day, 20.0);
+//! assert_eq!(january.hot_water_kwh, 620.0);
+//! assert_eq!(january.heating_kwh, 2480.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+use super::degree_days::{DegreeDayOptions, TemperatureSeries};
+use super::{daily_consumption, round, student_t_quantile};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+use crate::types::Granularity;
+
+/// Parameters of the heating and hot water split.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotWaterOptions {
+    /// Daily mean temperatures used to select the days above the heating
+    /// limit, or `None` to use the baseline months.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Degree day method whose heating limit selects the baseline days when
+    /// temperatures are given.
+    pub degree_days: DegreeDayOptions,
+
+    /// Calendar months, from 1 to 12, whose days form the baseline when no
+    /// temperatures are given.
+    pub baseline_months: Vec<u32>,
+
+    /// Share of a day's intervals required for the day to be a baseline day.
+    pub min_day_coverage: f64,
+
+    /// Minimum number of baseline days required to estimate the baseline.
+    pub min_baseline_days: usize,
+
+    /// Two-sided confidence level of the intervals, between 0 and 1.
+    pub confidence: f64,
+}
+
+impl Default for HotWaterOptions {
+    fn default() -> Self {
+        Self {
+            temperatures: None,
+            degree_days: DegreeDayOptions::default(),
+            baseline_months: vec![6, 7, 8],
+            min_day_coverage: 0.9,
+            min_baseline_days: 14,
+            confidence: 0.9,
+        }
+    }
+}
+
+impl HotWaterOptions {
+    /// Select the baseline days by their mean temperature.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the degree day method whose heating limit selects the baseline days.
+    pub fn with_degree_days(mut self, degree_days: DegreeDayOptions) -> Self {
+        self.degree_days = degree_days;
+        self
+    }
+
+    /// Set the calendar months of the baseline when no temperatures are given.
+    pub fn with_baseline_months(mut self, months: impl IntoIterator<Item = u32>) -> Self {
+        self.baseline_months = months.into_iter().collect();
+        self
+    }
+
+    /// Set the share of a day's intervals required for a baseline day.
+    pub fn with_min_day_coverage(mut self, min_day_coverage: f64) -> Self {
+        self.min_day_coverage = min_day_coverage;
+        self
+    }
+
+    /// Set the minimum number of baseline days.
+    pub fn with_min_baseline_days(mut self, min_baseline_days: usize) -> Self {
+        self.min_baseline_days = min_baseline_days;
+        self
+    }
+
+    /// Set the confidence level of the intervals, e.g. `0.9` for 90%.
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if self.temperatures.is_none()
+            && (self.baseline_months.is_empty()
+                || self
+                    .baseline_months
+                    .iter()
+                    .any(|month| !(1..=12).contains(month)))
+        {
+            return Err(EnergiaProError::InvalidArgument(
+                "baseline_months must contain months between 1 and 12".to_owned(),
+            ));
+        }
+        if !(self.min_day_coverage > 0.0 && self.min_day_coverage <= 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "min_day_coverage must be greater than 0 and at most 1".to_owned(),
+            ));
+        }
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "confidence must be between 0 and 1".to_owned(),
+            ));
+        }
+        self.degree_days.validate()
+    }
+}
+
+/// How the baseline days were selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BaselineMethod {
+    /// Days at or above the heating limit temperature.
+    HeatingLimit,
+    /// Days of the baseline months.
+    SummerMonths,
+}
+
+impl BaselineMethod {
+    /// Return the snake_case name of the method.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::HeatingLimit => "heating_limit",
+            Self::SummerMonths => "summer_months",
+        }
+    }
+}
+
+impl fmt::Display for BaselineMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Heating and hot water consumption of one calendar month.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonthlySplit {
+    /// First day of the month.
+    pub start: NaiveDate,
+    /// Last day of the month.
+    pub end: NaiveDate,
+    /// Number of days of the month with measurements.
+    pub days: usize,
+    /// Measured energy of the month in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Estimated hot water energy in kilowatt-hours.
+    pub hot_water_kwh: f64,
+    /// Lower bound of the hot water energy in kilowatt-hours.
+    pub hot_water_lower_kwh: f64,
+    /// Upper bound of the hot water energy in kilowatt-hours.
+    pub hot_water_upper_kwh: f64,
+    /// Estimated space heating energy in kilowatt-hours.
+    pub heating_kwh: f64,
+    /// Lower bound of the space heating energy in kilowatt-hours.
+    pub heating_lower_kwh: f64,
+    /// Upper bound of the space heating energy in kilowatt-hours.
+    pub heating_upper_kwh: f64,
+}
+
+/// Heating and hot water split of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HotWaterSplit {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// How the baseline days were selected.
+    pub method: BaselineMethod,
+    /// Number of baseline days.
+    pub baseline_days: usize,
+    /// Mean daily hot water consumption in kilowatt-hours.
+    pub baseline_kwh_per_day: f64,
+    /// Standard deviation of the daily consumption of the baseline days, in
+    /// kilowatt-hours.
+    pub baseline_std_kwh_per_day: f64,
+    /// Confidence level of the intervals.
+    pub confidence: f64,
+    /// Split of each month, in chronological order.
+    pub months: Vec<MonthlySplit>,
+}
+
+impl HotWaterSplit {
+    /// Return the share of the total consumption used for space heating,
+    /// or `None` if there is no consumption.
+    pub fn heating_share(&self) -> Option<f64> {
+        let consumption = self
+            .months
+            .iter()
+            .map(|month| month.consumption_kwh)
+            .sum::<f64>();
+        let heating = self
+            .months
+            .iter()
+            .map(|month| month.heating_kwh)
+            .sum::<f64>();
+        (consumption > 0.0).then(|| heating / consumption)
+    }
+}
+
+/// Split consumption with the default [`HotWaterOptions`].
+///
+/// # Errors
+///
+/// See [`split_with`].
+pub fn split(measurements: &[Measurement]) -> Result<HotWaterSplit, EnergiaProError> {
+    split_with(measurements, &HotWaterOptions::default())
+}
+
+/// Split consumption with custom options.
+///
+/// Every month with measurements is split, including incomplete months;
+/// baseline days must be complete days.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the measurements belong to
+/// more than one installation, if a timestamp cannot be parsed, or if fewer
+/// than `min_baseline_days` baseline days are found.
+pub fn split_with(
+    measurements: &[Measurement],
+    options: &HotWaterOptions,
+) -> Result<HotWaterSplit, EnergiaProError> {
+    options.validate()?;
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let (method, baseline) = match &options.temperatures {
+        Some(temperatures) => (
+            BaselineMethod::HeatingLimit,
+            daily_consumption(&series, options.min_day_coverage)
+                .into_iter()
+                .filter(|(date, _)| {
+                    temperatures
+                        .get(*date)
+                        .is_some_and(|celsius| celsius >= options.degree_days.heating_limit_celsius)
+                })
+                .map(|(_, kwh)| kwh)
+                .collect::<Vec<_>>(),
+        ),
+        None => (
+            BaselineMethod::SummerMonths,
+            daily_consumption(&series, options.min_day_coverage)
+                .into_iter()
+                .filter(|(date, _)| options.baseline_months.contains(&date.month()))
+                .map(|(_, kwh)| kwh)
+                .collect::<Vec<_>>(),
+        ),
+    };
+
+    let min_days = options.min_baseline_days.max(2);
+    if baseline.len() < min_days {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "installation `{}` has {} baseline days for the hot water estimate, at least {min_days} are required",
+            series.installation_id(),
+            baseline.len()
+        )));
+    }
+
+    let count = baseline.len() as f64;
+    let mean = baseline.iter().sum::<f64>() / count;
+    let std = (baseline
+        .iter()
+        .map(|kwh| (kwh - mean) * (kwh - mean))
+        .sum::<f64>()
+        / (count - 1.0))
+        .sqrt();
+    let t = student_t_quantile((1.0 + options.confidence) / 2.0, count - 1.0);
+
+    // Months include partial days: their consumption is part of the month.
+    let mut months: BTreeMap<NaiveDate, (usize, f64)> = BTreeMap::new();
+    for (date, kwh) in daily_consumption(&series, 0.0) {
+        let month = months.entry(Granularity::Month.period(date).0).or_default();
+        month.0 += 1;
+        month.1 += kwh;
+    }
+
+    let months = months
+        .into_iter()
+        .map(|(start, (days, consumption))| {
+            let days_f = days as f64;
+            let clamp = |kwh: f64| kwh.clamp(0.0, consumption.max(0.0));
+            let hot_water = clamp(mean * days_f);
+            let half_width = t * (days_f * std * std + days_f * days_f * std * std / count).sqrt();
+            let hot_water_lower = clamp(mean * days_f - half_width);
+            let hot_water_upper = clamp(mean * days_f + half_width);
+
+            MonthlySplit {
+                start,
+                end: Granularity::Month.period(start).1,
+                days,
+                consumption_kwh: round(consumption, 2),
+                hot_water_kwh: round(hot_water, 2),
+                hot_water_lower_kwh: round(hot_water_lower, 2),
+                hot_water_upper_kwh: round(hot_water_upper, 2),
+                heating_kwh: round(consumption - hot_water, 2),
+                heating_lower_kwh: round(consumption - hot_water_upper, 2),
+                heating_upper_kwh: round(consumption - hot_water_lower, 2),
+            }
+        })
+        .collect();
+
+    Ok(HotWaterSplit {
+        installation_id: series.installation_id().to_owned(),
+        method,
+        baseline_days: baseline.len(),
+        baseline_kwh_per_day: round(mean, 2),
+        baseline_std_kwh_per_day: round(std, 2),
+        confidence: options.confidence,
+        months,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+    use crate::analysis::test_support::daily;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn celsius(date: NaiveDate) -> f64 {
+        match date.month() {
+            12 | 1 | 2 => 0.0,
+            3 | 11 => 8.0,
+            4 | 10 => 11.0,
+            5 | 9 => 14.0,
+            _ => 20.0,
+        }
+    }
+
+    /// A year of daily consumption: 20 kWh of hot water with a small
+    /// variation, plus 5 kWh per degree day.
+    fn year() -> (Vec<Measurement>, TemperatureSeries) {
+        let start = date("2023-01-01");
+        let dates = (0..365)
+            .map(|day| start + Days::new(day))
+            .collect::<Vec<_>>();
+        let measurements = dates
+            .iter()
+            .enumerate()
+            .map(|(day, date)| {
+                let hot_water = 20.0 + [0.0, 2.0, -2.0][day % 3];
+                daily(
+                    *date,
+                    hot_water + 5.0 * DegreeDayOptions::default().degree_days(celsius(*date)),
+                )
+            })
+            .collect();
+        let temperatures =
+            TemperatureSeries::from_days(dates.iter().map(|date| (*date, celsius(*date))));
+        (measurements, temperatures)
+    }
+
+    #[test]
+    fn splits_months_with_summer_baseline() {
+        let (measurements, _) = year();
+
+        let split = split(&measurements).unwrap();
+
+        assert_eq!(split.method, BaselineMethod::SummerMonths);
+        assert_eq!(split.baseline_days, 92);
+        assert!((split.baseline_kwh_per_day - 20.0).abs() < 0.1);
+        assert_eq!(split.months.len(), 12);
+
+        let january = &split.months[0];
+        assert_eq!(january.start, date("2023-01-01"));
+        assert_eq!(january.end, date("2023-01-31"));
+        assert_eq!(january.days, 31);
+        assert!((january.hot_water_kwh - 620.0).abs() < 5.0);
+        assert!((january.heating_kwh - 3100.0).abs() < 5.0);
+        assert!(january.heating_lower_kwh < january.heating_kwh);
+        assert!(january.heating_upper_kwh > january.heating_kwh);
+        assert!(january.hot_water_lower_kwh < 620.0 && january.hot_water_upper_kwh > 620.0);
+
+        let july = &split.months[6];
+        assert!(july.heating_kwh.abs() < 5.0);
+        assert_eq!(july.hot_water_upper_kwh, july.consumption_kwh);
+        assert!(split.heating_share().unwrap() > 0.5);
+    }
+
+    #[test]
+    fn selects_baseline_days_above_heating_limit() {
+        let (measurements, temperatures) = year();
+        let options = HotWaterOptions::default().with_temperatures(temperatures);
+
+        let split = split_with(&measurements, &options).unwrap();
+
+        // May to September are at or above the 12 °C heating limit.
+        assert_eq!(split.method, BaselineMethod::HeatingLimit);
+        assert_eq!(split.baseline_days, 153);
+        assert!((split.baseline_kwh_per_day - 20.0).abs() < 0.1);
+        let april = &split.months[3];
+        assert!((april.heating_kwh - 30.0 * 5.0 * 9.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn widens_intervals_with_fewer_baseline_days() {
+        let (measurements, _) = year();
+        let width = |split: &HotWaterSplit| {
+            split.months[0].hot_water_upper_kwh - split.months[0].hot_water_lower_kwh
+        };
+
+        let wide = split_with(
+            &measurements,
+            &HotWaterOptions::default().with_baseline_months([8]),
+        )
+        .unwrap();
+        let narrow = split(&measurements).unwrap();
+
+        assert!(width(&wide) > width(&narrow));
+    }
+
+    #[test]
+    fn requires_enough_baseline_days() {
+        let (measurements, _) = year();
+        let winter = measurements
+            .into_iter()
+            .filter(|measurement| measurement.timestamp.as_str() < "2023-03-01")
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            split(&winter),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            split_with(
+                &winter,
+                &HotWaterOptions::default().with_baseline_months([13])
+            ),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
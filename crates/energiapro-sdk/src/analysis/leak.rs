@@ -0,0 +1,434 @@
+//! Continuous-flow leak detection.
+//!
+//! A gas leak or a pilot flame left burning shows up as consumption that never
+//! drops to zero, even at night and in summer. [`detect`] computes the
+//! continuous night flow of every day and reports an incident when it stays
+//! above a threshold for several consecutive nights.
+//!
+//! The continuous night flow is found with a rolling window sliding over the
+//! intervals starting within the night window: the lowest flow rate (in m³/h)
+//! of each window is the flow that lasted the whole window, and the night
+//! keeps the highest of them. Normal appliances are usually off for long
+//! stretches of the night, so a flow that persists for a whole window is
+//! likely continuous, and a leak starting in the middle of the night is
+//! already seen on that night. The wasted volume assumes that this flow
+//! lasted the whole day.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::leak::{self, LeakOptions};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let mut measurements = Vec::new();
+//! for day in 1..=10 {
+//!     for hour in 0..24 {
+//!         measurements.push(Measurement {
+//!             client_id: 1,
+//!             installation_id: "INSTALLATION_ID_1".to_owned(),
+//!             timestamp: format!("2024-07-{day:02} {hour:02}:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: 0.1,
+//!             consumption_kwh: 1.1,
+//!         });
+//!     }
+//! }
+//!
+//! let options = LeakOptions::default().with_min_days(7);
+//! let incidents = leak::detect_with(&measurements, &options)?;
+//!
+//! assert_eq!(incidents.len(), 1);
+//! assert_eq!(incidents[0].days, 10);
+//! assert_eq!(incidents[0].wasted_m3, 24.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Timelike};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use super::{detect_resolution, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+/// Thresholds used by [`detect_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeakOptions {
+    /// First local hour of the night window, from 0 to 23.
+    pub night_start_hour: u32,
+
+    /// Local hour at which the night window ends (exclusive), from 1 to 24.
+    pub night_end_hour: u32,
+
+    /// Length of the rolling window within the night, in hours, over which
+    /// the flow must stay above the threshold.
+    pub window_hours: u32,
+
+    /// Continuous night flow above which a night is suspicious, in m³/h.
+    pub threshold_m3_per_hour: f64,
+
+    /// Number of consecutive suspicious nights that make an incident.
+    pub min_days: usize,
+
+    /// Price of one kWh, to estimate the wasted cost.
+    pub price_per_kwh: Option<f64>,
+}
+
+impl Default for LeakOptions {
+    fn default() -> Self {
+        Self {
+            night_start_hour: 1,
+            night_end_hour: 5,
+            window_hours: 2,
+            threshold_m3_per_hour: 0.02,
+            min_days: 7,
+            price_per_kwh: None,
+        }
+    }
+}
+
+impl LeakOptions {
+    /// Set the local night window, from `start_hour` to `end_hour` (exclusive).
+    pub fn with_night(mut self, start_hour: u32, end_hour: u32) -> Self {
+        self.night_start_hour = start_hour;
+        self.night_end_hour = end_hour;
+        self
+    }
+
+    /// Set the length of the rolling window within the night, in hours.
+    pub fn with_window_hours(mut self, window_hours: u32) -> Self {
+        self.window_hours = window_hours;
+        self
+    }
+
+    /// Set the continuous night flow above which a night is suspicious, in m³/h.
+    pub fn with_threshold_m3_per_hour(mut self, threshold_m3_per_hour: f64) -> Self {
+        self.threshold_m3_per_hour = threshold_m3_per_hour;
+        self
+    }
+
+    /// Set the number of consecutive suspicious nights that make an incident.
+    pub fn with_min_days(mut self, min_days: usize) -> Self {
+        self.min_days = min_days;
+        self
+    }
+
+    /// Set the price of one kWh.
+    pub fn with_price_per_kwh(mut self, price_per_kwh: f64) -> Self {
+        self.price_per_kwh = Some(price_per_kwh);
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if self.night_start_hour >= self.night_end_hour || self.night_end_hour > 24 {
+            return Err(EnergiaProError::InvalidArgument(format!(
+                "night window {}-{} must be within one day with the start before the end",
+                self.night_start_hour, self.night_end_hour
+            )));
+        }
+        if self.window_hours == 0 || self.window_hours > self.night_end_hour - self.night_start_hour
+        {
+            return Err(EnergiaProError::InvalidArgument(format!(
+                "window of {} hours must be between 1 hour and the length of the night window",
+                self.window_hours
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A run of consecutive nights with a continuous flow.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeakIncident {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// First day of the incident.
+    pub start: NaiveDate,
+    /// Last day of the incident.
+    pub end: NaiveDate,
+    /// Number of days in the incident.
+    pub days: usize,
+    /// Lowest continuous night flow of the incident, in m³/h.
+    pub min_flow_m3_per_hour: f64,
+    /// Average continuous night flow of the incident, in m³/h.
+    pub mean_flow_m3_per_hour: f64,
+    /// Estimated wasted volume in cubic meters.
+    pub wasted_m3: f64,
+    /// Estimated wasted energy in kilowatt-hours.
+    pub wasted_kwh: f64,
+    /// Estimated wasted cost, when a price is given.
+    pub wasted_cost: Option<f64>,
+    /// Whether the incident lasts until the last night of the series.
+    pub ongoing: bool,
+}
+
+/// Detect leaks with the default [`LeakOptions`].
+///
+/// # Errors
+///
+/// See [`detect_with`].
+pub fn detect(measurements: &[Measurement]) -> Result<Vec<LeakIncident>, EnergiaProError> {
+    detect_with(measurements, &LeakOptions::default())
+}
+
+/// Detect leaks with custom thresholds.
+///
+/// Nights without a complete rolling window of measurements interrupt an
+/// incident. Wasted energy is converted from the wasted volume with the
+/// average kWh/m³ factor of the series.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the measurements belong
+/// to more than one installation or if a timestamp cannot be parsed.
+pub fn detect_with(
+    measurements: &[Measurement],
+    options: &LeakOptions,
+) -> Result<Vec<LeakIncident>, EnergiaProError> {
+    options.validate()?;
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let Some(resolution_minutes) = detect_resolution(series.timestamps()) else {
+        return Ok(Vec::new());
+    };
+    let interval_hours = resolution_minutes as f64 / 60.0;
+
+    let mut night_points: BTreeMap<NaiveDate, Vec<(DateTime<Tz>, f64)>> = BTreeMap::new();
+    for point in series.iter() {
+        if !(options.night_start_hour..options.night_end_hour).contains(&point.timestamp.hour()) {
+            continue;
+        }
+        night_points
+            .entry(point.timestamp.date_naive())
+            .or_default()
+            .push((point.timestamp, point.consumption_m3 / interval_hours));
+    }
+
+    let window = (i64::from(options.window_hours) * 60 / resolution_minutes).max(1) as usize;
+    let resolution = TimeDelta::minutes(resolution_minutes);
+    let nights: BTreeMap<NaiveDate, f64> = night_points
+        .into_iter()
+        .filter_map(|(date, points)| {
+            continuous_flow(&points, window, resolution).map(|flow| (date, flow))
+        })
+        .collect();
+
+    let total_m3 = series.consumption_m3().iter().sum::<f64>();
+    let kwh_per_m3 = if total_m3 > 0.0 {
+        series.consumption_kwh().iter().sum::<f64>() / total_m3
+    } else {
+        0.0
+    };
+    let last_night = nights.keys().next_back().copied();
+
+    let mut incidents = Vec::new();
+    let mut run: Vec<(NaiveDate, f64)> = Vec::new();
+    for (date, flow) in nights {
+        let consecutive = run
+            .last()
+            .is_none_or(|(previous, _)| previous.succ_opt() == Some(date));
+        if !consecutive || flow <= options.threshold_m3_per_hour {
+            incidents.extend(incident(&series, &run, kwh_per_m3, options, None));
+            run.clear();
+        }
+        if flow > options.threshold_m3_per_hour {
+            run.push((date, flow));
+        }
+    }
+    incidents.extend(incident(&series, &run, kwh_per_m3, options, last_night));
+
+    Ok(incidents)
+}
+
+/// Return the highest minimum flow of the rolling windows of `window`
+/// consecutive intervals, or `None` if the night has no complete window.
+fn continuous_flow(
+    points: &[(DateTime<Tz>, f64)],
+    window: usize,
+    resolution: TimeDelta,
+) -> Option<f64> {
+    points
+        .windows(window)
+        .filter(|intervals| {
+            intervals[window - 1].0 - intervals[0].0 == resolution * (window as i32 - 1)
+        })
+        .map(|intervals| {
+            intervals
+                .iter()
+                .map(|(_, flow)| *flow)
+                .fold(f64::INFINITY, f64::min)
+        })
+        .reduce(f64::max)
+}
+
+fn incident(
+    series: &MeasurementSeries,
+    run: &[(NaiveDate, f64)],
+    kwh_per_m3: f64,
+    options: &LeakOptions,
+    last_night: Option<NaiveDate>,
+) -> Option<LeakIncident> {
+    let (&(start, _), &(end, _)) = (run.first()?, run.last()?);
+    if run.len() < options.min_days.max(1) {
+        return None;
+    }
+
+    let flows = run.iter().map(|(_, flow)| *flow);
+    let wasted_m3 = round(flows.clone().map(|flow| flow * 24.0).sum(), 3);
+    let wasted_kwh = round(wasted_m3 * kwh_per_m3, 3);
+
+    Some(LeakIncident {
+        installation_id: series.installation_id().to_owned(),
+        start,
+        end,
+        days: run.len(),
+        min_flow_m3_per_hour: round(flows.clone().fold(f64::INFINITY, f64::min), 3),
+        mean_flow_m3_per_hour: round(flows.sum::<f64>() / run.len() as f64, 3),
+        wasted_m3,
+        wasted_kwh,
+        wasted_cost: options
+            .price_per_kwh
+            .map(|price| round(wasted_kwh * price, 3)),
+        ongoing: last_night == Some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// Hourly summer series with a daytime load and the given night flow per
+    /// day, in m³/h.
+    fn series(night_flows: &[f64]) -> Vec<Measurement> {
+        let start = date("2024-07-01");
+        let mut measurements = Vec::new();
+        for (day, night_flow) in night_flows.iter().enumerate() {
+            let date = start + Days::new(day as u64);
+            for hour in 0..24 {
+                let consumption_m3 = if (7..22).contains(&hour) {
+                    0.5
+                } else {
+                    *night_flow
+                };
+                measurements.push(measurement(
+                    format!("{date} {hour:02}:00:00"),
+                    consumption_m3 * 10.0,
+                ));
+            }
+        }
+        measurements
+    }
+
+    #[test]
+    fn reports_runs_of_continuous_night_flow() {
+        let mut flows = vec![0.0; 3];
+        flows.extend([0.1; 8]);
+        flows.extend([0.0; 3]);
+        let options = LeakOptions::default().with_price_per_kwh(0.12);
+
+        let incidents = detect_with(&series(&flows), &options).unwrap();
+
+        assert_eq!(
+            incidents,
+            vec![LeakIncident {
+                installation_id: "INSTALLATION_ID_1".to_owned(),
+                start: date("2024-07-04"),
+                end: date("2024-07-11"),
+                days: 8,
+                min_flow_m3_per_hour: 0.1,
+                mean_flow_m3_per_hour: 0.1,
+                wasted_m3: 19.2,
+                wasted_kwh: 192.0,
+                wasted_cost: Some(23.04),
+                ongoing: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_short_runs_and_flows_below_the_threshold() {
+        let mut flows = vec![0.1; 5];
+        flows.push(0.0);
+        flows.extend([0.01; 10]);
+
+        assert!(detect(&series(&flows)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn flags_ongoing_incidents_and_breaks_runs_on_missing_nights() {
+        let mut measurements = series(&[0.1; 20]);
+        // Drop the night of the 8th: the run restarts on the 9th.
+        measurements.retain(|measurement| {
+            !(measurement.timestamp.starts_with("2024-07-08 0")
+                && (1..5).any(|hour| measurement.timestamp.ends_with(&format!("0{hour}:00:00"))))
+        });
+
+        let incidents = detect(&measurements).unwrap();
+
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(incidents[0].end, date("2024-07-07"));
+        assert!(!incidents[0].ongoing);
+        assert_eq!(incidents[1].start, date("2024-07-09"));
+        assert_eq!(incidents[1].days, 12);
+        assert!(incidents[1].ongoing);
+    }
+
+    #[test]
+    fn detects_a_leak_starting_in_the_middle_of_the_night() {
+        let mut measurements = series(&[0.1; 8]);
+        // The leak starts at 03:00 on the first night.
+        for measurement in &mut measurements[1..3] {
+            measurement.consumption_m3 = 0.0;
+        }
+
+        let incidents = detect(&measurements).unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].start, date("2024-07-01"));
+        assert_eq!(incidents[0].days, 8);
+
+        let options = LeakOptions::default().with_window_hours(4);
+        let incidents = detect_with(&measurements, &options).unwrap();
+        assert_eq!(incidents[0].start, date("2024-07-02"));
+    }
+
+    #[test]
+    fn ignores_nights_with_a_short_continuous_flow() {
+        let mut measurements = series(&[0.1; 8]);
+        // Every night drops to zero between 02:00 and 04:00, so no
+        // window of two hours has a continuous flow.
+        for measurement in &mut measurements {
+            if measurement.timestamp.ends_with("02:00:00")
+                || measurement.timestamp.ends_with("04:00:00")
+            {
+                measurement.consumption_m3 = 0.0;
+            }
+        }
+
+        assert!(detect(&measurements).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_night_windows() {
+        for options in [
+            LeakOptions::default().with_night(5, 1),
+            LeakOptions::default().with_window_hours(0),
+            LeakOptions::default().with_window_hours(5),
+        ] {
+            assert!(matches!(
+                detect_with(&series(&[0.1; 10]), &options),
+                Err(EnergiaProError::InvalidArgument(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,496 @@
+//! Meter index discontinuities and continuous virtual index.
+//!
+//! When a gas meter rolls over, is replaced or has its index corrected, the
+//! raw `index_m3` jumps and any consumption derived from index deltas becomes
+//! meaningless. [`analyze`] finds those discontinuities, classifies them and
+//! computes a virtual index that grows continuously across them and never
+//! decreases.
+//!
+//! After missing intervals, the index also moves by the consumption of the
+//! missing rows. Such a jump is consumption, not a discontinuity, as long as
+//! it is positive and its mean flow over the gap stays plausible.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::meter::{self, DiscontinuityKind};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: &str, index_m3: f64, consumption_m3: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3,
+//!     consumption_m3,
+//!     consumption_kwh: consumption_m3 * 10.5,
+//! };
+//!
+//! let continuity = meter::analyze(&[
+//!     measurement("2024-04-01 00:00:00", 99_998.0, 1.0),
+//!     measurement("2024-04-01 01:00:00", 1.0, 3.0),
+//! ])?;
+//!
+//! assert_eq!(continuity.discontinuities[0].kind, DiscontinuityKind::Rollover);
+//! assert_eq!(continuity.virtual_index_m3, vec![99_998.0, 100_001.0]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use super::{detect_resolution, missing_intervals};
+use crate::errors::EnergiaProError;
+use crate::models::{LocalResolver, Measurement};
+
+/// Thresholds used by [`analyze_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterOptions {
+    /// Largest accepted difference between the index delta and the reported
+    /// consumption before a row is considered discontinuous, in cubic meters.
+    pub tolerance_m3: f64,
+
+    /// Largest index jump, in cubic meters, still considered a correction of
+    /// the same meter rather than a replacement.
+    pub correction_threshold_m3: f64,
+
+    /// Highest plausible mean flow in m3 per hour across missing intervals.
+    ///
+    /// A positive index jump after a gap is attributed to the consumption of
+    /// the missing rows when its mean flow stays below this value.
+    pub max_flow_m3_per_hour: f64,
+}
+
+impl Default for MeterOptions {
+    fn default() -> Self {
+        Self {
+            tolerance_m3: 0.01,
+            correction_threshold_m3: 10.0,
+            max_flow_m3_per_hour: 100.0,
+        }
+    }
+}
+
+impl MeterOptions {
+    /// Set the largest accepted difference between index delta and consumption.
+    pub fn with_tolerance_m3(mut self, tolerance_m3: f64) -> Self {
+        self.tolerance_m3 = tolerance_m3;
+        self
+    }
+
+    /// Set the largest index jump still considered a correction.
+    pub fn with_correction_threshold_m3(mut self, correction_threshold_m3: f64) -> Self {
+        self.correction_threshold_m3 = correction_threshold_m3;
+        self
+    }
+
+    /// Set the highest plausible mean flow across missing intervals.
+    pub fn with_max_flow_m3_per_hour(mut self, max_flow_m3_per_hour: f64) -> Self {
+        self.max_flow_m3_per_hour = max_flow_m3_per_hour;
+        self
+    }
+}
+
+/// Cause of a meter index discontinuity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscontinuityKind {
+    /// The index wrapped around after reaching the capacity of the meter
+    /// display, for example from `99 998` to `1`.
+    Rollover,
+    /// The meter was swapped and the index restarted from an unrelated value.
+    Replacement,
+    /// The index of the same meter was adjusted by a small amount, for example
+    /// after a manual reading.
+    Correction,
+}
+
+impl DiscontinuityKind {
+    /// Short machine-readable name of the kind.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Rollover => "rollover",
+            Self::Replacement => "replacement",
+            Self::Correction => "correction",
+        }
+    }
+}
+
+impl fmt::Display for DiscontinuityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A jump of the raw meter index that the reported consumption does not explain.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Discontinuity {
+    /// Timestamp of the first measurement after the jump.
+    pub timestamp: String,
+    /// Classification of the jump.
+    pub kind: DiscontinuityKind,
+    /// Raw index of the previous measurement, in cubic meters.
+    pub previous_index_m3: f64,
+    /// Raw index of the measurement, in cubic meters.
+    pub index_m3: f64,
+    /// Consumption reported for the measurement, in cubic meters.
+    pub consumption_m3: f64,
+    /// Offset added to raw indexes from this measurement on to obtain the
+    /// virtual index, in cubic meters.
+    pub offset_m3: f64,
+}
+
+/// Discontinuities of a meter and its continuous virtual index.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MeterContinuity {
+    /// Discontinuities in chronological order.
+    pub discontinuities: Vec<Discontinuity>,
+    /// Virtual index in cubic meters, aligned with the analyzed measurements.
+    ///
+    /// The virtual index equals the raw index until the first discontinuity,
+    /// and grows continuously afterwards. Upward corrections are kept as-is
+    /// because they adjust the reading of the same meter; whenever the index
+    /// would go down, after a downward correction or a small negative delta,
+    /// the offset is raised so that the virtual index stays flat instead.
+    pub virtual_index_m3: Vec<f64>,
+}
+
+impl MeterContinuity {
+    /// Return copies of `measurements` whose `index_m3` is the virtual index.
+    ///
+    /// `measurements` must be the slice that was analyzed.
+    pub fn apply(&self, measurements: &[Measurement]) -> Vec<Measurement> {
+        measurements
+            .iter()
+            .zip(&self.virtual_index_m3)
+            .map(|(measurement, &index_m3)| Measurement {
+                index_m3,
+                ..measurement.clone()
+            })
+            .collect()
+    }
+}
+
+/// Analyze measurements with the default [`MeterOptions`].
+///
+/// # Errors
+///
+/// Returns an error if the measurements belong to more than one installation.
+pub fn analyze(measurements: &[Measurement]) -> Result<MeterContinuity, EnergiaProError> {
+    analyze_with(measurements, &MeterOptions::default())
+}
+
+/// Analyze measurements with custom thresholds.
+///
+/// Measurements must belong to a single installation and be sorted in
+/// chronological order, as returned by the measurements resource.
+///
+/// # Errors
+///
+/// Returns an error if the measurements belong to more than one installation.
+pub fn analyze_with(
+    measurements: &[Measurement],
+    options: &MeterOptions,
+) -> Result<MeterContinuity, EnergiaProError> {
+    if let Some(first) = measurements.first()
+        && let Some(other) = measurements
+            .iter()
+            .find(|measurement| measurement.installation_id != first.installation_id)
+    {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "cannot analyze installations `{}` and `{}` together",
+            first.installation_id, other.installation_id
+        )));
+    }
+
+    let instants = local_instants(measurements);
+    let resolution_minutes =
+        detect_resolution(&instants.iter().flatten().copied().collect::<Vec<_>>());
+
+    let mut discontinuities = Vec::new();
+    let mut virtual_index_m3: Vec<f64> = Vec::with_capacity(measurements.len());
+    let mut offset_m3 = 0.0;
+
+    for (index, measurement) in measurements.iter().enumerate() {
+        let mut kind = None;
+        if let Some(previous) = index.checked_sub(1).map(|index| &measurements[index]) {
+            let delta = measurement.index_m3 - previous.index_m3;
+            let consumption = measurement.consumption_m3;
+            let gap_minutes = match (instants[index - 1], instants[index], resolution_minutes) {
+                (Some(before), Some(after), Some(resolution)) => {
+                    let elapsed = (after - before).num_minutes();
+                    (missing_intervals(elapsed, resolution) > 0).then_some(elapsed)
+                }
+                _ => None,
+            };
+            let consumed_during_gap = gap_minutes.is_some_and(|minutes| {
+                delta >= consumption - options.tolerance_m3
+                    && delta <= options.max_flow_m3_per_hour * minutes as f64 / 60.0
+            });
+
+            if (delta - consumption).abs() > options.tolerance_m3 && !consumed_during_gap {
+                let previous_virtual = virtual_index_m3[index - 1];
+                let discontinuity = classify(
+                    previous.index_m3,
+                    measurement.index_m3,
+                    consumption,
+                    options,
+                );
+
+                offset_m3 = match discontinuity {
+                    DiscontinuityKind::Rollover => offset_m3 + rollover_capacity(previous.index_m3),
+                    DiscontinuityKind::Replacement => {
+                        previous_virtual + consumption.max(0.0) - measurement.index_m3
+                    }
+                    DiscontinuityKind::Correction => offset_m3,
+                };
+                kind = Some(discontinuity);
+            }
+        }
+
+        // Carry the offset so that the virtual index never decreases.
+        if let Some(&previous_virtual) = virtual_index_m3.last() {
+            offset_m3 += (previous_virtual - (measurement.index_m3 + offset_m3)).max(0.0);
+        }
+
+        if let Some(kind) = kind {
+            let previous = &measurements[index - 1];
+            discontinuities.push(Discontinuity {
+                timestamp: measurement.timestamp.clone(),
+                kind,
+                previous_index_m3: previous.index_m3,
+                index_m3: measurement.index_m3,
+                consumption_m3: measurement.consumption_m3,
+                offset_m3,
+            });
+        }
+        virtual_index_m3.push(measurement.index_m3 + offset_m3);
+    }
+
+    Ok(MeterContinuity {
+        discontinuities,
+        virtual_index_m3,
+    })
+}
+
+/// Resolve the local timestamp of each measurement, keeping both occurrences
+/// of the repeated autumn hour apart. Unparseable timestamps yield `None`.
+fn local_instants(measurements: &[Measurement]) -> Vec<Option<DateTime<Tz>>> {
+    let mut resolver = LocalResolver::default();
+    measurements
+        .iter()
+        .map(|measurement| Some(resolver.resolve(measurement.local_timestamp().ok()?)))
+        .collect()
+}
+
+fn classify(
+    previous_index: f64,
+    index: f64,
+    consumption: f64,
+    options: &MeterOptions,
+) -> DiscontinuityKind {
+    let delta = index - previous_index;
+
+    if delta < 0.0 {
+        let capacity = rollover_capacity(previous_index);
+        let wrapped_delta = index + capacity - previous_index;
+        let explained_by_consumption =
+            (wrapped_delta - consumption).abs() <= options.correction_threshold_m3;
+        let wrapped_near_zero = previous_index >= capacity * 0.9 && index <= capacity * 0.1;
+
+        if explained_by_consumption || wrapped_near_zero {
+            return DiscontinuityKind::Rollover;
+        }
+    }
+
+    if (delta - consumption).abs() <= options.correction_threshold_m3 {
+        DiscontinuityKind::Correction
+    } else {
+        DiscontinuityKind::Replacement
+    }
+}
+
+/// Return the capacity of a meter display able to show `index`, e.g. `100 000`
+/// for an index of `99 998`.
+fn rollover_capacity(index: f64) -> f64 {
+    let digits = if index >= 1.0 {
+        index.log10().floor() + 1.0
+    } else {
+        1.0
+    };
+
+    10f64.powf(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::metered;
+
+    #[test]
+    fn keeps_continuous_index_unchanged() {
+        let measurements = [
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 101.5, 1.5),
+        ];
+
+        let continuity = analyze(&measurements).unwrap();
+        assert!(continuity.discontinuities.is_empty());
+        assert_eq!(continuity.virtual_index_m3, vec![100.0, 101.5]);
+    }
+
+    #[test]
+    fn detects_rollover() {
+        let continuity = analyze(&[
+            metered("2024-04-01 00:00:00", 9_999.0, 0.0),
+            metered("2024-04-01 01:00:00", 2.0, 3.0),
+            metered("2024-04-01 02:00:00", 4.0, 2.0),
+        ])
+        .unwrap();
+
+        assert_eq!(continuity.discontinuities.len(), 1);
+        assert_eq!(
+            continuity.discontinuities[0].kind,
+            DiscontinuityKind::Rollover
+        );
+        assert_eq!(continuity.discontinuities[0].offset_m3, 10_000.0);
+        assert_eq!(
+            continuity.virtual_index_m3,
+            vec![9_999.0, 10_002.0, 10_004.0]
+        );
+    }
+
+    #[test]
+    fn detects_replacement_with_reset_or_higher_index() {
+        let continuity = analyze(&[
+            metered("2024-04-01 00:00:00", 5_432.0, 0.0),
+            metered("2024-04-01 01:00:00", 12.0, 2.0),
+            metered("2024-04-01 02:00:00", 13.0, 1.0),
+            metered("2024-04-01 03:00:00", 800.0, 1.0),
+        ])
+        .unwrap();
+
+        let kinds = continuity
+            .discontinuities
+            .iter()
+            .map(|discontinuity| discontinuity.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                DiscontinuityKind::Replacement,
+                DiscontinuityKind::Replacement
+            ]
+        );
+        assert_eq!(
+            continuity.virtual_index_m3,
+            vec![5_432.0, 5_434.0, 5_435.0, 5_436.0]
+        );
+    }
+
+    #[test]
+    fn attributes_index_jumps_across_gaps_to_consumption() {
+        let continuity = analyze(&[
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 102.0, 2.0),
+            metered("2024-04-01 02:00:00", 104.0, 2.0),
+            metered("2024-04-01 06:00:00", 111.0, 1.0),
+            metered("2024-04-01 07:00:00", 112.0, 1.0),
+            metered("2024-04-01 10:00:00", 2_000.0, 1.0),
+        ])
+        .unwrap();
+
+        assert_eq!(continuity.discontinuities.len(), 1);
+        assert_eq!(
+            continuity.discontinuities[0].timestamp,
+            "2024-04-01 10:00:00"
+        );
+        assert_eq!(
+            continuity.discontinuities[0].kind,
+            DiscontinuityKind::Replacement
+        );
+        assert_eq!(
+            continuity.virtual_index_m3,
+            vec![100.0, 102.0, 104.0, 111.0, 112.0, 113.0]
+        );
+    }
+
+    #[test]
+    fn keeps_the_virtual_index_flat_after_a_downward_correction() {
+        let continuity = analyze(&[
+            metered("2024-04-01 00:00:00", 5_432.0, 0.0),
+            metered("2024-04-01 01:00:00", 5_428.0, 1.0),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            continuity.discontinuities[0].kind,
+            DiscontinuityKind::Correction
+        );
+        assert_eq!(continuity.discontinuities[0].offset_m3, 4.0);
+        assert_eq!(continuity.virtual_index_m3, vec![5_432.0, 5_432.0]);
+    }
+
+    #[test]
+    fn keeps_the_virtual_index_monotonic_across_rollovers_and_replacements() {
+        let continuity = analyze(&[
+            metered("2024-04-01 00:00:00", 9_998.0, 0.0),
+            metered("2024-04-01 01:00:00", 1.0, 3.0),
+            metered("2024-04-01 02:00:00", 2.0, 1.0),
+            metered("2024-04-01 03:00:00", 5_000.0, 1.0),
+            metered("2024-04-01 04:00:00", 4_995.0, 0.0),
+            metered("2024-04-01 05:00:00", 4_997.0, 2.0),
+        ])
+        .unwrap();
+
+        let kinds = continuity
+            .discontinuities
+            .iter()
+            .map(|discontinuity| discontinuity.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                DiscontinuityKind::Rollover,
+                DiscontinuityKind::Replacement,
+                DiscontinuityKind::Correction
+            ]
+        );
+        assert!(
+            continuity
+                .virtual_index_m3
+                .windows(2)
+                .all(|pair| pair[1] >= pair[0])
+        );
+        assert_eq!(
+            continuity.virtual_index_m3,
+            vec![9_998.0, 10_001.0, 10_002.0, 10_003.0, 10_003.0, 10_005.0]
+        );
+    }
+
+    #[test]
+    fn applies_virtual_index_to_measurements() {
+        let measurements = [
+            metered("2024-04-01 00:00:00", 999.0, 0.0),
+            metered("2024-04-01 01:00:00", 1.0, 2.0),
+        ];
+
+        let continuity = analyze(&measurements).unwrap();
+        let corrected = continuity.apply(&measurements);
+        assert_eq!(corrected[1].index_m3, 1_001.0);
+        assert_eq!(corrected[1].timestamp, "2024-04-01 01:00:00");
+    }
+
+    #[test]
+    fn rejects_mixed_installations() {
+        let mut other = metered("2024-04-01 01:00:00", 1.0, 0.0);
+        other.installation_id = "INSTALLATION_ID_2".to_owned();
+
+        let err = analyze(&[metered("2024-04-01 00:00:00", 1.0, 0.0), other]);
+        assert!(matches!(err, Err(EnergiaProError::InvalidArgument(_))));
+    }
+}
@@ -0,0 +1,380 @@
+//! Analyses computed locally from measurements already retrieved from the API.
+//!
+//! Analyses never perform network requests: fetch measurements with
+//! [`EnergiaPro`](crate::EnergiaPro) first, then pass them to the relevant
+//! module.
+
+pub mod allocation;
+pub mod anomaly;
+pub mod benchmark;
+pub mod calorific;
+pub mod change_point;
+pub mod comparison;
+pub mod cost;
+pub mod degree_days;
+pub mod emissions;
+pub mod fill;
+pub mod forecast;
+pub mod holidays;
+pub mod hot_water;
+pub mod leak;
+pub mod meter;
+pub mod peak;
+pub mod profile;
+pub mod quality;
+pub mod savings;
+pub mod signature;
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, Timelike};
+use chrono_tz::Tz;
+
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+/// Return the most common positive interval between consecutive timestamps, in minutes.
+///
+/// Ties are broken in favour of the shortest interval.
+pub(crate) fn detect_resolution(timestamps: &[DateTime<Tz>]) -> Option<i64> {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+
+    for pair in timestamps.windows(2) {
+        let minutes = (pair[1] - pair[0]).num_minutes();
+        if minutes > 0 {
+            *counts.entry(minutes).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by(|(a_minutes, a_count), (b_minutes, b_count)| {
+            a_count.cmp(b_count).then(b_minutes.cmp(a_minutes))
+        })
+        .map(|(minutes, _)| minutes)
+}
+
+/// Round `value` to `decimals` decimal places, hiding floating point noise.
+pub(crate) fn round(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+/// Return the median of `values`, which must not be empty.
+pub(crate) fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// Return a percentile of sorted values, interpolating linearly between the
+/// closest ranks. `fraction` is between 0 and 1.
+pub(crate) fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = fraction * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Return the number of intervals missing between two timestamps.
+///
+/// The elapsed time is rounded to the nearest interval, so that daily series
+/// are not reported incomplete on 23 or 25 hour daylight saving days.
+pub(crate) fn missing_intervals(elapsed_minutes: i64, resolution_minutes: i64) -> usize {
+    let intervals = (elapsed_minutes + resolution_minutes / 2) / resolution_minutes;
+    usize::try_from(intervals - 1).unwrap_or(0)
+}
+
+/// Sum consumption per local day, keeping only days with enough intervals.
+///
+/// `min_day_coverage` is the share of a day's intervals, at the detected
+/// resolution, that must be present. Partial days would understate the daily
+/// consumption.
+pub(crate) fn daily_consumption(
+    series: &MeasurementSeries,
+    min_day_coverage: f64,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut days: BTreeMap<NaiveDate, (f64, usize)> = BTreeMap::new();
+    for point in series.iter() {
+        let day = days.entry(point.timestamp.date_naive()).or_default();
+        day.0 += point.consumption_kwh;
+        day.1 += 1;
+    }
+
+    let resolution_minutes = detect_resolution(series.timestamps()).unwrap_or(1440);
+    days.into_iter()
+        .filter(|(_, (_, count))| {
+            (*count as f64 * resolution_minutes as f64 / 1440.0) >= min_day_coverage
+        })
+        .map(|(date, (kwh, _))| (date, kwh))
+        .collect()
+}
+
+/// Sum consumption per hour, keeping only complete hours.
+///
+/// Hours are keyed by their start with its offset, so that the repeated hour
+/// of the autumn daylight saving change is not merged. `analysis` names the
+/// caller in the error returned for measurements coarser than hourly.
+pub(crate) fn hourly_consumption(
+    series: &MeasurementSeries,
+    analysis: &str,
+) -> Result<BTreeMap<DateTime<Tz>, f64>, EnergiaProError> {
+    let Some(resolution_minutes) = detect_resolution(series.timestamps()) else {
+        return Ok(BTreeMap::new());
+    };
+    if resolution_minutes > 60 {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "{analysis} require hourly measurements, installation `{}` has a resolution of {resolution_minutes} minutes",
+            series.installation_id()
+        )));
+    }
+
+    let mut hours: BTreeMap<DateTime<Tz>, (f64, i64)> = BTreeMap::new();
+    for point in series.iter() {
+        let Some(hour) = point
+            .timestamp
+            .with_minute(0)
+            .and_then(|timestamp| timestamp.with_second(0))
+        else {
+            continue;
+        };
+        let entry = hours.entry(hour).or_default();
+        entry.0 += point.consumption_kwh;
+        entry.1 += resolution_minutes;
+    }
+
+    Ok(hours
+        .into_iter()
+        .filter(|(_, (_, minutes))| *minutes >= 60)
+        .map(|(hour, (kwh, _))| (hour, kwh))
+        .collect())
+}
+
+/// Group measurements by installation, in order of first appearance.
+///
+/// Measurements keep their relative order within each group.
+pub(crate) fn group_by_installation(
+    measurements: &[Measurement],
+) -> Vec<(&str, Vec<&Measurement>)> {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut groups: Vec<(&str, Vec<&Measurement>)> = Vec::new();
+
+    for measurement in measurements {
+        let installation_id = measurement.installation_id.as_str();
+        let position = *positions.entry(installation_id).or_insert_with(|| {
+            groups.push((installation_id, Vec::new()));
+            groups.len() - 1
+        });
+        groups[position].1.push(measurement);
+    }
+
+    groups
+}
+
+/// Scale factor making the MAD consistent with the standard deviation of a
+/// normal distribution.
+const MAD_SCALE: f64 = 0.6745;
+
+/// Scale factor making the mean absolute deviation consistent with the
+/// standard deviation, used when more than half of the deviations are zero.
+const MEAN_AD_SCALE: f64 = 0.7979;
+
+/// Estimate the standard deviation from absolute deviations around a center.
+///
+/// Uses the median absolute deviation, which ignores outliers, and falls back
+/// to the mean absolute deviation when more than half of the deviations are
+/// zero. Returns 0 without deviations.
+pub(crate) fn robust_scale(deviations: Vec<f64>) -> f64 {
+    if deviations.is_empty() {
+        return 0.0;
+    }
+
+    let mean = deviations.iter().sum::<f64>() / deviations.len() as f64;
+    let mad = median(deviations);
+    if mad > 0.0 {
+        mad / MAD_SCALE
+    } else {
+        mean / MEAN_AD_SCALE
+    }
+}
+
+/// Approximate the quantile of Student's t distribution.
+///
+/// Uses the Abramowitz and Stegun rational approximation of the normal
+/// quantile, corrected for the degrees of freedom with a Cornish-Fisher
+/// expansion. The error is below 0.01 from 3 degrees of freedom.
+pub(crate) fn student_t_quantile(probability: f64, degrees_of_freedom: f64) -> f64 {
+    let tail = 1.0 - probability;
+    let w = (-2.0 * tail.ln()).sqrt();
+    let z = w
+        - (2.515_517 + 0.802_853 * w + 0.010_328 * w * w)
+            / (1.0 + 1.432_788 * w + 0.189_269 * w * w + 0.001_308 * w * w * w);
+
+    let v = degrees_of_freedom;
+    z + (z.powi(3) + z) / (4.0 * v)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * v * v)
+        + (3.0 * z.powi(7) + 19.0 * z.powi(5) + 17.0 * z.powi(3) - 15.0 * z) / (384.0 * v.powi(3))
+}
+
+/// Open CSV text exported from a spreadsheet and read its header row.
+///
+/// A leading byte order mark is skipped and the delimiter (`;` or `,`) is
+/// detected from the header row. `file` names the input in error messages,
+/// for example `"unit file"`.
+pub(crate) fn csv_reader<'a>(
+    text: &'a str,
+    file: &str,
+) -> Result<(csv::Reader<&'a [u8]>, csv::StringRecord), EnergiaProError> {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let header = text.lines().next().unwrap_or_default();
+    let delimiter = if header.matches(';').count() >= header.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|err| EnergiaProError::InvalidArgument(format!("invalid {file}: {err}")))?
+        .clone();
+
+    Ok((reader, headers))
+}
+
+/// Position of the first column named like one of `names`, case-insensitively.
+pub(crate) fn csv_column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+}
+
+/// Measurement builders shared by the tests of the analyses.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use chrono::NaiveDate;
+
+    use crate::models::Measurement;
+
+    /// Build a measurement of installation `INSTALLATION_ID_1` of client 1,
+    /// with a calorific factor of 10 kWh/m³ and a zero index.
+    pub(crate) fn measurement(timestamp: impl Into<String>, consumption_kwh: f64) -> Measurement {
+        Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: timestamp.into(),
+            index_m3: 0.0,
+            consumption_m3: consumption_kwh / 10.0,
+            consumption_kwh,
+        }
+    }
+
+    /// Build the measurement of a whole day, timestamped at midnight.
+    pub(crate) fn daily(date: NaiveDate, consumption_kwh: f64) -> Measurement {
+        measurement(format!("{date} 00:00:00"), consumption_kwh)
+    }
+
+    /// Build daily measurements from `from` to `to`, both `YYYY-MM-DD`, with a
+    /// consumption of `kwh(date)`.
+    pub(crate) fn history(
+        from: &str,
+        to: &str,
+        kwh: impl Fn(NaiveDate) -> f64,
+    ) -> Vec<Measurement> {
+        let date = |value: &str| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+        date(from)
+            .iter_days()
+            .take_while(|day| *day <= date(to))
+            .map(|day| daily(day, kwh(day)))
+            .collect()
+    }
+
+    /// Build a measurement from a meter index and the volume of the interval.
+    pub(crate) fn metered(timestamp: &str, index_m3: f64, consumption_m3: f64) -> Measurement {
+        measurement(timestamp, consumption_m3 * 10.0)
+            .with_index(index_m3)
+            .with_volume(consumption_m3)
+    }
+
+    impl Measurement {
+        pub(crate) fn with_client(mut self, client_id: u64) -> Self {
+            self.client_id = client_id;
+            self
+        }
+
+        pub(crate) fn with_installation(mut self, installation_id: &str) -> Self {
+            self.installation_id = installation_id.to_owned();
+            self
+        }
+
+        pub(crate) fn with_index(mut self, index_m3: f64) -> Self {
+            self.index_m3 = index_m3;
+            self
+        }
+
+        pub(crate) fn with_volume(mut self, consumption_m3: f64) -> Self {
+            self.consumption_m3 = consumption_m3;
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    #[test]
+    fn groups_measurements_by_installation_in_order_of_appearance() {
+        let measurements = [
+            measurement("2024-01-01 00:00:00", 1.0).with_installation("B"),
+            measurement("2024-01-01 00:00:00", 2.0).with_installation("A"),
+            measurement("2024-01-01 01:00:00", 3.0).with_installation("B"),
+        ];
+
+        let groups = group_by_installation(&measurements)
+            .into_iter()
+            .map(|(installation_id, group)| {
+                let kwh = group.iter().map(|m| m.consumption_kwh).collect::<Vec<_>>();
+                (installation_id, kwh)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(groups, [("B", vec![1.0, 3.0]), ("A", vec![2.0])]);
+    }
+
+    #[test]
+    fn approximates_student_t_quantiles() {
+        assert!((student_t_quantile(0.95, 10.0) - 1.812).abs() < 0.005);
+        assert!((student_t_quantile(0.975, 30.0) - 2.042).abs() < 0.005);
+        assert!((student_t_quantile(0.95, 1_000.0) - 1.646).abs() < 0.005);
+    }
+
+    #[test]
+    fn estimates_a_robust_scale() {
+        assert_eq!(robust_scale(vec![1.0, 2.0, 100.0]), 2.0 / 0.6745);
+        assert_eq!(robust_scale(vec![0.0, 0.0, 4.0]), 4.0 / 3.0 / 0.7979);
+        assert_eq!(robust_scale(Vec::new()), 0.0);
+    }
+
+    #[test]
+    fn detects_the_csv_delimiter_and_finds_columns() {
+        let (mut reader, headers) =
+            csv_reader("\u{feff}Date;Temperature\n2024-01-01;1,5\n", "test file").unwrap();
+
+        assert_eq!(csv_column(&headers, &["temperature"]), Some(1));
+        assert_eq!(csv_column(&headers, &["missing"]), None);
+        assert_eq!(&reader.records().next().unwrap().unwrap()[1], "1,5");
+
+        let (_, headers) = csv_reader("date,temperature\n", "test file").unwrap();
+        assert_eq!(csv_column(&headers, &["TEMPERATURE"]), Some(1));
+    }
+}
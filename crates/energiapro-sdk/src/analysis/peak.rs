@@ -0,0 +1,413 @@
+//! Peak demand and load duration curve.
+//!
+//! Network tariffs and boiler sizing depend on the hourly peak demand rather
+//! than on the consumed volume. The demand of an hour, in kW, is the energy
+//! consumed during that hour: sub-hourly intervals are summed and hours
+//! missing intervals are skipped.
+//!
+//! The report lists the highest hours of every month and year, the load
+//! duration curve (hourly demands sorted from highest to lowest) and, when
+//! the installed capacity of the boiler is known, whether the boiler is
+//! oversized. Peaks are only as representative as the measured period: a
+//! range without a cold spell understates them.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::peak::{self, PeakOptions};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurements = (0..24)
+//!     .map(|hour| Measurement {
+//!         client_id: 1,
+//!         installation_id: "INSTALLATION_ID_1".to_owned(),
+//!         timestamp: format!("2024-01-15 {hour:02}:00:00"),
+//!         index_m3: 0.0,
+//!         consumption_m3: 1.0,
+//!         consumption_kwh: if hour == 7 { 18.0 } else { 6.0 },
+//!     })
+//!     .collect::<Vec<_>>();
+//!
+//! let options = PeakOptions::default().with_capacity_kw(60.0);
+//! let report = peak::analyze_with(&measurements, &options)?;
+//!
+//! assert_eq!(report.peak_kw, 18.0);
+//! assert_eq!(report.monthly[0].peaks[0].timestamp, "2024-01-15 07:00:00");
+//! assert!(report.capacity.unwrap().oversized);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use super::{hourly_consumption, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+use crate::types::Granularity;
+
+/// Parameters of the peak demand analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakOptions {
+    /// Number of peak hours listed per month and per year.
+    pub top_n: usize,
+
+    /// Installed capacity of the boiler in kW, to check its sizing.
+    pub capacity_kw: Option<f64>,
+
+    /// Share of the installed capacity below which the peak demand flags the
+    /// boiler as oversized.
+    pub min_utilization: f64,
+}
+
+impl Default for PeakOptions {
+    fn default() -> Self {
+        Self {
+            top_n: 3,
+            capacity_kw: None,
+            min_utilization: 0.5,
+        }
+    }
+}
+
+impl PeakOptions {
+    /// Set the number of peak hours listed per month and per year.
+    pub fn with_top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    /// Set the installed capacity of the boiler, in kW.
+    pub fn with_capacity_kw(mut self, capacity_kw: f64) -> Self {
+        self.capacity_kw = Some(capacity_kw);
+        self
+    }
+
+    /// Set the share of capacity below which the boiler is oversized.
+    pub fn with_min_utilization(mut self, min_utilization: f64) -> Self {
+        self.min_utilization = min_utilization;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if let Some(capacity_kw) = self.capacity_kw
+            && (!capacity_kw.is_finite() || capacity_kw <= 0.0)
+        {
+            return Err(EnergiaProError::InvalidArgument(
+                "capacity_kw must be a positive number".to_owned(),
+            ));
+        }
+        if !(self.min_utilization.is_finite()
+            && self.min_utilization > 0.0
+            && self.min_utilization <= 1.0)
+        {
+            return Err(EnergiaProError::InvalidArgument(
+                "min_utilization must be between 0 and 1".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Demand of one hour.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeakHour {
+    /// Rank of the hour within its period, starting at 1.
+    pub rank: usize,
+    /// Local start of the hour (`YYYY-MM-DD HH:MM:SS`).
+    pub timestamp: String,
+    /// Mean demand over the hour in kW.
+    pub demand_kw: f64,
+}
+
+/// Highest hours of one month or year.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodPeaks {
+    /// First day of the period.
+    pub start: NaiveDate,
+    /// Last day of the period.
+    pub end: NaiveDate,
+    /// Highest hours, from highest to lowest.
+    pub peaks: Vec<PeakHour>,
+}
+
+/// Point of the load duration curve.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DurationPoint {
+    /// Number of hours with a demand at least `demand_kw`.
+    pub hours: usize,
+    /// Share of the measured hours with a demand at least `demand_kw`,
+    /// between 0 and 1.
+    pub share_of_time: f64,
+    /// Demand in kW.
+    pub demand_kw: f64,
+}
+
+/// Sizing check of the boiler.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapacityCheck {
+    /// Installed capacity in kW.
+    pub capacity_kw: f64,
+    /// Highest measured demand in kW.
+    pub peak_kw: f64,
+    /// Peak demand as a share of the installed capacity.
+    pub utilization: f64,
+    /// Whether the utilization is below [`PeakOptions::min_utilization`].
+    pub oversized: bool,
+}
+
+/// Peak demand analysis of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeakReport {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Number of complete hours analysed.
+    pub hours: usize,
+    /// Highest hourly demand in kW.
+    pub peak_kw: f64,
+    /// Mean hourly demand in kW.
+    pub mean_kw: f64,
+    /// Mean demand as a share of the peak demand.
+    pub load_factor: Option<f64>,
+    /// Highest hours per calendar month.
+    pub monthly: Vec<PeriodPeaks>,
+    /// Highest hours per calendar year.
+    pub yearly: Vec<PeriodPeaks>,
+    /// Load duration curve, from highest to lowest demand.
+    pub duration_curve: Vec<DurationPoint>,
+    /// Sizing check, when the installed capacity is given.
+    pub capacity: Option<CapacityCheck>,
+}
+
+impl PeakReport {
+    /// Return the number of hours with a demand of at least `demand_kw`.
+    pub fn hours_above(&self, demand_kw: f64) -> usize {
+        self.duration_curve
+            .partition_point(|point| point.demand_kw >= demand_kw)
+    }
+}
+
+/// Analyse peak demand with the default [`PeakOptions`].
+///
+/// # Errors
+///
+/// See [`analyze_with`].
+pub fn analyze(measurements: &[Measurement]) -> Result<PeakReport, EnergiaProError> {
+    analyze_with(measurements, &PeakOptions::default())
+}
+
+/// Analyse peak demand with custom options.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if the measurements belong
+/// to more than one installation, if a timestamp cannot be parsed, or if the
+/// measurements are coarser than hourly.
+pub fn analyze_with(
+    measurements: &[Measurement],
+    options: &PeakOptions,
+) -> Result<PeakReport, EnergiaProError> {
+    options.validate()?;
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let hours = hourly_consumption(&series, "peak demand analyses")?
+        .into_iter()
+        .map(|(hour, kwh)| (hour.naive_local(), kwh))
+        .collect::<Vec<_>>();
+
+    let mut monthly: BTreeMap<NaiveDate, Vec<(NaiveDateTime, f64)>> = BTreeMap::new();
+    let mut yearly: BTreeMap<NaiveDate, Vec<(NaiveDateTime, f64)>> = BTreeMap::new();
+    for &(hour, kw) in &hours {
+        let date = hour.date();
+        monthly
+            .entry(Granularity::Month.period(date).0)
+            .or_default()
+            .push((hour, kw));
+        yearly
+            .entry(Granularity::Year.period(date).0)
+            .or_default()
+            .push((hour, kw));
+    }
+
+    let mut demands = hours.iter().map(|(_, kw)| *kw).collect::<Vec<_>>();
+    demands.sort_by(|a, b| b.total_cmp(a));
+    let count = demands.len();
+    let peak_kw = demands.first().copied().unwrap_or_default();
+    let mean_kw = if count > 0 {
+        demands.iter().sum::<f64>() / count as f64
+    } else {
+        0.0
+    };
+
+    Ok(PeakReport {
+        installation_id: series.installation_id().to_owned(),
+        hours: count,
+        peak_kw: round(peak_kw, 3),
+        mean_kw: round(mean_kw, 3),
+        load_factor: (peak_kw > 0.0).then(|| round(mean_kw / peak_kw, 4)),
+        monthly: top_hours(monthly, Granularity::Month, options.top_n),
+        yearly: top_hours(yearly, Granularity::Year, options.top_n),
+        duration_curve: demands
+            .iter()
+            .enumerate()
+            .map(|(index, kw)| DurationPoint {
+                hours: index + 1,
+                share_of_time: round((index + 1) as f64 / count as f64, 4),
+                demand_kw: round(*kw, 3),
+            })
+            .collect(),
+        capacity: options.capacity_kw.map(|capacity_kw| {
+            let utilization = round(peak_kw / capacity_kw, 4);
+            CapacityCheck {
+                capacity_kw,
+                peak_kw: round(peak_kw, 3),
+                utilization,
+                oversized: utilization < options.min_utilization,
+            }
+        }),
+    })
+}
+
+fn top_hours(
+    periods: BTreeMap<NaiveDate, Vec<(NaiveDateTime, f64)>>,
+    granularity: Granularity,
+    top_n: usize,
+) -> Vec<PeriodPeaks> {
+    periods
+        .into_iter()
+        .map(|(start, mut hours)| {
+            // Highest first, earliest first on ties.
+            hours.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            PeriodPeaks {
+                start,
+                end: granularity.period(start).1,
+                peaks: hours
+                    .into_iter()
+                    .take(top_n)
+                    .enumerate()
+                    .map(|(index, (hour, kw))| PeakHour {
+                        rank: index + 1,
+                        timestamp: hour.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        demand_kw: round(kw, 3),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    /// Hourly measurements on the 10th of January and February 2024, with a
+    /// demand of `hour` kW plus 10 kW in February.
+    fn two_months() -> Vec<Measurement> {
+        [("01", 0.0), ("02", 10.0)]
+            .iter()
+            .flat_map(|(month, offset)| {
+                (0..24).map(move |hour| {
+                    measurement(
+                        format!("2024-{month}-10 {hour:02}:00:00"),
+                        f64::from(hour) + offset,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_top_hours_per_month_and_year() {
+        let report = analyze_with(&two_months(), &PeakOptions::default().with_top_n(2)).unwrap();
+
+        assert_eq!(report.hours, 48);
+        assert_eq!(report.peak_kw, 33.0);
+        assert_eq!(report.mean_kw, 16.5);
+        assert_eq!(report.load_factor, Some(0.5));
+
+        assert_eq!(report.monthly.len(), 2);
+        assert_eq!(report.monthly[0].end.to_string(), "2024-01-31");
+        assert_eq!(
+            report.monthly[0].peaks,
+            vec![
+                PeakHour {
+                    rank: 1,
+                    timestamp: "2024-01-10 23:00:00".to_owned(),
+                    demand_kw: 23.0,
+                },
+                PeakHour {
+                    rank: 2,
+                    timestamp: "2024-01-10 22:00:00".to_owned(),
+                    demand_kw: 22.0,
+                },
+            ]
+        );
+        assert_eq!(report.yearly.len(), 1);
+        assert_eq!(report.yearly[0].peaks[0].timestamp, "2024-02-10 23:00:00");
+    }
+
+    #[test]
+    fn builds_load_duration_curve_from_sub_hourly_intervals() {
+        let measurements = (0..12)
+            .map(|quarter| {
+                let (hour, minute) = (quarter / 4, quarter % 4 * 15);
+                measurement(
+                    format!("2024-01-10 {hour:02}:{minute:02}:00"),
+                    f64::from(hour + 1),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let report = analyze(&measurements).unwrap();
+
+        assert_eq!(
+            report
+                .duration_curve
+                .iter()
+                .map(|point| (point.hours, point.demand_kw))
+                .collect::<Vec<_>>(),
+            vec![(1, 12.0), (2, 8.0), (3, 4.0)]
+        );
+        assert_eq!(report.duration_curve[1].share_of_time, 0.6667);
+        assert_eq!(report.hours_above(8.0), 2);
+        assert_eq!(report.hours_above(100.0), 0);
+    }
+
+    #[test]
+    fn flags_oversized_boilers() {
+        let check = |capacity_kw| {
+            analyze_with(
+                &two_months(),
+                &PeakOptions::default().with_capacity_kw(capacity_kw),
+            )
+            .unwrap()
+            .capacity
+            .unwrap()
+        };
+
+        let oversized = check(100.0);
+        assert_eq!(oversized.utilization, 0.33);
+        assert!(oversized.oversized);
+        assert!(!check(50.0).oversized);
+    }
+
+    #[test]
+    fn rejects_daily_measurements_and_invalid_capacities() {
+        let daily = [
+            measurement("2024-01-10 00:00:00".to_owned(), 100.0),
+            measurement("2024-01-11 00:00:00".to_owned(), 100.0),
+        ];
+        assert!(matches!(
+            analyze(&daily),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            analyze_with(&two_months(), &PeakOptions::default().with_capacity_kw(0.0)),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
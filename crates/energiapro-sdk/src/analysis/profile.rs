@@ -0,0 +1,461 @@
+//! Typical load profiles by hour, day type and season.
+//!
+//! Hourly consumption is grouped by calendar month or meteorological season,
+//! by day type and by local hour of day. Each group reports the mean and the
+//! 10th, 50th and 90th percentiles, which show when heating starts, how deep
+//! night setbacks go and how much consumption varies from day to day.
+//!
+//! Saturdays, Sundays and Swiss public holidays (see
+//! [`holidays`](super::holidays)) count as weekend days. Sub-hourly
+//! measurements are summed per hour, and hours missing intervals are skipped.
+//!
+//! Profiles export to CSV with [`LoadProfile::to_csv`], and to a Polars
+//! `DataFrame` with `LoadProfile::to_dataframe` when the `polars` feature is
+//! enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::profile::{self, DayType};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let mut measurements = Vec::new();
+//! for day in 1..=14 {
+//!     for hour in 0..24 {
+//!         measurements.push(Measurement {
+//!             client_id: 1,
+//!             installation_id: "INSTALLATION_ID_1".to_owned(),
+//!             timestamp: format!("2024-01-{day:02} {hour:02}:00:00"),
+//!             index_m3: 0.0,
+//!             consumption_m3: 0.5,
+//!             consumption_kwh: if (6..22).contains(&hour) { 5.0 } else { 2.0 },
+//!         });
+//!     }
+//! }
+//!
+//! let profile = profile::compute(&measurements)?;
+//! let seven = profile.get("jan", DayType::Workday, 7).unwrap();
+//!
+//! assert_eq!(seven.mean_kwh, 5.0);
+//! assert!(profile.to_csv()?.starts_with("installation_id,period,day_type,hour,"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{Datelike, Timelike};
+use serde::Serialize;
+
+use super::holidays::is_non_working_day;
+use super::{hourly_consumption, percentile, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Calendar grouping of a profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileGrouping {
+    /// One profile per calendar month, labelled `jan` to `dec`.
+    #[default]
+    Month,
+    /// One profile per meteorological season, labelled `winter` (December to
+    /// February), `spring`, `summer` and `autumn`.
+    Season,
+}
+
+impl ProfileGrouping {
+    /// Return the label of the period containing `month` (1 to 12).
+    pub fn label(self, month: u32) -> &'static str {
+        match self {
+            Self::Month => MONTHS[(month.clamp(1, 12) - 1) as usize],
+            Self::Season => match month {
+                3..=5 => "spring",
+                6..=8 => "summer",
+                9..=11 => "autumn",
+                _ => "winter",
+            },
+        }
+    }
+
+    /// Return the stable lowercase name of the grouping.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Month => "month",
+            Self::Season => "season",
+        }
+    }
+}
+
+impl fmt::Display for ProfileGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Type of day of a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayType {
+    /// Monday to Friday, except public holidays.
+    Workday,
+    /// Saturday, Sunday or public holiday.
+    Weekend,
+}
+
+impl DayType {
+    /// Return the stable lowercase name of the day type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Workday => "workday",
+            Self::Weekend => "weekend",
+        }
+    }
+}
+
+impl fmt::Display for DayType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parameters of the profile computation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileOptions {
+    /// Calendar grouping of the profile.
+    pub grouping: ProfileGrouping,
+}
+
+impl ProfileOptions {
+    /// Set the calendar grouping of the profile.
+    pub fn with_grouping(mut self, grouping: ProfileGrouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+}
+
+/// Typical consumption of one hour of day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfilePoint {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Label of the month or season, see [`ProfileGrouping::label`].
+    pub period: &'static str,
+    /// Type of day.
+    pub day_type: DayType,
+    /// Local hour of day, from 0 to 23.
+    pub hour: u32,
+    /// Number of hours averaged.
+    pub samples: usize,
+    /// Mean consumption in kWh.
+    pub mean_kwh: f64,
+    /// 10th percentile of the consumption in kWh.
+    pub p10_kwh: f64,
+    /// Median consumption in kWh.
+    pub p50_kwh: f64,
+    /// 90th percentile of the consumption in kWh.
+    pub p90_kwh: f64,
+}
+
+/// Typical load profile of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadProfile {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Calendar grouping of the profile.
+    pub grouping: ProfileGrouping,
+    /// Profile points ordered by period, day type and hour.
+    pub points: Vec<ProfilePoint>,
+}
+
+impl LoadProfile {
+    /// Return the point of a period, day type and hour, if any hour was
+    /// measured.
+    pub fn get(&self, period: &str, day_type: DayType, hour: u32) -> Option<&ProfilePoint> {
+        self.points.iter().find(|point| {
+            point.period == period && point.day_type == day_type && point.hour == hour
+        })
+    }
+
+    /// Write the profile points as CSV with a header row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a point cannot be serialized.
+    pub fn to_csv(&self) -> Result<String, EnergiaProError> {
+        let invalid = |err: csv::Error| {
+            EnergiaProError::InvalidArgument(format!("cannot write profile as CSV: {err}"))
+        };
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        if self.points.is_empty() {
+            writer
+                .write_record([
+                    "installation_id",
+                    "period",
+                    "day_type",
+                    "hour",
+                    "samples",
+                    "mean_kwh",
+                    "p10_kwh",
+                    "p50_kwh",
+                    "p90_kwh",
+                ])
+                .map_err(invalid)?;
+        }
+        for point in &self.points {
+            writer.serialize(point).map_err(invalid)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|err| invalid(err.into_error().into()))?;
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Convert the profile points into a Polars `DataFrame`, with one column
+    /// per [`ProfilePoint`] field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `DataFrame` cannot be built.
+    #[cfg(feature = "polars")]
+    pub fn to_dataframe(&self) -> polars::prelude::PolarsResult<polars::prelude::DataFrame> {
+        use polars::prelude::{DataFrame, NamedFrom, Series};
+
+        let points = &self.points;
+        let number = |f: fn(&ProfilePoint) -> f64| points.iter().map(f).collect::<Vec<_>>();
+
+        DataFrame::new_infer_height(vec![
+            Series::new(
+                "installation_id".into(),
+                points
+                    .iter()
+                    .map(|point| point.installation_id.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new(
+                "period".into(),
+                points.iter().map(|point| point.period).collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new(
+                "day_type".into(),
+                points
+                    .iter()
+                    .map(|point| point.day_type.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new(
+                "hour".into(),
+                points.iter().map(|point| point.hour).collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new(
+                "samples".into(),
+                points
+                    .iter()
+                    .map(|point| point.samples as u64)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new("mean_kwh".into(), number(|point| point.mean_kwh)).into(),
+            Series::new("p10_kwh".into(), number(|point| point.p10_kwh)).into(),
+            Series::new("p50_kwh".into(), number(|point| point.p50_kwh)).into(),
+            Series::new("p90_kwh".into(), number(|point| point.p90_kwh)).into(),
+        ])
+    }
+}
+
+/// Compute a monthly load profile with the default [`ProfileOptions`].
+///
+/// # Errors
+///
+/// See [`compute_with`].
+pub fn compute(measurements: &[Measurement]) -> Result<LoadProfile, EnergiaProError> {
+    compute_with(measurements, &ProfileOptions::default())
+}
+
+/// Compute a load profile with custom options.
+///
+/// # Errors
+///
+/// Returns an error if the measurements belong to more than one installation,
+/// if a timestamp cannot be parsed, or if the measurements are coarser than
+/// hourly.
+pub fn compute_with(
+    measurements: &[Measurement],
+    options: &ProfileOptions,
+) -> Result<LoadProfile, EnergiaProError> {
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let installation_id = series.installation_id().to_owned();
+    let hours = hourly_consumption(&series, "load profiles")?;
+
+    let mut groups: BTreeMap<(u32, DayType, u32), Vec<f64>> = BTreeMap::new();
+    for (hour, kwh) in hours {
+        let date = hour.date_naive();
+        let day_type = if is_non_working_day(date) {
+            DayType::Weekend
+        } else {
+            DayType::Workday
+        };
+        let period = match options.grouping {
+            ProfileGrouping::Month => date.month(),
+            // Order seasons from winter, with December in winter.
+            ProfileGrouping::Season => date.month() % 12 / 3,
+        };
+        groups
+            .entry((period, day_type, hour.hour()))
+            .or_default()
+            .push(kwh);
+    }
+
+    let points = groups
+        .into_iter()
+        .map(|((period, day_type, hour), mut values)| {
+            values.sort_by(f64::total_cmp);
+            let label = match options.grouping {
+                ProfileGrouping::Month => options.grouping.label(period),
+                ProfileGrouping::Season => options.grouping.label(period * 3 + 1),
+            };
+            ProfilePoint {
+                installation_id: installation_id.clone(),
+                period: label,
+                day_type,
+                hour,
+                samples: values.len(),
+                mean_kwh: round(values.iter().sum::<f64>() / values.len() as f64, 3),
+                p10_kwh: round(percentile(&values, 0.1), 3),
+                p50_kwh: round(percentile(&values, 0.5), 3),
+                p90_kwh: round(percentile(&values, 0.9), 3),
+            }
+        })
+        .collect();
+
+    Ok(LoadProfile {
+        installation_id,
+        grouping: options.grouping,
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    /// Hourly measurements of March 1st to 30th 2024, with 1 kWh per hour on
+    /// weekdays plus `day` kWh at 07:00, and 0.5 kWh per hour on weekends.
+    fn march() -> Vec<Measurement> {
+        (1..=30)
+            .flat_map(|day| {
+                (0..24).map(move |hour| {
+                    let timestamp = format!("2024-03-{day:02} {hour:02}:00:00");
+                    let weekend = matches!(day % 7, 2 | 3) || day == 29;
+                    let kwh = match (weekend, hour) {
+                        (true, _) => 0.5,
+                        (false, 7) => 1.0 + f64::from(day),
+                        (false, _) => 1.0,
+                    };
+                    measurement(timestamp, kwh)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn computes_hourly_statistics_per_day_type() {
+        let profile = compute(&march()).unwrap();
+
+        // Good Friday (29th) counts as a weekend day.
+        let morning = profile.get("mar", DayType::Workday, 7).unwrap();
+        assert_eq!(morning.samples, 20);
+        assert_eq!(morning.p50_kwh, 15.5);
+        assert!(morning.p10_kwh < morning.p50_kwh && morning.p50_kwh < morning.p90_kwh);
+
+        let weekend = profile.get("mar", DayType::Weekend, 7).unwrap();
+        assert_eq!(weekend.samples, 10);
+        assert_eq!(weekend.mean_kwh, 0.5);
+        assert_eq!(profile.points.len(), 48);
+    }
+
+    #[test]
+    fn groups_by_season_and_sums_sub_hourly_intervals() {
+        let measurements = ["2023-12-04", "2024-02-05", "2024-07-01"]
+            .iter()
+            .flat_map(|date| {
+                (0..4).map(move |quarter| {
+                    measurement(format!("{date} 08:{:02}:00", quarter * 15), 0.25)
+                })
+            })
+            .chain([measurement("2024-07-01 09:00:00".to_owned(), 3.0)])
+            .collect::<Vec<_>>();
+
+        let options = ProfileOptions::default().with_grouping(ProfileGrouping::Season);
+        let profile = compute_with(&measurements, &options).unwrap();
+
+        let winter = profile.get("winter", DayType::Workday, 8).unwrap();
+        assert_eq!((winter.samples, winter.mean_kwh), (2, 1.0));
+        assert!(profile.get("summer", DayType::Workday, 8).is_some());
+        // A single 15-minute interval does not make a complete hour.
+        assert!(profile.get("summer", DayType::Workday, 9).is_none());
+        assert_eq!(profile.points[0].period, "winter");
+    }
+
+    #[test]
+    fn exports_profiles_as_csv() {
+        let csv = compute(&march()).unwrap().to_csv().unwrap();
+
+        assert!(csv.starts_with(
+            "installation_id,period,day_type,hour,samples,mean_kwh,p10_kwh,p50_kwh,p90_kwh\n"
+        ));
+        assert!(csv.contains("INSTALLATION_ID_1,mar,weekend,7,10,0.5,0.5,0.5,0.5\n"));
+    }
+
+    #[test]
+    fn rejects_daily_measurements() {
+        let measurements = [
+            measurement("2024-03-01 00:00:00".to_owned(), 1.0),
+            measurement("2024-03-02 00:00:00".to_owned(), 1.0),
+        ];
+
+        assert!(matches!(
+            compute(&measurements),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn exports_profiles_as_dataframe() {
+        let dataframe = compute(&march()).unwrap().to_dataframe().unwrap();
+
+        assert_eq!(dataframe.shape(), (48, 9));
+        assert_eq!(
+            dataframe
+                .get_column_names()
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "installation_id",
+                "period",
+                "day_type",
+                "hour",
+                "samples",
+                "mean_kwh",
+                "p10_kwh",
+                "p50_kwh",
+                "p90_kwh"
+            ]
+        );
+    }
+}
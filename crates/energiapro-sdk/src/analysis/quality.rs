@@ -0,0 +1,607 @@
+//! Data-quality checks for measurement series.
+//!
+//! Use [`analyze`] before relying on measurements for billing: it reports the
+//! interval resolution of each installation, missing intervals, duplicate
+//! timestamps, negative or implausible consumption, meter index regressions
+//! and rows whose index delta disagrees with the reported consumption.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::Measurement;
+//! use energiapro::analysis::quality;
+//!
+//! let measurement = |timestamp: &str, index_m3: f64, consumption_m3: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp: timestamp.to_owned(),
+//!     index_m3,
+//!     consumption_m3,
+//!     consumption_kwh: consumption_m3 * 10.5,
+//! };
+//!
+//! let reports = quality::analyze(&[
+//!     measurement("2024-04-01 00:00:00", 100.0, 1.0),
+//!     measurement("2024-04-01 01:00:00", 101.0, 1.0),
+//!     measurement("2024-04-01 03:00:00", 103.0, 1.0),
+//! ]);
+//!
+//! assert_eq!(reports[0].resolution_minutes, Some(60));
+//! assert_eq!(reports[0].missing_count, 1);
+//! ```
+
+use std::fmt;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use super::{detect_resolution, group_by_installation, missing_intervals, round};
+use crate::models::{LocalResolver, Measurement};
+
+/// Thresholds used by [`analyze_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityOptions {
+    /// Highest plausible flow through the meter, in cubic meters per hour.
+    pub max_flow_m3_per_hour: f64,
+
+    /// Largest accepted difference between the index delta and the reported
+    /// consumption of an interval, in cubic meters.
+    pub delta_tolerance_m3: f64,
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        Self {
+            max_flow_m3_per_hour: 100.0,
+            delta_tolerance_m3: 0.01,
+        }
+    }
+}
+
+impl QualityOptions {
+    /// Set the highest plausible flow through the meter, in cubic meters per hour.
+    pub fn with_max_flow_m3_per_hour(mut self, max_flow_m3_per_hour: f64) -> Self {
+        self.max_flow_m3_per_hour = max_flow_m3_per_hour;
+        self
+    }
+
+    /// Set the largest accepted difference between index delta and consumption.
+    pub fn with_delta_tolerance_m3(mut self, delta_tolerance_m3: f64) -> Self {
+        self.delta_tolerance_m3 = delta_tolerance_m3;
+        self
+    }
+}
+
+/// A single data-quality problem found by [`analyze`].
+///
+/// Timestamps are reported as returned by the API.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QualityIssue {
+    /// The timestamp of a row could not be parsed.
+    InvalidTimestamp {
+        /// Raw timestamp of the row.
+        timestamp: String,
+    },
+    /// Intervals are missing between two consecutive measurements.
+    MissingIntervals {
+        /// Timestamp of the measurement before the gap.
+        after: String,
+        /// Timestamp of the measurement after the gap.
+        before: String,
+        /// Number of missing intervals.
+        count: usize,
+    },
+    /// Several rows share the same timestamp. Only the last one is analyzed.
+    DuplicateTimestamp {
+        /// Duplicated timestamp.
+        timestamp: String,
+        /// Number of rows with this timestamp.
+        occurrences: usize,
+    },
+    /// The consumed volume or energy is negative.
+    NegativeConsumption {
+        /// Timestamp of the measurement.
+        timestamp: String,
+        /// Consumed volume in cubic meters.
+        consumption_m3: f64,
+        /// Consumed energy in kilowatt-hours.
+        consumption_kwh: f64,
+    },
+    /// The consumed volume exceeds the highest plausible flow for the interval.
+    ImplausibleConsumption {
+        /// Timestamp of the measurement.
+        timestamp: String,
+        /// Consumed volume in cubic meters.
+        consumption_m3: f64,
+        /// Highest plausible volume for the interval, in cubic meters.
+        max_m3: f64,
+    },
+    /// The meter index went backwards.
+    IndexRegression {
+        /// Timestamp of the measurement.
+        timestamp: String,
+        /// Meter index of the previous measurement, in cubic meters.
+        previous_index_m3: f64,
+        /// Meter index of the measurement, in cubic meters.
+        index_m3: f64,
+    },
+    /// The index delta disagrees with the reported consumption.
+    DeltaMismatch {
+        /// Timestamp of the measurement.
+        timestamp: String,
+        /// Index difference with the previous measurement, in cubic meters.
+        index_delta_m3: f64,
+        /// Reported consumed volume in cubic meters.
+        consumption_m3: f64,
+    },
+}
+
+impl QualityIssue {
+    /// Short machine-readable name of the issue kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidTimestamp { .. } => "invalid_timestamp",
+            Self::MissingIntervals { .. } => "missing_intervals",
+            Self::DuplicateTimestamp { .. } => "duplicate_timestamp",
+            Self::NegativeConsumption { .. } => "negative_consumption",
+            Self::ImplausibleConsumption { .. } => "implausible_consumption",
+            Self::IndexRegression { .. } => "index_regression",
+            Self::DeltaMismatch { .. } => "delta_mismatch",
+        }
+    }
+
+    /// Timestamp the issue is attached to.
+    ///
+    /// For missing intervals, this is the timestamp of the measurement after the gap.
+    pub fn timestamp(&self) -> &str {
+        match self {
+            Self::MissingIntervals { before, .. } => before,
+            Self::InvalidTimestamp { timestamp }
+            | Self::DuplicateTimestamp { timestamp, .. }
+            | Self::NegativeConsumption { timestamp, .. }
+            | Self::ImplausibleConsumption { timestamp, .. }
+            | Self::IndexRegression { timestamp, .. }
+            | Self::DeltaMismatch { timestamp, .. } => timestamp,
+        }
+    }
+}
+
+impl fmt::Display for QualityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTimestamp { timestamp } => write!(f, "invalid timestamp `{timestamp}`"),
+            Self::MissingIntervals {
+                after,
+                before,
+                count,
+            } => write!(
+                f,
+                "{count} missing interval(s) between {after} and {before}"
+            ),
+            Self::DuplicateTimestamp { occurrences, .. } => {
+                write!(f, "{occurrences} rows share this timestamp")
+            }
+            Self::NegativeConsumption {
+                consumption_m3,
+                consumption_kwh,
+                ..
+            } => write!(
+                f,
+                "negative consumption ({consumption_m3} m3, {consumption_kwh} kWh)"
+            ),
+            Self::ImplausibleConsumption {
+                consumption_m3,
+                max_m3,
+                ..
+            } => write!(
+                f,
+                "consumption of {consumption_m3} m3 exceeds plausible maximum of {max_m3} m3"
+            ),
+            Self::IndexRegression {
+                previous_index_m3,
+                index_m3,
+                ..
+            } => write!(
+                f,
+                "index went back from {previous_index_m3} to {index_m3} m3"
+            ),
+            Self::DeltaMismatch {
+                index_delta_m3,
+                consumption_m3,
+                ..
+            } => write!(
+                f,
+                "index delta of {index_delta_m3} m3 differs from consumption of {consumption_m3} m3"
+            ),
+        }
+    }
+}
+
+/// Data-quality report for one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QualityReport {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Number of rows analyzed, duplicates included.
+    pub measurement_count: usize,
+    /// Timestamp of the earliest measurement.
+    pub first_timestamp: Option<String>,
+    /// Timestamp of the latest measurement.
+    pub last_timestamp: Option<String>,
+    /// Most common interval between consecutive measurements, in minutes.
+    pub resolution_minutes: Option<i64>,
+    /// Number of intervals expected between the first and last measurement.
+    pub expected_count: usize,
+    /// Number of missing intervals.
+    pub missing_count: usize,
+    /// Number of extra rows sharing the timestamp of another row.
+    pub duplicate_count: usize,
+    /// Number of rows with an unparseable timestamp.
+    pub invalid_timestamp_count: usize,
+    /// Number of rows with negative consumption.
+    pub negative_count: usize,
+    /// Number of rows with implausibly high consumption.
+    pub implausible_count: usize,
+    /// Number of rows where the meter index went backwards.
+    pub index_regression_count: usize,
+    /// Number of rows where the index delta disagrees with the consumption.
+    pub delta_mismatch_count: usize,
+    /// Overall quality score, from 0 (unusable) to 100 (no issue found).
+    ///
+    /// The score is the share of expected intervals that are present and free
+    /// of any issue.
+    pub score: f64,
+    /// Every issue found, sorted by timestamp. Rows with an invalid timestamp
+    /// come first.
+    pub issues: Vec<QualityIssue>,
+}
+
+impl QualityReport {
+    /// Return `true` if no issue was found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Analyze measurements with the default [`QualityOptions`].
+///
+/// Returns one report per installation, in order of first appearance.
+pub fn analyze(measurements: &[Measurement]) -> Vec<QualityReport> {
+    analyze_with(measurements, &QualityOptions::default())
+}
+
+/// Analyze measurements with custom thresholds.
+///
+/// Returns one report per installation, in order of first appearance.
+pub fn analyze_with(measurements: &[Measurement], options: &QualityOptions) -> Vec<QualityReport> {
+    group_by_installation(measurements)
+        .into_iter()
+        .map(|(installation_id, group)| analyze_installation(installation_id, &group, options))
+        .collect()
+}
+
+struct Row<'a> {
+    instant: DateTime<Tz>,
+    measurement: &'a Measurement,
+}
+
+fn analyze_installation(
+    installation_id: &str,
+    measurements: &[&Measurement],
+    options: &QualityOptions,
+) -> QualityReport {
+    let mut issues = Vec::new();
+    let mut rows = Vec::with_capacity(measurements.len());
+    let mut resolver = LocalResolver::default();
+
+    for &measurement in measurements {
+        match measurement.local_timestamp() {
+            Ok(local) => {
+                rows.push(Row {
+                    instant: resolver.resolve(local),
+                    measurement,
+                });
+            }
+            Err(_) => issues.push(QualityIssue::InvalidTimestamp {
+                timestamp: measurement.timestamp.clone(),
+            }),
+        }
+    }
+
+    rows.sort_by_key(|row| row.instant);
+
+    let mut unique: Vec<&Row> = Vec::with_capacity(rows.len());
+    let mut duplicate_count = 0;
+    for (index, row) in rows.iter().enumerate() {
+        let is_last_of_group = rows
+            .get(index + 1)
+            .is_none_or(|next| next.instant != row.instant);
+        let occurrences = rows[..=index]
+            .iter()
+            .rev()
+            .take_while(|other| other.instant == row.instant)
+            .count();
+
+        if is_last_of_group {
+            if occurrences > 1 {
+                duplicate_count += occurrences - 1;
+                issues.push(QualityIssue::DuplicateTimestamp {
+                    timestamp: row.measurement.timestamp.clone(),
+                    occurrences,
+                });
+            }
+            unique.push(row);
+        }
+    }
+
+    let resolution_minutes =
+        detect_resolution(&unique.iter().map(|row| row.instant).collect::<Vec<_>>());
+    let max_m3 =
+        resolution_minutes.map(|minutes| options.max_flow_m3_per_hour * minutes as f64 / 60.0);
+    let mut missing_count = 0;
+
+    for (index, row) in unique.iter().enumerate() {
+        let measurement = row.measurement;
+        let timestamp = &measurement.timestamp;
+
+        let previous = index.checked_sub(1).map(|previous| unique[previous]);
+        let mut after_gap = false;
+
+        if let (Some(previous), Some(resolution)) = (previous, resolution_minutes) {
+            let elapsed = (row.instant - previous.instant).num_minutes();
+            let missing = missing_intervals(elapsed, resolution);
+            if missing > 0 {
+                after_gap = true;
+                missing_count += missing;
+                issues.push(QualityIssue::MissingIntervals {
+                    after: previous.measurement.timestamp.clone(),
+                    before: timestamp.clone(),
+                    count: missing,
+                });
+            }
+        }
+
+        if measurement.consumption_m3 < 0.0 || measurement.consumption_kwh < 0.0 {
+            issues.push(QualityIssue::NegativeConsumption {
+                timestamp: timestamp.clone(),
+                consumption_m3: measurement.consumption_m3,
+                consumption_kwh: measurement.consumption_kwh,
+            });
+        }
+
+        if let Some(max_m3) = max_m3
+            && measurement.consumption_m3 > max_m3
+        {
+            issues.push(QualityIssue::ImplausibleConsumption {
+                timestamp: timestamp.clone(),
+                consumption_m3: measurement.consumption_m3,
+                max_m3,
+            });
+        }
+
+        if let Some(previous) = previous {
+            let previous_index_m3 = previous.measurement.index_m3;
+            let index_delta_m3 = measurement.index_m3 - previous_index_m3;
+
+            if index_delta_m3 < 0.0 {
+                issues.push(QualityIssue::IndexRegression {
+                    timestamp: timestamp.clone(),
+                    previous_index_m3,
+                    index_m3: measurement.index_m3,
+                });
+            } else if !after_gap
+                && (index_delta_m3 - measurement.consumption_m3).abs()
+                    > options.delta_tolerance_m3 + f64::EPSILON * index_delta_m3.abs().max(1.0)
+            {
+                issues.push(QualityIssue::DeltaMismatch {
+                    timestamp: timestamp.clone(),
+                    index_delta_m3,
+                    consumption_m3: measurement.consumption_m3,
+                });
+            }
+        }
+    }
+
+    // Duplicates and invalid timestamps are found before the chronological pass.
+    // The sort is stable, so both occurrences of the repeated autumn hour keep
+    // their order.
+    issues.sort_by(|a, b| {
+        let is_valid =
+            |issue: &QualityIssue| !matches!(issue, QualityIssue::InvalidTimestamp { .. });
+        is_valid(a)
+            .cmp(&is_valid(b))
+            .then_with(|| a.timestamp().cmp(b.timestamp()))
+    });
+
+    let count = |kind: &str| issues.iter().filter(|issue| issue.kind() == kind).count();
+    let invalid_timestamp_count = count("invalid_timestamp");
+    let negative_count = count("negative_consumption");
+    let implausible_count = count("implausible_consumption");
+    let index_regression_count = count("index_regression");
+    let delta_mismatch_count = count("delta_mismatch");
+
+    let expected_count = unique.len() + missing_count;
+    let problems = missing_count
+        + duplicate_count
+        + invalid_timestamp_count
+        + negative_count
+        + implausible_count
+        + index_regression_count
+        + delta_mismatch_count;
+    let score = if expected_count == 0 {
+        0.0
+    } else {
+        let ratio = 1.0 - problems as f64 / (expected_count + invalid_timestamp_count) as f64;
+        round(ratio.max(0.0) * 100.0, 1)
+    };
+
+    QualityReport {
+        installation_id: installation_id.to_owned(),
+        measurement_count: measurements.len(),
+        first_timestamp: unique.first().map(|row| row.measurement.timestamp.clone()),
+        last_timestamp: unique.last().map(|row| row.measurement.timestamp.clone()),
+        resolution_minutes,
+        expected_count,
+        missing_count,
+        duplicate_count,
+        invalid_timestamp_count,
+        negative_count,
+        implausible_count,
+        index_regression_count,
+        delta_mismatch_count,
+        score,
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_support::metered;
+
+    #[test]
+    fn reports_clean_series() {
+        let reports = analyze(&[
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 101.5, 1.5),
+            metered("2024-04-01 02:00:00", 103.0, 1.5),
+        ]);
+
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert!(report.is_clean());
+        assert_eq!(report.resolution_minutes, Some(60));
+        assert_eq!(report.expected_count, 3);
+        assert_eq!(report.score, 100.0);
+        assert_eq!(
+            report.first_timestamp.as_deref(),
+            Some("2024-04-01 00:00:00")
+        );
+        assert_eq!(
+            report.last_timestamp.as_deref(),
+            Some("2024-04-01 02:00:00")
+        );
+    }
+
+    #[test]
+    fn detects_gaps_and_duplicates() {
+        let reports = analyze(&[
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 101.0, 1.0),
+            metered("2024-04-01 01:00:00", 101.0, 1.0),
+            metered("2024-04-01 02:00:00", 102.0, 1.0),
+            metered("2024-04-01 05:00:00", 105.0, 1.0),
+        ]);
+
+        let report = &reports[0];
+        assert_eq!(report.missing_count, 2);
+        assert_eq!(report.duplicate_count, 1);
+        assert_eq!(report.expected_count, 6);
+        assert_eq!(report.delta_mismatch_count, 0);
+        assert!(report.issues.contains(&QualityIssue::MissingIntervals {
+            after: "2024-04-01 02:00:00".to_owned(),
+            before: "2024-04-01 05:00:00".to_owned(),
+            count: 2,
+        }));
+        assert_eq!(report.score, 50.0);
+    }
+
+    #[test]
+    fn sorts_issues_by_timestamp() {
+        let reports = analyze(&[
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 101.0, -1.0),
+            metered("2024-04-01 02:00:00", 102.0, 1.0),
+            metered("2024-04-01 02:00:00", 102.0, 1.0),
+            metered("not a timestamp", 103.0, 1.0),
+        ]);
+
+        let issues = reports[0]
+            .issues
+            .iter()
+            .map(|issue| (issue.kind(), issue.timestamp()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                ("invalid_timestamp", "not a timestamp"),
+                ("negative_consumption", "2024-04-01 01:00:00"),
+                ("delta_mismatch", "2024-04-01 01:00:00"),
+                ("duplicate_timestamp", "2024-04-01 02:00:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_value_issues() {
+        let reports = analyze(&[
+            metered("2024-04-01 00:00:00", 100.0, 0.0),
+            metered("2024-04-01 01:00:00", 99.0, -1.0),
+            metered("2024-04-01 02:00:00", 300.0, 201.0),
+            metered("2024-04-01 03:00:00", 302.0, 1.0),
+        ]);
+
+        let report = &reports[0];
+        assert_eq!(report.negative_count, 1);
+        assert_eq!(report.index_regression_count, 1);
+        assert_eq!(report.implausible_count, 1);
+        assert_eq!(report.delta_mismatch_count, 1);
+        assert!(report.issues.contains(&QualityIssue::DeltaMismatch {
+            timestamp: "2024-04-01 03:00:00".to_owned(),
+            index_delta_m3: 2.0,
+            consumption_m3: 1.0,
+        }));
+    }
+
+    #[test]
+    fn does_not_report_daylight_saving_changes() {
+        let reports = analyze(&[
+            metered("2024-10-27 01:00:00", 1.0, 0.0),
+            metered("2024-10-27 02:00:00", 2.0, 1.0),
+            metered("2024-10-27 02:00:00", 3.0, 1.0),
+            metered("2024-10-27 03:00:00", 4.0, 1.0),
+            metered("2025-03-30 01:00:00", 5.0, 1.0),
+            metered("2025-03-30 03:00:00", 6.0, 1.0),
+        ]);
+
+        let report = &reports[0];
+        assert_eq!(report.duplicate_count, 0);
+        assert_eq!(report.resolution_minutes, Some(60));
+        assert_eq!(report.missing_count, 3693);
+        assert_eq!(report.index_regression_count, 0);
+        assert!(!report.issues.iter().any(|issue| matches!(
+            issue,
+            QualityIssue::MissingIntervals { after, .. } if after == "2025-03-30 01:00:00"
+        )));
+    }
+
+    #[test]
+    fn reports_each_installation_separately() {
+        let mut other = metered("2024-04-01 00:00:00", 1.0, 0.0);
+        other.installation_id = "INSTALLATION_ID_2".to_owned();
+        let invalid = metered("01.04.2024", 1.0, 0.0);
+
+        let reports = analyze(&[metered("2024-04-01 00:00:00", 100.0, 0.0), other, invalid]);
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].installation_id, "INSTALLATION_ID_1");
+        assert_eq!(reports[0].invalid_timestamp_count, 1);
+        assert_eq!(reports[0].resolution_minutes, None);
+        assert_eq!(reports[1].installation_id, "INSTALLATION_ID_2");
+    }
+
+    #[test]
+    fn serializes_issues_with_kind_tag() {
+        let issue = QualityIssue::DuplicateTimestamp {
+            timestamp: "2024-04-01 00:00:00".to_owned(),
+            occurrences: 2,
+        };
+
+        let value = serde_json::to_value(&issue).unwrap();
+        assert_eq!(value["kind"], "duplicate_timestamp");
+        assert_eq!(value["occurrences"], 2);
+        assert_eq!(issue.to_string(), "2 rows share this timestamp");
+    }
+}
@@ -0,0 +1,633 @@
+//! Retrofit savings measurement and verification (IPMVP Option C).
+//!
+//! Savings cannot be metered directly: they are the difference between what
+//! the building would have consumed without the retrofit and what it actually
+//! consumed. Following IPMVP Option C (whole facility), a baseline model is
+//! fitted on daily consumption of the pre-retrofit period and projected onto
+//! the reporting period:
+//!
+//! - with a [`TemperatureSeries`], the baseline is an energy signature (see
+//!   [`signature`](super::signature)) and is adjusted to the weather of the
+//!   reporting period;
+//! - without temperatures, the baseline is the mean daily consumption.
+//!
+//! The uncertainty of the avoided energy follows ASHRAE Guideline 14 for
+//! models without autocorrelation:
+//!
+//! ```text
+//! uncertainty = t × 1.26 × RMSE × √(m × (1 + 2/n))
+//! ```
+//!
+//! where `n` and `m` are the number of baseline and reporting days.
+//!
+//! # Examples
+//!
+//! ```
+//! use energiapro::analysis::savings;
+//! use energiapro::{DateRange, Measurement};
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let measurement = |timestamp: String, consumption_kwh: f64| Measurement {
+//!     client_id: 1,
+//!     installation_id: "INSTALLATION_ID_1".to_owned(),
+//!     timestamp,
+//!     index_m3: 0.0,
+//!     consumption_m3: consumption_kwh / 10.0,
+//!     consumption_kwh,
+//! };
+//!
+//! let mut measurements = Vec::new();
+//! for day in 1..=28 {
+//!     measurements.push(measurement(format!("2023-02-{day:02} 00:00:00"), 100.0));
+//!     measurements.push(measurement(format!("2024-02-{day:02} 00:00:00"), 80.0));
+//! }
+//!
+//! let report = savings::estimate(
+//!     &measurements,
+//!     DateRange::between("2023-02-01", "2023-02-28")?,
+//!     DateRange::between("2024-02-01", "2024-02-28")?,
+//! )?;
+//!
+//! assert_eq!(report.baseline_kwh, 2800.0);
+//! assert_eq!(report.avoided_kwh, 560.0);
+//! assert_eq!(report.savings_fraction, Some(0.2));
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::degree_days::TemperatureSeries;
+use super::signature::{self, SignatureOptions};
+use super::{daily_consumption, round, student_t_quantile};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+use crate::types::DateRange;
+
+/// Empirical factor of ASHRAE Guideline 14 for the savings uncertainty.
+const ASHRAE_FACTOR: f64 = 1.26;
+
+/// Parameters of the savings estimate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavingsOptions {
+    /// Daily mean temperatures used to adjust the baseline to the weather, or
+    /// `None` to use the mean daily consumption.
+    pub temperatures: Option<TemperatureSeries>,
+
+    /// Fit options of the baseline. `min_days` and `min_day_coverage` also
+    /// apply when no temperatures are given.
+    pub baseline: SignatureOptions,
+
+    /// Price of one avoided kWh, to express savings as a cost.
+    pub price_per_kwh: Option<f64>,
+
+    /// Two-sided confidence level of the uncertainty, between 0 and 1.
+    pub confidence: f64,
+}
+
+impl Default for SavingsOptions {
+    fn default() -> Self {
+        Self {
+            temperatures: None,
+            baseline: SignatureOptions::default(),
+            price_per_kwh: None,
+            confidence: 0.9,
+        }
+    }
+}
+
+impl SavingsOptions {
+    /// Adjust the baseline to the weather with daily temperatures.
+    pub fn with_temperatures(mut self, temperatures: TemperatureSeries) -> Self {
+        self.temperatures = Some(temperatures);
+        self
+    }
+
+    /// Set the fit options of the baseline.
+    pub fn with_baseline(mut self, baseline: SignatureOptions) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    /// Set the price of one avoided kWh.
+    pub fn with_price_per_kwh(mut self, price_per_kwh: f64) -> Self {
+        self.price_per_kwh = Some(price_per_kwh);
+        self
+    }
+
+    /// Set the confidence level of the uncertainty, e.g. `0.9` for 90%.
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    fn validate(&self) -> Result<(), EnergiaProError> {
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(EnergiaProError::InvalidArgument(
+                "confidence must be between 0 and 1".to_owned(),
+            ));
+        }
+        if let Some(price) = self.price_per_kwh
+            && (!price.is_finite() || price < 0.0)
+        {
+            return Err(EnergiaProError::InvalidArgument(
+                "price_per_kwh must be a non-negative number".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Model projecting the baseline consumption onto the reporting period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BaselineModel {
+    /// Constant daily consumption.
+    Mean {
+        /// Mean consumption in kWh per day.
+        kwh_per_day: f64,
+    },
+    /// Heating change-point model adjusted to the outdoor temperature.
+    Signature {
+        /// Consumption independent of the weather, in kWh per day.
+        base_load_kwh_per_day: f64,
+        /// Additional consumption per degree below the balance temperature.
+        slope_kwh_per_degree: f64,
+        /// Outdoor temperature below which the building is heated, in °C.
+        balance_celsius: f64,
+    },
+}
+
+impl BaselineModel {
+    /// Return the baseline consumption of a day with the given mean
+    /// temperature, in kWh. The temperature is ignored by the mean model.
+    pub fn predict(&self, mean_celsius: f64) -> f64 {
+        match *self {
+            Self::Mean { kwh_per_day } => kwh_per_day,
+            Self::Signature {
+                base_load_kwh_per_day,
+                slope_kwh_per_degree,
+                balance_celsius,
+            } => {
+                base_load_kwh_per_day
+                    + slope_kwh_per_degree * (balance_celsius - mean_celsius).max(0.0)
+            }
+        }
+    }
+
+    /// Number of fitted parameters.
+    fn parameter_count(&self) -> usize {
+        match self {
+            Self::Mean { .. } => 1,
+            Self::Signature { .. } => 3,
+        }
+    }
+}
+
+/// Adjusted baseline and actual consumption of one reporting day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavingsDay {
+    /// Local calendar day.
+    pub date: NaiveDate,
+    /// Daily mean outdoor temperature in °C, when temperatures are used.
+    pub mean_celsius: Option<f64>,
+    /// Consumption predicted by the baseline model in kWh.
+    pub baseline_kwh: f64,
+    /// Measured consumption in kWh.
+    pub actual_kwh: f64,
+    /// Baseline minus actual consumption in kWh.
+    pub avoided_kwh: f64,
+}
+
+/// Savings of one installation over the reporting period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavingsReport {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Baseline model fitted on the pre-retrofit period.
+    pub model: BaselineModel,
+    /// Number of baseline days used by the fit.
+    pub baseline_days: usize,
+    /// Number of reporting days compared with the baseline.
+    pub reporting_days: usize,
+    /// Coefficient of determination of the baseline fit, for signature models.
+    pub r_squared: Option<f64>,
+    /// Coefficient of variation of the baseline RMSE, from 0 to 1, or `None`
+    /// when the mean baseline consumption is zero.
+    pub cv_rmse: Option<f64>,
+    /// Baseline consumption adjusted to the reporting period, in kWh.
+    pub baseline_kwh: f64,
+    /// Measured consumption over the reporting period, in kWh.
+    pub actual_kwh: f64,
+    /// Avoided energy use in kWh; negative when consumption increased.
+    pub avoided_kwh: f64,
+    /// Avoided energy as a share of the adjusted baseline.
+    pub savings_fraction: Option<f64>,
+    /// Confidence level of the uncertainty.
+    pub confidence: f64,
+    /// Half-width of the confidence interval of `avoided_kwh`, in kWh.
+    pub uncertainty_kwh: f64,
+    /// Avoided cost, when a price is given.
+    pub avoided_cost: Option<f64>,
+    /// Half-width of the confidence interval of `avoided_cost`.
+    pub cost_uncertainty: Option<f64>,
+    /// Reporting days in chronological order.
+    pub days: Vec<SavingsDay>,
+}
+
+impl SavingsReport {
+    /// Return `true` if the avoided energy exceeds its uncertainty, i.e. the
+    /// savings are distinguishable from zero at the chosen confidence.
+    pub fn is_significant(&self) -> bool {
+        self.avoided_kwh.abs() > self.uncertainty_kwh
+    }
+}
+
+/// Estimate savings with the default [`SavingsOptions`].
+///
+/// # Errors
+///
+/// See [`estimate_with`].
+pub fn estimate(
+    measurements: &[Measurement],
+    baseline: DateRange,
+    reporting: DateRange,
+) -> Result<SavingsReport, EnergiaProError> {
+    estimate_with(
+        measurements,
+        baseline,
+        reporting,
+        &SavingsOptions::default(),
+    )
+}
+
+/// Estimate the savings of the reporting period against the baseline period.
+///
+/// Measurements of both periods are expected in the same slice; others are
+/// ignored. Only complete days are used, and when temperatures are given,
+/// days without a temperature are left out of both periods.
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, if either period is unbounded
+/// or the periods overlap, if the measurements belong to more than one
+/// installation or a timestamp cannot be parsed, if the baseline model
+/// cannot be fitted, or if the reporting period has no complete day (with a
+/// temperature, when temperatures are given).
+pub fn estimate_with(
+    measurements: &[Measurement],
+    baseline: DateRange,
+    reporting: DateRange,
+    options: &SavingsOptions,
+) -> Result<SavingsReport, EnergiaProError> {
+    options.validate()?;
+
+    let (Some(baseline_start), Some(baseline_end), Some(reporting_start), Some(reporting_end)) = (
+        baseline.start(),
+        baseline.end(),
+        reporting.start(),
+        reporting.end(),
+    ) else {
+        return Err(EnergiaProError::InvalidArgument(
+            "baseline and reporting periods must both have a start and an end".to_owned(),
+        ));
+    };
+    if baseline_start <= reporting_end && reporting_start <= baseline_end {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "baseline period {baseline} overlaps reporting period {reporting}"
+        )));
+    }
+
+    let mut baseline_measurements = Vec::new();
+    let mut reporting_measurements = Vec::new();
+    for measurement in measurements {
+        let date = measurement.local_timestamp()?.date();
+        if baseline.contains(date) {
+            baseline_measurements.push(measurement.clone());
+        } else if reporting.contains(date) {
+            reporting_measurements.push(measurement.clone());
+        }
+    }
+
+    let Baseline {
+        installation_id,
+        model,
+        days: baseline_days,
+        r_squared,
+        rmse,
+        mean_daily_kwh,
+    } = fit_baseline(&baseline_measurements, options)?;
+
+    let reporting_series = MeasurementSeries::from_measurements(reporting_measurements)?;
+    if !reporting_series.is_empty() && reporting_series.installation_id() != installation_id {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "cannot compare installations `{installation_id}` and `{}`",
+            reporting_series.installation_id()
+        )));
+    }
+
+    let days = daily_consumption(&reporting_series, options.baseline.min_day_coverage)
+        .into_iter()
+        .filter_map(|(date, actual_kwh)| {
+            let mean_celsius = match &options.temperatures {
+                Some(temperatures) => Some(temperatures.get(date)?),
+                None => None,
+            };
+            let baseline_kwh = round(model.predict(mean_celsius.unwrap_or_default()), 2);
+            Some(SavingsDay {
+                date,
+                mean_celsius,
+                baseline_kwh,
+                actual_kwh,
+                avoided_kwh: round(baseline_kwh - actual_kwh, 2),
+            })
+        })
+        .collect::<Vec<_>>();
+    if days.is_empty() {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "reporting period {reporting} of installation `{installation_id}` has no complete day to compare"
+        )));
+    }
+
+    let baseline_kwh = round(days.iter().map(|day| day.baseline_kwh).sum(), 2);
+    let actual_kwh = round(days.iter().map(|day| day.actual_kwh).sum(), 2);
+    let avoided_kwh = round(baseline_kwh - actual_kwh, 2);
+
+    let n = baseline_days as f64;
+    let m = days.len() as f64;
+    let dof = baseline_days.saturating_sub(model.parameter_count()).max(1);
+    let t = student_t_quantile((1.0 + options.confidence) / 2.0, dof as f64);
+    let uncertainty_kwh = round(t * ASHRAE_FACTOR * rmse * (m * (1.0 + 2.0 / n)).sqrt(), 2);
+
+    Ok(SavingsReport {
+        installation_id,
+        model,
+        baseline_days,
+        reporting_days: days.len(),
+        r_squared,
+        cv_rmse: (mean_daily_kwh > 0.0).then(|| round(rmse / mean_daily_kwh, 4)),
+        baseline_kwh,
+        actual_kwh,
+        avoided_kwh,
+        savings_fraction: (baseline_kwh > 0.0).then(|| round(avoided_kwh / baseline_kwh, 4)),
+        confidence: options.confidence,
+        uncertainty_kwh,
+        avoided_cost: options
+            .price_per_kwh
+            .map(|price| round(avoided_kwh * price, 2)),
+        cost_uncertainty: options
+            .price_per_kwh
+            .map(|price| round(uncertainty_kwh * price, 2)),
+        days,
+    })
+}
+
+/// Baseline model fitted on the pre-retrofit period, with its statistics.
+struct Baseline {
+    installation_id: String,
+    model: BaselineModel,
+    /// Number of days used by the fit.
+    days: usize,
+    r_squared: Option<f64>,
+    rmse: f64,
+    mean_daily_kwh: f64,
+}
+
+fn fit_baseline(
+    measurements: &[Measurement],
+    options: &SavingsOptions,
+) -> Result<Baseline, EnergiaProError> {
+    let (installation_id, model, r_squared, observed, residuals) = match &options.temperatures {
+        Some(temperatures) => {
+            let fitted = signature::fit_with(measurements, temperatures, &options.baseline)?;
+            let model = BaselineModel::Signature {
+                base_load_kwh_per_day: fitted.base_load_kwh_per_day,
+                slope_kwh_per_degree: fitted.slope_kwh_per_degree,
+                balance_celsius: fitted.balance_celsius,
+            };
+            let observed = fitted
+                .residuals
+                .iter()
+                .map(|day| day.consumption_kwh)
+                .collect::<Vec<_>>();
+            let residuals = fitted
+                .residuals
+                .iter()
+                .map(|day| day.residual_kwh)
+                .collect::<Vec<_>>();
+            (
+                fitted.installation_id,
+                model,
+                Some(fitted.r_squared),
+                observed,
+                residuals,
+            )
+        }
+        None => {
+            let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+            let observed = daily_consumption(&series, options.baseline.min_day_coverage)
+                .into_values()
+                .collect::<Vec<_>>();
+            if observed.len() < options.baseline.min_days.max(2) {
+                return Err(EnergiaProError::InvalidArgument(format!(
+                    "baseline of installation `{}` has {} complete days, at least {} are required",
+                    series.installation_id(),
+                    observed.len(),
+                    options.baseline.min_days.max(2)
+                )));
+            }
+            let mean = observed.iter().sum::<f64>() / observed.len() as f64;
+            let residuals = observed.iter().map(|kwh| kwh - mean).collect();
+            (
+                series.installation_id().to_owned(),
+                BaselineModel::Mean {
+                    kwh_per_day: round(mean, 2),
+                },
+                None,
+                observed,
+                residuals,
+            )
+        }
+    };
+
+    let count = observed.len();
+    let dof = count.saturating_sub(model.parameter_count()).max(1) as f64;
+    let rmse = (residuals
+        .iter()
+        .map(|residual| residual * residual)
+        .sum::<f64>()
+        / dof)
+        .sqrt();
+    let mean = observed.iter().sum::<f64>() / count as f64;
+
+    Ok(Baseline {
+        installation_id,
+        model,
+        days: count,
+        r_squared,
+        rmse,
+        mean_daily_kwh: mean,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+    use crate::analysis::test_support::daily;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// Thirty days per winter with a balance temperature of 16 °C; the
+    /// reporting winter is 3 °C milder and the retrofit halved the slope.
+    fn retrofit(noise: f64) -> (Vec<Measurement>, TemperatureSeries) {
+        let mut measurements = Vec::new();
+        let mut temperatures = Vec::new();
+
+        for day in 0..30 {
+            let disturbance = if day % 2 == 0 { noise } else { -noise };
+            let celsius = -5.0 + day as f64 * 0.8;
+
+            let before = date("2023-01-01") + Days::new(day);
+            temperatures.push((before, celsius));
+            measurements.push(daily(
+                before,
+                40.0 + 6.0 * (16.0 - celsius).max(0.0) + disturbance,
+            ));
+
+            let after = date("2024-01-01") + Days::new(day);
+            temperatures.push((after, celsius + 3.0));
+            measurements.push(daily(after, 40.0 + 3.0 * (13.0 - celsius).max(0.0)));
+        }
+
+        (measurements, TemperatureSeries::from_days(temperatures))
+    }
+
+    fn periods() -> (DateRange, DateRange) {
+        (
+            DateRange::between("2023-01-01", "2023-01-30").unwrap(),
+            DateRange::between("2024-01-01", "2024-01-30").unwrap(),
+        )
+    }
+
+    #[test]
+    fn adjusts_the_baseline_to_the_reporting_weather() {
+        let (measurements, temperatures) = retrofit(0.0);
+        let (baseline, reporting) = periods();
+        let options = SavingsOptions::default()
+            .with_temperatures(temperatures)
+            .with_price_per_kwh(0.15);
+
+        let report = estimate_with(&measurements, baseline, reporting, &options).unwrap();
+
+        assert_eq!(
+            report.model,
+            BaselineModel::Signature {
+                base_load_kwh_per_day: 40.0,
+                slope_kwh_per_degree: 6.0,
+                balance_celsius: 16.0,
+            }
+        );
+        assert_eq!(report.baseline_days, 30);
+        assert_eq!(report.reporting_days, 30);
+        assert_eq!(report.r_squared, Some(1.0));
+        let first = &report.days[0];
+        assert_eq!(first.mean_celsius, Some(-2.0));
+        assert_eq!(first.baseline_kwh, 148.0);
+        assert_eq!(first.actual_kwh, 94.0);
+        assert_eq!(report.baseline_kwh, 2469.6);
+        assert_eq!(report.actual_kwh, 1834.8);
+        assert_eq!(report.avoided_kwh, 634.8);
+        assert_eq!(report.savings_fraction, Some(0.257));
+        assert_eq!(report.uncertainty_kwh, 0.0);
+        assert_eq!(report.avoided_cost, Some(95.22));
+        assert!(report.is_significant());
+    }
+
+    #[test]
+    fn estimates_uncertainty_from_the_baseline_residuals() {
+        let (measurements, temperatures) = retrofit(10.0);
+        let (baseline, reporting) = periods();
+        let options = SavingsOptions::default().with_temperatures(temperatures);
+
+        let report = estimate_with(&measurements, baseline, reporting, &options).unwrap();
+        let wider = estimate_with(
+            &measurements,
+            baseline,
+            reporting,
+            &options.clone().with_confidence(0.95),
+        )
+        .unwrap();
+
+        assert!(report.cv_rmse.is_some_and(|cv_rmse| cv_rmse > 0.0));
+        assert!(report.uncertainty_kwh > 0.0);
+        assert!(wider.uncertainty_kwh > report.uncertainty_kwh);
+        assert!(report.is_significant());
+    }
+
+    #[test]
+    fn uses_the_mean_without_temperatures() {
+        let (measurements, _) = retrofit(0.0);
+        let (baseline, reporting) = periods();
+
+        let report = estimate(&measurements, baseline, reporting).unwrap();
+
+        assert!(matches!(report.model, BaselineModel::Mean { .. }));
+        assert_eq!(report.r_squared, None);
+        assert!(report.days.iter().all(|day| day.mean_celsius.is_none()));
+        assert!(report.uncertainty_kwh > 0.0);
+    }
+
+    #[test]
+    fn rejects_an_empty_baseline_or_reporting_period() {
+        let (baseline, reporting) = periods();
+        let days = |from: &str| {
+            (0..30)
+                .map(|day| daily(date(from) + Days::new(day), 5.0))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(matches!(
+            estimate(&days("2023-01-01"), baseline, reporting),
+            Err(EnergiaProError::InvalidArgument(message)) if message.contains("no complete day")
+        ));
+        assert!(matches!(
+            estimate(&days("2024-01-01"), baseline, reporting),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn leaves_the_cv_rmse_unset_for_an_idle_baseline() {
+        let (baseline, reporting) = periods();
+        let mut measurements = (0..30)
+            .map(|day| daily(date("2023-01-01") + Days::new(day), 0.0))
+            .collect::<Vec<_>>();
+        measurements.push(daily(date("2024-01-01"), 5.0));
+
+        let report = estimate(&measurements, baseline, reporting).unwrap();
+
+        assert_eq!(report.cv_rmse, None);
+        assert_eq!(report.reporting_days, 1);
+    }
+
+    #[test]
+    fn rejects_invalid_periods() {
+        let (measurements, _) = retrofit(0.0);
+        let (baseline, _) = periods();
+
+        for reporting in [
+            DateRange::between("2023-01-15", "2023-02-15").unwrap(),
+            DateRange::since("2024-01-01").unwrap(),
+        ] {
+            assert!(matches!(
+                estimate(&measurements, baseline, reporting),
+                Err(EnergiaProError::InvalidArgument(_))
+            ));
+        }
+    }
+}
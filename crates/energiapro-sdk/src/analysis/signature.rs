@@ -0,0 +1,427 @@
+//! Energy signature of a building.
+//!
+//! The energy signature regresses daily consumption against the daily mean
+//! outdoor temperature with a heating change-point model:
+//!
+//! ```text
+//! kwh_per_day = base_load + slope × max(0, balance − temperature)
+//! ```
+//!
+//! The base load covers domestic hot water and losses, the slope is the heat
+//! demand per degree below the balance temperature. A rising slope or base
+//! load over the years points to a degraded heating system, and the model
+//! extrapolated to the design temperature gives the load to size a
+//! replacement boiler or heat pump.
+//!
+//! Temperatures come from a [`TemperatureSeries`], typically loaded from a
+//! MeteoSwiss CSV export.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::{Days, NaiveDate};
+//! use energiapro::Measurement;
+//! use energiapro::analysis::degree_days::TemperatureSeries;
+//! use energiapro::analysis::signature;
+//!
+//! # fn main() -> Result<(), energiapro::EnergiaProError> {
+//! let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+//! let mut temperatures = Vec::new();
+//! let mut measurements = Vec::new();
+//! for day in 0..30 {
+//!     let date = start + Days::new(day);
+//!     let celsius = day as f64 - 5.0;
+//!     temperatures.push((date, celsius));
+//!     measurements.push(Measurement {
+//!         client_id: 1,
+//!         installation_id: "INSTALLATION_ID_1".to_owned(),
+//!         timestamp: format!("{date} 00:00:00"),
+//!         index_m3: 0.0,
+//!         consumption_m3: 0.0,
+//!         consumption_kwh: 20.0 + 5.0 * (15.0 - celsius).max(0.0),
+//!     });
+//! }
+//!
+//! let signature = signature::fit(&measurements, &TemperatureSeries::from_days(temperatures))?;
+//!
+//! assert_eq!(signature.base_load_kwh_per_day, 20.0);
+//! assert_eq!(signature.slope_kwh_per_degree, 5.0);
+//! assert_eq!(signature.balance_celsius, 15.0);
+//! assert_eq!(signature.r_squared, 1.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::degree_days::TemperatureSeries;
+use super::{daily_consumption, round};
+use crate::errors::EnergiaProError;
+use crate::models::{Measurement, MeasurementSeries};
+
+/// Spacing of the balance temperatures tried by the fit, in °C.
+const BALANCE_STEP_CELSIUS: f64 = 0.1;
+
+/// Parameters of the energy signature fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureOptions {
+    /// Lowest balance temperature tried, in °C.
+    pub min_balance_celsius: f64,
+
+    /// Highest balance temperature tried, in °C.
+    pub max_balance_celsius: f64,
+
+    /// Share of a day's intervals that must be present for the day to be used,
+    /// between 0 and 1. Partial days would understate the daily consumption.
+    pub min_day_coverage: f64,
+
+    /// Smallest number of days required to fit a signature.
+    pub min_days: usize,
+}
+
+impl Default for SignatureOptions {
+    fn default() -> Self {
+        Self {
+            min_balance_celsius: 8.0,
+            max_balance_celsius: 22.0,
+            min_day_coverage: 0.9,
+            min_days: 14,
+        }
+    }
+}
+
+impl SignatureOptions {
+    /// Set the range of balance temperatures tried, in °C.
+    pub fn with_balance_range(mut self, min_celsius: f64, max_celsius: f64) -> Self {
+        self.min_balance_celsius = min_celsius;
+        self.max_balance_celsius = max_celsius;
+        self
+    }
+
+    /// Set the share of intervals a day needs to be used.
+    pub fn with_min_day_coverage(mut self, min_day_coverage: f64) -> Self {
+        self.min_day_coverage = min_day_coverage;
+        self
+    }
+
+    /// Set the smallest number of days required to fit a signature.
+    pub fn with_min_days(mut self, min_days: usize) -> Self {
+        self.min_days = min_days;
+        self
+    }
+}
+
+/// Observed and modelled consumption of one day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignatureResidual {
+    /// Local calendar day.
+    pub date: NaiveDate,
+    /// Daily mean outdoor temperature in °C.
+    pub mean_celsius: f64,
+    /// Measured energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Energy predicted by the signature in kilowatt-hours.
+    pub predicted_kwh: f64,
+    /// Measured minus predicted energy in kilowatt-hours.
+    pub residual_kwh: f64,
+}
+
+/// Fitted heating change-point model of one installation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnergySignature {
+    /// Installation identifier.
+    pub installation_id: String,
+    /// Consumption independent of the weather, in kWh per day.
+    pub base_load_kwh_per_day: f64,
+    /// Additional consumption per degree below the balance temperature, in
+    /// kWh per day and °C.
+    pub slope_kwh_per_degree: f64,
+    /// Outdoor temperature below which the building is heated, in °C.
+    pub balance_celsius: f64,
+    /// Coefficient of determination of the fit, from 0 to 1.
+    pub r_squared: f64,
+    /// Daily observations used by the fit, in chronological order.
+    pub residuals: Vec<SignatureResidual>,
+}
+
+impl EnergySignature {
+    /// Return the predicted consumption of a day, in kWh.
+    pub fn predict(&self, mean_celsius: f64) -> f64 {
+        self.base_load_kwh_per_day
+            + self.slope_kwh_per_degree * (self.balance_celsius - mean_celsius).max(0.0)
+    }
+
+    /// Return the average heating load at a design temperature, in kW.
+    ///
+    /// This is the daily prediction spread over 24 hours, a starting point to
+    /// size a replacement heat generator.
+    pub fn design_load_kw(&self, design_celsius: f64) -> f64 {
+        self.predict(design_celsius) / 24.0
+    }
+}
+
+/// Fit an energy signature with the default [`SignatureOptions`].
+///
+/// # Errors
+///
+/// See [`fit_with`].
+pub fn fit(
+    measurements: &[Measurement],
+    temperatures: &TemperatureSeries,
+) -> Result<EnergySignature, EnergiaProError> {
+    fit_with(measurements, temperatures, &SignatureOptions::default())
+}
+
+/// Fit an energy signature with custom options.
+///
+/// Consumption is summed per local day. Days with too few intervals or
+/// without a temperature are left out. Every balance temperature of the
+/// configured range is tried in steps of 0.1 °C, and the one with the lowest
+/// squared error and a positive slope is kept.
+///
+/// # Errors
+///
+/// Returns an error if the measurements belong to more than one installation,
+/// if a timestamp cannot be parsed, if fewer than `min_days` days can be used
+/// or if consumption does not increase as the temperature drops.
+pub fn fit_with(
+    measurements: &[Measurement],
+    temperatures: &TemperatureSeries,
+    options: &SignatureOptions,
+) -> Result<EnergySignature, EnergiaProError> {
+    if !options.min_balance_celsius.is_finite()
+        || !options.max_balance_celsius.is_finite()
+        || options.min_balance_celsius > options.max_balance_celsius
+    {
+        return Err(EnergiaProError::InvalidArgument(
+            "balance temperature range must be finite and in ascending order".to_owned(),
+        ));
+    }
+
+    let series = MeasurementSeries::from_measurements(measurements.iter().cloned())?;
+    let days = daily_consumption(&series, options.min_day_coverage)
+        .into_iter()
+        .filter_map(|(date, kwh)| temperatures.get(date).map(|celsius| (date, celsius, kwh)))
+        .collect::<Vec<_>>();
+
+    if days.len() < options.min_days.max(2) {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "installation `{}` has {} complete days with a temperature, at least {} are required",
+            series.installation_id(),
+            days.len(),
+            options.min_days.max(2)
+        )));
+    }
+
+    let steps = ((options.max_balance_celsius - options.min_balance_celsius) / BALANCE_STEP_CELSIUS)
+        .round() as usize;
+    let (balance, best) = (0..=steps)
+        .map(|step| options.min_balance_celsius + step as f64 * BALANCE_STEP_CELSIUS)
+        .filter_map(|balance| regress(&days, balance).map(|fit| (balance, fit)))
+        .filter(|(_, fit)| fit.slope > 0.0)
+        .min_by(|(_, a), (_, b)| a.sse.total_cmp(&b.sse))
+        .ok_or_else(|| {
+            EnergiaProError::InvalidArgument(format!(
+                "consumption of installation `{}` does not depend on the outdoor temperature",
+                series.installation_id()
+            ))
+        })?;
+
+    let mean = days.iter().map(|(_, _, kwh)| kwh).sum::<f64>() / days.len() as f64;
+    let sst = days
+        .iter()
+        .map(|(_, _, kwh)| (kwh - mean).powi(2))
+        .sum::<f64>();
+    let r_squared = if sst > 0.0 {
+        (1.0 - best.sse / sst).max(0.0)
+    } else {
+        1.0
+    };
+
+    let mut signature = EnergySignature {
+        installation_id: series.installation_id().to_owned(),
+        base_load_kwh_per_day: round(best.intercept, 3),
+        slope_kwh_per_degree: round(best.slope, 3),
+        balance_celsius: round(balance, 3),
+        r_squared: round(r_squared, 3),
+        residuals: Vec::with_capacity(days.len()),
+    };
+    signature.residuals = days
+        .iter()
+        .map(|&(date, mean_celsius, consumption_kwh)| {
+            let predicted_kwh = round(signature.predict(mean_celsius), 3);
+            SignatureResidual {
+                date,
+                mean_celsius,
+                consumption_kwh,
+                predicted_kwh,
+                residual_kwh: round(consumption_kwh - predicted_kwh, 3),
+            }
+        })
+        .collect();
+
+    Ok(signature)
+}
+
+/// Ordinary least squares fit of `y = intercept + slope × x`.
+pub(crate) struct LinearFit {
+    pub(crate) intercept: f64,
+    pub(crate) slope: f64,
+    /// Sum of the squared residuals.
+    pub(crate) sse: f64,
+}
+
+/// Fit `(x, y)` points by ordinary least squares, or return `None` if `x`
+/// does not vary.
+pub(crate) fn least_squares(points: &[(f64, f64)]) -> Option<LinearFit> {
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+
+    let (covariance, variance) =
+        points
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                let dx = x - mean_x;
+                (covariance + dx * (y - mean_y), variance + dx * dx)
+            });
+    if variance <= f64::EPSILON {
+        return None;
+    }
+
+    let slope = covariance / variance;
+    let intercept = mean_y - slope * mean_x;
+    let sse = points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum();
+
+    Some(LinearFit {
+        intercept,
+        slope,
+        sse,
+    })
+}
+
+/// Regress consumption against degrees below `balance`.
+fn regress(days: &[(NaiveDate, f64, f64)], balance: f64) -> Option<LinearFit> {
+    let points = days
+        .iter()
+        .map(|&(_, celsius, kwh)| ((balance - celsius).max(0.0), kwh))
+        .collect::<Vec<_>>();
+    least_squares(&points)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+    use crate::analysis::test_support::measurement;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// Hourly measurements and daily temperatures following a known signature,
+    /// with an alternating disturbance on every other day.
+    fn building(days: u64, noise: f64) -> (Vec<Measurement>, TemperatureSeries) {
+        let start = date("2024-01-01");
+        let mut measurements = Vec::new();
+        let mut temperatures = Vec::new();
+
+        for day in 0..days {
+            let date = start + Days::new(day);
+            let celsius = -5.0 + day as f64;
+            let disturbance = if day % 2 == 0 { noise } else { -noise };
+            let daily_kwh = 48.0 + 6.0 * (16.0 - celsius).max(0.0) + disturbance;
+
+            temperatures.push((date, celsius));
+            for hour in 0..24 {
+                measurements.push(measurement(
+                    format!("{date} {hour:02}:00:00"),
+                    daily_kwh / 24.0,
+                ));
+            }
+        }
+
+        (measurements, TemperatureSeries::from_days(temperatures))
+    }
+
+    #[test]
+    fn recovers_change_point_parameters() {
+        let (measurements, temperatures) = building(30, 0.0);
+
+        let signature = fit(&measurements, &temperatures).unwrap();
+
+        assert_eq!(signature.base_load_kwh_per_day, 48.0);
+        assert_eq!(signature.slope_kwh_per_degree, 6.0);
+        assert_eq!(signature.balance_celsius, 16.0);
+        assert_eq!(signature.r_squared, 1.0);
+        assert_eq!(signature.residuals.len(), 30);
+        assert!(
+            signature
+                .residuals
+                .iter()
+                .all(|residual| residual.residual_kwh == 0.0)
+        );
+        assert_eq!(signature.design_load_kw(-8.0), 8.0);
+    }
+
+    #[test]
+    fn reports_residuals_and_goodness_of_fit() {
+        let (measurements, temperatures) = building(30, 10.0);
+
+        let signature = fit(&measurements, &temperatures).unwrap();
+
+        assert!((signature.slope_kwh_per_degree - 6.0).abs() < 0.5);
+        assert!(signature.r_squared > 0.9 && signature.r_squared < 1.0);
+        let first = &signature.residuals[0];
+        assert_eq!(first.date, date("2024-01-01"));
+        assert_eq!(first.mean_celsius, -5.0);
+        assert_eq!(
+            first.residual_kwh,
+            round(first.consumption_kwh - first.predicted_kwh, 3)
+        );
+    }
+
+    #[test]
+    fn skips_partial_days_and_days_without_temperature() {
+        let (mut measurements, temperatures) = building(20, 0.0);
+        // A partial day and a day outside the temperature series.
+        measurements.push(measurement("2024-01-21 00:00:00".to_owned(), 1.0));
+        measurements
+            .extend((0..24).map(|hour| measurement(format!("2024-01-22 {hour:02}:00:00"), 100.0)));
+
+        let signature = fit(&measurements, &temperatures).unwrap();
+
+        assert_eq!(signature.residuals.len(), 20);
+        assert_eq!(signature.slope_kwh_per_degree, 6.0);
+    }
+
+    #[test]
+    fn requires_enough_days_and_a_heating_dependence() {
+        let (measurements, temperatures) = building(5, 0.0);
+        assert!(matches!(
+            fit(&measurements, &temperatures),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+
+        let summer = TemperatureSeries::from_days(
+            (0..20).map(|day| (date("2024-07-01") + Days::new(day), 25.0 + day as f64)),
+        );
+        let flat = (0..20)
+            .map(|day| {
+                measurement(
+                    format!("{} 00:00:00", date("2024-07-01") + Days::new(day)),
+                    30.0,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            fit(&flat, &summer),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! Local HTTP server answering the client with canned responses in tests.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::token_manager::TokenManager;
+use super::{Client, ClientOptions};
+
+/// Answer of the mock server to one request.
+pub(crate) enum MockResponse {
+    /// Answer with a status code and a JSON body.
+    Json(u16, &'static str),
+    /// Never answer, so that the client times out.
+    Hang,
+}
+
+/// HTTP server answering each request with the next queued response.
+///
+/// Requests arriving once the queue is empty are left unanswered.
+pub(crate) struct MockServer {
+    url: String,
+    bodies: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Start a server on a free local port.
+    pub(crate) async fn start(responses: impl IntoIterator<Item = MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = Arc::new(Mutex::new(responses.into_iter().collect::<VecDeque<_>>()));
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&bodies);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let responses = Arc::clone(&responses);
+                let received = Arc::clone(&received);
+                tokio::spawn(async move {
+                    let Some(body) = read_body(&mut socket).await else {
+                        return;
+                    };
+                    received.lock().unwrap().push(body);
+
+                    let response = responses.lock().unwrap().pop_front();
+                    match response {
+                        Some(MockResponse::Json(status, payload)) => {
+                            let head = format!(
+                                "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                                payload.len()
+                            );
+                            let _ = socket.write_all(head.as_bytes()).await;
+                            let _ = socket.write_all(payload.as_bytes()).await;
+                        }
+                        Some(MockResponse::Hang) | None => std::future::pending::<()>().await,
+                    }
+                });
+            }
+        });
+
+        Self { url, bodies }
+    }
+
+    /// Base URL of the server.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Build a client sending its requests to this server with a valid token.
+    pub(crate) fn client(&self, options: ClientOptions) -> Client {
+        let mut client = Client::with_options("username", "secret", options).unwrap();
+        client.base_url = self.url.clone();
+        client.token = TokenManager::authenticated("token");
+        client
+    }
+
+    /// Form-encoded bodies of the requests received so far.
+    pub(crate) fn bodies(&self) -> Vec<String> {
+        self.bodies.lock().unwrap().clone()
+    }
+}
+
+/// Read one request and return its body, or `None` if the connection closed.
+async fn read_body(socket: &mut TcpStream) -> Option<String> {
+    let mut request = Vec::new();
+    let mut chunk = [0; 1024];
+
+    loop {
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_ascii_lowercase();
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|length| length.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            let body_start = end + 4;
+            if request.len() >= body_start + length {
+                let body = &request[body_start..body_start + length];
+                return Some(String::from_utf8_lossy(body).into_owned());
+            }
+        }
+
+        let read = socket.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&chunk[..read]);
+    }
+}
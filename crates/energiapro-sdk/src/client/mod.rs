@@ -1,9 +1,17 @@
+#[cfg(test)]
+pub(crate) mod mock;
 mod options;
 mod token_manager;
 
+use self::options::SharedClock;
 use self::token_manager::TokenManager;
 use crate::errors::EnergiaProError;
-use crate::requests::Request;
+use crate::requests::{Request, StreamingRequest};
+use crate::responses::{JsonRowReader, PayloadKind};
+use crate::types::{Clock, DateWindow};
+use chrono::NaiveDate;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::de::Error as _;
 use serde_json::Value;
 
 pub use options::ClientOptions;
@@ -19,6 +27,18 @@ pub(crate) struct Client {
 
     /// The token lifecycle owner responsible for obtaining and refreshing authentication tokens.
     token: TokenManager,
+
+    /// The window size used to split long date ranges into separate requests.
+    date_range_window: DateWindow,
+
+    /// The maximum number of window requests running at the same time.
+    max_concurrent_requests: usize,
+
+    /// The number of times a failed window request is retried.
+    max_retries: u32,
+
+    /// The clock deciding where ranges without an end stop.
+    clock: SharedClock,
 }
 
 impl Client {
@@ -50,19 +70,49 @@ impl Client {
             ));
         }
 
+        if options.max_concurrent_requests == 0 {
+            return Err(EnergiaProError::InvalidArgument(
+                "max_concurrent_requests must be greater than zero".to_owned(),
+            ));
+        }
+
+        let base_url = Self::normalize_base_url(options.base_url)?;
         let http_client = reqwest::Client::builder()
             .timeout(options.timeout)
             .build()?;
-        let base_url = Self::normalize_base_url(options.base_url)?;
         let token = TokenManager::new(username, secret_key);
 
         Ok(Self {
             http_client,
             base_url,
             token,
+            date_range_window: options.date_range_window,
+            max_concurrent_requests: options.max_concurrent_requests,
+            max_retries: options.max_retries,
+            clock: options.clock,
         })
     }
 
+    /// Window size used to split long date ranges into separate requests.
+    pub(crate) fn date_range_window(&self) -> DateWindow {
+        self.date_range_window
+    }
+
+    /// Maximum number of window requests running at the same time.
+    pub(crate) fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    /// Number of times a failed window request is retried.
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Current date according to the configured clock.
+    pub(crate) fn today(&self) -> NaiveDate {
+        self.clock.today()
+    }
+
     /// Send an authenticated request to the EnergiaPro API and parse the response.
     ///
     /// This method handles token management, including refreshing the token if
@@ -89,6 +139,38 @@ impl Client {
         }
     }
 
+    /// Send an authenticated request to the EnergiaPro API and stream its rows.
+    ///
+    /// Token handling mirrors [`Client::send`]: API error payloads are detected
+    /// before the first row is produced, so the request can still be retried
+    /// once with a fresh token. Rows are then parsed one at a time while the
+    /// response body is being received.
+    pub(crate) async fn send_streaming<R>(
+        &self,
+        request: R,
+    ) -> Result<BoxStream<'static, Result<R::Row, EnergiaProError>>, EnergiaProError>
+    where
+        R: StreamingRequest + Send + 'static,
+        R::Row: Send + 'static,
+    {
+        let mut has_retried_with_fresh_token = false;
+
+        loop {
+            let token = self.token.obtain(self).await?;
+            match self.open_stream(&request, &token).await {
+                Err(EnergiaProError::Api { code, .. })
+                    if code.is_token_error() && !has_retried_with_fresh_token =>
+                {
+                    self.token.clear().await;
+                    has_retried_with_fresh_token = true;
+                    continue;
+                }
+                Err(error) => return Err(error),
+                Ok((response, reader)) => return Ok(Self::stream_rows(request, response, reader)),
+            }
+        }
+    }
+
     pub(super) async fn execute_request<R>(
         &self,
         request: &R,
@@ -122,6 +204,95 @@ impl Client {
         request.parse_response(payload)
     }
 
+    /// Send a streaming request and read the response until its shape is known.
+    ///
+    /// Array payloads are handed back together with the partially filled row
+    /// reader. Anything else is buffered and mapped to an API error.
+    async fn open_stream<R>(
+        &self,
+        request: &R,
+        token: &str,
+    ) -> Result<(reqwest::Response, JsonRowReader), EnergiaProError>
+    where
+        R: StreamingRequest,
+    {
+        request.validate_request()?;
+
+        let mut response = request
+            .to_request_builder(&self.http_client, &self.base_url, token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let endpoint = response.url().to_string();
+
+        if !status.is_success() {
+            let payload = response.text().await?;
+            let payload = payload.trim_start_matches('\u{feff}');
+            return Err(Self::map_non_success_response(status, endpoint, payload));
+        }
+
+        let mut reader = JsonRowReader::default();
+        loop {
+            match reader.kind() {
+                Some(PayloadKind::Array) => return Ok((response, reader)),
+                Some(PayloadKind::Other) => break,
+                None => match response.chunk().await? {
+                    Some(chunk) => reader.push(&chunk),
+                    None => break,
+                },
+            }
+        }
+
+        // Error payloads are small objects, so it is fine to buffer them.
+        let mut payload = reader.into_bytes();
+        while let Some(chunk) = response.chunk().await? {
+            payload.extend_from_slice(&chunk);
+        }
+
+        let payload: Value = serde_json::from_slice(&payload)?;
+
+        if let Some(error) = EnergiaProError::from_api_payload(&payload) {
+            return Err(error);
+        }
+
+        Err(EnergiaProError::Json(serde_json::Error::custom(
+            "expected a JSON array payload",
+        )))
+    }
+
+    /// Turn an open response into a stream of parsed rows.
+    fn stream_rows<R>(
+        request: R,
+        response: reqwest::Response,
+        reader: JsonRowReader,
+    ) -> BoxStream<'static, Result<R::Row, EnergiaProError>>
+    where
+        R: StreamingRequest + Send + 'static,
+        R::Row: Send + 'static,
+    {
+        stream::try_unfold(
+            (request, response, reader),
+            |(request, mut response, mut reader)| async move {
+                loop {
+                    if let Some(row) = reader.next_row()? {
+                        let row = request.parse_row(&row)?;
+                        return Ok(Some((row, (request, response, reader))));
+                    }
+
+                    match response.chunk().await? {
+                        Some(chunk) => reader.push(&chunk),
+                        None => {
+                            reader.finish()?;
+                            return Ok(None);
+                        }
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+
     /// Normalize the base URL.
     fn normalize_base_url(base_url: String) -> Result<String, EnergiaProError> {
         let normalized = base_url.trim().trim_end_matches('/').to_owned();
@@ -223,6 +394,33 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn rejects_zero_max_concurrent_requests() {
+        let err = Client::with_options(
+            "username",
+            "secret",
+            ClientOptions::default().with_max_concurrent_requests(0),
+        );
+        assert!(matches!(
+            err,
+            Err(EnergiaProError::InvalidArgument(message))
+                if message == "max_concurrent_requests must be greater than zero"
+        ));
+    }
+
+    #[test]
+    fn takes_today_from_the_configured_clock() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let client = Client::with_options(
+            "username",
+            "secret",
+            ClientOptions::default().with_clock(crate::types::FixedClock::new(today)),
+        )
+        .unwrap();
+
+        assert_eq!(client.today(), today);
+    }
+
     #[test]
     fn maps_non_success_json_api_error_payload() {
         let err = Client::map_non_success_response(
@@ -1,13 +1,33 @@
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::types::{Clock, DateWindow, SystemClock};
+
 /// Configuration options for the EnergiaPro API client.
+///
+/// Start from [`ClientOptions::default`] and adjust it with the `with_*`
+/// methods; new options may be added in minor releases.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ClientOptions {
     /// Base URL for requests to the EnergiaPro API.
     pub base_url: String,
 
     /// Timeout for requests to the EnergiaPro API.
     pub timeout: Duration,
+
+    /// Window size used to split long date ranges into separate requests.
+    pub date_range_window: DateWindow,
+
+    /// Maximum number of window requests running at the same time.
+    pub max_concurrent_requests: usize,
+
+    /// Number of times a failed window request is retried before giving up.
+    pub max_retries: u32,
+
+    /// Clock deciding where ranges without an end stop.
+    pub(crate) clock: SharedClock,
 }
 
 /// Default options for the EnergiaPro API client.
@@ -16,6 +36,10 @@ impl Default for ClientOptions {
         Self {
             base_url: "https://web2.holdigaz.ch/espace-client-api/api".to_owned(),
             timeout: Duration::from_secs(30),
+            date_range_window: DateWindow::default(),
+            max_concurrent_requests: 4,
+            max_retries: 2,
+            clock: SharedClock(Arc::new(SystemClock)),
         }
     }
 }
@@ -32,4 +56,44 @@ impl ClientOptions {
         self.timeout = timeout;
         self
     }
+
+    /// Set the window size used to split long date ranges into separate requests.
+    pub fn with_date_range_window(mut self, window: DateWindow) -> Self {
+        self.date_range_window = window;
+        self
+    }
+
+    /// Set the maximum number of window requests running at the same time.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    /// Set the number of times a failed window request is retried.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the clock used to stop ranges without an end, [`SystemClock`] by default.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.clock = SharedClock(Arc::new(clock));
+        self
+    }
+}
+
+/// Clock shared between the options and the client built from them.
+#[derive(Clone)]
+pub(crate) struct SharedClock(Arc<dyn Clock + Send + Sync>);
+
+impl Clock for SharedClock {
+    fn today(&self) -> chrono::NaiveDate {
+        self.0.today()
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedClock")
+    }
 }
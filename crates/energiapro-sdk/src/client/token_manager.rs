@@ -47,6 +47,20 @@ impl TokenManager {
         }
     }
 
+    /// Create a manager holding a valid token, so that tests never authenticate.
+    #[cfg(test)]
+    pub(super) fn authenticated(token: &str) -> Self {
+        Self {
+            username: String::new(),
+            secret_key: String::new(),
+            cached_token: Mutex::new(Some((
+                token.to_owned(),
+                Instant::now() + Duration::from_secs(60 * 60),
+            ))),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
     /// Obtain an EnergiaPro API token
     ///
     /// This method will first attempt to obtain a valid token from the cache.
@@ -46,6 +46,7 @@ impl EnergiaPro {
     /// - `username` is empty or contains only whitespace.
     /// - `secret_key` is empty or contains only whitespace.
     /// - `options.base_url` is empty, invalid, or does not use `https`.
+    /// - `options.max_concurrent_requests` is zero.
     /// - the underlying HTTP client cannot be initialized.
     pub fn with_options(
         username: impl Into<String>,
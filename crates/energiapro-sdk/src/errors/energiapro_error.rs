@@ -28,6 +28,9 @@ pub enum EnergiaProError {
         /// Trimmed excerpt of the response body for diagnostics.
         body_snippet: String,
     },
+    /// Failed to read a local file.
+    #[error("failed to read file: {0}")]
+    Io(#[from] std::io::Error),
     /// Failed to parse or serialize JSON payloads.
     #[error("invalid json payload: {0}")]
     Json(#[from] serde_json::Error),
@@ -45,6 +48,20 @@ pub enum EnergiaProError {
 }
 
 impl EnergiaProError {
+    /// Return `true` if the failure is transient and the request may succeed when retried.
+    ///
+    /// Timeouts, connection failures and errors while sending the request are
+    /// transient; builder, redirect and decoding errors are not.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(error) => error.is_timeout() || error.is_connect() || error.is_request(),
+            Self::HttpStatus { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn from_api_payload(payload: &Value) -> Option<Self> {
         let object = payload.as_object()?;
         let error_code = object.get("errorCode")?;
@@ -73,7 +90,10 @@ impl EnergiaProError {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
+    use crate::client::mock::{MockResponse, MockServer};
 
     #[test]
     fn maps_api_error_code_from_payload() {
@@ -98,6 +118,42 @@ mod tests {
         assert!(EnergiaProError::from_api_payload(&payload).is_none());
     }
 
+    #[test]
+    fn classifies_transient_errors() {
+        let server_error = EnergiaProError::HttpStatus {
+            status: reqwest::StatusCode::BAD_GATEWAY,
+            endpoint: "https://example.com/api/index.php".to_owned(),
+            body_snippet: String::new(),
+        };
+        let client_error = EnergiaProError::HttpStatus {
+            status: reqwest::StatusCode::BAD_REQUEST,
+            endpoint: "https://example.com/api/index.php".to_owned(),
+            body_snippet: String::new(),
+        };
+
+        assert!(server_error.is_transient());
+        assert!(!client_error.is_transient());
+        assert!(!EnergiaProError::InvalidArgument("from".to_owned()).is_transient());
+    }
+
+    #[tokio::test]
+    async fn classifies_http_errors() {
+        let server = MockServer::start([MockResponse::Hang]).await;
+        let builder_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let timeout_error = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap()
+            .get(server.url())
+            .send()
+            .await
+            .unwrap_err();
+
+        assert!(!EnergiaProError::Http(builder_error).is_transient());
+        assert!(timeout_error.is_timeout());
+        assert!(EnergiaProError::Http(timeout_error).is_transient());
+    }
+
     #[tokio::test]
     async fn maps_blocking_task_join_error() {
         let join_error = tokio::task::spawn_blocking(|| {
@@ -23,6 +23,7 @@
 //! # }
 //! ```
 
+pub mod analysis;
 mod client;
 mod energiapro;
 mod errors;
@@ -35,5 +36,11 @@ mod types;
 pub use client::ClientOptions;
 pub use energiapro::EnergiaPro;
 pub use errors::{ApiErrorCode, EnergiaProError};
-pub use models::{Installation, Measurement};
-pub use types::{DateInput, MeasurementScope};
+pub use models::{
+    Installation, Measurement, MeasurementBatch, MeasurementPoint, MeasurementSeries, PeriodSummary,
+};
+pub use resources::{MeasurementBatchStream, MeasurementStream};
+pub use types::{
+    Clock, DateExpression, DateInput, DateRange, DateWindow, FixedClock, Granularity,
+    MeasurementScope, SystemClock,
+};
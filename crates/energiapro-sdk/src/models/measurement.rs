@@ -1,5 +1,10 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
+use crate::errors::EnergiaProError;
+
+const TIMESTAMP_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+
 /// A single measurement row returned by the EnergiaPro API.
 ///
 /// Numeric fields are normalized by the SDK during deserialization.
@@ -24,6 +29,25 @@ pub struct Measurement {
     pub consumption_kwh: f64,
 }
 
+impl Measurement {
+    /// Parse [`Measurement::timestamp`] as a Europe/Zurich wall-clock time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timestamp is not formatted as `YYYY-MM-DD HH:MM:SS`.
+    pub fn local_timestamp(&self) -> Result<NaiveDateTime, EnergiaProError> {
+        TIMESTAMP_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(&self.timestamp, format).ok())
+            .ok_or_else(|| {
+                EnergiaProError::InvalidArgument(format!(
+                    "timestamp `{}` must be in YYYY-MM-DD HH:MM:SS format",
+                    self.timestamp
+                ))
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,5 +74,26 @@ mod tests {
         assert_eq!(data[0].index_m3, 145506.0);
         assert_eq!(data[0].consumption_m3, 77.1);
         assert_eq!(data[0].consumption_kwh, 798.45);
+        assert_eq!(
+            data[0].local_timestamp().unwrap().to_string(),
+            "2024-04-01 15:00:00"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        let measurement = Measurement {
+            client_id: 0,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: "01.04.2024 15:00".to_owned(),
+            index_m3: 0.0,
+            consumption_m3: 0.0,
+            consumption_kwh: 0.0,
+        };
+
+        assert!(matches!(
+            measurement.local_timestamp(),
+            Err(EnergiaProError::InvalidArgument(_))
+        ));
     }
 }
@@ -0,0 +1,19 @@
+use chrono::NaiveDate;
+
+use super::Measurement;
+
+/// Measurements retrieved for one window of a longer date range.
+///
+/// Batches are produced by `MeasurementsResource::stream` in chronological
+/// order. Each batch carries the inclusive bounds of its window, so a long
+/// backfill can be checkpointed after each batch and resumed from the day
+/// following [`MeasurementBatch::to`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementBatch {
+    /// First day of the window (inclusive).
+    pub from: NaiveDate,
+    /// Last day of the window (inclusive).
+    pub to: NaiveDate,
+    /// Measurements of the window, sorted by timestamp.
+    pub measurements: Vec<Measurement>,
+}
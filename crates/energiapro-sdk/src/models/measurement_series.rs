@@ -0,0 +1,562 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Europe::Zurich;
+use chrono_tz::Tz;
+
+use super::Measurement;
+use crate::errors::EnergiaProError;
+use crate::types::{DateRange, Granularity};
+
+/// A single point of a [`MeasurementSeries`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeasurementPoint {
+    /// Measurement timestamp in Europe/Zurich.
+    pub timestamp: DateTime<Tz>,
+    /// Meter index in cubic meters.
+    pub index_m3: f64,
+    /// Consumed volume in cubic meters for the interval.
+    pub consumption_m3: f64,
+    /// Consumed energy in kilowatt-hours for the interval.
+    pub consumption_kwh: f64,
+}
+
+/// Aggregated consumption of one period, as returned by [`MeasurementSeries::resample`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodSummary {
+    /// First day of the period (inclusive).
+    pub start: NaiveDate,
+    /// Last day of the period (inclusive).
+    pub end: NaiveDate,
+    /// Meter index of the last measurement of the period, in cubic meters.
+    pub index_m3: f64,
+    /// Total consumed volume in cubic meters.
+    pub consumption_m3: f64,
+    /// Total consumed energy in kilowatt-hours.
+    pub consumption_kwh: f64,
+    /// Number of measurements aggregated into the period.
+    pub count: usize,
+}
+
+/// Sorted, deduplicated measurements of a single installation, stored by column.
+///
+/// Timestamps returned by the API are Europe/Zurich wall-clock times. They are
+/// resolved to time-zone aware instants when the series is built: during the
+/// autumn daylight saving change, the repeated hour is attributed to summer
+/// time on its first occurrence and to winter time on later occurrences,
+/// whatever the order of the other measurements.
+///
+/// # Examples
+///
+/// ```no_run
+/// use energiapro::{DateRange, EnergiaPro, Granularity, MeasurementScope, MeasurementSeries};
+///
+/// # async fn demo() -> Result<(), energiapro::EnergiaProError> {
+/// let sdk = EnergiaPro::new("username", "secret_key")?;
+/// let measurements = sdk
+///     .measurements
+///     .query(
+///         "CLIENT_ID_1",
+///         "INSTALLATION_ID_1",
+///         MeasurementScope::LpnJson,
+///         DateRange::year(2024)?,
+///     )
+///     .await?;
+///
+/// let series = MeasurementSeries::from_measurements(measurements)?;
+/// for month in series.resample(Granularity::Month) {
+///     println!("{}: {:.1} kWh", month.start, month.consumption_kwh);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeasurementSeries {
+    client_id: u64,
+    installation_id: String,
+    timestamps: Vec<DateTime<Tz>>,
+    index_m3: Vec<f64>,
+    consumption_m3: Vec<f64>,
+    consumption_kwh: Vec<f64>,
+}
+
+impl MeasurementSeries {
+    /// Build a series from measurements of a single installation.
+    ///
+    /// Measurements are sorted by timestamp. When several measurements share
+    /// the same instant, the last one wins; a third occurrence of a time of
+    /// the repeated autumn hour replaces the second.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a timestamp cannot be parsed or if the measurements
+    /// belong to more than one installation.
+    pub fn from_measurements(
+        measurements: impl IntoIterator<Item = Measurement>,
+    ) -> Result<Self, EnergiaProError> {
+        let mut series = Self::default();
+        let mut locals = Vec::new();
+
+        for measurement in measurements {
+            if locals.is_empty() {
+                series.client_id = measurement.client_id;
+                series.installation_id = measurement.installation_id.clone();
+            } else if measurement.installation_id != series.installation_id {
+                return Err(EnergiaProError::InvalidArgument(format!(
+                    "series cannot mix installations `{}` and `{}`",
+                    series.installation_id, measurement.installation_id
+                )));
+            }
+
+            locals.push((measurement.local_timestamp()?, measurement));
+        }
+
+        // Sorting by wall-clock time first makes the resolution of the
+        // repeated autumn hour independent of the order of the input.
+        locals.sort_by_key(|(local, _)| *local);
+        let mut resolver = LocalResolver::default();
+        let points = locals
+            .into_iter()
+            .map(|(local, measurement)| MeasurementPoint {
+                timestamp: resolver.resolve(local),
+                index_m3: measurement.index_m3,
+                consumption_m3: measurement.consumption_m3,
+                consumption_kwh: measurement.consumption_kwh,
+            })
+            .collect();
+
+        series.extend_sorted(points);
+        Ok(series)
+    }
+
+    /// Numeric client identifier of the series.
+    pub fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    /// Installation identifier of the series, empty if the series was built
+    /// without any measurement.
+    pub fn installation_id(&self) -> &str {
+        &self.installation_id
+    }
+
+    /// Number of points in the series.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Return `true` if the series has no point.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Timestamps of the series, in ascending order.
+    pub fn timestamps(&self) -> &[DateTime<Tz>] {
+        &self.timestamps
+    }
+
+    /// Meter indexes in cubic meters, aligned with [`MeasurementSeries::timestamps`].
+    pub fn index_m3(&self) -> &[f64] {
+        &self.index_m3
+    }
+
+    /// Consumed volumes in cubic meters, aligned with [`MeasurementSeries::timestamps`].
+    pub fn consumption_m3(&self) -> &[f64] {
+        &self.consumption_m3
+    }
+
+    /// Consumed energy in kilowatt-hours, aligned with [`MeasurementSeries::timestamps`].
+    pub fn consumption_kwh(&self) -> &[f64] {
+        &self.consumption_kwh
+    }
+
+    /// Return the point at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<MeasurementPoint> {
+        Some(MeasurementPoint {
+            timestamp: *self.timestamps.get(index)?,
+            index_m3: self.index_m3[index],
+            consumption_m3: self.consumption_m3[index],
+            consumption_kwh: self.consumption_kwh[index],
+        })
+    }
+
+    /// Return the earliest point of the series, if any.
+    pub fn first(&self) -> Option<MeasurementPoint> {
+        self.get(0)
+    }
+
+    /// Return the latest point of the series, if any.
+    pub fn last(&self) -> Option<MeasurementPoint> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// Iterate over the points of the series in chronological order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = MeasurementPoint> + '_ {
+        (0..self.len()).map(|index| MeasurementPoint {
+            timestamp: self.timestamps[index],
+            index_m3: self.index_m3[index],
+            consumption_m3: self.consumption_m3[index],
+            consumption_kwh: self.consumption_kwh[index],
+        })
+    }
+
+    /// Return the points whose local date falls within `range`.
+    pub fn slice(&self, range: DateRange) -> Self {
+        let start = self.timestamps.partition_point(|timestamp| {
+            range
+                .start()
+                .is_some_and(|start| timestamp.date_naive() < start)
+        });
+        let end = self.timestamps.partition_point(|timestamp| {
+            range.end().is_none_or(|end| timestamp.date_naive() <= end)
+        });
+        let end = end.max(start);
+
+        Self {
+            client_id: self.client_id,
+            installation_id: self.installation_id.clone(),
+            timestamps: self.timestamps[start..end].to_vec(),
+            index_m3: self.index_m3[start..end].to_vec(),
+            consumption_m3: self.consumption_m3[start..end].to_vec(),
+            consumption_kwh: self.consumption_kwh[start..end].to_vec(),
+        }
+    }
+
+    /// Merge another series of the same installation into this one.
+    ///
+    /// Points of `other` replace points of `self` that share their timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if both series are non-empty and belong to different
+    /// installations.
+    pub fn merge(mut self, other: Self) -> Result<Self, EnergiaProError> {
+        if other.is_empty() {
+            return Ok(self);
+        }
+
+        if self.is_empty() {
+            return Ok(other);
+        }
+
+        if self.installation_id != other.installation_id {
+            return Err(EnergiaProError::InvalidArgument(format!(
+                "cannot merge series of installations `{}` and `{}`",
+                self.installation_id, other.installation_id
+            )));
+        }
+
+        let mut points = self.iter().collect::<Vec<_>>();
+        points.extend(other.iter());
+        self.extend_sorted(points);
+        Ok(self)
+    }
+
+    /// Aggregate the series into calendar periods.
+    ///
+    /// Consumption is summed over each period and the index of the last
+    /// measurement of the period is kept. Each point belongs to the period
+    /// containing its local date; periods without any point are omitted.
+    pub fn resample(&self, granularity: Granularity) -> Vec<PeriodSummary> {
+        let mut periods: Vec<PeriodSummary> = Vec::new();
+
+        for point in self.iter() {
+            let (start, end) = granularity.period(point.timestamp.date_naive());
+
+            match periods.last_mut() {
+                Some(period) if period.start == start => {
+                    period.index_m3 = point.index_m3;
+                    period.consumption_m3 += point.consumption_m3;
+                    period.consumption_kwh += point.consumption_kwh;
+                    period.count += 1;
+                }
+                _ => periods.push(PeriodSummary {
+                    start,
+                    end,
+                    index_m3: point.index_m3,
+                    consumption_m3: point.consumption_m3,
+                    consumption_kwh: point.consumption_kwh,
+                    count: 1,
+                }),
+            }
+        }
+
+        periods
+    }
+
+    /// Convert the series back into API measurements with local timestamps.
+    pub fn to_measurements(&self) -> Vec<Measurement> {
+        self.iter()
+            .map(|point| Measurement {
+                client_id: self.client_id,
+                installation_id: self.installation_id.clone(),
+                timestamp: point.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                index_m3: point.index_m3,
+                consumption_m3: point.consumption_m3,
+                consumption_kwh: point.consumption_kwh,
+            })
+            .collect()
+    }
+
+    /// Replace the columns with `points`, sorted and deduplicated by timestamp.
+    fn extend_sorted(&mut self, mut points: Vec<MeasurementPoint>) {
+        points.sort_by_key(|point| point.timestamp);
+
+        let mut deduplicated: Vec<MeasurementPoint> = Vec::with_capacity(points.len());
+        for point in points {
+            match deduplicated.last_mut() {
+                Some(last) if last.timestamp == point.timestamp => *last = point,
+                _ => deduplicated.push(point),
+            }
+        }
+
+        self.timestamps = deduplicated.iter().map(|point| point.timestamp).collect();
+        self.index_m3 = deduplicated.iter().map(|point| point.index_m3).collect();
+        self.consumption_m3 = deduplicated
+            .iter()
+            .map(|point| point.consumption_m3)
+            .collect();
+        self.consumption_kwh = deduplicated
+            .iter()
+            .map(|point| point.consumption_kwh)
+            .collect();
+    }
+}
+
+impl TryFrom<Vec<Measurement>> for MeasurementSeries {
+    type Error = EnergiaProError;
+
+    fn try_from(measurements: Vec<Measurement>) -> Result<Self, Self::Error> {
+        Self::from_measurements(measurements)
+    }
+}
+
+impl<'a> IntoIterator for &'a MeasurementSeries {
+    type Item = MeasurementPoint;
+    type IntoIter = Box<dyn ExactSizeIterator<Item = MeasurementPoint> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+/// Resolve a Europe/Zurich wall-clock time to an instant.
+///
+/// Ambiguous times resolve to summer time on their first `occurrence` (0)
+/// and to winter time on later ones. Times skipped by the spring change are
+/// shifted forward by one hour.
+pub(crate) fn resolve_local(local: NaiveDateTime, occurrence: usize) -> DateTime<Tz> {
+    match Zurich.from_local_datetime(&local) {
+        chrono::LocalResult::Single(timestamp) => timestamp,
+        chrono::LocalResult::Ambiguous(earliest, latest) => {
+            if occurrence == 0 {
+                earliest
+            } else {
+                latest
+            }
+        }
+        chrono::LocalResult::None => Zurich
+            .from_local_datetime(&(local + TimeDelta::hours(1)))
+            .earliest()
+            .unwrap_or_else(|| Zurich.from_utc_datetime(&local)),
+    }
+}
+
+/// Resolves wall-clock times with [`resolve_local`], counting the occurrences
+/// of each time seen so far.
+#[derive(Debug, Default)]
+pub(crate) struct LocalResolver {
+    occurrences: HashMap<NaiveDateTime, usize>,
+}
+
+impl LocalResolver {
+    /// Resolve `local` as one more occurrence of that wall-clock time.
+    pub(crate) fn resolve(&mut self, local: NaiveDateTime) -> DateTime<Tz> {
+        let occurrence = self.occurrences.entry(local).or_default();
+        let instant = resolve_local(local, *occurrence);
+        *occurrence += 1;
+        instant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(timestamp: &str, index_m3: f64, consumption_m3: f64) -> Measurement {
+        Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: timestamp.to_owned(),
+            index_m3,
+            consumption_m3,
+            consumption_kwh: consumption_m3 * 10.0,
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn sorts_and_deduplicates_measurements() {
+        let series = MeasurementSeries::from_measurements(vec![
+            measurement("2024-04-02 00:00:00", 12.0, 2.0),
+            measurement("2024-04-01 00:00:00", 10.0, 1.0),
+            measurement("2024-04-02 00:00:00", 13.0, 3.0),
+        ])
+        .unwrap();
+
+        assert_eq!(series.len(), 2);
+        assert_eq!(series.installation_id(), "INSTALLATION_ID_1");
+        assert_eq!(series.index_m3(), &[10.0, 13.0]);
+        assert_eq!(series.consumption_m3(), &[1.0, 3.0]);
+        assert_eq!(series.consumption_kwh(), &[10.0, 30.0]);
+        assert_eq!(series.last().unwrap().index_m3, 13.0);
+    }
+
+    #[test]
+    fn rejects_mixed_installations() {
+        let mut other = measurement("2024-04-01 01:00:00", 10.0, 1.0);
+        other.installation_id = "INSTALLATION_ID_2".to_owned();
+
+        let err = MeasurementSeries::from_measurements(vec![
+            measurement("2024-04-01 00:00:00", 10.0, 1.0),
+            other,
+        ]);
+        assert!(matches!(err, Err(EnergiaProError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn keeps_both_occurrences_of_the_repeated_autumn_hour() {
+        let series = MeasurementSeries::from_measurements(vec![
+            measurement("2024-10-27 01:00:00", 1.0, 1.0),
+            measurement("2024-10-27 02:00:00", 2.0, 1.0),
+            measurement("2024-10-27 02:00:00", 3.0, 1.0),
+            measurement("2024-10-27 03:00:00", 4.0, 1.0),
+        ])
+        .unwrap();
+
+        assert_eq!(series.len(), 4);
+        assert_eq!(
+            series.timestamps()[2] - series.timestamps()[1],
+            TimeDelta::hours(1)
+        );
+        assert_eq!(series.index_m3(), &[1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn resolves_the_repeated_autumn_hour_whatever_the_input_order() {
+        let sorted = MeasurementSeries::from_measurements(vec![
+            measurement("2024-10-27 01:00:00", 1.0, 1.0),
+            measurement("2024-10-27 02:00:00", 2.0, 1.0),
+            measurement("2024-10-27 02:00:00", 3.0, 1.0),
+            measurement("2024-10-27 03:00:00", 4.0, 1.0),
+        ])
+        .unwrap();
+        let shuffled = MeasurementSeries::from_measurements(vec![
+            measurement("2024-10-27 03:00:00", 4.0, 1.0),
+            measurement("2024-10-27 02:00:00", 2.0, 1.0),
+            measurement("2024-10-27 01:00:00", 1.0, 1.0),
+            measurement("2024-10-27 02:00:00", 3.0, 1.0),
+        ])
+        .unwrap();
+
+        assert_eq!(shuffled, sorted);
+    }
+
+    #[test]
+    fn deduplicates_a_repeated_row_of_the_autumn_hour() {
+        let series = MeasurementSeries::from_measurements(vec![
+            measurement("2024-10-27 02:00:00", 2.0, 1.0),
+            measurement("2024-10-27 02:00:00", 3.0, 1.0),
+            measurement("2024-10-27 02:00:00", 3.5, 1.0),
+            measurement("2024-10-27 03:00:00", 4.0, 1.0),
+        ])
+        .unwrap();
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.index_m3(), &[2.0, 3.5, 4.0]);
+        assert_eq!(
+            series.timestamps()[1] - series.timestamps()[0],
+            TimeDelta::hours(1)
+        );
+    }
+
+    #[test]
+    fn resamples_on_local_calendar_boundaries() {
+        let series = MeasurementSeries::from_measurements(vec![
+            measurement("2024-01-31 23:00:00", 100.0, 1.0),
+            measurement("2024-02-01 00:00:00", 102.0, 2.0),
+            measurement("2024-02-29 23:00:00", 105.0, 3.0),
+            measurement("2024-03-01 00:00:00", 109.0, 4.0),
+        ])
+        .unwrap();
+
+        let months = series.resample(Granularity::Month);
+        assert_eq!(months.len(), 3);
+        assert_eq!(months[1].start, date("2024-02-01"));
+        assert_eq!(months[1].end, date("2024-02-29"));
+        assert_eq!(months[1].consumption_m3, 5.0);
+        assert_eq!(months[1].consumption_kwh, 50.0);
+        assert_eq!(months[1].index_m3, 105.0);
+        assert_eq!(months[1].count, 2);
+
+        let seasons = series.resample(Granularity::HeatingSeason);
+        assert_eq!(seasons.len(), 1);
+        assert_eq!(seasons[0].start, date("2023-10-01"));
+        assert_eq!(seasons[0].consumption_m3, 10.0);
+    }
+
+    #[test]
+    fn slices_by_local_date() {
+        let series = MeasurementSeries::from_measurements(vec![
+            measurement("2024-04-01 23:00:00", 1.0, 1.0),
+            measurement("2024-04-02 00:00:00", 2.0, 1.0),
+            measurement("2024-04-02 23:00:00", 3.0, 1.0),
+            measurement("2024-04-03 00:00:00", 4.0, 1.0),
+        ])
+        .unwrap();
+
+        let day = series.slice(DateRange::day("2024-04-02").unwrap());
+        assert_eq!(day.index_m3(), &[2.0, 3.0]);
+        assert_eq!(series.slice(DateRange::all()).len(), 4);
+        assert!(
+            series
+                .slice(DateRange::since("2024-05-01").unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn merges_series_with_later_values_winning() {
+        let first = MeasurementSeries::from_measurements(vec![
+            measurement("2024-04-01 00:00:00", 1.0, 1.0),
+            measurement("2024-04-02 00:00:00", 2.0, 1.0),
+        ])
+        .unwrap();
+        let second = MeasurementSeries::from_measurements(vec![
+            measurement("2024-04-02 00:00:00", 20.0, 1.0),
+            measurement("2024-04-03 00:00:00", 30.0, 1.0),
+        ])
+        .unwrap();
+
+        let merged = first.merge(second).unwrap();
+        assert_eq!(merged.index_m3(), &[1.0, 20.0, 30.0]);
+
+        let merged = MeasurementSeries::default().merge(merged).unwrap();
+        assert_eq!(merged.installation_id(), "INSTALLATION_ID_1");
+    }
+
+    #[test]
+    fn round_trips_to_measurements() {
+        let measurements = vec![
+            measurement("2024-04-01 00:00:00", 1.0, 1.0),
+            measurement("2024-04-01 01:00:00", 2.0, 1.0),
+        ];
+        let series = MeasurementSeries::try_from(measurements.clone()).unwrap();
+
+        assert_eq!(series.to_measurements(), measurements);
+        assert_eq!((&series).into_iter().count(), 2);
+    }
+}
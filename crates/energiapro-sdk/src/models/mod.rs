@@ -1,5 +1,10 @@
 mod installation;
 mod measurement;
+mod measurement_batch;
+mod measurement_series;
 
 pub use installation::Installation;
 pub use measurement::Measurement;
+pub use measurement_batch::MeasurementBatch;
+pub(crate) use measurement_series::{LocalResolver, resolve_local};
+pub use measurement_series::{MeasurementPoint, MeasurementSeries, PeriodSummary};
@@ -1,12 +1,12 @@
 use chrono::NaiveDate;
 use reqwest::header::AUTHORIZATION;
-use serde_json::Value;
 
 use crate::errors::EnergiaProError;
-use crate::responses::MeasurementsResponse;
-use crate::types::{DateInput, MeasurementScope};
+use crate::models::Measurement;
+use crate::responses::parse_measurement_row;
+use crate::types::{DateInput, DateRange, MeasurementScope, parse_date_argument};
 
-use super::Request;
+use super::StreamingRequest;
 
 const MEASUREMENTS_ENDPOINT: &str = "index.php";
 
@@ -58,6 +58,13 @@ impl MeasurementsRequest {
         self
     }
 
+    /// Set both date filters from a [`DateRange`]. Unbounded sides are left unset.
+    pub(crate) fn range(mut self, range: DateRange) -> Self {
+        self.from = range.start().map(DateInput::into_date_string);
+        self.to = range.end().map(DateInput::into_date_string);
+        self
+    }
+
     pub(crate) fn validate(&self) -> Result<(), EnergiaProError> {
         if self.scope.as_str().trim().is_empty() {
             return Err(EnergiaProError::InvalidArgument(
@@ -77,27 +84,34 @@ impl MeasurementsRequest {
             ));
         }
 
+        if let (Some(from), Some(to)) = self.date_bounds()?
+            && from > to
+        {
+            return Err(EnergiaProError::InvalidArgument(
+                "from must be less than or equal to to".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parse the start and end date filters, if set.
+    pub(crate) fn date_bounds(
+        &self,
+    ) -> Result<(Option<NaiveDate>, Option<NaiveDate>), EnergiaProError> {
         let from = self
             .from
             .as_deref()
-            .map(|from| validate_date_argument("from", from))
+            .map(|from| parse_date_argument("from", from))
             .transpose()?;
 
         let to = self
             .to
             .as_deref()
-            .map(|to| validate_date_argument("to", to))
+            .map(|to| parse_date_argument("to", to))
             .transpose()?;
 
-        if let (Some(from), Some(to)) = (from, to)
-            && from > to
-        {
-            return Err(EnergiaProError::InvalidArgument(
-                "from must be less than or equal to to".to_owned(),
-            ));
-        }
-
-        Ok(())
+        Ok((from, to))
     }
 
     pub(crate) fn form_data(&self) -> Vec<(&'static str, String)> {
@@ -123,22 +137,8 @@ impl MeasurementsRequest {
     }
 }
 
-fn validate_date_argument(field: &str, value: &str) -> Result<NaiveDate, EnergiaProError> {
-    let parsed = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
-        EnergiaProError::InvalidArgument(format!("{field} must be in YYYY-MM-DD format"))
-    })?;
-
-    if parsed.format("%Y-%m-%d").to_string() != value {
-        return Err(EnergiaProError::InvalidArgument(format!(
-            "{field} must be in YYYY-MM-DD format"
-        )));
-    }
-
-    Ok(parsed)
-}
-
-impl Request for MeasurementsRequest {
-    type Response = MeasurementsResponse;
+impl StreamingRequest for MeasurementsRequest {
+    type Row = Measurement;
 
     fn validate_request(&self) -> Result<(), EnergiaProError> {
         self.validate()
@@ -159,8 +159,8 @@ impl Request for MeasurementsRequest {
             .header(AUTHORIZATION, format!("Bearer {token}"))
     }
 
-    fn parse_response(&self, payload: Value) -> Result<Self::Response, EnergiaProError> {
-        Ok(MeasurementsResponse::new(payload, self.installation_id()))
+    fn parse_row(&self, row: &[u8]) -> Result<Self::Row, EnergiaProError> {
+        parse_measurement_row(row, self.installation_id())
     }
 }
 
@@ -260,6 +260,28 @@ mod tests {
         assert!(err.is_ok());
     }
 
+    #[test]
+    fn builds_measurements_form_data_from_range() {
+        let request = MeasurementsRequest::new("CLIENT_ID_1", "INSTALLATION_ID_1")
+            .range(DateRange::month(2024, 2).unwrap());
+
+        assert_eq!(
+            request.form_data(),
+            vec![
+                ("scope", "lpn-json".to_owned()),
+                ("client_id", "CLIENT_ID_1".to_owned()),
+                ("num_inst", "INSTALLATION_ID_1".to_owned()),
+                ("date_debut", "2024-02-01".to_owned()),
+                ("date_fin", "2024-02-29".to_owned()),
+            ]
+        );
+
+        let request = MeasurementsRequest::new("CLIENT_ID_1", "INSTALLATION_ID_1")
+            .range(DateRange::since("2024-02-01").unwrap());
+
+        assert_eq!(request.form_data().len(), 4);
+    }
+
     #[test]
     fn accepts_known_scope_variant() {
         let request = MeasurementsRequest::new("CLIENT_ID_1", "INSTALLATION_ID_1")
@@ -34,6 +34,31 @@ pub(crate) trait Request {
     fn parse_response(&self, payload: Value) -> Result<Self::Response, EnergiaProError>;
 }
 
+/// A trait representing a request whose response is streamed row by row.
+///
+/// Some endpoints return large top-level JSON arrays. Instead of parsing the
+/// whole payload at once, the client splits the response body into rows as it
+/// is received and hands each raw row to [`StreamingRequest::parse_row`].
+pub(crate) trait StreamingRequest {
+    /// The type of each row produced by the response stream.
+    type Row;
+
+    /// Validate the request parameters before sending it to the API
+    fn validate_request(&self) -> Result<(), EnergiaProError>;
+
+    /// Convert the request into a `reqwest::RequestBuilder` that can be sent
+    /// to the API.
+    fn to_request_builder(
+        &self,
+        http_client: &reqwest::Client,
+        base_url: &str,
+        token: &str,
+    ) -> reqwest::RequestBuilder;
+
+    /// Parse a single raw JSON row from the response payload.
+    fn parse_row(&self, row: &[u8]) -> Result<Self::Row, EnergiaProError>;
+}
+
 pub(crate) use authenticate::AuthenticateRequest;
 
 // Re-exports
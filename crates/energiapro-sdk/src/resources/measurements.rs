@@ -1,11 +1,25 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{StreamExt, TryStreamExt, future};
 
 use crate::client::Client;
-use crate::errors::EnergiaProError;
-use crate::models::Measurement;
+use crate::errors::{ApiErrorCode, EnergiaProError};
+use crate::models::{Measurement, MeasurementBatch};
 use crate::requests::MeasurementsRequest;
-use crate::responses::Response;
-use crate::types::{DateInput, MeasurementScope};
+use crate::types::{DateInput, DateRange, DateWindow, MeasurementScope};
+
+/// Stream of measurements parsed one at a time from the API response.
+pub type MeasurementStream = BoxStream<'static, Result<Measurement, EnergiaProError>>;
+
+/// Stream of measurement batches, one per window of a date range.
+pub type MeasurementBatchStream = BoxStream<'static, Result<MeasurementBatch, EnergiaProError>>;
+
+/// Delay before the first retry of a failed window request, doubled on each attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
 
 /// Resource for measurement-related API operations.
 #[derive(Clone)]
@@ -18,7 +32,65 @@ impl MeasurementsResource {
         Self { client }
     }
 
+    /// Retrieve measurements for a given installation and date range.
+    ///
+    /// This is the main entry point for fetching measurements. The
+    /// [`DateRange`] decides which rows are returned, for example
+    /// [`DateRange::all`], [`DateRange::month`] or [`DateRange::last_n_days`].
+    ///
+    /// Ranges with both a start and an end are split into windows following
+    /// [`ClientOptions::date_range_window`](crate::ClientOptions::date_range_window),
+    /// which are fetched concurrently up to
+    /// [`ClientOptions::max_concurrent_requests`](crate::ClientOptions::max_concurrent_requests)
+    /// at a time. A failed window is retried on its own, up to
+    /// [`ClientOptions::max_retries`](crate::ClientOptions::max_retries) times,
+    /// without refetching the rest of the range. Windows without data are
+    /// treated as empty. Rows returned by two consecutive windows are
+    /// deduplicated and the result is sorted chronologically. Both occurrences
+    /// of the local hour repeated when daylight saving time ends are kept, as
+    /// with open-ended ranges.
+    ///
+    /// Open-ended ranges are fetched with a single request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `client_id` or `installation_id` is empty or contains only whitespace.
+    /// - authentication fails or a token cannot be obtained/refreshed.
+    /// - the HTTP request fails.
+    /// - the API returns a non-success status or error payload.
+    /// - the response payload cannot be parsed into measurements.
+    pub async fn query(
+        &self,
+        client_id: impl AsRef<str>,
+        installation_id: impl AsRef<str>,
+        scope: impl Into<MeasurementScope>,
+        range: DateRange,
+    ) -> Result<Vec<Measurement>, EnergiaProError> {
+        if range.start().is_none() || range.end().is_none() {
+            let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
+                .scope(scope)
+                .range(range);
+
+            return self.fetch(request).await;
+        }
+
+        let window = self.client.date_range_window();
+        let batches: Vec<MeasurementBatch> = self
+            .stream(client_id, installation_id, scope, range, window)?
+            .try_collect()
+            .await?;
+
+        Ok(sort_measurements(
+            batches
+                .into_iter()
+                .flat_map(|batch| batch.measurements)
+                .collect(),
+        ))
+    }
+
     /// Retrieve measurements for a given installation and optional date range.
+    #[deprecated(note = "use `query` with a `DateRange` instead")]
     pub async fn get(
         &self,
         client_id: impl AsRef<str>,
@@ -27,45 +99,65 @@ impl MeasurementsResource {
         from: Option<impl DateInput>,
         to: Option<impl DateInput>,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let request =
-            MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref()).scope(scope);
-
-        let request = if let Some(from) = from {
-            request.from(from)
-        } else {
-            request
+        let range = match (from, to) {
+            (Some(from), Some(to)) => DateRange::between(from, to)?,
+            (Some(from), None) => DateRange::since(from)?,
+            (None, Some(to)) => DateRange::until(to)?,
+            (None, None) => DateRange::all(),
         };
 
-        let request = if let Some(to) = to {
-            request.to(to)
-        } else {
-            request
-        };
+        self.query(client_id, installation_id, scope, range).await
+    }
 
-        self.client.send(&request).await?.into_model()
+    /// Stream measurements for a given installation and date range.
+    ///
+    /// Rows are deserialized one at a time while the response body is being
+    /// received, so memory use stays flat regardless of the payload size. This
+    /// is the preferred way to process large datasets such as the full history
+    /// of an installation with hourly data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments are invalid, authentication fails, or
+    /// the API responds with an error before the first row. Errors occurring
+    /// while the body is received are yielded by the stream itself.
+    pub async fn stream_rows(
+        &self,
+        client_id: impl AsRef<str>,
+        installation_id: impl AsRef<str>,
+        scope: impl Into<MeasurementScope>,
+        range: DateRange,
+    ) -> Result<MeasurementStream, EnergiaProError> {
+        let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
+            .scope(scope)
+            .range(range);
+
+        self.client.send_streaming(request).await
     }
 
     /// Retrieve all measurements for a given installation.
     ///
+    /// Shorthand for [`MeasurementsResource::query`] with [`DateRange::all`].
+    ///
     /// # Notes
     ///
     /// This method retrieves all measurements for the specified installation,
     /// which may result in a large amount of data being returned. Consider
-    /// using the other methods in this resource to filter measurements by date
-    /// or date range if you do not need the entire dataset.
+    /// using a narrower [`DateRange`] or [`MeasurementsResource::stream_rows`]
+    /// if you do not need the entire dataset at once.
     pub async fn all(
         &self,
         client_id: impl AsRef<str>,
         installation_id: impl AsRef<str>,
         scope: impl Into<MeasurementScope>,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let request =
-            MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref()).scope(scope);
-
-        self.client.send(&request).await?.into_model()
+        self.query(client_id, installation_id, scope, DateRange::all())
+            .await
     }
 
     /// Retrieve measurements for a given installation and date.
+    ///
+    /// Shorthand for [`MeasurementsResource::query`] with [`DateRange::day`].
     pub async fn for_date(
         &self,
         client_id: impl AsRef<str>,
@@ -73,17 +165,13 @@ impl MeasurementsResource {
         scope: impl Into<MeasurementScope>,
         date: impl DateInput,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let date = date.into_date_string();
-
-        let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
-            .scope(scope)
-            .from(date.clone())
-            .to(date);
-
-        self.client.send(&request).await?.into_model()
+        self.query(client_id, installation_id, scope, DateRange::day(date)?)
+            .await
     }
 
-    /// Retrieve measurements for an installation and optional date range.
+    /// Retrieve measurements for an installation and date range.
+    ///
+    /// Shorthand for [`MeasurementsResource::query`] with [`DateRange::between`].
     pub async fn for_date_range(
         &self,
         client_id: impl AsRef<str>,
@@ -92,15 +180,13 @@ impl MeasurementsResource {
         from: impl DateInput,
         to: impl DateInput,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
-            .scope(scope)
-            .from(from)
-            .to(to);
-
-        self.client.send(&request).await?.into_model()
+        let range = DateRange::between(from, to)?;
+        self.query(client_id, installation_id, scope, range).await
     }
 
     /// Retrieve measurements for an installation since a given date.
+    ///
+    /// Shorthand for [`MeasurementsResource::query`] with [`DateRange::since`].
     pub async fn since(
         &self,
         client_id: impl AsRef<str>,
@@ -108,14 +194,13 @@ impl MeasurementsResource {
         scope: impl Into<MeasurementScope>,
         date: impl DateInput,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
-            .scope(scope)
-            .from(date);
-
-        self.client.send(&request).await?.into_model()
+        self.query(client_id, installation_id, scope, DateRange::since(date)?)
+            .await
     }
 
     /// Retrieve measurements for an installation up to a given date.
+    ///
+    /// Shorthand for [`MeasurementsResource::query`] with [`DateRange::until`].
     pub async fn up_to(
         &self,
         client_id: impl AsRef<str>,
@@ -123,10 +208,348 @@ impl MeasurementsResource {
         scope: impl Into<MeasurementScope>,
         date: impl DateInput,
     ) -> Result<Vec<Measurement>, EnergiaProError> {
-        let request = MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref())
-            .scope(scope)
-            .to(date);
+        self.query(client_id, installation_id, scope, DateRange::until(date)?)
+            .await
+    }
+
+    /// Stream measurements for an installation and date range, one window at a time.
+    ///
+    /// The range is split into windows of the given size and each window
+    /// produces one [`MeasurementBatch`]. Windows are fetched concurrently up
+    /// to [`ClientOptions::max_concurrent_requests`](crate::ClientOptions::max_concurrent_requests)
+    /// at a time, but batches are always yielded in chronological order. Failed
+    /// windows are retried on their own and windows without data yield empty
+    /// batches. A range without an end stops at today's date according to
+    /// [`ClientOptions::with_clock`](crate::ClientOptions::with_clock).
+    ///
+    /// Rows that were already part of the previous non-empty batch are
+    /// dropped, so windows overlapping at their boundary do not produce
+    /// duplicates. Each batch is sorted, and a row returned late by the API
+    /// is kept in the batch that returned it rather than dropped. To
+    /// resume an interrupted backfill, start a new stream on the day following
+    /// the last processed [`MeasurementBatch::to`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments are invalid or if `range` has no
+    /// start. Request failures are yielded by the stream for the affected
+    /// window.
+    pub fn stream(
+        &self,
+        client_id: impl AsRef<str>,
+        installation_id: impl AsRef<str>,
+        scope: impl Into<MeasurementScope>,
+        range: DateRange,
+        window: DateWindow,
+    ) -> Result<MeasurementBatchStream, EnergiaProError> {
+        let request =
+            MeasurementsRequest::new(client_id.as_ref(), installation_id.as_ref()).scope(scope);
+        request.validate()?;
+
+        let from = range.start().ok_or_else(|| {
+            EnergiaProError::InvalidArgument(
+                "range must have a start date to be split into windows".to_owned(),
+            )
+        })?;
+        let to = range.end().unwrap_or_else(|| self.client.today());
+
+        let resource = self.clone();
+        let batches = stream::iter(window.split(from, to))
+            .map(move |(from, to)| {
+                let resource = resource.clone();
+                let request = request.clone().from(from).to(to);
+                async move { resource.fetch_batch(request, from, to).await }
+            })
+            .buffered(self.client.max_concurrent_requests())
+            .scan(SeenRows::new(), |seen, batch| {
+                future::ready(Some(batch.map(|batch| drop_already_seen(batch, seen))))
+            });
+
+        Ok(batches.boxed())
+    }
+
+    /// Send a measurements request and collect the streamed rows.
+    async fn fetch(
+        &self,
+        request: MeasurementsRequest,
+    ) -> Result<Vec<Measurement>, EnergiaProError> {
+        self.client
+            .send_streaming(request)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    /// Fetch a single window, retrying transient failures.
+    async fn fetch_batch(
+        &self,
+        request: MeasurementsRequest,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<MeasurementBatch, EnergiaProError> {
+        let mut attempt = 0;
+
+        loop {
+            let measurements = match self.fetch(request.clone()).await {
+                Ok(measurements) => measurements,
+                Err(EnergiaProError::Api {
+                    code: ApiErrorCode::NoLpnData,
+                    ..
+                }) => Vec::new(),
+                Err(error) if error.is_transient() && attempt < self.client.max_retries() => {
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.saturating_pow(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            return Ok(MeasurementBatch {
+                from,
+                to,
+                measurements: sort_measurements(measurements),
+            });
+        }
+    }
+}
+
+/// Sort measurements chronologically.
+///
+/// The sort is stable and keeps rows sharing a timestamp: the local hour
+/// repeated when daylight saving time ends appears twice, in API order.
+fn sort_measurements(mut measurements: Vec<Measurement>) -> Vec<Measurement> {
+    measurements.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    measurements
+}
+
+/// Number of rows per timestamp in the previous non-empty batch.
+type SeenRows = HashMap<String, usize>;
+
+/// Drop rows already returned by the previous non-empty batch.
+///
+/// Only the overlap between consecutive windows is compared: a row is dropped
+/// when the previous batch returned its timestamp at least as many times, so
+/// both occurrences of the repeated autumn hour are kept and an older row that
+/// no previous window returned is not lost.
+fn drop_already_seen(mut batch: MeasurementBatch, seen: &mut SeenRows) -> MeasurementBatch {
+    if batch.measurements.is_empty() {
+        return batch;
+    }
+
+    let mut current = SeenRows::new();
+    for measurement in &batch.measurements {
+        *current.entry(measurement.timestamp.clone()).or_default() += 1;
+    }
+
+    batch.measurements.retain(
+        |measurement| match seen.get_mut(measurement.timestamp.as_str()) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                false
+            }
+            _ => true,
+        },
+    );
+    *seen = current;
+
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientOptions;
+    use crate::client::mock::{MockResponse, MockServer};
+
+    fn measurement(timestamp: &str) -> Measurement {
+        Measurement {
+            client_id: 1,
+            installation_id: "INSTALLATION_ID_1".to_owned(),
+            timestamp: timestamp.to_owned(),
+            index_m3: 0.0,
+            consumption_m3: 0.0,
+            consumption_kwh: 0.0,
+        }
+    }
+
+    fn batch(from: &str, to: &str, timestamps: &[&str]) -> MeasurementBatch {
+        MeasurementBatch {
+            from: NaiveDate::parse_from_str(from, "%Y-%m-%d").unwrap(),
+            to: NaiveDate::parse_from_str(to, "%Y-%m-%d").unwrap(),
+            measurements: timestamps.iter().map(|t| measurement(t)).collect(),
+        }
+    }
+
+    fn timestamps(measurements: &[Measurement]) -> Vec<&str> {
+        measurements.iter().map(|m| m.timestamp.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_measurements_and_keeps_repeated_timestamps() {
+        let sorted = sort_measurements(vec![
+            measurement("2024-10-27 03:00:00"),
+            measurement("2024-10-27 02:00:00"),
+            measurement("2024-10-27 01:00:00"),
+            measurement("2024-10-27 02:00:00"),
+        ]);
+
+        assert_eq!(
+            timestamps(&sorted),
+            vec![
+                "2024-10-27 01:00:00",
+                "2024-10-27 02:00:00",
+                "2024-10-27 02:00:00",
+                "2024-10-27 03:00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_rows_already_seen_in_previous_batches() {
+        let mut seen = SeenRows::new();
+
+        let first = drop_already_seen(
+            batch(
+                "2024-01-01",
+                "2024-01-31",
+                &["2024-01-31 23:00:00", "2024-02-01 00:00:00"],
+            ),
+            &mut seen,
+        );
+        let empty = drop_already_seen(batch("2024-02-01", "2024-02-29", &[]), &mut seen);
+        let second = drop_already_seen(
+            batch(
+                "2024-03-01",
+                "2024-03-31",
+                &["2024-02-01 00:00:00", "2024-03-01 00:00:00"],
+            ),
+            &mut seen,
+        );
+
+        assert_eq!(first.measurements.len(), 2);
+        assert!(empty.measurements.is_empty());
+        assert_eq!(
+            timestamps(&second.measurements),
+            vec!["2024-03-01 00:00:00"]
+        );
+        assert_eq!(second.from.to_string(), "2024-03-01");
+    }
+
+    #[test]
+    fn keeps_the_repeated_autumn_hour_across_windows() {
+        let mut seen = SeenRows::new();
+
+        let saturday = drop_already_seen(
+            batch(
+                "2024-10-26",
+                "2024-10-26",
+                &["2024-10-26 23:00:00", "2024-10-27 00:00:00"],
+            ),
+            &mut seen,
+        );
+        let sunday = drop_already_seen(
+            batch(
+                "2024-10-27",
+                "2024-10-27",
+                &[
+                    "2024-10-27 00:00:00",
+                    "2024-10-27 01:00:00",
+                    "2024-10-27 02:00:00",
+                    "2024-10-27 02:00:00",
+                ],
+            ),
+            &mut seen,
+        );
+        let overlap = drop_already_seen(
+            batch(
+                "2024-10-28",
+                "2024-10-28",
+                &[
+                    "2024-10-27 02:00:00",
+                    "2024-10-27 02:00:00",
+                    "2024-10-27 03:00:00",
+                ],
+            ),
+            &mut seen,
+        );
+
+        assert_eq!(saturday.measurements.len(), 2);
+        assert_eq!(
+            timestamps(&sunday.measurements),
+            vec![
+                "2024-10-27 01:00:00",
+                "2024-10-27 02:00:00",
+                "2024-10-27 02:00:00"
+            ]
+        );
+        assert_eq!(
+            timestamps(&overlap.measurements),
+            vec!["2024-10-27 03:00:00"]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_a_window_after_a_transient_error() {
+        let server = MockServer::start([
+            MockResponse::Json(503, r#"{"message":"unavailable"}"#),
+            MockResponse::Json(
+                200,
+                r#"[{"client_id":"1","timestamp":"2024-01-01 00:00:00","index_m3":"1","consumption_m3":"1","consumption_kwh":"10"}]"#,
+            ),
+        ])
+        .await;
+        let client = server.client(ClientOptions::default().with_max_retries(1));
+        let resource = MeasurementsResource::new(Arc::new(client));
+
+        let batches: Vec<MeasurementBatch> = resource
+            .stream(
+                "1",
+                "INSTALLATION_ID_1",
+                "lpn-json",
+                DateRange::day("2024-01-01").unwrap(),
+                DateWindow::Month,
+            )
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(server.bodies().len(), 2);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(
+            timestamps(&batches[0].measurements),
+            vec!["2024-01-01 00:00:00"]
+        );
+    }
+
+    #[test]
+    fn keeps_rows_that_no_previous_batch_returned() {
+        let mut seen = SeenRows::new();
+
+        drop_already_seen(
+            batch(
+                "2024-01-01",
+                "2024-01-31",
+                &["2024-01-30 00:00:00", "2024-01-31 00:00:00"],
+            ),
+            &mut seen,
+        );
+        let late = drop_already_seen(
+            batch(
+                "2024-02-01",
+                "2024-02-29",
+                &[
+                    "2024-01-15 00:00:00",
+                    "2024-01-31 00:00:00",
+                    "2024-02-01 00:00:00",
+                ],
+            ),
+            &mut seen,
+        );
 
-        self.client.send(&request).await?.into_model()
+        assert_eq!(
+            timestamps(&late.measurements),
+            vec!["2024-01-15 00:00:00", "2024-02-01 00:00:00"]
+        );
     }
 }
@@ -3,3 +3,4 @@ mod measurements;
 
 pub(crate) use installations::InstallationsResource;
 pub(crate) use measurements::MeasurementsResource;
+pub use measurements::{MeasurementBatchStream, MeasurementStream};
@@ -0,0 +1,344 @@
+use serde::de::Error as _;
+
+use crate::errors::EnergiaProError;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Shape of a payload as detected from its first significant byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PayloadKind {
+    /// The payload is a top-level JSON array whose rows can be streamed.
+    Array,
+    /// The payload is anything else (typically an API error object).
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Waiting for the opening `[` of the payload.
+    Start,
+    /// Waiting for the next row, or for `]` if no row was read yet.
+    ExpectRow { first: bool },
+    /// Inside a row that started at `start` in the buffer.
+    Row {
+        start: usize,
+        depth: usize,
+        in_string: bool,
+        escaped: bool,
+    },
+    /// Waiting for `,` or `]` after a row.
+    ExpectSeparator,
+    /// The closing `]` was read.
+    End,
+}
+
+/// Incremental splitter for payloads shaped as a top-level JSON array.
+///
+/// Bytes are pushed as they arrive from the network and complete rows are
+/// returned as raw JSON slices, so callers can deserialize each row directly
+/// into its model without ever holding the whole payload in memory. Only the
+/// row currently being received is buffered.
+#[derive(Debug)]
+pub(crate) struct JsonRowReader {
+    /// Bytes received but not yet returned as rows.
+    buffer: Vec<u8>,
+    /// Position of the next byte to scan in `buffer`.
+    cursor: usize,
+    /// Current position in the array grammar.
+    state: State,
+    /// Whether the leading byte-order mark has been checked.
+    bom_checked: bool,
+}
+
+impl Default for JsonRowReader {
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            cursor: 0,
+            state: State::Start,
+            bom_checked: false,
+        }
+    }
+}
+
+impl JsonRowReader {
+    /// Append a chunk of payload bytes.
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Detect the payload shape, or `None` if no significant byte was received yet.
+    pub(crate) fn kind(&mut self) -> Option<PayloadKind> {
+        if self.state != State::Start {
+            return Some(PayloadKind::Array);
+        }
+
+        if !self.skip_bom() {
+            return None;
+        }
+
+        self.skip_whitespace();
+        match self.buffer.get(self.cursor) {
+            None => None,
+            Some(b'[') => Some(PayloadKind::Array),
+            Some(_) => Some(PayloadKind::Other),
+        }
+    }
+
+    /// Consume the reader and return every byte received so far, without the BOM.
+    pub(crate) fn into_bytes(mut self) -> Vec<u8> {
+        if self.buffer.starts_with(UTF8_BOM) {
+            self.buffer.drain(..UTF8_BOM.len());
+        }
+        self.buffer
+    }
+
+    /// Return the next complete row, or `None` if more bytes are needed.
+    pub(crate) fn next_row(&mut self) -> Result<Option<Vec<u8>>, EnergiaProError> {
+        loop {
+            match self.state {
+                State::Start => {
+                    if !self.skip_bom() {
+                        return Ok(None);
+                    }
+                    self.skip_whitespace();
+                    match self.buffer.get(self.cursor) {
+                        None => return Ok(None),
+                        Some(b'[') => {
+                            self.cursor += 1;
+                            self.state = State::ExpectRow { first: true };
+                        }
+                        Some(_) => return Err(invalid_payload("expected a JSON array")),
+                    }
+                }
+                State::ExpectRow { first } => {
+                    self.skip_whitespace();
+                    match self.buffer.get(self.cursor) {
+                        None => return Ok(None),
+                        Some(b']') if first => {
+                            self.cursor += 1;
+                            self.state = State::End;
+                        }
+                        Some(b',' | b']') => return Err(invalid_payload("expected a row")),
+                        Some(_) => {
+                            self.state = State::Row {
+                                start: self.cursor,
+                                depth: 0,
+                                in_string: false,
+                                escaped: false,
+                            };
+                        }
+                    }
+                }
+                State::Row { start, .. } => match self.scan_row()? {
+                    Some(end) => {
+                        let row = self.buffer[start..end].to_vec();
+                        self.buffer.drain(..end);
+                        self.cursor = 0;
+                        self.state = State::ExpectSeparator;
+                        return Ok(Some(row));
+                    }
+                    None => return Ok(None),
+                },
+                State::ExpectSeparator => {
+                    self.skip_whitespace();
+                    match self.buffer.get(self.cursor) {
+                        None => return Ok(None),
+                        Some(b',') => {
+                            self.cursor += 1;
+                            self.state = State::ExpectRow { first: false };
+                        }
+                        Some(b']') => {
+                            self.cursor += 1;
+                            self.state = State::End;
+                        }
+                        Some(_) => return Err(invalid_payload("expected `,` or `]`")),
+                    }
+                }
+                State::End => {
+                    self.skip_whitespace();
+                    if self.cursor < self.buffer.len() {
+                        return Err(invalid_payload("trailing characters after array"));
+                    }
+                    self.buffer.clear();
+                    self.cursor = 0;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Check that the payload ended cleanly once the body is exhausted.
+    pub(crate) fn finish(&mut self) -> Result<(), EnergiaProError> {
+        if self.next_row()?.is_some() || self.state != State::End {
+            return Err(invalid_payload("unexpected end of array"));
+        }
+
+        Ok(())
+    }
+
+    /// Scan the current row and return its exclusive end offset once complete.
+    fn scan_row(&mut self) -> Result<Option<usize>, EnergiaProError> {
+        let State::Row {
+            start,
+            mut depth,
+            mut in_string,
+            mut escaped,
+        } = self.state
+        else {
+            unreachable!("scan_row is only called while reading a row");
+        };
+
+        while let Some(&byte) = self.buffer.get(self.cursor) {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                    if depth == 0 {
+                        self.cursor += 1;
+                        return Ok(Some(self.cursor));
+                    }
+                }
+                self.cursor += 1;
+                continue;
+            }
+
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth == 0 => return Ok(Some(self.cursor)),
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.cursor += 1;
+                        return Ok(Some(self.cursor));
+                    }
+                }
+                b',' | b' ' | b'\t' | b'\n' | b'\r' if depth == 0 => {
+                    return Ok(Some(self.cursor));
+                }
+                _ => {}
+            }
+            self.cursor += 1;
+        }
+
+        self.state = State::Row {
+            start,
+            depth,
+            in_string,
+            escaped,
+        };
+
+        Ok(None)
+    }
+
+    /// Skip a leading UTF-8 byte-order mark, returning `false` until enough bytes arrived.
+    fn skip_bom(&mut self) -> bool {
+        if self.bom_checked {
+            return true;
+        }
+
+        let available = self.buffer.len().min(UTF8_BOM.len());
+        if self.buffer[..available] != UTF8_BOM[..available] {
+            self.bom_checked = true;
+            return true;
+        }
+
+        if available < UTF8_BOM.len() {
+            return false;
+        }
+
+        self.cursor = UTF8_BOM.len();
+        self.bom_checked = true;
+        true
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.buffer.get(self.cursor) {
+            self.cursor += 1;
+        }
+    }
+}
+
+fn invalid_payload(message: &str) -> EnergiaProError {
+    EnergiaProError::Json(serde_json::Error::custom(format!(
+        "invalid array payload: {message}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(chunks: &[&[u8]]) -> Result<Vec<String>, EnergiaProError> {
+        let mut reader = JsonRowReader::default();
+        let mut rows = Vec::new();
+
+        for chunk in chunks {
+            reader.push(chunk);
+            while let Some(row) = reader.next_row()? {
+                rows.push(String::from_utf8(row).unwrap());
+            }
+        }
+
+        reader.finish()?;
+        Ok(rows)
+    }
+
+    #[test]
+    fn splits_rows_from_single_chunk() {
+        let rows = read_all(&[br#"[{"a":1},{"b":"x,]"} , 3, "s"]"#]).unwrap();
+        assert_eq!(rows, vec![r#"{"a":1}"#, r#"{"b":"x,]"}"#, "3", r#""s""#]);
+    }
+
+    #[test]
+    fn splits_rows_across_arbitrary_chunk_boundaries() {
+        let payload = "\u{feff} [ {\"a\":\"q\\\"}\"}, {\"b\":[1,{\"c\":2}]} ]\n".as_bytes();
+
+        for size in 1..payload.len() {
+            let chunks = payload.chunks(size).collect::<Vec<_>>();
+            let rows = read_all(&chunks).unwrap();
+            assert_eq!(rows, vec![r#"{"a":"q\"}"}"#, r#"{"b":[1,{"c":2}]}"#]);
+        }
+    }
+
+    #[test]
+    fn accepts_empty_array() {
+        assert!(read_all(&[b" [ ] "]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn detects_payload_kind() {
+        let mut reader = JsonRowReader::default();
+        assert_eq!(reader.kind(), None);
+
+        reader.push(b"\xEF\xBB");
+        assert_eq!(reader.kind(), None);
+
+        reader.push(b"\xBF  {\"errorCode\":\"220\"}");
+        assert_eq!(reader.kind(), Some(PayloadKind::Other));
+        assert_eq!(reader.into_bytes(), b"  {\"errorCode\":\"220\"}");
+
+        let mut reader = JsonRowReader::default();
+        reader.push(b"\n[");
+        assert_eq!(reader.kind(), Some(PayloadKind::Array));
+    }
+
+    #[test]
+    fn rejects_truncated_payload() {
+        let err = read_all(&[br#"[{"a":1},{"b""#]);
+        assert!(matches!(err, Err(EnergiaProError::Json(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_separators() {
+        let err = read_all(&[br#"[{"a":1} {"b":2}]"#]);
+        assert!(matches!(err, Err(EnergiaProError::Json(_))));
+
+        let err = read_all(&[b"[1,,2]"]);
+        assert!(matches!(err, Err(EnergiaProError::Json(_))));
+    }
+}
@@ -1,73 +1,28 @@
 use serde::Deserialize;
 use serde::de;
-use serde_json::Value;
 
 use crate::errors::EnergiaProError;
 use crate::models::Measurement;
-use crate::responses::Response;
 
-#[derive(Debug, Clone)]
-pub(crate) struct MeasurementsResponse {
-    payload: Value,
-    installation_id: String,
-}
-
-impl MeasurementsResponse {
-    pub(crate) fn new(payload: Value, installation_id: impl Into<String>) -> Self {
-        Self {
-            payload,
-            installation_id: installation_id.into(),
-        }
-    }
-}
-
-impl Response for MeasurementsResponse {
-    type Model = Vec<Measurement>;
-
-    fn transform(mut self) -> Result<Self, EnergiaProError> {
-        fn ensure_installation_id_on_rows(payload: Value, installation_id: &str) -> Value {
-            match payload {
-                Value::Array(rows) => Value::Array(
-                    rows.into_iter()
-                        .map(|row| ensure_installation_id_on_row(row, installation_id))
-                        .collect(),
-                ),
-                other => other,
-            }
-        }
-
-        fn ensure_installation_id_on_row(row: Value, installation_id: &str) -> Value {
-            match row {
-                Value::Object(mut object) => {
-                    if !object.contains_key("installation_id") && !object.contains_key("num_inst") {
-                        object.insert(
-                            "num_inst".to_owned(),
-                            Value::String(installation_id.to_owned()),
-                        );
-                    }
-
-                    Value::Object(object)
-                }
-                other => other,
-            }
-        }
-
-        self.payload = ensure_installation_id_on_rows(self.payload, &self.installation_id);
-        Ok(self)
-    }
-
-    fn map(self) -> Result<Self::Model, EnergiaProError> {
-        let measurements: Vec<ApiMeasurement> = serde_json::from_value(self.payload)?;
-        Ok(measurements.into_iter().map(Into::into).collect())
-    }
+/// Deserialize a single row of a measurements payload.
+///
+/// Rows are deserialized straight from their raw JSON bytes. When a row does
+/// not carry its installation identifier (`num_inst`), the identifier of the
+/// requested installation is injected instead.
+pub(crate) fn parse_measurement_row(
+    row: &[u8],
+    installation_id: &str,
+) -> Result<Measurement, EnergiaProError> {
+    let row: ApiMeasurement = serde_json::from_slice(row)?;
+    Ok(row.into_measurement(installation_id))
 }
 
 #[derive(Debug, Deserialize)]
 struct ApiMeasurement {
     #[serde(deserialize_with = "deserialize_u64_from_string_or_number")]
     client_id: u64,
-    #[serde(alias = "num_inst", alias = "installation_id")]
-    installation_id: String,
+    #[serde(default, alias = "num_inst", alias = "installation_id")]
+    installation_id: Option<String>,
     #[serde(alias = "date")]
     timestamp: String,
     #[serde(
@@ -87,15 +42,17 @@ struct ApiMeasurement {
     consumption_kwh: f64,
 }
 
-impl From<ApiMeasurement> for Measurement {
-    fn from(value: ApiMeasurement) -> Self {
-        Self {
-            client_id: value.client_id,
-            installation_id: value.installation_id,
-            timestamp: value.timestamp,
-            index_m3: value.index_m3,
-            consumption_m3: value.consumption_m3,
-            consumption_kwh: value.consumption_kwh,
+impl ApiMeasurement {
+    fn into_measurement(self, installation_id: &str) -> Measurement {
+        Measurement {
+            client_id: self.client_id,
+            installation_id: self
+                .installation_id
+                .unwrap_or_else(|| installation_id.to_owned()),
+            timestamp: self.timestamp,
+            index_m3: self.index_m3,
+            consumption_m3: self.consumption_m3,
+            consumption_kwh: self.consumption_kwh,
         }
     }
 }
@@ -142,104 +99,77 @@ where
 mod tests {
     use super::*;
 
-    #[test]
-    fn parses_payload_and_injects_installation_id_when_missing() {
-        let payload = serde_json::json!([
-            {
-                "client_id": 0,
-                "date": "2024-04-01 15:00:00",
-                "quantite_m3": "77.10",
-                "index_m3": "145506.00",
-                "consommation_kw_h": "798.45"
-            }
-        ]);
-
-        let response = MeasurementsResponse::new(payload, "INSTALLATION_ID_1");
-        let measurements = response.into_model().unwrap();
-
-        assert_eq!(measurements[0].client_id, 0);
-        assert_eq!(measurements[0].installation_id, "INSTALLATION_ID_1");
-        assert_eq!(measurements[0].consumption_m3, 77.10);
+    fn parse(row: serde_json::Value) -> Measurement {
+        parse_measurement_row(row.to_string().as_bytes(), "INSTALLATION_ID_1").unwrap()
     }
 
     #[test]
-    fn injects_num_inst_when_missing_from_response() {
-        let payload = serde_json::json!([
-            {
-                "client_id": 0,
-                "date": "2024-04-01 15:00:00",
-                "quantite_m3": 77.10
-            }
-        ]);
-
-        let transformed = MeasurementsResponse::new(payload, "INSTALLATION_ID_1")
-            .transform()
-            .unwrap();
-        let first = transformed.payload.as_array().unwrap().first().unwrap();
-
-        assert_eq!(first.get("num_inst").unwrap(), "INSTALLATION_ID_1");
+    fn parses_row_and_injects_installation_id_when_missing() {
+        let measurement = parse(serde_json::json!({
+            "client_id": 0,
+            "date": "2024-04-01 15:00:00",
+            "quantite_m3": "77.10",
+            "index_m3": "145506.00",
+            "consommation_kw_h": "798.45"
+        }));
+
+        assert_eq!(measurement.client_id, 0);
+        assert_eq!(measurement.installation_id, "INSTALLATION_ID_1");
+        assert_eq!(measurement.consumption_m3, 77.10);
     }
 
     #[test]
     fn keeps_num_inst_from_response_when_present() {
-        let payload = serde_json::json!([
-            {
-                "client_id": 0,
-                "num_inst": "INSTALLATION_ID_PRESENT",
-                "quantite_m3": 77.10
-            }
-        ]);
-
-        let transformed = MeasurementsResponse::new(payload, "INSTALLATION_ID_1")
-            .transform()
-            .unwrap();
-        let first = transformed.payload.as_array().unwrap().first().unwrap();
-
-        assert_eq!(first.get("num_inst").unwrap(), "INSTALLATION_ID_PRESENT");
+        let measurement = parse(serde_json::json!({
+            "client_id": 0,
+            "num_inst": "INSTALLATION_ID_PRESENT",
+            "date": "2024-04-01 15:00:00",
+            "quantite_m3": 77.10,
+            "index_m3": 145506.00,
+            "consommation_kw_h": 798.45
+        }));
+
+        assert_eq!(measurement.installation_id, "INSTALLATION_ID_PRESENT");
     }
 
     #[test]
     fn keeps_installation_id_from_response_when_present() {
-        let payload = serde_json::json!([
-            {
-                "client_id": 0,
-                "installation_id": "INSTALLATION_ID_PRESENT",
-                "quantite_m3": 77.10
-            }
-        ]);
-
-        let transformed = MeasurementsResponse::new(payload, "INSTALLATION_ID_1")
-            .transform()
-            .unwrap();
-        let first = transformed.payload.as_array().unwrap().first().unwrap();
-
-        assert_eq!(
-            first.get("installation_id").unwrap(),
-            "INSTALLATION_ID_PRESENT"
-        );
-        assert!(first.get("num_inst").is_none());
+        let measurement = parse(serde_json::json!({
+            "client_id": 0,
+            "installation_id": "INSTALLATION_ID_PRESENT",
+            "date": "2024-04-01 15:00:00",
+            "quantite_m3": 77.10,
+            "index_m3": 145506.00,
+            "consommation_kw_h": 798.45
+        }));
+
+        assert_eq!(measurement.installation_id, "INSTALLATION_ID_PRESENT");
     }
 
     #[test]
     fn maps_mixed_string_and_numeric_field_formats() {
-        let payload = serde_json::json!([
-            {
-                "client_id": "0",
-                "num_inst": "INSTALLATION_ID_1",
-                "date": "2024-04-01 15:00:00",
-                "quantite_m3": 77.10,
-                "index_m3": "145506.00",
-                "consommation_kw_h": 798.45
-            }
-        ]);
-
-        let measurements = MeasurementsResponse::new(payload, "INSTALLATION_ID_1")
-            .into_model()
-            .unwrap();
-
-        assert_eq!(measurements[0].client_id, 0);
-        assert_eq!(measurements[0].index_m3, 145506.00);
-        assert_eq!(measurements[0].consumption_m3, 77.10);
-        assert_eq!(measurements[0].consumption_kwh, 798.45);
+        let measurement = parse(serde_json::json!({
+            "client_id": "0",
+            "num_inst": "INSTALLATION_ID_1",
+            "date": "2024-04-01 15:00:00",
+            "quantite_m3": 77.10,
+            "index_m3": "145506.00",
+            "consommation_kw_h": 798.45
+        }));
+
+        assert_eq!(measurement.client_id, 0);
+        assert_eq!(measurement.index_m3, 145506.00);
+        assert_eq!(measurement.consumption_m3, 77.10);
+        assert_eq!(measurement.consumption_kwh, 798.45);
+    }
+
+    #[test]
+    fn rejects_row_with_invalid_number() {
+        let err = parse_measurement_row(
+            br#"{"client_id":0,"date":"2024-04-01 15:00:00","quantite_m3":"n/a","index_m3":1,"consommation_kw_h":1}"#,
+            "INSTALLATION_ID_1",
+        );
+
+        assert!(matches!(err, Err(EnergiaProError::Json(_))));
     }
 }
@@ -1,5 +1,6 @@
 mod authenticate;
 mod installations;
+mod json_rows;
 mod measurements;
 
 use crate::errors::EnergiaProError;
@@ -43,4 +44,5 @@ pub(crate) trait Response {
 
 pub(crate) use authenticate::AuthenticateResponse;
 pub(crate) use installations::InstallationsResponse;
-pub(crate) use measurements::MeasurementsResponse;
+pub(crate) use json_rows::{JsonRowReader, PayloadKind};
+pub(crate) use measurements::parse_measurement_row;
@@ -0,0 +1,47 @@
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Europe::Zurich;
+
+/// Source of the current date used to resolve relative dates.
+///
+/// Dates are always expressed in the Europe/Zurich time zone, which is the
+/// time zone used by the EnergiaPro API. Use [`SystemClock`] in production
+/// and [`FixedClock`] to get reproducible results, for example in tests.
+pub trait Clock {
+    /// Return the current calendar day in Europe/Zurich.
+    fn today(&self) -> NaiveDate;
+}
+
+/// Clock backed by the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&Zurich).date_naive()
+    }
+}
+
+/// Clock that always returns the same day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock {
+    today: NaiveDate,
+}
+
+impl FixedClock {
+    /// Create a clock frozen on `today`.
+    pub fn new(today: NaiveDate) -> Self {
+        Self { today }
+    }
+}
+
+impl Clock for FixedClock {
+    fn today(&self) -> NaiveDate {
+        self.today
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn today(&self) -> NaiveDate {
+        (**self).today()
+    }
+}
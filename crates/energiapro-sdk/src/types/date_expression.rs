@@ -0,0 +1,312 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+use super::clock::Clock;
+use super::date_input::parse_date_argument;
+use super::date_range::last_day_of_month;
+use crate::errors::EnergiaProError;
+
+/// Absolute or relative date, resolved against a [`Clock`].
+///
+/// The following expressions are supported:
+///
+/// - `2024-01-15`: that exact day.
+/// - `today`, `yesterday`, `tomorrow`: the current day, or the day before or after.
+/// - `-30d`, `+2w`, `-3m`, `-1y`: today shifted by days, weeks, months or years.
+/// - `start-of-week`, `start-of-month`, `start-of-year`: first day of the current period.
+/// - `end-of-week`, `end-of-month`, `end-of-year`: last day of the current period.
+/// - `last-week`, `last-month`, `last-year`: first day of the previous period.
+///
+/// Weeks start on Monday. Month and year offsets are clamped to the last day
+/// of the target month, so `-1m` on March 31st resolves to the last day of
+/// February.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use energiapro::{DateExpression, FixedClock};
+///
+/// # fn demo() -> Result<(), energiapro::EnergiaProError> {
+/// let clock = FixedClock::new(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+///
+/// let start = DateExpression::parse("start-of-month")?.resolve(&clock)?;
+/// assert_eq!(start, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+///
+/// let month_ago = DateExpression::parse("-1m")?.resolve(&clock)?;
+/// assert_eq!(month_ago, NaiveDate::from_ymd_opt(2024, 2, 15).unwrap());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateExpression {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Absolute(NaiveDate),
+    Offset { amount: i64, unit: Unit },
+    StartOf(Period),
+    EndOf(Period),
+    StartOfPrevious(Period),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl DateExpression {
+    /// Parse an absolute `YYYY-MM-DD` date or a relative expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `expression` is not a supported expression.
+    pub fn parse(expression: &str) -> Result<Self, EnergiaProError> {
+        let expression = expression.trim();
+        let kind = match expression.to_ascii_lowercase().as_str() {
+            "today" => Kind::Offset {
+                amount: 0,
+                unit: Unit::Day,
+            },
+            "yesterday" => Kind::Offset {
+                amount: -1,
+                unit: Unit::Day,
+            },
+            "tomorrow" => Kind::Offset {
+                amount: 1,
+                unit: Unit::Day,
+            },
+            "start-of-week" => Kind::StartOf(Period::Week),
+            "start-of-month" => Kind::StartOf(Period::Month),
+            "start-of-year" => Kind::StartOf(Period::Year),
+            "end-of-week" => Kind::EndOf(Period::Week),
+            "end-of-month" => Kind::EndOf(Period::Month),
+            "end-of-year" => Kind::EndOf(Period::Year),
+            "last-week" => Kind::StartOfPrevious(Period::Week),
+            "last-month" => Kind::StartOfPrevious(Period::Month),
+            "last-year" => Kind::StartOfPrevious(Period::Year),
+            other if other.starts_with(['+', '-']) => parse_offset(other)?,
+            _ => Kind::Absolute(parse_date_argument("date", expression).map_err(|_| {
+                EnergiaProError::InvalidArgument(format!(
+                    "unsupported date expression `{expression}`"
+                ))
+            })?),
+        };
+
+        Ok(Self { kind })
+    }
+
+    /// Resolve the expression to a calendar day using `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resolved date is out of range.
+    pub fn resolve(&self, clock: &impl Clock) -> Result<NaiveDate, EnergiaProError> {
+        let today = clock.today();
+        let resolved = match self.kind {
+            Kind::Absolute(date) => Some(date),
+            Kind::Offset { amount, unit } => shift(today, amount, unit),
+            Kind::StartOf(period) => Some(start_of(today, period)),
+            Kind::EndOf(period) => end_of(today, period),
+            Kind::StartOfPrevious(period) => start_of(today, period)
+                .pred_opt()
+                .map(|day| start_of(day, period)),
+        };
+
+        resolved.ok_or_else(|| EnergiaProError::InvalidArgument("date is out of range".to_owned()))
+    }
+}
+
+impl FromStr for DateExpression {
+    type Err = EnergiaProError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+impl fmt::Display for DateExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Absolute(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Kind::Offset { amount: 0, .. } => f.write_str("today"),
+            Kind::Offset { amount, unit } => {
+                let unit = match unit {
+                    Unit::Day => 'd',
+                    Unit::Week => 'w',
+                    Unit::Month => 'm',
+                    Unit::Year => 'y',
+                };
+                write!(f, "{amount:+}{unit}")
+            }
+            Kind::StartOf(period) => write!(f, "start-of-{}", period.as_str()),
+            Kind::EndOf(period) => write!(f, "end-of-{}", period.as_str()),
+            Kind::StartOfPrevious(period) => write!(f, "last-{}", period.as_str()),
+        }
+    }
+}
+
+impl Period {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+        }
+    }
+}
+
+fn parse_offset(expression: &str) -> Result<Kind, EnergiaProError> {
+    let invalid =
+        || EnergiaProError::InvalidArgument(format!("unsupported date expression `{expression}`"));
+
+    let unit = expression.chars().last().ok_or_else(invalid)?;
+    let number = &expression[..expression.len() - unit.len_utf8()];
+    let unit = match unit {
+        'd' => Unit::Day,
+        'w' => Unit::Week,
+        'm' => Unit::Month,
+        'y' => Unit::Year,
+        _ => return Err(invalid()),
+    };
+    let amount = number.parse::<i64>().map_err(|_| invalid())?;
+
+    Ok(Kind::Offset { amount, unit })
+}
+
+fn shift(date: NaiveDate, amount: i64, unit: Unit) -> Option<NaiveDate> {
+    let magnitude = amount.unsigned_abs();
+    match unit {
+        Unit::Day | Unit::Week => {
+            let days = Days::new(if unit == Unit::Week {
+                magnitude.checked_mul(7)?
+            } else {
+                magnitude
+            });
+            if amount < 0 {
+                date.checked_sub_days(days)
+            } else {
+                date.checked_add_days(days)
+            }
+        }
+        Unit::Month | Unit::Year => {
+            let months = if unit == Unit::Year {
+                magnitude.checked_mul(12)?
+            } else {
+                magnitude
+            };
+            let months = Months::new(u32::try_from(months).ok()?);
+            if amount < 0 {
+                date.checked_sub_months(months)
+            } else {
+                date.checked_add_months(months)
+            }
+        }
+    }
+}
+
+fn start_of(date: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+        Period::Month => date.with_day(1).unwrap_or(date),
+        Period::Year => date.with_ordinal(1).unwrap_or(date),
+    }
+}
+
+fn end_of(date: NaiveDate, period: Period) -> Option<NaiveDate> {
+    match period {
+        Period::Week => start_of(date, period).checked_add_days(Days::new(6)),
+        Period::Month => Some(last_day_of_month(date)),
+        Period::Year => NaiveDate::from_ymd_opt(date.year(), 12, 31),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FixedClock;
+
+    fn resolve(expression: &str) -> String {
+        // Friday, March 15th 2024
+        let clock = FixedClock::new(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+        DateExpression::parse(expression)
+            .unwrap()
+            .resolve(&clock)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn resolves_named_days() {
+        assert_eq!(resolve("today"), "2024-03-15");
+        assert_eq!(resolve("Yesterday"), "2024-03-14");
+        assert_eq!(resolve("tomorrow"), "2024-03-16");
+        assert_eq!(resolve(" 2023-12-24 "), "2023-12-24");
+    }
+
+    #[test]
+    fn resolves_offsets() {
+        assert_eq!(resolve("-30d"), "2024-02-14");
+        assert_eq!(resolve("+2w"), "2024-03-29");
+        assert_eq!(resolve("-1m"), "2024-02-15");
+        assert_eq!(resolve("-1y"), "2023-03-15");
+    }
+
+    #[test]
+    fn clamps_month_offsets_to_month_end() {
+        let clock = FixedClock::new(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        let date = DateExpression::parse("-1m")
+            .unwrap()
+            .resolve(&clock)
+            .unwrap();
+        assert_eq!(date.to_string(), "2024-02-29");
+    }
+
+    #[test]
+    fn resolves_period_boundaries() {
+        assert_eq!(resolve("start-of-week"), "2024-03-11");
+        assert_eq!(resolve("end-of-week"), "2024-03-17");
+        assert_eq!(resolve("start-of-month"), "2024-03-01");
+        assert_eq!(resolve("end-of-month"), "2024-03-31");
+        assert_eq!(resolve("start-of-year"), "2024-01-01");
+        assert_eq!(resolve("end-of-year"), "2024-12-31");
+        assert_eq!(resolve("last-week"), "2024-03-04");
+        assert_eq!(resolve("last-month"), "2024-02-01");
+        assert_eq!(resolve("last-year"), "2023-01-01");
+    }
+
+    #[test]
+    fn rejects_unsupported_expressions() {
+        for expression in ["", "next-week", "-d", "-3q", "2024-3-1", "+1.5d", "-3é"] {
+            assert!(matches!(
+                DateExpression::parse(expression),
+                Err(EnergiaProError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn displays_normalized_expression() {
+        assert_eq!(DateExpression::parse("-30d").unwrap().to_string(), "-30d");
+        assert_eq!(DateExpression::parse("+2w").unwrap().to_string(), "+2w");
+        assert_eq!(DateExpression::parse("TODAY").unwrap().to_string(), "today");
+        assert_eq!(
+            DateExpression::parse("last-month").unwrap().to_string(),
+            "last-month"
+        );
+    }
+}
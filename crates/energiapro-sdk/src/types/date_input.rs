@@ -1,10 +1,33 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono_tz::Europe::Zurich;
+
+use super::clock::Clock;
+use super::date_expression::DateExpression;
+use crate::errors::EnergiaProError;
 
 /// Input accepted by public SDK methods where a date is required.
 ///
-/// String-based inputs must be provided using the `YYYY-MM-DD` format.
+/// String-based inputs must be provided using the `YYYY-MM-DD` format. Use
+/// [`DateExpression`] for relative dates such as `yesterday` or `-30d`.
+///
+/// Timestamps ([`DateTime`]) are converted to the Europe/Zurich time zone
+/// before their calendar day is taken. With the `time` feature enabled,
+/// `time::Date` is accepted as well.
 pub trait DateInput: private::Sealed {
     fn into_date_string(self) -> String;
+
+    /// Resolve the input to a calendar day, taking today from `clock` for relative inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming `field` if the input is not a valid
+    /// `YYYY-MM-DD` date, or the error of the [`DateExpression`] itself.
+    fn into_date(self, field: &str, _clock: &impl Clock) -> Result<NaiveDate, EnergiaProError>
+    where
+        Self: Sized,
+    {
+        parse_date_argument(field, &self.into_date_string())
+    }
 }
 
 impl DateInput for NaiveDate {
@@ -13,6 +36,36 @@ impl DateInput for NaiveDate {
     }
 }
 
+impl<Tz: TimeZone> DateInput for DateTime<Tz> {
+    fn into_date_string(self) -> String {
+        self.with_timezone(&Zurich).date_naive().into_date_string()
+    }
+}
+
+/// Relative expressions are resolved against the clock given to
+/// [`DateInput::into_date`]; as a string, an expression keeps its own text.
+impl DateInput for DateExpression {
+    fn into_date_string(self) -> String {
+        self.to_string()
+    }
+
+    fn into_date(self, _field: &str, clock: &impl Clock) -> Result<NaiveDate, EnergiaProError> {
+        self.resolve(clock)
+    }
+}
+
+#[cfg(feature = "time")]
+impl DateInput for time::Date {
+    fn into_date_string(self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}",
+            self.year(),
+            u8::from(self.month()),
+            self.day()
+        )
+    }
+}
+
 impl DateInput for String {
     fn into_date_string(self) -> String {
         self
@@ -25,12 +78,33 @@ impl DateInput for &str {
     }
 }
 
+/// Parse a date argument, requiring the strict `YYYY-MM-DD` format.
+pub(crate) fn parse_date_argument(field: &str, value: &str) -> Result<NaiveDate, EnergiaProError> {
+    let parsed = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        EnergiaProError::InvalidArgument(format!("{field} must be in YYYY-MM-DD format"))
+    })?;
+
+    if parsed.format("%Y-%m-%d").to_string() != value {
+        return Err(EnergiaProError::InvalidArgument(format!(
+            "{field} must be in YYYY-MM-DD format"
+        )));
+    }
+
+    Ok(parsed)
+}
+
 mod private {
-    use chrono::NaiveDate;
+    use chrono::{DateTime, NaiveDate, TimeZone};
+
+    use crate::types::DateExpression;
 
     pub trait Sealed {}
 
     impl Sealed for NaiveDate {}
+    impl<Tz: TimeZone> Sealed for DateTime<Tz> {}
+    impl Sealed for DateExpression {}
+    #[cfg(feature = "time")]
+    impl Sealed for time::Date {}
     impl Sealed for String {}
     impl Sealed for &str {}
 }
@@ -38,6 +112,7 @@ mod private {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::FixedClock;
 
     #[test]
     fn formats_naive_date() {
@@ -45,6 +120,62 @@ mod tests {
         assert_eq!(date.into_date_string(), "2024-04-01");
     }
 
+    #[test]
+    fn formats_date_time_in_zurich_time_zone() {
+        let late_evening_utc = chrono::Utc
+            .with_ymd_and_hms(2024, 3, 31, 22, 30, 0)
+            .unwrap();
+        assert_eq!(late_evening_utc.into_date_string(), "2024-04-01");
+
+        let offset = chrono::FixedOffset::west_opt(5 * 3600).unwrap();
+        let morning = offset.with_ymd_and_hms(2024, 4, 1, 8, 0, 0).unwrap();
+        assert_eq!(morning.into_date_string(), "2024-04-01");
+    }
+
+    #[test]
+    fn resolves_absolute_date_expression() {
+        let expression = DateExpression::parse("2024-04-01").unwrap();
+        assert_eq!(expression.into_date_string(), "2024-04-01");
+    }
+
+    #[test]
+    fn resolves_relative_date_expression_with_the_given_clock() {
+        let clock = FixedClock::new(NaiveDate::from_ymd_opt(2024, 4, 2).unwrap());
+        let expression = DateExpression::parse("yesterday").unwrap();
+
+        assert_eq!(
+            expression.into_date("from", &clock).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn reports_the_error_of_the_date_expression() {
+        let clock = FixedClock::new(NaiveDate::MAX);
+        let expression = DateExpression::parse("+1d").unwrap();
+
+        assert!(matches!(
+            expression.into_date("to", &clock),
+            Err(EnergiaProError::InvalidArgument(message)) if message == "date is out of range"
+        ));
+    }
+
+    #[test]
+    fn names_the_field_of_an_invalid_string() {
+        assert!(matches!(
+            "2024/04/01".into_date("from", &FixedClock::new(NaiveDate::MIN)),
+            Err(EnergiaProError::InvalidArgument(message))
+                if message == "from must be in YYYY-MM-DD format"
+        ));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn formats_time_date() {
+        let date = time::Date::from_calendar_date(2024, time::Month::April, 1).unwrap();
+        assert_eq!(date.into_date_string(), "2024-04-01");
+    }
+
     #[test]
     fn keeps_string_input() {
         assert_eq!("2024-04-01".into_date_string(), "2024-04-01");
@@ -0,0 +1,494 @@
+use std::fmt;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use super::clock::{Clock, SystemClock};
+use super::date_input::DateInput;
+use crate::errors::EnergiaProError;
+
+/// Inclusive range of calendar days used to filter measurements.
+///
+/// Either side may be left open: a range without a start covers everything up
+/// to its end, a range without an end covers everything since its start, and
+/// [`DateRange::all`] covers the whole history of an installation.
+///
+/// Constructors validate their input up front, so a `DateRange` always has its
+/// start on or before its end.
+///
+/// # Examples
+///
+/// ```
+/// use energiapro::DateRange;
+///
+/// # fn demo() -> Result<(), energiapro::EnergiaProError> {
+/// let january = DateRange::month(2024, 1)?;
+/// assert_eq!(january.to_string(), "2024-01-01..=2024-01-31");
+///
+/// let winter = DateRange::heating_season(2023)?;
+/// assert_eq!(winter.to_string(), "2023-10-01..=2024-09-30");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DateRange {
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// Create a range that covers the whole history, without any date filter.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Create a range that covers a single day.
+    ///
+    /// A relative [`DateExpression`](crate::DateExpression) is resolved
+    /// against the [`SystemClock`]; use [`DateRange::day_with`] to pick the clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date.
+    pub fn day(date: impl DateInput) -> Result<Self, EnergiaProError> {
+        Self::day_with(date, &SystemClock)
+    }
+
+    /// Create a range that covers a single day, resolving relative dates with `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date or cannot
+    /// be resolved.
+    pub fn day_with(date: impl DateInput, clock: &impl Clock) -> Result<Self, EnergiaProError> {
+        let date = date.into_date("date", clock)?;
+        Ok(Self::closed(date, date))
+    }
+
+    /// Create a range between two days, both included.
+    ///
+    /// Relative dates are resolved against the [`SystemClock`]; use
+    /// [`DateRange::between_with`] to pick the clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either date is invalid or if `from` is after `to`.
+    pub fn between(from: impl DateInput, to: impl DateInput) -> Result<Self, EnergiaProError> {
+        Self::between_with(from, to, &SystemClock)
+    }
+
+    /// Create a range between two days, both included, resolving relative dates with `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either date is invalid or if `from` is after `to`.
+    pub fn between_with(
+        from: impl DateInput,
+        to: impl DateInput,
+        clock: &impl Clock,
+    ) -> Result<Self, EnergiaProError> {
+        let from = from.into_date("from", clock)?;
+        let to = to.into_date("to", clock)?;
+
+        if from > to {
+            return Err(EnergiaProError::InvalidArgument(
+                "from must be less than or equal to to".to_owned(),
+            ));
+        }
+
+        Ok(Self::closed(from, to))
+    }
+
+    /// Create a range that starts on a given day and has no end.
+    ///
+    /// Relative dates are resolved against the [`SystemClock`]; use
+    /// [`DateRange::since_with`] to pick the clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date.
+    pub fn since(date: impl DateInput) -> Result<Self, EnergiaProError> {
+        Self::since_with(date, &SystemClock)
+    }
+
+    /// Create a range that starts on a given day and has no end, resolving relative dates with `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date or cannot
+    /// be resolved.
+    pub fn since_with(date: impl DateInput, clock: &impl Clock) -> Result<Self, EnergiaProError> {
+        Ok(Self {
+            start: Some(date.into_date("from", clock)?),
+            end: None,
+        })
+    }
+
+    /// Create a range that ends on a given day and has no start.
+    ///
+    /// Relative dates are resolved against the [`SystemClock`]; use
+    /// [`DateRange::until_with`] to pick the clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date.
+    pub fn until(date: impl DateInput) -> Result<Self, EnergiaProError> {
+        Self::until_with(date, &SystemClock)
+    }
+
+    /// Create a range that ends on a given day and has no start, resolving relative dates with `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `date` is not a valid `YYYY-MM-DD` date or cannot
+    /// be resolved.
+    pub fn until_with(date: impl DateInput, clock: &impl Clock) -> Result<Self, EnergiaProError> {
+        Ok(Self {
+            start: None,
+            end: Some(date.into_date("to", clock)?),
+        })
+    }
+
+    /// Create a range covering the last `days` days, today included.
+    ///
+    /// Today is taken from the [`SystemClock`], in Europe/Zurich.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `days` is zero.
+    pub fn last_n_days(days: u32) -> Result<Self, EnergiaProError> {
+        Self::last_n_days_with(days, &SystemClock)
+    }
+
+    /// Create a range covering the last `days` days, with today taken from `clock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `days` is zero.
+    pub fn last_n_days_with(days: u32, clock: &impl Clock) -> Result<Self, EnergiaProError> {
+        if days == 0 {
+            return Err(EnergiaProError::InvalidArgument(
+                "days must be greater than zero".to_owned(),
+            ));
+        }
+
+        let today = clock.today();
+        let start = today
+            .checked_sub_days(Days::new(u64::from(days - 1)))
+            .unwrap_or(NaiveDate::MIN);
+
+        Ok(Self::closed(start, today))
+    }
+
+    /// Create a range covering a calendar month.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `month` is not between 1 and 12 or `year` is out of range.
+    pub fn month(year: i32, month: u32) -> Result<Self, EnergiaProError> {
+        let start = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(|| {
+            EnergiaProError::InvalidArgument(format!("invalid month {year}-{month:02}"))
+        })?;
+
+        Ok(Self::closed(start, last_day_of_month(start)))
+    }
+
+    /// Create a range covering a calendar quarter, from 1 to 4.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `quarter` is not between 1 and 4 or `year` is out of range.
+    pub fn quarter(year: i32, quarter: u32) -> Result<Self, EnergiaProError> {
+        let invalid =
+            || EnergiaProError::InvalidArgument(format!("invalid quarter {year}-Q{quarter}"));
+        if !(1..=4).contains(&quarter) {
+            return Err(invalid());
+        }
+
+        let start = NaiveDate::from_ymd_opt(year, quarter * 3 - 2, 1).ok_or_else(invalid)?;
+        let last_month = NaiveDate::from_ymd_opt(year, quarter * 3, 1).ok_or_else(invalid)?;
+
+        Ok(Self::closed(start, last_day_of_month(last_month)))
+    }
+
+    /// Create a range covering an ISO week, from Monday to Sunday.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `week` does not exist in the given ISO year.
+    pub fn iso_week(year: i32, week: u32) -> Result<Self, EnergiaProError> {
+        let invalid =
+            || EnergiaProError::InvalidArgument(format!("invalid ISO week {year}-W{week:02}"));
+        let start = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(invalid)?;
+        let end = NaiveDate::from_isoywd_opt(year, week, Weekday::Sun).ok_or_else(invalid)?;
+
+        Ok(Self::closed(start, end))
+    }
+
+    /// Create a range covering a calendar year.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `year` is out of range.
+    pub fn year(year: i32) -> Result<Self, EnergiaProError> {
+        let invalid = || EnergiaProError::InvalidArgument(format!("invalid year {year}"));
+        let start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(invalid)?;
+        let end = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(invalid)?;
+
+        Ok(Self::closed(start, end))
+    }
+
+    /// Create a range covering the heating season starting in `year`.
+    ///
+    /// The heating season is a heating year running from October 1st of `year`
+    /// to September 30th of the following year, the same period as
+    /// [`Granularity::HeatingSeason`](crate::Granularity::HeatingSeason), so
+    /// that the summer is included and a query resamples into one full
+    /// season.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `year` is out of range.
+    pub fn heating_season(year: i32) -> Result<Self, EnergiaProError> {
+        let (start, end) = heating_season_bounds(year).ok_or_else(|| {
+            EnergiaProError::InvalidArgument(format!("invalid heating season {year}"))
+        })?;
+
+        Ok(Self::closed(start, end))
+    }
+
+    /// Create a range from January 1st of the current year up to today.
+    ///
+    /// Today is taken from the [`SystemClock`], in Europe/Zurich.
+    pub fn year_to_date() -> Self {
+        Self::year_to_date_with(&SystemClock)
+    }
+
+    /// Create a range from January 1st of the current year up to today, with
+    /// today taken from `clock`.
+    pub fn year_to_date_with(clock: &impl Clock) -> Self {
+        let today = clock.today();
+        let start = today.with_ordinal(1).unwrap_or(today);
+        Self::closed(start, today)
+    }
+
+    /// First day of the range, or `None` if the range has no start.
+    pub fn start(&self) -> Option<NaiveDate> {
+        self.start
+    }
+
+    /// Last day of the range, or `None` if the range has no end.
+    pub fn end(&self) -> Option<NaiveDate> {
+        self.end
+    }
+
+    /// Return `true` if `date` falls within the range.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start.is_none_or(|start| start <= date) && self.end.is_none_or(|end| date <= end)
+    }
+
+    fn closed(start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{}", start.format("%Y-%m-%d"))?;
+        }
+
+        f.write_str("..")?;
+
+        if let Some(end) = self.end {
+            write!(f, "={}", end.format("%Y-%m-%d"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the first and last day of the heating season starting in `year`,
+/// from October 1st to September 30th of the following year.
+pub(crate) fn heating_season_bounds(year: i32) -> Option<(NaiveDate, NaiveDate)> {
+    Some((
+        NaiveDate::from_ymd_opt(year, 10, 1)?,
+        NaiveDate::from_ymd_opt(year.checked_add(1)?, 9, 30)?,
+    ))
+}
+
+/// Return the last day of the month containing `date`.
+pub(crate) fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first_of_next| first_of_next.pred_opt())
+        .unwrap_or(NaiveDate::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DateExpression, FixedClock, Granularity};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn builds_closed_ranges() {
+        assert_eq!(
+            DateRange::day("2024-04-01").unwrap().to_string(),
+            "2024-04-01..=2024-04-01"
+        );
+        assert_eq!(
+            DateRange::between("2024-04-01", date("2024-04-30"))
+                .unwrap()
+                .to_string(),
+            "2024-04-01..=2024-04-30"
+        );
+        assert_eq!(
+            DateRange::month(2024, 2).unwrap().to_string(),
+            "2024-02-01..=2024-02-29"
+        );
+        assert_eq!(
+            DateRange::month(2023, 12).unwrap().to_string(),
+            "2023-12-01..=2023-12-31"
+        );
+        assert_eq!(
+            DateRange::quarter(2024, 1).unwrap().to_string(),
+            "2024-01-01..=2024-03-31"
+        );
+        assert_eq!(
+            DateRange::quarter(2024, 4).unwrap().to_string(),
+            "2024-10-01..=2024-12-31"
+        );
+        assert_eq!(
+            DateRange::year(2024).unwrap().to_string(),
+            "2024-01-01..=2024-12-31"
+        );
+        assert_eq!(
+            DateRange::heating_season(2023).unwrap().to_string(),
+            "2023-10-01..=2024-09-30"
+        );
+    }
+
+    #[test]
+    fn matches_the_heating_season_granularity() {
+        let season = DateRange::heating_season(2023).unwrap();
+        let (start, end) = (season.start().unwrap(), season.end().unwrap());
+
+        assert_eq!(Granularity::HeatingSeason.period(start), (start, end));
+        assert_eq!(Granularity::HeatingSeason.period(end), (start, end));
+    }
+
+    #[test]
+    fn builds_iso_weeks() {
+        assert_eq!(
+            DateRange::iso_week(2024, 1).unwrap().to_string(),
+            "2024-01-01..=2024-01-07"
+        );
+        assert_eq!(
+            DateRange::iso_week(2021, 1).unwrap().to_string(),
+            "2021-01-04..=2021-01-10"
+        );
+        assert!(DateRange::iso_week(2021, 53).is_err());
+    }
+
+    #[test]
+    fn builds_open_ranges() {
+        assert_eq!(DateRange::all().to_string(), "..");
+        assert_eq!(
+            DateRange::since("2024-04-01").unwrap().to_string(),
+            "2024-04-01.."
+        );
+        assert_eq!(
+            DateRange::until("2024-04-01").unwrap().to_string(),
+            "..=2024-04-01"
+        );
+    }
+
+    #[test]
+    fn builds_ranges_relative_to_today() {
+        let clock = FixedClock::new(date("2024-03-10"));
+
+        assert_eq!(
+            DateRange::last_n_days_with(7, &clock).unwrap(),
+            DateRange::between("2024-03-04", "2024-03-10").unwrap()
+        );
+        assert_eq!(
+            DateRange::last_n_days_with(1, &clock).unwrap(),
+            DateRange::day("2024-03-10").unwrap()
+        );
+        assert!(DateRange::last_n_days_with(0, &clock).is_err());
+        assert_eq!(
+            DateRange::year_to_date_with(&clock),
+            DateRange::between("2024-01-01", "2024-03-10").unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_date_expressions_with_the_given_clock() {
+        let clock = FixedClock::new(date("2024-03-10"));
+        let expression = |value: &str| DateExpression::parse(value).unwrap();
+
+        assert_eq!(
+            DateRange::between_with(
+                expression("start-of-month"),
+                expression("yesterday"),
+                &clock
+            )
+            .unwrap(),
+            DateRange::between("2024-03-01", "2024-03-09").unwrap()
+        );
+        assert_eq!(
+            DateRange::day_with(expression("today"), &clock).unwrap(),
+            DateRange::day("2024-03-10").unwrap()
+        );
+        assert_eq!(
+            DateRange::since_with(expression("-1w"), &clock).unwrap(),
+            DateRange::since("2024-03-03").unwrap()
+        );
+        assert_eq!(
+            DateRange::until_with(expression("end-of-month"), &clock).unwrap(),
+            DateRange::until("2024-03-31").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(matches!(
+            DateRange::between("2024-04-30", "2024-04-01"),
+            Err(EnergiaProError::InvalidArgument(message))
+                if message == "from must be less than or equal to to"
+        ));
+        assert!(matches!(
+            DateRange::day("2024/04/01"),
+            Err(EnergiaProError::InvalidArgument(message))
+                if message == "date must be in YYYY-MM-DD format"
+        ));
+        assert!(DateRange::month(2024, 13).is_err());
+        assert!(DateRange::quarter(2024, 0).is_err());
+        assert!(DateRange::quarter(2024, 5).is_err());
+    }
+
+    #[test]
+    fn checks_containment() {
+        let range = DateRange::month(2024, 4).unwrap();
+        assert!(range.contains(date("2024-04-01")));
+        assert!(range.contains(date("2024-04-30")));
+        assert!(!range.contains(date("2024-05-01")));
+        assert!(DateRange::all().contains(date("1970-01-01")));
+        assert!(
+            !DateRange::since("2024-04-01")
+                .unwrap()
+                .contains(date("2024-03-31"))
+        );
+    }
+}
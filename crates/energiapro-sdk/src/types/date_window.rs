@@ -0,0 +1,115 @@
+use chrono::{Datelike, Days, NaiveDate};
+
+use super::date_range::last_day_of_month;
+
+/// Size of the windows a long date range is split into before fetching.
+///
+/// Windows follow calendar boundaries: weeks start on Monday and months on
+/// their first day. The first and last windows are clamped to the requested
+/// range. [`DateWindow::Month`] is the default window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateWindow {
+    /// One window per calendar day.
+    Day,
+    /// One window per ISO week (Monday to Sunday).
+    Week,
+    /// One window per calendar month.
+    #[default]
+    Month,
+}
+
+impl DateWindow {
+    /// Split the inclusive range `from..=to` into consecutive inclusive windows.
+    pub(crate) fn split(self, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut windows = Vec::new();
+        let mut start = from;
+
+        while start <= to {
+            let end = self.window_end(start).min(to);
+            windows.push((start, end));
+
+            match end.succ_opt() {
+                Some(next) => start = next,
+                None => break,
+            }
+        }
+
+        windows
+    }
+
+    /// Return the last day of the window containing `date`.
+    fn window_end(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => {
+                let remaining = 6 - u64::from(date.weekday().num_days_from_monday());
+                date.checked_add_days(Days::new(remaining))
+                    .unwrap_or(NaiveDate::MAX)
+            }
+            Self::Month => last_day_of_month(date),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn splits_range_into_days() {
+        assert_eq!(
+            DateWindow::Day.split(date("2024-02-28"), date("2024-03-01")),
+            vec![
+                (date("2024-02-28"), date("2024-02-28")),
+                (date("2024-02-29"), date("2024-02-29")),
+                (date("2024-03-01"), date("2024-03-01")),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_range_into_iso_weeks() {
+        assert_eq!(
+            DateWindow::Week.split(date("2024-04-03"), date("2024-04-16")),
+            vec![
+                (date("2024-04-03"), date("2024-04-07")),
+                (date("2024-04-08"), date("2024-04-14")),
+                (date("2024-04-15"), date("2024-04-16")),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_range_into_months_across_years() {
+        assert_eq!(
+            DateWindow::Month.split(date("2023-11-15"), date("2024-02-10")),
+            vec![
+                (date("2023-11-15"), date("2023-11-30")),
+                (date("2023-12-01"), date("2023-12-31")),
+                (date("2024-01-01"), date("2024-01-31")),
+                (date("2024-02-01"), date("2024-02-10")),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_single_day_range_as_one_window() {
+        assert_eq!(
+            DateWindow::Month.split(date("2024-04-01"), date("2024-04-01")),
+            vec![(date("2024-04-01"), date("2024-04-01"))]
+        );
+    }
+
+    #[test]
+    fn returns_no_window_for_inverted_range() {
+        assert!(
+            DateWindow::Day
+                .split(date("2024-04-02"), date("2024-04-01"))
+                .is_empty()
+        );
+    }
+}
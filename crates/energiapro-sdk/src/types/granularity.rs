@@ -0,0 +1,110 @@
+use chrono::{Datelike, Days, NaiveDate};
+
+use super::date_range::{heating_season_bounds, last_day_of_month};
+
+/// Size of the periods a measurement series is resampled into.
+///
+/// Periods follow the Europe/Zurich calendar: days start at local midnight,
+/// weeks start on Monday and months on their first day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Granularity {
+    /// One period per calendar day.
+    Day,
+    /// One period per ISO week (Monday to Sunday).
+    Week,
+    /// One period per calendar month.
+    Month,
+    /// One period per calendar year.
+    Year,
+    /// One period per heating year, from October 1st to September 30th of the
+    /// following year.
+    ///
+    /// Summer consumption is attributed to the heating season that started the
+    /// previous October, so every measurement belongs to exactly one season.
+    /// Each period matches [`DateRange::heating_season`](crate::DateRange::heating_season).
+    HeatingSeason,
+}
+
+impl Granularity {
+    /// Return the first and last day of the period containing `date`.
+    pub fn period(self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Self::Day => (date, date),
+            Self::Week => {
+                let start = date
+                    .checked_sub_days(Days::new(u64::from(date.weekday().num_days_from_monday())))
+                    .unwrap_or(NaiveDate::MIN);
+                let end = start
+                    .checked_add_days(Days::new(6))
+                    .unwrap_or(NaiveDate::MAX);
+                (start, end)
+            }
+            Self::Month => {
+                let start = date.with_day(1).unwrap_or(date);
+                (start, last_day_of_month(date))
+            }
+            Self::Year => (
+                date.with_ordinal(1).unwrap_or(date),
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).unwrap_or(NaiveDate::MAX),
+            ),
+            Self::HeatingSeason => {
+                let year = if date.month() >= 10 {
+                    date.year()
+                } else {
+                    date.year() - 1
+                };
+                heating_season_bounds(year).unwrap_or((NaiveDate::MIN, NaiveDate::MAX))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn period(granularity: Granularity, value: &str) -> (String, String) {
+        let (start, end) = granularity.period(date(value));
+        (start.to_string(), end.to_string())
+    }
+
+    #[test]
+    fn computes_calendar_periods() {
+        assert_eq!(
+            period(Granularity::Day, "2024-02-29"),
+            ("2024-02-29".to_owned(), "2024-02-29".to_owned())
+        );
+        assert_eq!(
+            period(Granularity::Week, "2024-01-03"),
+            ("2024-01-01".to_owned(), "2024-01-07".to_owned())
+        );
+        assert_eq!(
+            period(Granularity::Month, "2024-02-10"),
+            ("2024-02-01".to_owned(), "2024-02-29".to_owned())
+        );
+        assert_eq!(
+            period(Granularity::Year, "2024-06-15"),
+            ("2024-01-01".to_owned(), "2024-12-31".to_owned())
+        );
+    }
+
+    #[test]
+    fn computes_heating_seasons() {
+        assert_eq!(
+            period(Granularity::HeatingSeason, "2023-10-01"),
+            ("2023-10-01".to_owned(), "2024-09-30".to_owned())
+        );
+        assert_eq!(
+            period(Granularity::HeatingSeason, "2024-09-30"),
+            ("2023-10-01".to_owned(), "2024-09-30".to_owned())
+        );
+        assert_eq!(
+            period(Granularity::HeatingSeason, "2024-01-15"),
+            ("2023-10-01".to_owned(), "2024-09-30".to_owned())
+        );
+    }
+}
@@ -1,5 +1,16 @@
+mod clock;
+mod date_expression;
 mod date_input;
+mod date_range;
+mod date_window;
+mod granularity;
 mod measurement_scope;
 
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use date_expression::DateExpression;
 pub use date_input::DateInput;
+pub(crate) use date_input::parse_date_argument;
+pub use date_range::DateRange;
+pub use date_window::DateWindow;
+pub use granularity::Granularity;
 pub use measurement_scope::MeasurementScope;